@@ -0,0 +1,178 @@
+//! Big integers in the ext encoding used by msgpack-javascript.
+//!
+//! JavaScript numbers are doubles, so msgpack-javascript and libraries following it encode
+//! `BigInt`s as an ext of type [`BIGINT_EXT_TAG`], whose data is itself a MessagePack value: an
+//! integer if it fits in a double without loss (±(2^53 - 1)), a decimal str otherwise.
+//!
+//! The helpers in this module write that encoding, and read it back as well as plain integers and
+//! decimal strings.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Balance {
+//!     #[serde(with = "rmp_serde::bigint::int128")]
+//!     amount: i128,
+//! }
+//!
+//! let balance = Balance { amount: -170141183460469231731687303715884105728 };
+//! let buf = rmp_serde::to_vec(&balance).unwrap();
+//! assert_eq!(balance, rmp_serde::from_slice(&buf).unwrap());
+//! # }
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+use core::marker::PhantomData;
+use core::str::FromStr;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+use crate::{Ext, ExtRef};
+
+pub use num_bigint::BigInt;
+
+/// Ext type of big integers in msgpack-javascript.
+pub const BIGINT_EXT_TAG: i8 = 0;
+
+/// Largest integer magnitude a JavaScript number represents exactly.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Serializes `i128` as a bigint ext.
+///
+/// Use with `#[serde(with = "rmp_serde::bigint::int128")]`.
+pub mod int128 {
+    use serde::{Deserializer, Serializer};
+
+    /// Serializes the integer as a bigint ext.
+    #[inline]
+    pub fn serialize<S: Serializer>(val: &i128, se: S) -> Result<S::Ok, S::Error> {
+        super::serialize(val, se)
+    }
+
+    /// Deserializes a bigint ext, an integer or a decimal str.
+    #[inline]
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<i128, D::Error> {
+        super::deserialize(de)
+    }
+}
+
+/// Serializes `u128` as a bigint ext.
+///
+/// Use with `#[serde(with = "rmp_serde::bigint::uint128")]`.
+pub mod uint128 {
+    use serde::{Deserializer, Serializer};
+
+    /// Serializes the integer as a bigint ext.
+    #[inline]
+    pub fn serialize<S: Serializer>(val: &u128, se: S) -> Result<S::Ok, S::Error> {
+        super::serialize(val, se)
+    }
+
+    /// Deserializes a bigint ext, an integer or a decimal str.
+    #[inline]
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<u128, D::Error> {
+        super::deserialize(de)
+    }
+}
+
+/// Serializes [`BigInt`] as a bigint ext.
+///
+/// Use with `#[serde(with = "rmp_serde::bigint::big_int")]`.
+pub mod big_int {
+    use serde::{Deserializer, Serializer};
+
+    use super::BigInt;
+
+    /// Serializes the integer as a bigint ext.
+    #[inline]
+    pub fn serialize<S: Serializer>(val: &BigInt, se: S) -> Result<S::Ok, S::Error> {
+        super::serialize(val, se)
+    }
+
+    /// Deserializes a bigint ext, an integer or a decimal str.
+    #[inline]
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<BigInt, D::Error> {
+        super::deserialize(de)
+    }
+}
+
+fn serialize<T: Display, S: Serializer>(val: &T, se: S) -> Result<S::Ok, S::Error> {
+    let digits = val.to_string();
+    let mut data = Vec::with_capacity(digits.len() + 2);
+    let res = match digits.parse::<i64>() {
+        Ok(v) if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v) => {
+            rmp::encode::write_sint(&mut data, v).map(drop)
+        }
+        _ => rmp::encode::write_str(&mut data, &digits),
+    };
+    res.map_err(ser::Error::custom)?;
+    ExtRef::new(BIGINT_EXT_TAG, &data).serialize(se)
+}
+
+fn deserialize<'de, T, D>(de: D) -> Result<T, D::Error>
+where T: FromStr,
+      D: Deserializer<'de>
+{
+    de.deserialize_any(BigIntVisitor { allow_ext: true, marker: PhantomData })
+}
+
+struct BigIntVisitor<T> {
+    /// Whether an ext is accepted, i.e. this is not its data already.
+    allow_ext: bool,
+    marker: PhantomData<T>,
+}
+
+impl<T: FromStr> BigIntVisitor<T> {
+    fn parse<E: de::Error>(&self, v: &str) -> Result<T, E> {
+        v.parse().map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), self))
+    }
+}
+
+impl<'de, T: FromStr> Visitor<'de> for BigIntVisitor<T> {
+    type Value = T;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a bigint ext, an integer or a decimal string")
+    }
+
+    #[inline]
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        self.parse(&v.to_string())
+    }
+
+    #[inline]
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        self.parse(&v.to_string())
+    }
+
+    #[inline]
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<T, E> {
+        self.parse(&v.to_string())
+    }
+
+    #[inline]
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<T, E> {
+        self.parse(&v.to_string())
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        self.parse(v)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, de: D) -> Result<T, D::Error> {
+        if !self.allow_ext {
+            return Err(de::Error::invalid_type(de::Unexpected::Other("nested ext"), &self));
+        }
+        let ext = Ext::from_newtype(de)?;
+        if ext.tag() != BIGINT_EXT_TAG {
+            return Err(de::Error::invalid_value(de::Unexpected::Signed(ext.tag().into()), &"a bigint ext"));
+        }
+        let mut inner = crate::Deserializer::new(ext.data());
+        inner.deserialize_any(BigIntVisitor { allow_ext: false, marker: PhantomData })
+            .map_err(de::Error::custom)
+    }
+}
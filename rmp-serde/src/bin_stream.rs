@@ -0,0 +1,235 @@
+//! Streaming of large bin payloads.
+//!
+//! Deserializing a bin into a `Vec<u8>` keeps all of it in memory. [`BinSink`] instead copies the
+//! data into an `io::Write` in chunks of at most 64 KiB as it is read, e.g. into a file, while
+//! the rest of the value is decoded normally. Other serde deserializers pass the whole bin at
+//! once.
+//!
+//! In the other direction, [`BinReader`] serializes as a bin by copying from an `io::Read` in
+//! chunks.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! use std::io::{self, Write};
+//!
+//! /// Counts bytes instead of keeping them.
+//! #[derive(Default)]
+//! struct Counter(usize);
+//!
+//! impl Write for Counter {
+//!     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+//!         self.0 += buf.len();
+//!         Ok(buf.len())
+//!     }
+//!
+//!     fn flush(&mut self) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Upload {
+//!     name: String,
+//!     #[serde(deserialize_with = "rmp_serde::bin_stream::deserialize")]
+//!     data: Counter,
+//! }
+//!
+//! let buf = rmp_serde::to_vec(&("big.bin", serde_bytes::Bytes::new(&[0; 100_000]))).unwrap();
+//! let upload: Upload = rmp_serde::from_read(&buf[..]).unwrap();
+//! assert_eq!(100_000, upload.data.0);
+//! # }
+//! ```
+
+use core::cell::RefCell;
+use core::fmt::{self, Formatter};
+use std::io;
+
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeTuple, Serializer};
+
+use crate::Bytes;
+
+/// Name of the newtype struct asking the deserializer for the data of a bin in chunks, as a
+/// sequence of bytes.
+pub(crate) const BIN_CHUNKS_NAME: &str = "_BinChunks";
+
+/// Maximum size of the chunks.
+pub(crate) const CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Deserializes a bin by writing its data into the wrapped writer, returning the number of bytes
+/// written.
+///
+/// Use with `DeserializeSeed::deserialize`, e.g. with a `&mut File`. A str is written the same
+/// way, anything else fails. The writer isn't flushed.
+#[derive(Debug)]
+pub struct BinSink<W>(pub W);
+
+impl<'de, W: io::Write> DeserializeSeed<'de> for BinSink<W> {
+    type Value = u64;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<u64, D::Error> {
+        de.deserialize_newtype_struct(BIN_CHUNKS_NAME, SinkVisitor(self.0))
+    }
+}
+
+/// Deserializes a bin into a new `W`, in chunks.
+///
+/// Use with `#[serde(deserialize_with = "rmp_serde::bin_stream::deserialize")]` on fields of a
+/// type implementing `io::Write` and `Default`, e.g. a spooled temporary file.
+#[inline]
+pub fn deserialize<'de, W, D>(de: D) -> Result<W, D::Error>
+where W: io::Write + Default,
+      D: Deserializer<'de>
+{
+    let mut wr = W::default();
+    BinSink(&mut wr).deserialize(de)?;
+    Ok(wr)
+}
+
+struct SinkVisitor<W>(W);
+
+impl<W: io::Write> SinkVisitor<W> {
+    fn write<E: de::Error>(&mut self, v: &[u8]) -> Result<u64, E> {
+        self.0.write_all(v).map_err(de::Error::custom)?;
+        Ok(v.len() as u64)
+    }
+}
+
+impl<'de, W: io::Write> Visitor<'de> for SinkVisitor<W> {
+    type Value = u64;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a MessagePack bin")
+    }
+
+    #[inline]
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, de: D) -> Result<u64, D::Error> {
+        de.deserialize_bytes(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<u64, A::Error> {
+        let mut written = 0;
+        while let Some(len) = seq.next_element_seed(ChunkSeed(&mut self))? {
+            written += len;
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(mut self, v: &[u8]) -> Result<u64, E> {
+        self.write(v)
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(mut self, v: &str) -> Result<u64, E> {
+        self.write(v.as_bytes())
+    }
+}
+
+/// Writes a single chunk.
+struct ChunkSeed<'a, W>(&'a mut SinkVisitor<W>);
+
+impl<'de, W: io::Write> DeserializeSeed<'de> for ChunkSeed<'_, W> {
+    type Value = u64;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<u64, D::Error> {
+        de.deserialize_bytes(self)
+    }
+}
+
+impl<'de, W: io::Write> Visitor<'de> for ChunkSeed<'_, W> {
+    type Value = u64;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a chunk of bytes")
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<u64, E> {
+        self.0.write(v)
+    }
+}
+
+/// Serializes as a bin of the given length, read from the wrapped reader in chunks.
+///
+/// The reader must provide at least `len` bytes, only that many are read. As serializing takes
+/// `&self`, the reader is kept in a `RefCell`, and a `BinReader` can be serialized only once.
+///
+/// With the MessagePack serializer the data goes straight to the output. Other serde serializers
+/// see a tuple of the length followed by the chunks as bytes.
+///
+/// ```
+/// use rmp_serde::bin_stream::BinReader;
+///
+/// let data = vec![7; 100_000];
+/// let buf = rmp_serde::to_vec(&("big.bin", BinReader::new(100_000, &data[..]))).unwrap();
+///
+/// let (_, bytes): (String, rmp_serde::ByteBuf) = rmp_serde::from_slice(&buf).unwrap();
+/// assert_eq!(data, bytes.into_vec());
+/// ```
+#[derive(Debug)]
+pub struct BinReader<R> {
+    len: u32,
+    rd: RefCell<R>,
+}
+
+impl<R: io::Read> BinReader<R> {
+    /// Creates a bin of `len` bytes read from `rd`.
+    #[inline]
+    pub fn new(len: u32, rd: R) -> Self {
+        BinReader { len, rd: RefCell::new(rd) }
+    }
+
+    /// Returns the length of the bin.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns `true` if the bin is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Consumes this wrapper, returning the reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.rd.into_inner()
+    }
+}
+
+impl<R: io::Read> Serialize for BinReader<R> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_newtype_struct(BIN_CHUNKS_NAME, &ReadChunks(self))
+    }
+}
+
+/// Serializes the length and the chunks as a tuple.
+struct ReadChunks<'a, R>(&'a BinReader<R>);
+
+impl<R: io::Read> Serialize for ReadChunks<'_, R> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        let mut rd = self.0.rd.try_borrow_mut()
+            .map_err(|_| ser::Error::custom("BinReader is already being serialized"))?;
+        let chunks = self.0.len.div_ceil(CHUNK_SIZE) as usize;
+        let mut tuple = se.serialize_tuple(1 + chunks)?;
+        tuple.serialize_element(&self.0.len)?;
+
+        let mut buf = vec![0; self.0.len.min(CHUNK_SIZE) as usize];
+        let mut left = self.0.len;
+        while left > 0 {
+            let chunk = &mut buf[..left.min(CHUNK_SIZE) as usize];
+            rd.read_exact(chunk).map_err(ser::Error::custom)?;
+            tuple.serialize_element(&Bytes(chunk))?;
+            left -= chunk.len() as u32;
+        }
+        tuple.end()
+    }
+}
@@ -0,0 +1,216 @@
+//! [`http_body::Body`] implementations that serialize values as they are sent.
+//!
+//! Instead of buffering the whole MessagePack response in a `Vec<u8>`, these bodies serialize a few
+//! values at a time and hand them out as chunks of about 8 KiB:
+//!
+//! * [`MsgPackArrayBody`] writes the items of an iterator as a single MessagePack array, which is
+//!   how a large sequence would be written by [`crate::to_vec`].
+//! * [`MsgPackStreamBody`] writes the values of a [`Stream`] one after another, the same format as
+//!   read by [`crate::stream::MsgPackStream`] or [`crate::from_read`] in a loop.
+//!
+//! Errors are reported as [`io::Error`]s of the [`io::ErrorKind::InvalidData`] kind, and end the
+//! body.
+
+use core::fmt::{self, Debug, Formatter};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use http_body::{Body, Frame};
+use serde::Serialize;
+
+use crate::encode::BytesWriter;
+use crate::frame;
+
+/// The size above which the buffered bytes are sent as a data frame.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A [`Body`] writing the items of an iterator as a MessagePack array.
+///
+/// The array length is taken from the [`ExactSizeIterator`] up front. If the iterator then yields
+/// a different number of items, the body ends with an error.
+pub struct MsgPackArrayBody<I> {
+    iter: I,
+    /// How many items are still expected.
+    remaining: usize,
+    buf: BytesMut,
+    started: bool,
+    done: bool,
+}
+
+impl<I: ExactSizeIterator> MsgPackArrayBody<I> {
+    /// Creates a new body from the given iterator.
+    #[inline]
+    pub fn new<T>(iter: T) -> Self
+    where T: IntoIterator<IntoIter = I>
+    {
+        let iter = iter.into_iter();
+        MsgPackArrayBody {
+            remaining: iter.len(),
+            iter,
+            buf: BytesMut::new(),
+            started: false,
+            done: false,
+        }
+    }
+}
+
+impl<I> MsgPackArrayBody<I> {
+    /// Consumes this body returning the underlying iterator.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I> Debug for MsgPackArrayBody<I> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("MsgPackArrayBody")
+            .field("remaining", &self.remaining)
+            .field("buffered", &self.buf.len())
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<I> MsgPackArrayBody<I>
+where I: Iterator,
+      I::Item: Serialize
+{
+    /// Serializes items into the buffer until a chunk is full or the array is complete.
+    fn fill(&mut self) -> Result<(), io::Error> {
+        if !self.started {
+            self.started = true;
+            let len = u32::try_from(self.remaining)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "array is too long"))?;
+            rmp::encode::write_array_len(&mut BytesWriter::new(&mut self.buf), len)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        }
+        while self.buf.len() < CHUNK_SIZE {
+            match (self.iter.next(), self.remaining) {
+                (Some(..), 0) => return Err(io::Error::new(io::ErrorKind::InvalidData, "iterator yielded too many items")),
+                (Some(item), _) => {
+                    frame::encode(&item, &mut self.buf)?;
+                    self.remaining -= 1;
+                }
+                (None, 0) => {
+                    self.done = true;
+                    break;
+                }
+                (None, _) => return Err(io::Error::new(io::ErrorKind::InvalidData, "iterator yielded too few items")),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<I> Body for MsgPackArrayBody<I>
+where I: Iterator + Unpin,
+      I::Item: Serialize
+{
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        if let Err(err) = this.fill() {
+            this.done = true;
+            this.buf.clear();
+            return Poll::Ready(Some(Err(err)));
+        }
+        Poll::Ready(Some(Ok(Frame::data(this.buf.split().freeze()))))
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+}
+
+/// A [`Body`] writing the values of a [`Stream`] one after another.
+///
+/// Values that are ready are coalesced into chunks. Whatever is buffered is sent as soon as the
+/// stream would block, so slow streams don't delay values that were already produced.
+pub struct MsgPackStreamBody<S> {
+    stream: S,
+    buf: BytesMut,
+    done: bool,
+}
+
+impl<S> MsgPackStreamBody<S> {
+    /// Creates a new body from the given stream.
+    #[inline]
+    pub fn new(stream: S) -> Self {
+        MsgPackStreamBody {
+            stream,
+            buf: BytesMut::new(),
+            done: false,
+        }
+    }
+
+    /// Gets a reference to the underlying stream.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consumes this body returning the underlying stream.
+    ///
+    /// Values that were serialized but not sent yet are lost.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Debug for MsgPackStreamBody<S>
+where S: Debug
+{
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("MsgPackStreamBody")
+            .field("stream", &self.stream)
+            .field("buffered", &self.buf.len())
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<S> Body for MsgPackStreamBody<S>
+where S: Stream + Unpin,
+      S::Item: Serialize
+{
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        let this = self.get_mut();
+        while !this.done && this.buf.len() < CHUNK_SIZE {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(val)) => {
+                    if let Err(err) = frame::encode(&val, &mut this.buf) {
+                        this.done = true;
+                        this.buf.clear();
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending if this.buf.is_empty() => return Poll::Pending,
+                Poll::Pending => break,
+            }
+        }
+        if this.buf.is_empty() {
+            return Poll::Ready(None);
+        }
+        Poll::Ready(Some(Ok(Frame::data(this.buf.split().freeze()))))
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.done && self.buf.is_empty()
+    }
+}
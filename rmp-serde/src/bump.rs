@@ -0,0 +1,102 @@
+//! Arena allocation of decoded strings and bytes, backed by [`bumpalo`](https://docs.rs/bumpalo).
+//!
+//! Decoding into `String` or `Vec<u8>` makes one heap allocation for each of them. The seeds in
+//! this module instead copy them into a caller-supplied [`Bump`] arena, returning `&'bump str` and
+//! `&'bump [u8]`. Resetting the arena between batches frees all of them at once, which takes most
+//! of the load off the allocator when decoding many small messages.
+//!
+//! The data is always copied, even if the input is a slice it could be borrowed from, so the input
+//! buffer doesn't have to outlive the arena.
+//!
+//! ```
+//! use rmp_serde::bump::{Bump, BumpStr};
+//! use serde::de::DeserializeSeed;
+//!
+//! let mut bump = Bump::new();
+//! for name in ["alice", "bob"] {
+//!     let buf = rmp_serde::to_vec(&name).unwrap();
+//!
+//!     let mut de = rmp_serde::Deserializer::new(&buf[..]);
+//!     let decoded: &str = BumpStr(&bump).deserialize(&mut de).unwrap();
+//!     assert_eq!(name, decoded);
+//!
+//!     bump.reset();
+//! }
+//! ```
+
+use core::fmt::{self, Formatter};
+
+use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
+
+pub use bumpalo::Bump;
+
+/// Deserializes a str into the wrapped arena.
+///
+/// A bin is accepted too, if it's valid UTF-8.
+#[derive(Clone, Copy, Debug)]
+pub struct BumpStr<'bump>(pub &'bump Bump);
+
+impl<'de, 'bump> DeserializeSeed<'de> for BumpStr<'bump> {
+    type Value = &'bump str;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<&'bump str, D::Error> {
+        de.deserialize_str(self)
+    }
+}
+
+impl<'de, 'bump> Visitor<'de> for BumpStr<'bump> {
+    type Value = &'bump str;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a string")
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<&'bump str, E> {
+        Ok(self.0.alloc_str(v))
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<&'bump str, E> {
+        match core::str::from_utf8(v) {
+            Ok(v) => self.visit_str(v),
+            Err(..) => Err(de::Error::invalid_value(de::Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+/// Deserializes a bin into the wrapped arena.
+///
+/// A str is accepted too.
+#[derive(Clone, Copy, Debug)]
+pub struct BumpBytes<'bump>(pub &'bump Bump);
+
+impl<'de, 'bump> DeserializeSeed<'de> for BumpBytes<'bump> {
+    type Value = &'bump [u8];
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<&'bump [u8], D::Error> {
+        de.deserialize_bytes(self)
+    }
+}
+
+impl<'de, 'bump> Visitor<'de> for BumpBytes<'bump> {
+    type Value = &'bump [u8];
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a MessagePack bin or str")
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<&'bump [u8], E> {
+        Ok(self.0.alloc_slice_copy(v))
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<&'bump [u8], E> {
+        self.visit_bytes(v.as_bytes())
+    }
+}
@@ -0,0 +1,178 @@
+//! Byte string wrappers that serialize as MessagePack bin.
+
+use core::fmt::{self, Formatter};
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use core::ops::DerefMut;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// A borrowed byte slice that serializes as bin.
+///
+/// serde serializes `&[u8]` as an array of integers. Wrapping it in `Bytes` writes a bin instead.
+/// Deserializing borrows a bin or str from the input, so only works when decoding from a slice,
+/// use [`ByteBuf`] otherwise.
+///
+/// ```
+/// use rmp_serde::Bytes;
+///
+/// let buf = rmp_serde::to_vec(&Bytes(b"hi")).unwrap();
+/// assert_eq!(vec![0xc4, 0x02, b'h', b'i'], buf);
+///
+/// let bytes: Bytes<'_> = rmp_serde::from_slice(&buf).unwrap();
+/// assert_eq!(b"hi", &*bytes);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Bytes<'a>(pub &'a [u8]);
+
+impl<'a> Bytes<'a> {
+    /// Returns the wrapped slice.
+    #[inline]
+    pub fn into_inner(self) -> &'a [u8] {
+        self.0
+    }
+}
+
+impl<'a> From<&'a [u8]> for Bytes<'a> {
+    #[inline]
+    fn from(v: &'a [u8]) -> Self {
+        Bytes(v)
+    }
+}
+
+impl Deref for Bytes<'_> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for Bytes<'_> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl Serialize for Bytes<'_> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_bytes(self.0)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Bytes<'a> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_bytes(BytesVisitor)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes<'de>;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a borrowed MessagePack bin or str")
+    }
+
+    #[inline]
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(Bytes(v))
+    }
+
+    #[inline]
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Bytes(v.as_bytes()))
+    }
+}
+
+/// An owned byte buffer that serializes as bin.
+///
+/// serde serializes `Vec<u8>` as an array of integers. Wrapping it in `ByteBuf` writes a bin
+/// instead, and deserializes from a bin or str.
+///
+/// ```
+/// use rmp_serde::ByteBuf;
+///
+/// let buf = rmp_serde::to_vec(&ByteBuf(vec![1, 2])).unwrap();
+/// assert_eq!(vec![0xc4, 0x02, 0x01, 0x02], buf);
+///
+/// let bytes: ByteBuf = rmp_serde::from_read(&buf[..]).unwrap();
+/// assert_eq!(vec![1, 2], bytes.into_vec());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ByteBuf(pub Vec<u8>);
+
+#[cfg(feature = "std")]
+impl ByteBuf {
+    /// Consumes this wrapper, returning the buffer.
+    #[inline]
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Vec<u8>> for ByteBuf {
+    #[inline]
+    fn from(v: Vec<u8>) -> Self {
+        ByteBuf(v)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ByteBuf> for Vec<u8> {
+    #[inline]
+    fn from(v: ByteBuf) -> Self {
+        v.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Deref for ByteBuf {
+    type Target = Vec<u8>;
+
+    #[inline]
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl DerefMut for ByteBuf {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<[u8]> for ByteBuf {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Serialize for ByteBuf {
+    #[inline]
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for ByteBuf {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        crate::with::bytes::deserialize(de)
+    }
+}
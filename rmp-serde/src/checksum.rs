@@ -0,0 +1,308 @@
+//! Messages followed by a checksum.
+//!
+//! [`write_checksummed`] writes a value followed by a 4-byte big-endian trailer holding the
+//! checksum of its encoding, and [`from_slice_checksummed`] verifies the trailer before
+//! deserializing. Both CRC-32 (as used by zlib and Ethernet) and xxHash32 are implemented without
+//! dependencies and without an allocator, for links that need cheap integrity checks.
+//!
+//! ```
+//! use rmp_serde::checksum::{self, Checksum, ChecksumError};
+//!
+//! let mut buf = Vec::new();
+//! checksum::write_checksummed(&mut buf, &("temp", 21.5), Checksum::Crc32).unwrap();
+//! assert_eq!(("temp", 21.5), checksum::from_slice_checksummed(&buf, Checksum::Crc32).unwrap());
+//!
+//! buf[1] ^= 1;
+//! match checksum::from_slice_checksummed::<(&str, f64)>(&buf, Checksum::Crc32) {
+//!     Err(ChecksumError::ChecksumMismatch { .. }) => {}
+//!     other => panic!("unexpected result: {:?}", other),
+//! }
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+
+use rmp::decode::ValueReadError;
+use rmp::encode::{RmpWrite, ValueWriteError};
+use serde::{Deserialize, Serialize};
+
+use crate::decode::{self, BytesReadError};
+use crate::encode;
+
+/// The size of the trailer.
+pub const TRAILER_SIZE: usize = 4;
+
+/// A checksum algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Checksum {
+    /// CRC-32 with the IEEE polynomial, as used by zlib, PNG and Ethernet.
+    Crc32,
+    /// xxHash32 with seed 0, which is faster than CRC-32 in software.
+    Xxh32,
+}
+
+impl Checksum {
+    /// Computes the checksum of the data.
+    #[inline]
+    pub fn compute(self, data: &[u8]) -> u32 {
+        let mut hasher = Hasher::new(self);
+        hasher.update(data);
+        hasher.finish()
+    }
+}
+
+/// The error of [`from_slice_checksummed`].
+#[derive(Debug)]
+pub enum ChecksumError {
+    /// The trailer doesn't match the checksum of the message.
+    ChecksumMismatch {
+        /// The checksum found in the trailer.
+        expected: u32,
+        /// The checksum of the message.
+        actual: u32,
+    },
+    /// The checksum matches, but the message can't be deserialized, or the data is too short to
+    /// hold a trailer.
+    Decode(decode::Error<BytesReadError>),
+}
+
+impl Display for ChecksumError {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            ChecksumError::ChecksumMismatch { expected, actual } => {
+                write!(fmt, "checksum mismatch, expected {:#010x} but found {:#010x}", expected, actual)
+            }
+            ChecksumError::Decode(ref err) => Display::fmt(err, fmt),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChecksumError {
+    #[cold]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            ChecksumError::ChecksumMismatch { .. } => None,
+            ChecksumError::Decode(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<decode::Error<BytesReadError>> for ChecksumError {
+    #[cold]
+    fn from(err: decode::Error<BytesReadError>) -> Self {
+        ChecksumError::Decode(err)
+    }
+}
+
+/// Serializes the value with structs written as arrays, like [`write`](crate::encode::write),
+/// followed by the checksum of its encoding.
+///
+/// # Errors
+///
+/// Fails like [`write`](crate::encode::write).
+pub fn write_checksummed<W, T>(wr: W, val: &T, checksum: Checksum) -> Result<(), encode::Error<W::Error>>
+where
+    W: RmpWrite,
+    T: Serialize + ?Sized
+{
+    let mut se = encode::Serializer::new(ChecksumWrite { wr, hasher: Hasher::new(checksum) });
+    val.serialize(&mut se)?;
+    let mut wr = se.into_inner();
+    let trailer = wr.hasher.finish().to_be_bytes();
+    wr.wr.write_bytes(&trailer).map_err(ValueWriteError::InvalidDataWrite)?;
+    Ok(())
+}
+
+/// Verifies the checksum in the trailer, and returns the message in front of it.
+///
+/// # Errors
+///
+/// Fails with [`ChecksumError::ChecksumMismatch`] if the checksum doesn't match, or with
+/// [`ChecksumError::Decode`] if the data is shorter than the trailer.
+pub fn verify_checksum(buf: &[u8], checksum: Checksum) -> Result<&[u8], ChecksumError> {
+    let Some(msg_len) = buf.len().checked_sub(TRAILER_SIZE) else {
+        let err = BytesReadError::InsufficientBytes { expected: TRAILER_SIZE, actual: buf.len(), position: 0 };
+        return Err(decode::Error::InvalidValueRead(ValueReadError::InvalidDataRead(err)).into());
+    };
+    let (msg, trailer) = buf.split_at(msg_len);
+    let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let actual = checksum.compute(msg);
+    if expected != actual {
+        return Err(ChecksumError::ChecksumMismatch { expected, actual });
+    }
+    Ok(msg)
+}
+
+/// Verifies the checksum in the trailer, and deserializes the message in front of it, like
+/// [`from_slice`](crate::from_slice).
+///
+/// # Errors
+///
+/// Fails like [`verify_checksum`], or with [`ChecksumError::Decode`] if the message can't be
+/// deserialized.
+pub fn from_slice_checksummed<'a, T>(buf: &'a [u8], checksum: Checksum) -> Result<T, ChecksumError>
+where T: Deserialize<'a>
+{
+    let msg = verify_checksum(buf, checksum)?;
+    Ok(decode::from_slice(msg)?)
+}
+
+/// A writer computing the checksum of what is written through it.
+struct ChecksumWrite<W> {
+    wr: W,
+    hasher: Hasher,
+}
+
+impl<W: RmpWrite> RmpWrite for ChecksumWrite<W> {
+    type Error = W::Error;
+
+    #[inline]
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.hasher.update(buf);
+        self.wr.write_bytes(buf)
+    }
+}
+
+/// The running state of a checksum.
+enum Hasher {
+    Crc32(u32),
+    Xxh32(Xxh32),
+}
+
+impl Hasher {
+    #[inline]
+    fn new(checksum: Checksum) -> Self {
+        match checksum {
+            Checksum::Crc32 => Hasher::Crc32(!0),
+            Checksum::Xxh32 => Hasher::Xxh32(Xxh32::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Crc32(crc) => {
+                for &byte in data {
+                    *crc = CRC32_TABLE[((*crc ^ u32::from(byte)) & 0xff) as usize] ^ (*crc >> 8);
+                }
+            }
+            Hasher::Xxh32(xxh) => xxh.update(data),
+        }
+    }
+
+    #[inline]
+    fn finish(&self) -> u32 {
+        match self {
+            Hasher::Crc32(crc) => !crc,
+            Hasher::Xxh32(xxh) => xxh.finish(),
+        }
+    }
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+}
+
+const PRIME1: u32 = 0x9e37_79b1;
+const PRIME2: u32 = 0x85eb_ca77;
+const PRIME3: u32 = 0xc2b2_ae3d;
+const PRIME4: u32 = 0x27d4_eb2f;
+const PRIME5: u32 = 0x1656_67b1;
+
+/// The streaming state of xxHash32 with seed 0.
+struct Xxh32 {
+    acc: [u32; 4],
+    /// The input that doesn't fill a stripe of 16 bytes yet.
+    buf: [u8; 16],
+    buf_len: usize,
+    total_len: u64,
+}
+
+impl Xxh32 {
+    #[inline]
+    fn new() -> Self {
+        Xxh32 {
+            acc: [PRIME1.wrapping_add(PRIME2), PRIME2, 0, 0u32.wrapping_sub(PRIME1)],
+            buf: [0; 16],
+            buf_len: 0,
+            total_len: 0,
+        }
+    }
+
+    #[inline]
+    fn round(acc: u32, lane: &[u8]) -> u32 {
+        let lane = u32::from_le_bytes([lane[0], lane[1], lane[2], lane[3]]);
+        acc.wrapping_add(lane.wrapping_mul(PRIME2)).rotate_left(13).wrapping_mul(PRIME1)
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        for (acc, lane) in self.acc.iter_mut().zip(stripe.chunks_exact(4)) {
+            *acc = Self::round(*acc, lane);
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+        if self.buf_len > 0 {
+            let len = data.len().min(16 - self.buf_len);
+            self.buf[self.buf_len..self.buf_len + len].copy_from_slice(&data[..len]);
+            self.buf_len += len;
+            data = &data[len..];
+            if self.buf_len < 16 {
+                return;
+            }
+            let buf = self.buf;
+            self.stripe(&buf);
+            self.buf_len = 0;
+        }
+        let mut stripes = data.chunks_exact(16);
+        for stripe in &mut stripes {
+            self.stripe(stripe);
+        }
+        let rest = stripes.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.buf_len = rest.len();
+    }
+
+    fn finish(&self) -> u32 {
+        let mut hash = if self.total_len >= 16 {
+            self.acc[0].rotate_left(1)
+                .wrapping_add(self.acc[1].rotate_left(7))
+                .wrapping_add(self.acc[2].rotate_left(12))
+                .wrapping_add(self.acc[3].rotate_left(18))
+        } else {
+            PRIME5
+        };
+        hash = hash.wrapping_add(self.total_len as u32);
+
+        let mut words = self.buf[..self.buf_len].chunks_exact(4);
+        for word in &mut words {
+            let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            hash = hash.wrapping_add(word.wrapping_mul(PRIME3)).rotate_left(17).wrapping_mul(PRIME4);
+        }
+        for &byte in words.remainder() {
+            hash = hash.wrapping_add(u32::from(byte).wrapping_mul(PRIME5)).rotate_left(11).wrapping_mul(PRIME1);
+        }
+
+        hash ^= hash >> 15;
+        hash = hash.wrapping_mul(PRIME2);
+        hash ^= hash >> 13;
+        hash = hash.wrapping_mul(PRIME3);
+        hash ^ (hash >> 16)
+    }
+}
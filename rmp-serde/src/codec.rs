@@ -0,0 +1,123 @@
+//! [`tokio_util::codec`] support for framing a byte stream as a sequence of MessagePack values.
+//!
+//! MessagePack values are self-delimiting, so no extra length prefix is written: frames are
+//! plain concatenated values, compatible with any other MessagePack stream reader or writer.
+//!
+//! ```ignore
+//! let mut framed = Framed::new(socket, MsgPackCodec::<Request>::new());
+//! while let Some(request) = framed.next().await {
+//!     framed.send(handle(request?)).await?;
+//! }
+//! ```
+
+use core::fmt::{self, Debug, Formatter};
+use core::marker::PhantomData;
+use std::io;
+
+use bytes::BytesMut;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::frame::{self, Decoded};
+
+/// A codec that decodes values of type `T`, and encodes any serializable value.
+///
+/// Values are encoded with the default (compact) representation, i.e. structs are serialized as
+/// arrays.
+///
+/// Decoding and encoding errors are reported as [`io::ErrorKind::InvalidData`] errors, wrapping
+/// the original [`decode::Error`](crate::decode::Error) or [`encode::Error`](crate::encode::Error).
+pub struct MsgPackCodec<T> {
+    max_frame_length: usize,
+    /// How many bytes must be buffered before it's worth trying to decode again.
+    min_len: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> MsgPackCodec<T> {
+    /// Creates a new codec without a frame length limit.
+    #[inline]
+    pub fn new() -> Self {
+        MsgPackCodec {
+            max_frame_length: usize::MAX,
+            min_len: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Limits how many bytes may be buffered while waiting for an incomplete value.
+    ///
+    /// Once the limit is exceeded decoding fails, instead of buffering a value of unbounded size.
+    #[inline]
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Returns the maximum frame length.
+    #[inline(always)]
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+}
+
+impl<T> Default for MsgPackCodec<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for MsgPackCodec<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        MsgPackCodec {
+            max_frame_length: self.max_frame_length,
+            min_len: self.min_len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for MsgPackCodec<T> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("MsgPackCodec")
+            .field("max_frame_length", &self.max_frame_length)
+            .finish()
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for MsgPackCodec<T> {
+    type Item = T;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, io::Error> {
+        if src.len() < self.min_len {
+            return Ok(None);
+        }
+
+        match frame::decode(src)? {
+            Decoded::Value(val) => {
+                self.min_len = 0;
+                Ok(Some(val))
+            }
+            Decoded::Incomplete(needed) => {
+                self.min_len = src.len().saturating_add(needed);
+                if self.min_len > self.max_frame_length {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "frame length limit exceeded"));
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<T, I: Serialize> Encoder<I> for MsgPackCodec<T> {
+    type Error = io::Error;
+
+    #[inline]
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), io::Error> {
+        frame::encode(&item, dst)
+    }
+}
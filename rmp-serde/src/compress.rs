@@ -0,0 +1,249 @@
+//! MessagePack compressed with zstd or LZ4.
+//!
+//! [`to_vec_compressed`] and [`from_slice_compressed`] write and read a value as a single
+//! compressed frame, and [`CompressWriter`] and [`DecompressReader`] do the same for any writer and
+//! reader, e.g. to use [`Serializer`](crate::Serializer) options. The compression format is found
+//! from the magic number of the frame when reading, so data compressed with either format can be
+//! read by the same code.
+//!
+//! Decompression stops with an error once more data than the given limit was decompressed, so
+//! small, maliciously compressed inputs can't be used to exhaust memory.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! use rmp_serde::compress::{self, Compression};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Log {
+//!     lines: Vec<String>,
+//! }
+//!
+//! let log = Log { lines: vec!["GET /index.html 200".into(); 1000] };
+//! # #[cfg(feature = "zstd")]
+//! let buf = compress::to_vec_compressed(&log, Compression::Zstd(3)).unwrap();
+//! # #[cfg(not(feature = "zstd"))]
+//! # let buf = compress::to_vec_compressed(&log, Compression::Lz4).unwrap();
+//! assert!(buf.len() < 1000);
+//!
+//! assert_eq!(log, compress::from_slice_compressed(&buf, 1 << 20).unwrap());
+//! assert!(compress::from_slice_compressed::<Log>(&buf, 1 << 10).is_err());
+//! # }
+//! ```
+
+use std::io::{self, Chain, Cursor, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::decode;
+use crate::encode;
+
+/// The magic number starting a zstd frame, in little-endian order.
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The magic number starting an LZ4 frame, in little-endian order.
+#[cfg(feature = "lz4")]
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+
+/// A compression format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// A zstd frame, compressed at the given level, from 1 to 22.
+    ///
+    /// Level 0 selects the default level of zstd, which is 3.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+    /// An LZ4 frame.
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+/// A writer compressing everything written to it into a single frame.
+///
+/// The frame must be completed with [`Self::finish`], dropping the writer loses the data that is
+/// still buffered.
+pub struct CompressWriter<W: Write> {
+    inner: Encoder<W>,
+}
+
+enum Encoder<W: Write> {
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    #[cfg(feature = "lz4")]
+    Lz4(lz4_flex::frame::FrameEncoder<W>),
+}
+
+impl<W: Write> CompressWriter<W> {
+    /// Starts a frame of the given format, written to `wr`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the compression level isn't supported.
+    pub fn new(wr: W, compression: Compression) -> io::Result<Self> {
+        let inner = match compression {
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => Encoder::Zstd(zstd::stream::write::Encoder::new(wr, level)?),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Encoder::Lz4(lz4_flex::frame::FrameEncoder::new(wr)),
+        };
+        Ok(CompressWriter { inner })
+    }
+
+    /// Completes the frame, and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing the rest of the frame fails.
+    pub fn finish(self) -> io::Result<W> {
+        match self.inner {
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(enc) => enc.finish(),
+            #[cfg(feature = "lz4")]
+            Encoder::Lz4(enc) => enc.finish().map_err(io::Error::from),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressWriter<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.inner {
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref mut enc) => enc.write(buf),
+            #[cfg(feature = "lz4")]
+            Encoder::Lz4(ref mut enc) => enc.write(buf),
+        }
+    }
+
+    /// Flushes the data compressed so far, which makes the compression worse.
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner {
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref mut enc) => enc.flush(),
+            #[cfg(feature = "lz4")]
+            Encoder::Lz4(ref mut enc) => enc.flush(),
+        }
+    }
+}
+
+/// A reader decompressing a zstd or LZ4 frame, and failing once it's larger than a limit.
+pub struct DecompressReader<R: Read> {
+    inner: Decoder<Chain<Cursor<[u8; 4]>, R>>,
+    /// The number of bytes that may still be decompressed.
+    left: u64,
+}
+
+enum Decoder<R: Read> {
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::read::Decoder<'static, io::BufReader<R>>),
+    #[cfg(feature = "lz4")]
+    Lz4(lz4_flex::frame::FrameDecoder<R>),
+}
+
+impl<R: Read> DecompressReader<R> {
+    /// Reads the magic number of the frame at the start of `rd`, and prepares to decompress at
+    /// most `max_size` bytes from it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the frame is neither zstd nor LZ4, or if its
+    /// format isn't enabled.
+    pub fn new(mut rd: R, max_size: u64) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        rd.read_exact(&mut magic)?;
+        let rd = Cursor::new(magic).chain(rd);
+        let inner = match magic {
+            #[cfg(feature = "zstd")]
+            ZSTD_MAGIC => Decoder::Zstd(zstd::stream::read::Decoder::new(rd)?),
+            #[cfg(feature = "lz4")]
+            LZ4_MAGIC => Decoder::Lz4(lz4_flex::frame::FrameDecoder::new(rd)),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown compression format")),
+        };
+        Ok(DecompressReader { inner, left: max_size })
+    }
+}
+
+impl<R: Read> Read for DecompressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // Read one byte past the limit, to tell a frame of exactly the limit from a larger one.
+        let len = buf.len().min(self.left.saturating_add(1).try_into().unwrap_or(usize::MAX));
+        let read = match self.inner {
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(ref mut dec) => dec.read(&mut buf[..len])?,
+            #[cfg(feature = "lz4")]
+            Decoder::Lz4(ref mut dec) => dec.read(&mut buf[..len])?,
+        };
+        if read as u64 > self.left {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "decompressed data exceeds the size limit"));
+        }
+        self.left -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Serializes the value into a compressed frame, with structs written as arrays like
+/// [`to_vec`](crate::to_vec).
+///
+/// # Errors
+///
+/// Fails like [`to_vec`](crate::to_vec), or if the compression level isn't supported.
+pub fn to_vec_compressed<T>(val: &T, compression: Compression) -> Result<Vec<u8>, encode::Error<io::Error>>
+where
+    T: Serialize + ?Sized
+{
+    write_compressed(Vec::with_capacity(128), val, compression)
+}
+
+/// Serializes the value into a compressed frame written to `wr`, and returns the writer.
+///
+/// # Errors
+///
+/// Fails like [`to_vec_compressed`], or if writing fails.
+pub fn write_compressed<W, T>(wr: W, val: &T, compression: Compression) -> Result<W, encode::Error<io::Error>>
+where
+    W: Write,
+    T: Serialize + ?Sized
+{
+    let mut wr = CompressWriter::new(wr, compression).map_err(encode_err)?;
+    encode::write(&mut wr, val)?;
+    wr.finish().map_err(encode_err)
+}
+
+#[cold]
+fn encode_err(err: io::Error) -> encode::Error<io::Error> {
+    encode::Error::InvalidValueWrite(rmp::encode::ValueWriteError::InvalidDataWrite(err))
+}
+
+/// Deserializes an instance of type `T` from a zstd or LZ4 frame, which may decompress to at most
+/// `max_size` bytes.
+///
+/// The value is deserialized as the frame is decompressed, without an intermediate buffer, so `T`
+/// can't borrow from it.
+///
+/// # Errors
+///
+/// Fails like [`DecompressReader::new`], if the data is larger than `max_size` or the frame is
+/// corrupt, which are reported as failed reads, or like [`from_read`](crate::from_read).
+#[inline]
+pub fn from_slice_compressed<T: DeserializeOwned>(buf: &[u8], max_size: u64) -> Result<T, decode::Error<io::Error>> {
+    read_compressed(buf, max_size)
+}
+
+/// Deserializes an instance of type `T` from a zstd or LZ4 frame read from `rd`, like
+/// [`from_slice_compressed`].
+///
+/// # Errors
+///
+/// Fails like [`from_slice_compressed`].
+pub fn read_compressed<R: Read, T: DeserializeOwned>(rd: R, max_size: u64) -> Result<T, decode::Error<io::Error>> {
+    let rd = DecompressReader::new(rd, max_size)
+        .map_err(|err| decode::Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidMarkerRead(err)))?;
+    decode::from_read(rd)
+}
@@ -1,5 +1,5 @@
 //! Change MessagePack behavior with configuration wrappers.
-use rmp::encode::{self, RmpWrite};
+use rmp::encode::RmpWrite;
 use serde::{Serialize, Serializer};
 
 use crate::encode::{Error, UnderlyingWrite};
@@ -49,9 +49,108 @@ mod sealed {
         /// Determines the value of `Serializer::is_human_readable` and
         /// `Deserializer::is_human_readable`.
         fn is_human_readable() -> bool;
+
+        /// Whether structs are written as maps with field names.
+        fn is_struct_map() -> bool;
     }
 }
 
+/// Options that don't affect the type of the serializer or deserializer.
+///
+/// They are set with the `with_*` methods of [`Serializer`](crate::Serializer) and
+/// [`Deserializer`](crate::Deserializer), and inherited by the serializers used internally.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct RuntimeConfig {
+    /// Write and read `Option` as an array of zero or one elements.
+    pub option_as_array: bool,
+    /// Handle struct fields missing from the end of an array like missing keys of a map.
+    pub trailing_defaults: bool,
+    /// Reject structs encoded as maps with unknown keys.
+    pub strict_structs: bool,
+    /// Match integer keys of structs encoded as maps against the hashes of the field names.
+    pub hashed_fields: bool,
+    /// Accept integer map keys where a string is expected, formatting them in decimal.
+    pub int_keys_as_str: bool,
+    /// Accept numbers where a string is expected, if the format is human-readable.
+    pub numbers_as_str: bool,
+    /// Reject numbers whose encoded type doesn't match the requested one.
+    pub strict_numbers: bool,
+    /// Convert integers that don't fit the requested type instead of failing.
+    pub int_overflow: Option<IntOverflow>,
+    /// Reject doubles read as `f32` unless they are exactly representable.
+    pub exact_f32: bool,
+    /// Write floats with an integral value as integers.
+    pub floats_as_ints: bool,
+    /// Reject NaN and infinite floats, both when writing and reading.
+    pub finite_floats: bool,
+    /// Write all NaNs with the same bit pattern.
+    pub canonical_nan: bool,
+    /// How to write 128-bit integers.
+    pub int128_repr: Int128Repr,
+    /// Follow the MessagePack spec before 2013: write raw data as str without Str8, and read str
+    /// data as bytes where bytes are expected.
+    pub old_spec: bool,
+    /// Write strings of 32 to 255 bytes as Str16 instead of Str8.
+    pub no_str8: bool,
+    /// Write integers with the marker matching their Rust type instead of the smallest one.
+    pub fixed_width_ints: bool,
+    /// Write str, bin, array and map headers with a 32-bit length.
+    pub widest_headers: bool,
+    /// Accept arrays of integers where bytes are expected.
+    #[cfg(feature = "std")]
+    pub int_arrays_as_bytes: bool,
+    /// Omit struct fields whose value is `None` when writing structs as maps.
+    #[cfg(feature = "std")]
+    pub skip_none: bool,
+    /// Omit struct fields for which this returns `true`, given the field name and its encoded
+    /// value, when writing structs as maps.
+    #[cfg(feature = "std")]
+    pub skip_field: Option<fn(&'static str, &[u8]) -> bool>,
+    /// Write the structs with the given name as maps with field names if this returns `true`, or
+    /// as arrays if it returns `false`, instead of following the config.
+    #[cfg(feature = "std")]
+    pub struct_map_if: Option<fn(&'static str) -> bool>,
+    /// Emit a span for each array and map read, and an event for errors.
+    #[cfg(feature = "tracing")]
+    pub trace: bool,
+}
+
+#[cfg(feature = "std")]
+impl RuntimeConfig {
+    /// Returns `true` if struct fields may be omitted when writing structs as maps.
+    #[inline]
+    pub fn skips_fields(&self) -> bool {
+        self.skip_none || self.skip_field.is_some()
+    }
+}
+
+/// How 128-bit integers are written, see
+/// [`Serializer::with_int128_repr`](crate::Serializer::with_int128_repr).
+///
+/// MessagePack has no 128-bit integers. All of these representations are accepted when
+/// deserializing `i128` and `u128`, as well as plain integers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Int128Repr {
+    /// A 16-byte bin holding the big-endian two's complement value.
+    ///
+    /// This is specific to this crate.
+    #[default]
+    Bin,
+    /// A 16-byte ext with the given tag, holding the big-endian two's complement value.
+    Ext(i8),
+    /// A str holding the value in decimal.
+    String,
+}
+
+/// How integers that don't fit the requested type are converted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum IntOverflow {
+    /// Clamp to the nearest value of the type.
+    Saturate,
+    /// Keep the low bits, like an `as` cast.
+    Wrap,
+}
+
 /// The default serializer/deserializer configuration.
 ///
 /// This configuration:
@@ -70,7 +169,8 @@ impl sealed::SerializerConfig for DefaultConfig {
         S: UnderlyingWrite,
         for<'a> &'a mut S: Serializer<Ok = (), Error = Error<<S::Write as RmpWrite>::Error>>,
     {
-        encode::write_array_len(ser.get_mut(), len as u32)?;
+        // Goes through the serializer, so that its options pick the header.
+        ser.serialize_tuple(len)?;
 
         Ok(())
     }
@@ -102,6 +202,11 @@ impl sealed::SerializerConfig for DefaultConfig {
     fn is_human_readable() -> bool {
         false
     }
+
+    #[inline(always)]
+    fn is_struct_map() -> bool {
+        false
+    }
 }
 
 /// Config wrapper, that overrides struct serialization by packing as a map with field names.
@@ -131,7 +236,90 @@ where
         S: UnderlyingWrite,
         for<'a> &'a mut S: Serializer<Ok = (), Error = Error<<S::Write as RmpWrite>::Error>>,
     {
-        encode::write_map_len(ser.get_mut(), len as u32)?;
+        // Goes through the serializer, so that its options pick the header.
+        ser.serialize_map(Some(len))?;
+
+        Ok(())
+    }
+
+    fn write_struct_field<S, T>(ser: &mut S, key: &'static str, value: &T) -> Result<(), Error<<S::Write as RmpWrite>::Error>>
+    where
+        S: UnderlyingWrite,
+        for<'a> &'a mut S: Serializer<Ok = (), Error = Error<<S::Write as RmpWrite>::Error>>,
+        T: ?Sized + Serialize,
+    {
+        ser.serialize_str(key)?;
+        value.serialize(ser)
+    }
+
+    #[inline]
+    fn write_variant_ident<S>(
+        ser: &mut S,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error<<S::Write as RmpWrite>::Error>>
+    where
+        S: UnderlyingWrite,
+        for<'a> &'a mut S: Serializer<Ok = (), Error = Error<<S::Write as RmpWrite>::Error>>,
+    {
+        C::write_variant_ident(ser, variant_index, variant)
+    }
+
+    #[inline(always)]
+    fn is_human_readable() -> bool {
+        C::is_human_readable()
+    }
+
+    #[inline(always)]
+    fn is_struct_map() -> bool {
+        true
+    }
+}
+
+/// Returns the 32-bit FNV-1a hash of a field name, the key written by [`StructHashConfig`].
+///
+/// The hash is stable across versions and platforms, so other implementations can compute it.
+#[inline]
+pub const fn field_hash(name: &str) -> u32 {
+    let bytes = name.as_bytes();
+    let mut hash = 0x811c_9dc5_u32;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+/// Config wrapper that overrides struct serialization by packing as a map keyed by the
+/// [`field_hash`] of each field name.
+///
+/// Like with [`StructMapConfig`], fields can be added, removed and reordered, but each key takes
+/// at most 5 bytes whatever the length of the name. Read it back with
+/// [`Deserializer::with_hashed_fields`](crate::Deserializer::with_hashed_fields).
+#[derive(Copy, Clone, Debug)]
+pub struct StructHashConfig<C>(C);
+
+impl<C> StructHashConfig<C> {
+    /// Creates a `StructHashConfig` inheriting unchanged configuration options from the given configuration.
+    #[inline]
+    pub fn new(inner: C) -> Self {
+        StructHashConfig(inner)
+    }
+}
+
+impl<C> sealed::SerializerConfig for StructHashConfig<C>
+where
+    C: sealed::SerializerConfig,
+{
+    fn write_struct_len<S>(ser: &mut S, len: usize) -> Result<(), Error<<S::Write as RmpWrite>::Error>>
+    where
+        S: UnderlyingWrite,
+        for<'a> &'a mut S: Serializer<Ok = (), Error = Error<<S::Write as RmpWrite>::Error>>,
+    {
+        // Goes through the serializer, so that its options pick the header.
+        ser.serialize_map(Some(len))?;
 
         Ok(())
     }
@@ -142,7 +330,7 @@ where
         for<'a> &'a mut S: Serializer<Ok = (), Error = Error<<S::Write as RmpWrite>::Error>>,
         T: ?Sized + Serialize,
     {
-        encode::write_str(ser.get_mut(), key)?;
+        ser.serialize_u32(field_hash(key))?;
         value.serialize(ser)
     }
 
@@ -163,6 +351,11 @@ where
     fn is_human_readable() -> bool {
         C::is_human_readable()
     }
+
+    #[inline(always)]
+    fn is_struct_map() -> bool {
+        true
+    }
 }
 
 /// Config wrapper that overrides struct serlization by packing as a tuple without field
@@ -187,7 +380,8 @@ where
         S: UnderlyingWrite,
         for<'a> &'a mut S: Serializer<Ok = (), Error = Error<<S::Write as RmpWrite>::Error>>,
     {
-        encode::write_array_len(ser.get_mut(), len as u32)?;
+        // Goes through the serializer, so that its options pick the header.
+        ser.serialize_tuple(len)?;
 
         Ok(())
     }
@@ -219,6 +413,11 @@ where
     fn is_human_readable() -> bool {
         C::is_human_readable()
     }
+
+    #[inline(always)]
+    fn is_struct_map() -> bool {
+        false
+    }
 }
 
 /// Config wrapper that overrides `Serializer::is_human_readable` and
@@ -274,6 +473,11 @@ where
     fn is_human_readable() -> bool {
         true
     }
+
+    #[inline(always)]
+    fn is_struct_map() -> bool {
+        C::is_struct_map()
+    }
 }
 
 /// Config wrapper that overrides `Serializer::is_human_readable` and
@@ -329,4 +533,9 @@ where
     fn is_human_readable() -> bool {
         false
     }
+
+    #[inline(always)]
+    fn is_struct_map() -> bool {
+        C::is_struct_map()
+    }
 }
@@ -0,0 +1,155 @@
+//! Exact decimal numbers, backed by [`rust_decimal`](https://docs.rs/rust_decimal) or
+//! [`bigdecimal`](https://docs.rs/bigdecimal).
+//!
+//! MessagePack has no decimal type, and going through a float loses precision. The helpers in
+//! this module write decimals as their canonical string, either as a plain str ([`string`]) or as
+//! the data of an ext with type [`DECIMAL_EXT_TAG`] ([`ext`]), which tells decimals apart from
+//! other strings. Both accept either form when decoding, as well as integers.
+//!
+//! They work with [`Decimal`] if the `rust_decimal` feature is enabled, and with [`BigDecimal`]
+//! if the `bigdecimal` feature is.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! # #[cfg(feature = "rust_decimal")] {
+//! use rmp_serde::decimal::Decimal;
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Price {
+//!     #[serde(with = "rmp_serde::decimal::ext")]
+//!     amount: Decimal,
+//!     #[serde(with = "rmp_serde::decimal::string")]
+//!     rate: Decimal,
+//! }
+//!
+//! let price = Price { amount: "19.99".parse().unwrap(), rate: "0.075".parse().unwrap() };
+//! let buf = rmp_serde::to_vec(&price).unwrap();
+//! assert_eq!([0x92, 0xc7, 0x05, 0x01], buf[..4]);
+//! assert_eq!(price, rmp_serde::from_slice(&buf).unwrap());
+//! # }
+//! # }
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+use core::marker::PhantomData;
+use core::str::FromStr;
+
+use serde::de::{self, Deserializer, Visitor};
+
+use crate::Ext;
+
+#[cfg(feature = "bigdecimal")]
+pub use bigdecimal::BigDecimal;
+#[cfg(feature = "rust_decimal")]
+pub use rust_decimal::Decimal;
+
+/// Ext type used by [`ext`].
+pub const DECIMAL_EXT_TAG: i8 = 1;
+
+/// Decimal types supported by the helpers in this module.
+pub trait DecimalType: Display + FromStr + private::Sealed {}
+
+#[cfg(feature = "rust_decimal")]
+impl DecimalType for Decimal {}
+#[cfg(feature = "rust_decimal")]
+impl private::Sealed for Decimal {}
+
+#[cfg(feature = "bigdecimal")]
+impl DecimalType for BigDecimal {}
+#[cfg(feature = "bigdecimal")]
+impl private::Sealed for BigDecimal {}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Serializes decimals as an ext with type [`DECIMAL_EXT_TAG`], holding the decimal string.
+///
+/// Use with `#[serde(with = "rmp_serde::decimal::ext")]`.
+pub mod ext {
+    use serde::{Deserializer, Serialize, Serializer};
+
+    use super::{DecimalType, DECIMAL_EXT_TAG};
+    use crate::ExtRef;
+
+    /// Serializes the decimal as an ext.
+    #[inline]
+    pub fn serialize<T: DecimalType, S: Serializer>(val: &T, se: S) -> Result<S::Ok, S::Error> {
+        ExtRef::new(DECIMAL_EXT_TAG, val.to_string().as_bytes()).serialize(se)
+    }
+
+    /// Deserializes a decimal ext, a decimal str or an integer.
+    #[inline]
+    pub fn deserialize<'de, T: DecimalType, D: Deserializer<'de>>(de: D) -> Result<T, D::Error> {
+        super::deserialize(de)
+    }
+}
+
+/// Serializes decimals as a str.
+///
+/// Use with `#[serde(with = "rmp_serde::decimal::string")]`.
+pub mod string {
+    use serde::{Deserializer, Serializer};
+
+    use super::DecimalType;
+
+    /// Serializes the decimal as a str.
+    #[inline]
+    pub fn serialize<T: DecimalType, S: Serializer>(val: &T, se: S) -> Result<S::Ok, S::Error> {
+        se.collect_str(val)
+    }
+
+    /// Deserializes a decimal str, a decimal ext or an integer.
+    #[inline]
+    pub fn deserialize<'de, T: DecimalType, D: Deserializer<'de>>(de: D) -> Result<T, D::Error> {
+        super::deserialize(de)
+    }
+}
+
+fn deserialize<'de, T: DecimalType, D: Deserializer<'de>>(de: D) -> Result<T, D::Error> {
+    de.deserialize_any(DecimalVisitor(PhantomData))
+}
+
+struct DecimalVisitor<T>(PhantomData<T>);
+
+impl<T: DecimalType> DecimalVisitor<T> {
+    fn parse<E: de::Error>(&self, v: &str) -> Result<T, E> {
+        v.parse().map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), self))
+    }
+}
+
+impl<'de, T: DecimalType> Visitor<'de> for DecimalVisitor<T> {
+    type Value = T;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a decimal ext, a decimal string or an integer")
+    }
+
+    #[inline]
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        self.parse(&v.to_string())
+    }
+
+    #[inline]
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        self.parse(&v.to_string())
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        self.parse(v)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, de: D) -> Result<T, D::Error> {
+        let ext = Ext::from_newtype(de)?;
+        if ext.tag() != DECIMAL_EXT_TAG {
+            return Err(de::Error::invalid_value(de::Unexpected::Signed(ext.tag().into()), &self));
+        }
+        match core::str::from_utf8(ext.data()) {
+            Ok(v) => self.parse(v),
+            Err(..) => Err(de::Error::invalid_value(de::Unexpected::Bytes(ext.data()), &self)),
+        }
+    }
+}
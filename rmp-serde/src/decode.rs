@@ -1,26 +1,32 @@
 //! Generic MessagePack deserialization.
 
 use core::convert::TryInto;
-use core::fmt::{self, Display, Formatter, Debug};
+use core::fmt::{self, Display, Formatter, Debug, Write};
 use core::num::TryFromIntError;
 use core::str::{self, Utf8Error};
 
 #[cfg(feature = "std")]
 use std::{
     error,
-    io::Cursor,
+    io::{self, BufRead},
 };
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::{String, ToString};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
 
 use serde::forward_to_deserialize_any;
 use serde::de::{self, Deserialize, DeserializeSeed, Unexpected, Visitor};
-#[cfg(feature = "std")]
+#[cfg(feature = "alloc")]
 use serde::de::DeserializeOwned;
 
 use rmp;
 use rmp::decode::{self, RmpRead, DecodeStringError, MarkerReadError, NumValueReadError, ValueReadError, RmpReadErr};
 use rmp::Marker;
 
-use crate::config::{BinaryConfig, DefaultConfig, HumanReadableConfig, SerializerConfig};
+use crate::config::{BinaryConfig, DefaultConfig, HumanReadableConfig, IntOverflow, RuntimeConfig, SerializerConfig};
 use crate::MSGPACK_EXT_STRUCT_NAME;
 
 /// Enum representing errors that can occur while decoding MessagePack data.
@@ -28,8 +34,9 @@ use crate::MSGPACK_EXT_STRUCT_NAME;
 pub enum Error<R> {
     /// Failed to read a MessagePack value.
     InvalidValueRead(ValueReadError<R>),
-    /// A mismatch occurred between the decoded and expected value types.
-    TypeMismatch(Marker),
+    /// A mismatch occurred between the decoded and expected value types, detected either by the
+    /// deserializer or by the visitor.
+    TypeMismatch(TypeMismatch),
     /// A numeric cast failed due to an out-of-range error.
     OutOfRange,
     /// A decoded array did not have the enclosed expected length.
@@ -39,16 +46,66 @@ pub enum Error<R> {
     Uncategorized(&'static str),
     /// A general error occurred while deserializing the expected type. See the
     /// enclosed string for details.
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     Syntax(String),
-    #[cfg(not(feature = "std"))]
-    Syntax(),
+    #[cfg(not(feature = "alloc"))]
+    Syntax(SyntaxError),
     /// An encoded string could not be parsed as UTF-8.
     Utf8Error(Utf8Error),
     /// The depth limit was exceeded.
     DepthLimitExceeded,
 }
 
+#[cfg(any(feature = "std", feature = "embedded-io"))]
+impl<R> Error<R> {
+    /// Converts the error of the reader with `f`, keeping the other errors as they are.
+    pub(crate) fn map_read_err<S>(self, f: impl FnOnce(R) -> S) -> Error<S> {
+        match self {
+            Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(err)) => {
+                Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(f(err)))
+            }
+            Error::InvalidValueRead(ValueReadError::InvalidDataRead(err)) => {
+                Error::InvalidValueRead(ValueReadError::InvalidDataRead(f(err)))
+            }
+            Error::InvalidValueRead(ValueReadError::TypeMismatch(marker)) => {
+                Error::InvalidValueRead(ValueReadError::TypeMismatch(marker))
+            }
+            Error::TypeMismatch(err) => Error::TypeMismatch(err),
+            Error::OutOfRange => Error::OutOfRange,
+            Error::LengthMismatch(len) => Error::LengthMismatch(len),
+            Error::Uncategorized(msg) => Error::Uncategorized(msg),
+            Error::Syntax(msg) => Error::Syntax(msg),
+            Error::Utf8Error(err) => Error::Utf8Error(err),
+            Error::DepthLimitExceeded => Error::DepthLimitExceeded,
+        }
+    }
+}
+
+impl<R> Error<R> {
+    /// A mismatch with the given marker, without a position.
+    #[cold]
+    pub(crate) fn type_mismatch(marker: Marker, expected: &'static str) -> Self {
+        Error::TypeMismatch(TypeMismatch::new(Found::Marker(marker), describe_static(expected)))
+    }
+
+    /// Attributes a mismatch reported by a visitor to the last marker read, and fills in the
+    /// position of a mismatch with that marker.
+    #[cold]
+    fn locate(mut self, last_marker: Option<(Marker, Option<u64>)>) -> Self {
+        if let (Error::TypeMismatch(TypeMismatch(ref mut err)), Some((marker, position))) = (&mut self, last_marker) {
+            if err.position.is_none() {
+                match err.found {
+                    Found::Unexpected(..) => err.found = Found::Marker(marker),
+                    Found::Marker(found) if found == marker => {}
+                    Found::Marker(..) => return self,
+                }
+                err.position = position;
+            }
+        }
+        self
+    }
+}
+
 macro_rules! depth_count(
     ( $counter:expr, $expr:expr ) => {
         {
@@ -82,12 +139,299 @@ impl<R: RmpReadErr> error::Error for Error<R> {
 
 impl<R: RmpReadErr> de::Error for Error<R> {
     #[cold]
-    fn custom<T: Display>(_msg: T) -> Self {
-        #[cfg(feature = "std")]
-        return Error::Syntax(_msg.to_string());
+    fn custom<T: Display>(msg: T) -> Self {
+        #[cfg(feature = "alloc")]
+        return Error::Syntax(msg.to_string());
+
+        #[cfg(not(feature = "alloc"))]
+        return Error::Syntax(SyntaxError::Custom(ErrorMessage::new(msg)));
+    }
+
+    #[cold]
+    fn invalid_type(unexp: Unexpected<'_>, exp: &dyn de::Expected) -> Self {
+        Error::TypeMismatch(TypeMismatch::new(Found::Unexpected(describe(unexp)), describe(exp)))
+    }
+
+    // Without an allocator, the common errors are kept structured rather than formatted into a
+    // truncated message.
+
+    #[cfg(not(feature = "alloc"))]
+    #[cold]
+    fn invalid_length(len: usize, _exp: &dyn de::Expected) -> Self {
+        Error::Syntax(SyntaxError::InvalidLength(len))
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[cold]
+    fn unknown_variant(variant: &str, _expected: &'static [&'static str]) -> Self {
+        Error::Syntax(SyntaxError::UnknownVariant(ErrorMessage::new(variant)))
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[cold]
+    fn unknown_field(field: &str, _expected: &'static [&'static str]) -> Self {
+        Error::Syntax(SyntaxError::UnknownField(ErrorMessage::new(field)))
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[cold]
+    fn missing_field(field: &'static str) -> Self {
+        Error::Syntax(SyntaxError::MissingField(field))
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[cold]
+    fn duplicate_field(field: &'static str) -> Self {
+        Error::Syntax(SyntaxError::DuplicateField(field))
+    }
+}
+
+/// The syntax errors of builds without the `alloc` feature, which can't allocate their message.
+#[cfg(not(feature = "alloc"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxError {
+    /// A struct has a field with this name, which it doesn't know.
+    UnknownField(ErrorMessage),
+    /// An enum has no variant with this name.
+    UnknownVariant(ErrorMessage),
+    /// A struct is missing this required field.
+    MissingField(&'static str),
+    /// A struct has this field more than once.
+    DuplicateField(&'static str),
+    /// A sequence or map has this number of elements, which the type doesn't accept.
+    InvalidLength(usize),
+    /// Any other error, such as an invalid type or value.
+    Custom(ErrorMessage),
+}
+
+#[cfg(not(feature = "alloc"))]
+impl Display for SyntaxError {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match *self {
+            SyntaxError::UnknownField(ref field) => write!(fmt, "unknown field `{}`", field),
+            SyntaxError::UnknownVariant(ref variant) => write!(fmt, "unknown variant `{}`", variant),
+            SyntaxError::MissingField(field) => write!(fmt, "missing field `{}`", field),
+            SyntaxError::DuplicateField(field) => write!(fmt, "duplicate field `{}`", field),
+            SyntaxError::InvalidLength(len) => write!(fmt, "invalid length {}", len),
+            SyntaxError::Custom(ref msg) => Display::fmt(msg, fmt),
+        }
+    }
+}
+
+#[cfg(all(feature = "defmt", not(feature = "alloc")))]
+impl defmt::Format for SyntaxError {
+    #[cold]
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match *self {
+            SyntaxError::UnknownField(ref field) => defmt::write!(fmt, "unknown field `{}`", field),
+            SyntaxError::UnknownVariant(ref variant) => defmt::write!(fmt, "unknown variant `{}`", variant),
+            SyntaxError::MissingField(field) => defmt::write!(fmt, "missing field `{=str}`", field),
+            SyntaxError::DuplicateField(field) => defmt::write!(fmt, "duplicate field `{=str}`", field),
+            SyntaxError::InvalidLength(len) => defmt::write!(fmt, "invalid length {=usize}", len),
+            SyntaxError::Custom(ref msg) => defmt::write!(fmt, "{}", msg),
+        }
+    }
+}
+
+/// An error message stored inline, truncated to [`ErrorMessage::CAPACITY`] bytes.
+///
+/// Used instead of a `String` by the errors of builds without the `alloc` feature.
+#[cfg(not(feature = "alloc"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ErrorMessage {
+    buf: [u8; ErrorMessage::CAPACITY],
+    len: usize,
+}
+
+#[cfg(not(feature = "alloc"))]
+impl ErrorMessage {
+    /// The maximum length of a message, in bytes.
+    pub const CAPACITY: usize = 64;
+
+    /// Formats the message, cutting it at the last char that fits.
+    #[cold]
+    pub(crate) fn new<T: Display>(msg: T) -> Self {
+        let mut buf = ErrorMessage { buf: [0; Self::CAPACITY], len: 0 };
+        let _ = write!(buf, "{}", msg);
+        buf
+    }
+
+    /// Returns the message.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // Only whole chars are written.
+        str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl Write for ErrorMessage {
+    /// Fails once a fragment doesn't fit, so that shorter fragments after it aren't appended to
+    /// the truncated message.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(Self::CAPACITY - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        if len < s.len() {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl Debug for ErrorMessage {
+    #[inline]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), fmt)
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl Display for ErrorMessage {
+    #[inline]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+#[cfg(all(feature = "defmt", not(feature = "alloc")))]
+impl defmt::Format for ErrorMessage {
+    #[inline]
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        defmt::write!(fmt, "{=str}", self.as_str())
+    }
+}
+
+/// The details of an [`Error::TypeMismatch`]: what was found, what was expected, and where.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeMismatch(Boxed<Mismatch>);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Mismatch {
+    found: Found,
+    expected: Description,
+    position: Option<u64>,
+}
+
+// Boxed when possible, so the mismatch doesn't make every `Result` larger.
+#[cfg(feature = "alloc")]
+type Boxed<T> = alloc::boxed::Box<T>;
+#[cfg(not(feature = "alloc"))]
+type Boxed<T> = T;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Found {
+    Marker(Marker),
+    /// The description of a value rejected by a visitor, before it's attributed to a marker.
+    Unexpected(Description),
+}
+
+#[cfg(feature = "alloc")]
+type Description = Cow<'static, str>;
+#[cfg(not(feature = "alloc"))]
+type Description = ErrorMessage;
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn describe_static(desc: &'static str) -> Description {
+    Cow::Borrowed(desc)
+}
+
+#[cfg(not(feature = "alloc"))]
+#[inline]
+fn describe_static(desc: &'static str) -> Description {
+    ErrorMessage::new(desc)
+}
+
+#[cfg(feature = "alloc")]
+#[cold]
+fn describe<T: Display>(desc: T) -> Description {
+    Cow::Owned(desc.to_string())
+}
+
+#[cfg(not(feature = "alloc"))]
+#[cold]
+fn describe<T: Display>(desc: T) -> Description {
+    ErrorMessage::new(desc)
+}
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn description_str(desc: &Description) -> &str {
+    desc
+}
+
+#[cfg(not(feature = "alloc"))]
+#[inline]
+fn description_str(desc: &Description) -> &str {
+    desc.as_str()
+}
+
+impl TypeMismatch {
+    #[cold]
+    fn new(found: Found, expected: Description) -> Self {
+        let mismatch = Mismatch { found, expected, position: None };
+        #[cfg(feature = "alloc")]
+        return TypeMismatch(Boxed::new(mismatch));
+        #[cfg(not(feature = "alloc"))]
+        return TypeMismatch(mismatch);
+    }
+
+    /// Returns the marker that was found.
+    ///
+    /// This is `None` if a visitor rejected a value that didn't come straight from the input, e.g.
+    /// one buffered by serde for an untagged enum.
+    #[inline]
+    pub fn marker(&self) -> Option<Marker> {
+        match self.0.found {
+            Found::Marker(marker) => Some(marker),
+            Found::Unexpected(..) => None,
+        }
+    }
+
+    /// Returns a description of what was expected, e.g. `"u8"` or, from the visitor, `"a string"`.
+    #[inline]
+    pub fn expected(&self) -> &str {
+        description_str(&self.0.expected)
+    }
+
+    /// Returns the offset of the value in the input, if the reader keeps track of it.
+    #[inline(always)]
+    pub fn position(&self) -> Option<u64> {
+        self.0.position
+    }
+}
+
+impl Display for TypeMismatch {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        match self.0.found {
+            Found::Marker(marker) => write!(fmt, "wrong msgpack marker {:?}, expected {}", marker, self.expected())?,
+            Found::Unexpected(ref unexp) => write!(fmt, "invalid type: {}, expected {}", description_str(unexp), self.expected())?,
+        }
+        match self.0.position {
+            Some(position) => write!(fmt, " at offset {}", position),
+            None => Ok(()),
+        }
+    }
+}
 
-        #[cfg(not(feature = "std"))]
-        return Error::Syntax();
+#[cfg(feature = "defmt")]
+impl defmt::Format for TypeMismatch {
+    #[cold]
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match self.0.found {
+            Found::Marker(marker) => defmt::write!(fmt, "wrong msgpack marker {}, expected {=str}", marker, self.expected()),
+            Found::Unexpected(ref unexp) => defmt::write!(fmt, "invalid type: {=str}, expected {=str}", description_str(unexp), self.expected()),
+        }
+        if let Some(position) = self.0.position {
+            defmt::write!(fmt, " at offset {=u64}", position);
+        }
     }
 }
 
@@ -96,9 +440,7 @@ impl<R: RmpReadErr> Display for Error<R> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         match *self {
             Error::InvalidValueRead(ref err) => write!(fmt, "{err}"),
-            Error::TypeMismatch(ref actual_marker) => {
-                write!(fmt, "wrong msgpack marker {:?}", actual_marker)
-            }
+            Error::TypeMismatch(ref err) => Display::fmt(err, fmt),
             Error::OutOfRange => fmt.write_str("numeric cast found out of range"),
             Error::LengthMismatch(expected_length) => write!(
                 fmt,
@@ -106,9 +448,9 @@ impl<R: RmpReadErr> Display for Error<R> {
                 expected_length
             ),
             Error::Uncategorized(ref msg) => write!(fmt, "uncategorized error: {}", msg),
-            #[cfg(not(feature = "std"))]
-            Error::Syntax() => fmt.write_str("syntax error"),
-            #[cfg(feature = "std")]
+            #[cfg(not(feature = "alloc"))]
+            Error::Syntax(ref err) => Display::fmt(err, fmt),
+            #[cfg(feature = "alloc")]
             Error::Syntax(ref msg) => fmt.write_str(msg),
             Error::Utf8Error(ref err) => write!(fmt, "string found to be invalid utf8: {}", err),
             Error::DepthLimitExceeded => fmt.write_str("depth limit exceeded"),
@@ -116,6 +458,30 @@ impl<R: RmpReadErr> Display for Error<R> {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl<R: defmt::Format> defmt::Format for Error<R> {
+    #[cold]
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match *self {
+            Error::InvalidValueRead(ref err) => defmt::write!(fmt, "{}", err),
+            Error::TypeMismatch(ref err) => defmt::write!(fmt, "{}", err),
+            Error::OutOfRange => defmt::write!(fmt, "numeric cast found out of range"),
+            Error::LengthMismatch(expected_length) => {
+                defmt::write!(fmt, "array had incorrect length, expected {=u32}", expected_length)
+            }
+            Error::Uncategorized(msg) => defmt::write!(fmt, "uncategorized error: {=str}", msg),
+            #[cfg(not(feature = "alloc"))]
+            Error::Syntax(ref err) => defmt::write!(fmt, "{}", err),
+            #[cfg(feature = "alloc")]
+            Error::Syntax(ref msg) => defmt::write!(fmt, "{=str}", msg),
+            Error::Utf8Error(ref err) => {
+                defmt::write!(fmt, "string found to be invalid utf8 after {=usize} bytes", err.valid_up_to())
+            }
+            Error::DepthLimitExceeded => defmt::write!(fmt, "depth limit exceeded"),
+        }
+    }
+}
+
 impl<R> From<ValueReadError<R>> for Error<R> {
     #[cold]
     fn from(err: ValueReadError<R>) -> Self {
@@ -143,7 +509,7 @@ impl<R> From<NumValueReadError<R>> for Error<R> {
     #[cold]
     fn from(err: NumValueReadError<R>) -> Self {
         match err {
-            NumValueReadError::TypeMismatch(marker) => Error::TypeMismatch(marker),
+            NumValueReadError::TypeMismatch(marker) => Error::type_mismatch(marker, "a number"),
             NumValueReadError::InvalidMarkerRead(err) => Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(err)),
             NumValueReadError::InvalidDataRead(err) => Error::InvalidValueRead(ValueReadError::InvalidDataRead(err)),
             NumValueReadError::OutOfRange => Error::OutOfRange,
@@ -157,7 +523,7 @@ impl<'a, R> From<DecodeStringError<'a, R>> for Error<R> {
         match err {
             DecodeStringError::InvalidMarkerRead(err) => Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(err)),
             DecodeStringError::InvalidDataRead(err) => Error::InvalidValueRead(ValueReadError::InvalidDataRead(err)),
-            DecodeStringError::TypeMismatch(marker) => Error::TypeMismatch(marker),
+            DecodeStringError::TypeMismatch(marker) => Error::type_mismatch(marker, "a string"),
             DecodeStringError::BufferSizeTooSmall(..) => Error::Uncategorized("BufferSizeTooSmall"),
             DecodeStringError::InvalidUtf8(..) => Error::Uncategorized("InvalidUtf8"),
         }
@@ -171,6 +537,115 @@ impl<R> From<TryFromIntError> for Error<R> {
     }
 }
 
+/// A deserialization error that doesn't depend on the type of the reader.
+///
+/// [`Error`] is generic over the error type of the reader, so the errors of [`from_slice`] and
+/// [`from_read`] can't be returned from the same function without converting them. Any `Error<R>`
+/// converts into this type with `?`, and the reader error is kept in an [`AnyReadError`].
+///
+/// ```
+/// use rmp_serde::ErasedError;
+///
+/// fn parse(buf: &[u8], from_reader: bool) -> Result<u32, ErasedError> {
+///     if from_reader {
+///         Ok(rmp_serde::from_read(buf)?)
+///     } else {
+///         Ok(rmp_serde::from_slice(buf)?)
+///     }
+/// }
+///
+/// assert_eq!(42, parse(&[0x2a], true).unwrap());
+/// assert!(parse(&[0xcd], false).is_err());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ErasedError(Error<AnyReadError>);
+
+#[cfg(feature = "std")]
+impl ErasedError {
+    /// Gets a reference to the underlying error.
+    #[inline]
+    pub fn get_ref(&self) -> &Error<AnyReadError> {
+        &self.0
+    }
+
+    /// Consumes this error returning the underlying error.
+    #[inline]
+    pub fn into_inner(self) -> Error<AnyReadError> {
+        self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for ErasedError {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        Display::fmt(&self.0, fmt)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ErasedError {
+    #[cold]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.0 {
+            Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(ref err)) |
+            Error::InvalidValueRead(ValueReadError::InvalidDataRead(ref err)) => Some(err),
+            ref err => err.source(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: RmpReadErr + Send + Sync> From<Error<R>> for ErasedError {
+    #[cold]
+    fn from(err: Error<R>) -> Self {
+        ErasedError(err.map_read_err(|err| AnyReadError(Box::new(err))))
+    }
+}
+
+/// The error of any reader, held by an [`ErasedError`].
+///
+/// The original error can be recovered with [`AnyReadError::downcast_ref`], e.g. as an
+/// [`io::Error`] or a [`BytesReadError`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct AnyReadError(Box<dyn error::Error + Send + Sync>);
+
+#[cfg(feature = "std")]
+impl AnyReadError {
+    /// Returns the original error if it has the type `E`.
+    #[inline]
+    pub fn downcast_ref<E: error::Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+
+    /// Consumes this error returning the original error.
+    #[inline]
+    pub fn into_inner(self) -> Box<dyn error::Error + Send + Sync> {
+        self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for AnyReadError {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        Display::fmt(&self.0, fmt)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for AnyReadError {
+    #[cold]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+#[cfg(feature = "std")]
+impl RmpReadErr for AnyReadError {}
+
 /// A Deserializer that reads bytes from a buffer.
 ///
 /// # Note
@@ -181,29 +656,13 @@ impl<R> From<TryFromIntError> for Error<R> {
 pub struct Deserializer<R, C = DefaultConfig> {
     rd: R,
     config: C,
+    options: RuntimeConfig,
     marker: Option<Marker>,
+    /// The last marker read from the input and its offset, to locate type mismatches.
+    last_marker: Option<(Marker, Option<u64>)>,
     depth: usize,
 }
 
-impl<R: RmpRead, C> Deserializer<R, C> {
-    #[inline]
-    fn take_or_read_marker(&mut self) -> Result<Marker, MarkerReadError<R::Error>> {
-        self.marker
-            .take()
-            .map_or_else(|| rmp::decode::read_marker(&mut self.rd), Ok)
-    }
-
-    #[inline]
-    fn peek_or_read_marker(&mut self) -> Result<Marker, MarkerReadError<R::Error>> {
-        if let Some(m) = self.marker {
-            Ok(m)
-        } else {
-            let m = rmp::decode::read_marker(&mut self.rd)?;
-            Ok(*self.marker.insert(m))
-        }
-    }
-}
-
 #[cfg(feature = "std")]
 impl<R: RmpRead> Deserializer<ReadReader<R>, DefaultConfig> {
     /// Constructs a new `Deserializer` by consuming the given reader.
@@ -212,15 +671,45 @@ impl<R: RmpRead> Deserializer<ReadReader<R>, DefaultConfig> {
         Self {
             rd: ReadReader::new(rd),
             config: DefaultConfig,
+            options: RuntimeConfig::default(),
             // Cached marker in case of deserializing optional values.
             marker: None,
+            last_marker: None,
+            depth: 1024,
+        }
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl<R: RmpRead, A: allocator_api2::alloc::Allocator> Deserializer<ReadReader<R, allocator_api2::vec::Vec<u8, A>>, DefaultConfig> {
+    /// Constructs a new `Deserializer` by consuming the given reader, like [`Self::new`], that
+    /// allocates its scratch buffer for strings and bytes with `alloc`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use allocator_api2::alloc::Global;
+    /// use serde::Deserialize;
+    ///
+    /// let buf = rmp_serde::to_vec(&"hello").unwrap();
+    /// let mut de = rmp_serde::Deserializer::new_in(&buf[..], Global);
+    /// assert_eq!("hello", String::deserialize(&mut de).unwrap());
+    /// ```
+    #[inline]
+    pub fn new_in(rd: R, alloc: A) -> Self {
+        Self {
+            rd: ReadReader { rd, buf: allocator_api2::vec::Vec::with_capacity_in(128, alloc), position: 0 },
+            config: DefaultConfig,
+            options: RuntimeConfig::default(),
+            marker: None,
+            last_marker: None,
             depth: 1024,
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl<R: RmpRead, C> Deserializer<ReadReader<R>, C> {
+impl<R: RmpRead, B: ScratchBuf, C> Deserializer<ReadReader<R, B>, C> {
     /// Gets a reference to the underlying reader in this decoder.
     #[inline(always)]
     pub fn get_ref(&self) -> &R {
@@ -238,6 +727,12 @@ impl<R: RmpRead, C> Deserializer<ReadReader<R>, C> {
     pub fn into_inner(self) -> R {
         self.rd.rd
     }
+
+    /// Returns the current position of this deserializer, i.e. how many bytes were read.
+    #[inline(always)]
+    pub fn position(&self) -> u64 {
+        self.rd.position
+    }
 }
 
 impl<R: RmpRead, C: SerializerConfig> Deserializer<R, C> {
@@ -248,11 +743,13 @@ impl<R: RmpRead, C: SerializerConfig> Deserializer<R, C> {
     /// versions of `rmp-serde`.
     #[inline]
     pub fn with_human_readable(self) -> Deserializer<R, HumanReadableConfig<C>> {
-        let Deserializer { rd, config, marker, depth } = self;
+        let Deserializer { rd, config, options, marker, last_marker, depth } = self;
         Deserializer {
             rd,
             config: HumanReadableConfig::new(config),
+            options,
             marker,
+            last_marker,
             depth,
         }
     }
@@ -264,51 +761,516 @@ impl<R: RmpRead, C: SerializerConfig> Deserializer<R, C> {
     /// representation.
     #[inline]
     pub fn with_binary(self) -> Deserializer<R, BinaryConfig<C>> {
-        let Deserializer { rd, config, marker, depth } = self;
+        let Deserializer { rd, config, options, marker, last_marker, depth } = self;
         Deserializer {
             rd,
             config: BinaryConfig::new(config),
+            options,
             marker,
+            last_marker,
             depth,
         }
     }
-}
 
-#[cfg(feature = "std")]
-impl<R: AsRef<[u8]>> Deserializer<ReadReader<Cursor<R>>> {
-    /// Returns the current position of this deserializer, i.e. how many bytes were read.
-    #[inline(always)]
-    pub fn position(&self) -> u64 {
-        self.rd.rd.position()
+    /// Consumes this deserializer and returns a new one, which will deserialize `Option`s from an
+    /// array of zero (`None`) or one (`Some`) elements, as written by
+    /// [`Serializer::with_option_as_array`](crate::Serializer::with_option_as_array).
+    ///
+    /// Nil is accepted as `None` as well.
+    #[inline]
+    pub fn with_option_as_array(mut self) -> Self {
+        self.options.option_as_array = true;
+        self
     }
-}
 
-impl<'de> Deserializer<ReadRefReader<'de>> {
-    /// Constructs a new `Deserializer` from the given byte slice.
-    #[inline(always)]
-    pub fn from_bytes(rd: &'de [u8]) -> Self {
-        Deserializer {
-            rd: ReadRefReader::new(rd),
-            config: DefaultConfig,
-            marker: None,
-            depth: 1024,
-        }
+    /// Consumes this deserializer and returns a new one, which will accept structs encoded as
+    /// arrays with fewer elements than the struct has fields.
+    ///
+    /// The missing trailing fields are handled like missing keys of a struct encoded as a map:
+    /// `Option` fields are `None`, fields marked with `#[serde(default)]` get their default value,
+    /// and any other missing field is an error. This allows appending fields to structs while
+    /// staying compatible with data written by older versions.
+    #[inline]
+    pub fn with_trailing_defaults(mut self) -> Self {
+        self.options.trailing_defaults = true;
+        self
     }
-}
 
-impl<'de, R: ReadSlice<'de>, C: SerializerConfig> Deserializer<R, C> {
+    /// Consumes this deserializer and returns a new one, which will reject structs encoded as
+    /// maps with a string key that isn't the name of one of their fields.
+    ///
+    /// This has the same effect as `#[serde(deny_unknown_fields)]`, but for all structs at once,
+    /// including those of other crates. Structs encoded as arrays with more elements than fields
+    /// are always rejected.
+    #[inline]
+    pub fn with_strict_structs(mut self) -> Self {
+        self.options.strict_structs = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will read structs encoded as maps
+    /// keyed by the hash of their field names, as written by
+    /// [`Serializer::with_struct_hash`](crate::Serializer::with_struct_hash).
+    ///
+    /// Integer keys of such maps are matched against the [`field_hash`](crate::config::field_hash)
+    /// of each field, and passed to the struct as the field name. Keys that match no field are
+    /// passed as their hash in hexadecimal, e.g. `0x1234abcd`, so that they are ignored or
+    /// reported as unknown fields. String keys are still accepted.
+    #[inline]
+    pub fn with_hashed_fields(mut self) -> Self {
+        self.options.hashed_fields = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will accept integer map keys
+    /// where a string is expected, e.g. for `HashMap<String, V>`, formatting them in decimal.
+    ///
+    /// Some producers, like Lua and PHP, write integer keys for maps that are otherwise keyed by
+    /// strings. Keys deserialized as integers or as struct field identifiers are not affected.
+    #[inline]
+    pub fn with_int_keys_as_str(mut self) -> Self {
+        self.options.int_keys_as_str = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will accept integers and floats
+    /// where a string is expected, formatting them as Rust does.
+    ///
+    /// This only has an effect if the deserializer is human-readable (see
+    /// [`Self::with_human_readable`]), for types that represent themselves as decimal strings in
+    /// human-readable formats, but are given raw numbers by other producers.
+    #[inline]
+    pub fn with_numbers_as_str(mut self) -> Self {
+        self.options.numbers_as_str = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will reject numbers encoded with a
+    /// type that doesn't match the requested Rust type, even if the value is in range.
+    ///
+    /// Unsigned integers must be encoded as unsigned integers at most as wide as the requested
+    /// type, signed integers as signed or unsigned integers at most as wide, and floats with the
+    /// exact same width. For example, `u8` accepts neither an `U16` nor an `I8`, and `f64`
+    /// doesn't accept an `F32`. Everything written by [`Serializer`](crate::Serializer) for the
+    /// same types is accepted.
+    #[inline]
+    pub fn with_strict_numbers(mut self) -> Self {
+        self.options.strict_numbers = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will clamp integers that don't fit
+    /// the requested integer type to its nearest value, instead of failing.
+    ///
+    /// For example, `300` is read as `255` into an `u8`, and `-1` as `0`.
+    #[inline]
+    pub fn with_saturating_ints(mut self) -> Self {
+        self.options.int_overflow = Some(IntOverflow::Saturate);
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will keep the low bits of integers
+    /// that don't fit the requested integer type, instead of failing.
+    ///
+    /// This is the same conversion as an `as` cast, e.g. `300` is read as `44` into an `u8`, and
+    /// `-1` as `255`.
+    #[inline]
+    pub fn with_wrapping_ints(mut self) -> Self {
+        self.options.int_overflow = Some(IntOverflow::Wrap);
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will only read doubles as `f32` if
+    /// they are exactly representable, e.g. `0.5` or NaN, but not `0.1`.
+    ///
+    /// By default, doubles are rounded to the nearest `f32`, as many producers always write
+    /// doubles.
+    #[inline]
+    pub fn with_exact_f32(mut self) -> Self {
+        self.options.exact_f32 = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will reject NaN and infinite
+    /// floats.
+    #[inline]
+    pub fn with_finite_floats(mut self) -> Self {
+        self.options.finite_floats = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will accept arrays of integers
+    /// where bytes are expected, e.g. for `serde_bytes` or [`crate::ByteBuf`].
+    ///
+    /// Some encoders write byte buffers as arrays, e.g. generic serde code or converted JavaScript
+    /// arrays. Each element must fit in a `u8`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn with_int_arrays_as_bytes(mut self) -> Self {
+        self.options.int_arrays_as_bytes = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will pass str data as is where
+    /// bytes are expected, e.g. for `serde_bytes` or [`crate::ByteBuf`].
+    ///
+    /// Before 2013, MessagePack had a single raw type for both strings and binary data, which is
+    /// read as a str now. By default, str data that is valid UTF-8 is passed as a str, and as bytes
+    /// otherwise. With this option it's always passed as bytes when bytes are expected, so the
+    /// visitor sees the same type for all values. Strings are still read as before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// // ["name", <raw 0xff 0x00>] from an old encoder.
+    /// let buf = [0x92, 0xa4, b'n', b'a', b'm', b'e', 0xa2, 0xff, 0x00];
+    /// let mut de = rmp_serde::Deserializer::new(&buf[..]).with_old_spec();
+    /// let (name, data) = <(String, serde_bytes::ByteBuf)>::deserialize(&mut de).unwrap();
+    ///
+    /// assert_eq!("name", name);
+    /// assert_eq!(vec![0xff, 0x00], data.into_vec());
+    /// ```
+    #[inline]
+    pub fn with_old_spec(mut self) -> Self {
+        self.options.old_spec = true;
+        self
+    }
+
+    /// Consumes this deserializer and returns a new one, which will report its progress to
+    /// [`tracing`].
+    ///
+    /// Each array and map is read in a `msgpack` span at the `TRACE` level, with its `kind`, its
+    /// `len` and the `offset` of its first element. An error is reported as a `DEBUG` event with
+    /// the `offset` the reader stopped at, in the span of each container it propagates through, so
+    /// the events show the path to the value that failed. Offsets are left out for readers that
+    /// don't keep track of them.
+    ///
+    /// Nothing is recorded unless a subscriber is interested in these levels.
+    #[cfg(feature = "tracing")]
+    #[inline]
+    pub fn with_tracing(mut self) -> Self {
+        self.options.trace = true;
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Deserializer<BufReadReader<R>, DefaultConfig> {
+    /// Constructs a new `Deserializer` that reads directly from the buffer of the given
+    /// `BufRead`.
+    ///
+    /// Unlike [`Deserializer::new`], strings and byte arrays which fit into the reader's buffer
+    /// are passed to the visitor without an intermediate copy.
+    #[inline]
+    pub fn from_buf_read(rd: R) -> Self {
+        Self {
+            rd: BufReadReader::new(rd),
+            config: DefaultConfig,
+            options: RuntimeConfig::default(),
+            marker: None,
+            last_marker: None,
+            depth: 1024,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead, C> Deserializer<BufReadReader<R>, C> {
+    /// Gets a reference to the underlying reader in this decoder.
+    ///
+    /// The bytes of the last string or byte array borrowed from the reader's buffer are only
+    /// consumed on the next read, or when the reader is accessed through [`Self::get_mut`],
+    /// [`Self::into_inner`], or by dropping the deserializer.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &R {
+        self.rd.inner()
+    }
+
+    /// Gets a mutable reference to the underlying reader in this decoder.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        self.rd.consume_pending();
+        self.rd.inner_mut()
+    }
+
+    /// Consumes this deserializer returning the underlying reader.
+    #[inline]
+    pub fn into_inner(mut self) -> R {
+        self.rd.consume_pending();
+        self.rd.rd.take().expect(READER_TAKEN)
+    }
+
+    /// Returns the current position of this deserializer, i.e. how many bytes were read.
+    #[inline(always)]
+    pub fn position(&self) -> u64 {
+        self.rd.position
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::Buf> Deserializer<BytesReader<B>, DefaultConfig> {
+    /// Constructs a new `Deserializer` from the given [`bytes::Buf`], such as `Bytes` or
+    /// `BytesMut`, without first flattening it into a contiguous slice.
+    #[inline]
+    pub fn from_buf(rd: B) -> Self {
+        Self {
+            rd: BytesReader::new(rd),
+            config: DefaultConfig,
+            options: RuntimeConfig::default(),
+            marker: None,
+            last_marker: None,
+            depth: 1024,
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::Buf, C> Deserializer<BytesReader<B>, C> {
+    /// Gets a reference to the underlying buffer in this decoder.
+    ///
+    /// The bytes of the last string or byte array borrowed from the buffer may not have been
+    /// advanced over yet.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &B {
+        &self.rd.rd
+    }
+
+    /// Gets a mutable reference to the underlying buffer in this decoder.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut B {
+        self.rd.advance_pending();
+        &mut self.rd.rd
+    }
+
+    /// Consumes this deserializer returning the underlying buffer, advanced past everything that
+    /// was decoded.
+    #[inline]
+    pub fn into_inner(mut self) -> B {
+        self.rd.advance_pending();
+        self.rd.rd
+    }
+
+    /// Returns the current position of this deserializer, i.e. how many bytes were read.
+    #[inline(always)]
+    pub fn position(&self) -> u64 {
+        self.rd.position
+    }
+}
+
+
+impl<'de> Deserializer<ReadRefReader<'de>> {
+    /// Constructs a new `Deserializer` from the given byte slice.
+    #[inline(always)]
+    pub fn from_bytes(rd: &'de [u8]) -> Self {
+        Deserializer {
+            rd: ReadRefReader::new(rd),
+            config: DefaultConfig,
+            options: RuntimeConfig::default(),
+            marker: None,
+            last_marker: None,
+            depth: 1024,
+        }
+    }
+}
+
+impl<'de, C> Deserializer<ReadRefReader<'de>, C> {
+    /// Returns the current position of this deserializer, i.e. how many bytes were read.
+    #[inline(always)]
+    pub fn position(&self) -> u64 {
+        self.rd.offset() as u64
+    }
+
+    /// Returns the part of the input that hasn't been deserialized yet.
+    ///
+    /// ```
+    /// use rmp_serde::Deserializer;
+    /// use serde::Deserialize;
+    ///
+    /// let buf = [0x01, 0xa1, b'x', 0xff];
+    /// let mut de = Deserializer::from_bytes(&buf);
+    /// u8::deserialize(&mut de).unwrap();
+    /// assert_eq!([0xa1, b'x', 0xff], de.remaining_slice());
+    /// <&str>::deserialize(&mut de).unwrap();
+    /// assert_eq!(1, de.remaining());
+    /// ```
+    #[inline]
+    pub fn remaining_slice(&self) -> &'de [u8] {
+        // A peeked marker has been read, but not deserialized.
+        let offset = self.rd.offset() - usize::from(self.marker.is_some());
+        &self.rd.whole_slice[offset..]
+    }
+
+    /// Returns the number of bytes that haven't been deserialized yet.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining_slice().len()
+    }
+
+    /// Returns the current position in the input, to go back to with [`Self::rewind`].
+    ///
+    /// This allows trying to decode the upcoming bytes one way, and decoding them another way if
+    /// that fails, without copying the input or going through serde's buffering of untagged
+    /// enums.
+    ///
+    /// ```
+    /// use rmp_serde::Deserializer;
+    /// use serde::Deserialize;
+    ///
+    /// let buf = rmp_serde::to_vec(&"text").unwrap();
+    /// let mut de = Deserializer::from_bytes(&buf);
+    ///
+    /// let checkpoint = de.checkpoint();
+    /// assert!(u32::deserialize(&mut de).is_err());
+    /// de.rewind(checkpoint);
+    /// assert_eq!("text", <&str>::deserialize(&mut de).unwrap());
+    /// ```
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            offset: self.rd.offset(),
+            marker: self.marker,
+            depth: self.depth,
+        }
+    }
+
+    /// Goes back to a position returned by [`Self::checkpoint`], undoing everything read since.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint is past the end of the input, which can only happen if it was
+    /// taken from another deserializer.
+    #[inline]
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.rd.buf = &self.rd.whole_slice[checkpoint.offset..];
+        self.marker = checkpoint.marker;
+        self.depth = checkpoint.depth;
+    }
+
+    /// Deserializes a `T` from the upcoming bytes, or goes back to where it started if that fails.
+    ///
+    /// On error, the deserializer is left as if nothing was read, so the same bytes can be
+    /// deserialized as another type. This allows dispatching like an untagged enum, without
+    /// buffering the value.
+    ///
+    /// ```
+    /// use rmp_serde::Deserializer;
+    ///
+    /// let buf = rmp_serde::to_vec(&(1, "text")).unwrap();
+    /// let mut de = Deserializer::from_bytes(&buf);
+    ///
+    /// assert!(de.try_deserialize::<(u8, u8)>().is_err());
+    /// assert_eq!((1, "text"), de.try_deserialize::<(u8, &str)>().unwrap());
+    /// ```
+    pub fn try_deserialize<T>(&mut self) -> Result<T, Error<BytesReadError>>
+    where T: Deserialize<'de>,
+          C: SerializerConfig
+    {
+        let checkpoint = self.checkpoint();
+        let res = T::deserialize(&mut *self);
+        if res.is_err() {
+            self.rewind(checkpoint);
+        }
+        res
+    }
+}
+
+/// A position in the input of a slice deserializer, see [`Deserializer::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Checkpoint {
+    offset: usize,
+    marker: Option<Marker>,
+    depth: usize,
+}
+
+impl Checkpoint {
+    /// Returns the number of bytes that had been deserialized when the checkpoint was taken.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset - usize::from(self.marker.is_some())
+    }
+}
+
+impl<'de, R: ReadSlice<'de>, C: SerializerConfig> Deserializer<R, C> {
     /// Changes the maximum nesting depth that is allowed
     #[inline(always)]
     pub fn set_max_depth(&mut self, depth: usize) {
         self.depth = depth;
     }
 
+    /// Reads the length of a str, or puts the marker back if the next value isn't one.
+    fn take_str_len(&mut self) -> Result<Option<u32>, Error<R::Error>> {
+        let marker = self.take_or_read_marker()?;
+        Ok(Some(match marker {
+            Marker::FixStr(len) => len.into(),
+            Marker::Str8 => read_u8(&mut self.rd)?.into(),
+            Marker::Str16 => read_u16(&mut self.rd)?.into(),
+            Marker::Str32 => read_u32(&mut self.rd)?,
+            marker => {
+                self.marker = Some(marker);
+                return Ok(None);
+            }
+        }))
+    }
+
+    /// Skips the next value, including everything nested in it.
+    ///
+    /// Strings and bins are skipped without being validated or copied, and containers are counted
+    /// instead of recursed into, so the depth limit doesn't apply.
+    fn skip_value(&mut self) -> Result<(), Error<R::Error>> {
+        let mut pending = 1u64;
+        while pending > 0 {
+            pending -= 1;
+            let len = match self.take_or_read_marker()? {
+                Marker::Null | Marker::True | Marker::False | Marker::FixPos(_) | Marker::FixNeg(_) => 0,
+                Marker::U8 | Marker::I8 => 1,
+                Marker::U16 | Marker::I16 => 2,
+                Marker::U32 | Marker::I32 | Marker::F32 => 4,
+                Marker::U64 | Marker::I64 | Marker::F64 => 8,
+                Marker::FixStr(len) => len.into(),
+                Marker::Str8 | Marker::Bin8 => read_u8(&mut self.rd)?.into(),
+                Marker::Str16 | Marker::Bin16 => read_u16(&mut self.rd)?.into(),
+                Marker::Str32 | Marker::Bin32 => read_u32(&mut self.rd)? as usize,
+                Marker::FixArray(len) => {
+                    pending += u64::from(len);
+                    0
+                }
+                Marker::Array16 => {
+                    pending += u64::from(read_u16(&mut self.rd)?);
+                    0
+                }
+                Marker::Array32 => {
+                    pending += u64::from(read_u32(&mut self.rd)?);
+                    0
+                }
+                Marker::FixMap(len) => {
+                    pending += 2 * u64::from(len);
+                    0
+                }
+                Marker::Map16 => {
+                    pending += 2 * u64::from(read_u16(&mut self.rd)?);
+                    0
+                }
+                Marker::Map32 => {
+                    pending += 2 * u64::from(read_u32(&mut self.rd)?);
+                    0
+                }
+                // The data follows the type.
+                marker if is_ext_marker(marker) => ext_len(&mut self.rd, marker)? as usize + 1,
+                marker => return Err(self.type_mismatch(marker, "a value")),
+            };
+            self.rd.skip(len).map_err(ValueReadError::InvalidDataRead)?;
+        }
+        Ok(())
+    }
+
     fn read_str_data<V>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error<R::Error>>
         where V: Visitor<'de>
     {
         match read_bin_data(&mut self.rd, len as u32)? {
             Reference::Borrowed(buf) => {
-                match str::from_utf8(buf) {
+                match crate::from_utf8(buf) {
                     Ok(s) => visitor.visit_borrowed_str(s),
                     Err(err) => {
                         // Allow to unpack invalid UTF-8 bytes into a byte array.
@@ -320,7 +1282,7 @@ impl<'de, R: ReadSlice<'de>, C: SerializerConfig> Deserializer<R, C> {
                 }
             }
             Reference::Copied(buf) => {
-                match str::from_utf8(buf) {
+                match crate::from_utf8(buf) {
                     Ok(s) => visitor.visit_str(s),
                     Err(err) => {
                         // Allow to unpack invalid UTF-8 bytes into a byte array.
@@ -334,25 +1296,49 @@ impl<'de, R: ReadSlice<'de>, C: SerializerConfig> Deserializer<R, C> {
         }
     }
 
-    fn read_128(&mut self) -> Result<[u8; 16], Error<R::Error>> {
+    /// Reads a 128-bit integer in any of the representations of [`Int128Repr`], or from a plain
+    /// integer.
+    fn read_128<T: Int128>(&mut self) -> Result<T, Error<R::Error>> {
         let marker = self.take_or_read_marker()?;
-
-        if marker != Marker::Bin8 {
-            return Err(Error::TypeMismatch(marker));
-        }
-
-        let len = read_u8(&mut self.rd)?;
-
-        if len != 16 {
-            return Err(Error::LengthMismatch(16));
+        match marker {
+            Marker::Bin8 => {
+                let len = read_u8(&mut self.rd)?;
+                if len != 16 {
+                    return Err(Error::LengthMismatch(16));
+                }
+            }
+            Marker::FixExt16 => {
+                // Any tag is accepted, as there is no standard one.
+                self.rd.read_data_i8()?;
+            }
+            Marker::FixStr(_) | Marker::Str8 => {
+                let len = match marker {
+                    Marker::FixStr(len) => len.into(),
+                    _ => read_u8(&mut self.rd)?.into(),
+                };
+                let buf = match read_bin_data(&mut self.rd, len)? {
+                    Reference::Borrowed(buf) => buf,
+                    Reference::Copied(buf) => buf,
+                };
+                let s = crate::from_utf8(buf).map_err(Error::Utf8Error)?;
+                return s.parse()
+                    .map_err(|_| de::Error::invalid_value(Unexpected::Str(s), &"a decimal 128-bit integer"));
+            }
+            marker => {
+                return match self.read_int(marker)? {
+                    Some(val) => T::try_from(val).map_err(|_| Error::OutOfRange),
+                    None => Err(self.type_mismatch(marker, "an integer")),
+                };
+            }
         }
 
-        let buf = match read_bin_data(&mut self.rd, len as u32)? {
+        let buf = match read_bin_data(&mut self.rd, 16)? {
             Reference::Borrowed(buf) => buf,
             Reference::Copied(buf) => buf,
         };
-
-        Ok(buf.try_into().unwrap())
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(buf);
+        Ok(T::from_be_bytes(bytes))
     }
 }
 
@@ -389,7 +1375,7 @@ fn ext_len<R: RmpRead>(rd: &mut R, marker: Marker) -> Result<u32, Error<R::Error
         Marker::Ext8 => read_u8(rd)? as u32,
         Marker::Ext16 => read_u16(rd)? as u32,
         Marker::Ext32 => read_u32(rd)? as u32,
-        _ => return Err(Error::TypeMismatch(marker)),
+        _ => return Err(Error::type_mismatch(marker, "an ext")),
     })
 }
 
@@ -400,6 +1386,10 @@ enum ExtDeserializerState {
     ReadBinary,
 }
 
+/// Presents an ext as a `(tag, data)` sequence.
+///
+/// Use it through a `&mut` reference and call [`Self::end`] afterwards, so that whatever the
+/// visitor didn't consume is skipped and the input stays in sync.
 #[derive(Debug)]
 struct ExtDeserializer<'a, R, C> {
     rd: &'a mut R,
@@ -417,9 +1407,22 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> ExtDeserializer<'a, R
             state: ExtDeserializerState::New,
         }
     }
+
+    /// Skips the parts of the ext that were not visited.
+    fn end(&mut self) -> Result<(), Error<R::Error>> {
+        if let ExtDeserializerState::New = self.state {
+            self.rd.read_data_i8()?;
+            self.state = ExtDeserializerState::ReadTag;
+        }
+        if let ExtDeserializerState::ReadTag = self.state {
+            self.rd.read_slice(self.len as usize).map_err(ValueReadError::InvalidDataRead)?;
+            self.state = ExtDeserializerState::ReadBinary;
+        }
+        Ok(())
+    }
 }
 
-impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::Deserializer<'de> for ExtDeserializer<'a, R, C> {
+impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::Deserializer<'de> for &mut ExtDeserializer<'a, R, C> {
     type Error = Error<R::Error>;
 
     #[inline(always)]
@@ -436,7 +1439,7 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::Deserializer<'de>
     }
 }
 
-impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::SeqAccess<'de> for ExtDeserializer<'a, R, C> {
+impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::SeqAccess<'de> for &mut ExtDeserializer<'a, R, C> {
     type Error = Error<R::Error>;
 
     #[inline]
@@ -445,29 +1448,40 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::SeqAccess<'de> fo
         T: DeserializeSeed<'de>,
     {
         match self.state {
-            ExtDeserializerState::New | ExtDeserializerState::ReadTag => Ok(Some(seed.deserialize(self)?)),
+            ExtDeserializerState::New | ExtDeserializerState::ReadTag => Ok(Some(seed.deserialize(ExtElement(&mut **self))?)),
             ExtDeserializerState::ReadBinary => Ok(None)
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        match self.state {
+            ExtDeserializerState::New => Some(2),
+            ExtDeserializerState::ReadTag => Some(1),
+            ExtDeserializerState::ReadBinary => Some(0),
+        }
+    }
 }
 
+/// Deserializer for the elements of an ext, the tag and then the data.
+struct ExtElement<'b, 'a, R, C>(&'b mut ExtDeserializer<'a, R, C>);
 
-/// Deserializer for Ext SeqAccess
-impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::Deserializer<'de> for &mut ExtDeserializer<'a, R, C> {
+impl<'de, 'a, 'b, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::Deserializer<'de> for ExtElement<'b, 'a, R, C> {
     type Error = Error<R::Error>;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
-        match self.state {
+        let ext = self.0;
+        match ext.state {
             ExtDeserializerState::New => {
-                let tag = self.rd.read_data_i8()?;
-                self.state = ExtDeserializerState::ReadTag;
+                let tag = ext.rd.read_data_i8()?;
+                ext.state = ExtDeserializerState::ReadTag;
                 visitor.visit_i8(tag)
             }
             ExtDeserializerState::ReadTag => {
-                let data = self.rd.read_slice(self.len as usize).map_err(ValueReadError::InvalidDataRead)?;
-                self.state = ExtDeserializerState::ReadBinary;
+                let data = ext.rd.read_slice(ext.len as usize).map_err(ValueReadError::InvalidDataRead)?;
+                ext.state = ExtDeserializerState::ReadBinary;
                 match data {
                     Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
                     Reference::Copied(bytes) => visitor.visit_bytes(bytes),
@@ -484,36 +1498,293 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::Deserializer<'de>
     }
 }
 
-impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> for &'a mut Deserializer<R, C> {
-    type Error = Error<R::Error>;
+impl<'de, R: ReadSlice<'de>, C: SerializerConfig> Deserializer<R, C> {
+    #[inline]
+    fn take_or_read_marker(&mut self) -> Result<Marker, MarkerReadError<R::Error>> {
+        match self.marker.take() {
+            Some(m) => Ok(m),
+            None => self.read_marker(),
+        }
+    }
 
-    #[inline(always)]
-    fn is_human_readable(&self) -> bool {
-        C::is_human_readable()
+    #[inline]
+    fn peek_or_read_marker(&mut self) -> Result<Marker, MarkerReadError<R::Error>> {
+        if let Some(m) = self.marker {
+            Ok(m)
+        } else {
+            let m = self.read_marker()?;
+            Ok(*self.marker.insert(m))
+        }
     }
 
-    #[inline(never)]
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-        where V: Visitor<'de>
-    {
-        let marker = self.take_or_read_marker()?;
+    #[inline]
+    fn read_marker(&mut self) -> Result<Marker, MarkerReadError<R::Error>> {
+        let position = self.rd.position();
+        let m = rmp::decode::read_marker(&mut self.rd)?;
+        self.last_marker = Some((m, position));
+        Ok(m)
+    }
 
-        match marker {
-            Marker::Null => visitor.visit_unit(),
-            Marker::True |
-            Marker::False => visitor.visit_bool(marker == Marker::True),
-            Marker::FixPos(val) => visitor.visit_u8(val),
-            Marker::FixNeg(val) => visitor.visit_i8(val),
-            Marker::U8 => visitor.visit_u8(self.rd.read_data_u8()?),
-            Marker::U16 => visitor.visit_u16(self.rd.read_data_u16()?),
-            Marker::U32 => visitor.visit_u32(self.rd.read_data_u32()?),
-            Marker::U64 => visitor.visit_u64(self.rd.read_data_u64()?),
+    /// A mismatch with a marker that was just read.
+    #[cold]
+    fn type_mismatch(&self, marker: Marker, expected: &'static str) -> Error<R::Error> {
+        Error::type_mismatch(marker, expected).locate(self.last_marker)
+    }
+
+    /// Visits an ext, whose marker and length were already read, as a newtype wrapping a
+    /// `(tag, data)` sequence.
+    fn visit_ext_newtype<V: Visitor<'de>>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error<R::Error>> {
+        let mut ext_de = ExtDeserializer::new(self, len);
+        let res = visitor.visit_newtype_struct(&mut ext_de)?;
+        ext_de.end()?;
+        Ok(res)
+    }
+
+    /// Visits an ext, whose marker and length were already read, as a `(tag, data)` sequence.
+    fn visit_ext_seq<V: Visitor<'de>>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error<R::Error>> {
+        let mut ext_de = ExtDeserializer::new(self, len);
+        let res = visitor.visit_seq(&mut ext_de)?;
+        ext_de.end()?;
+        Ok(res)
+    }
+
+    /// Fails if the float is not finite and such floats are rejected.
+    #[inline]
+    fn check_float(&self, val: f64) -> Result<(), Error<R::Error>> {
+        if self.options.finite_floats && !val.is_finite() {
+            return Err(de::Error::invalid_value(Unexpected::Float(val), &"a finite float"));
+        }
+        Ok(())
+    }
+
+    /// Reads the integer with the given marker.
+    ///
+    /// Returns `None` without reading anything if the marker isn't an integer.
+    fn read_int(&mut self, marker: Marker) -> Result<Option<i128>, Error<R::Error>> {
+        let val = match marker {
+            Marker::FixPos(val) => val.into(),
+            Marker::FixNeg(val) => val.into(),
+            Marker::U8 => self.rd.read_data_u8()?.into(),
+            Marker::U16 => self.rd.read_data_u16()?.into(),
+            Marker::U32 => self.rd.read_data_u32()?.into(),
+            Marker::U64 => self.rd.read_data_u64()?.into(),
+            Marker::I8 => self.rd.read_data_i8()?.into(),
+            Marker::I16 => self.rd.read_data_i16()?.into(),
+            Marker::I32 => self.rd.read_data_i32()?.into(),
+            Marker::I64 => self.rd.read_data_i64()?.into(),
+            _ => return Ok(None),
+        };
+        Ok(Some(val))
+    }
+
+    /// Reads the number with the given marker and formats it into the buffer.
+    ///
+    /// Returns `false` without reading anything if the marker isn't a number.
+    fn read_number_str(&mut self, marker: Marker, out: &mut NumStr) -> Result<bool, Error<R::Error>> {
+        let res = match marker {
+            Marker::FixPos(val) => write!(out, "{}", val),
+            Marker::FixNeg(val) => write!(out, "{}", val),
+            Marker::U8 => write!(out, "{}", self.rd.read_data_u8()?),
+            Marker::U16 => write!(out, "{}", self.rd.read_data_u16()?),
+            Marker::U32 => write!(out, "{}", self.rd.read_data_u32()?),
+            Marker::U64 => write!(out, "{}", self.rd.read_data_u64()?),
+            Marker::I8 => write!(out, "{}", self.rd.read_data_i8()?),
+            Marker::I16 => write!(out, "{}", self.rd.read_data_i16()?),
+            Marker::I32 => write!(out, "{}", self.rd.read_data_i32()?),
+            Marker::I64 => write!(out, "{}", self.rd.read_data_i64()?),
+            // Unlike `Display`, `Debug` switches to the exponent notation for large numbers.
+            Marker::F32 => write!(out, "{:?}", self.rd.read_data_f32()?),
+            Marker::F64 => write!(out, "{:?}", self.rd.read_data_f64()?),
+            _ => return Ok(false),
+        };
+        // The buffer fits any of these numbers.
+        debug_assert!(res.is_ok());
+        Ok(true)
+    }
+
+    /// Visits the elements of an array of the given length, the header has already been read.
+    ///
+    /// If `fill_missing` is set, the visitor may ask for more elements than there are, see
+    /// [`Self::with_trailing_defaults`].
+    fn visit_array<V: Visitor<'de>>(&mut self, len: u32, fill_missing: bool, visitor: V) -> Result<V::Value, Error<R::Error>> {
+        self.traced("array", len, |de| {
+            let mut seq = SeqAccess::new(de, len);
+            seq.fill_missing = fill_missing;
+            let res = visitor.visit_seq(&mut seq)?;
+            match seq.left {
+                0 => Ok(res),
+                excess => Err(Error::LengthMismatch(len - excess)),
+            }
+        })
+    }
+
+    /// Visits the entries of a map of the given length, the header has already been read.
+    ///
+    /// If `fields` are given, string keys must be one of them.
+    fn visit_map<V: Visitor<'de>>(&mut self, len: u32, fields: Option<&'static [&'static str]>, visitor: V) -> Result<V::Value, Error<R::Error>> {
+        self.traced("map", len, |de| {
+            let mut seq = MapAccess::new(de, len);
+            seq.fields = fields;
+            let res = visitor.visit_map(&mut seq)?;
+            match seq.left {
+                0 => Ok(res),
+                excess => Err(Error::LengthMismatch(len - excess)),
+            }
+        })
+    }
+
+    /// Reads the elements of a container with `f`, in a span if tracing is enabled, see
+    /// [`Self::with_tracing`].
+    #[inline(always)]
+    fn traced<T>(&mut self, kind: &'static str, len: u32, f: impl FnOnce(&mut Self) -> Result<T, Error<R::Error>>) -> Result<T, Error<R::Error>> {
+        #[cfg(feature = "tracing")]
+        if self.options.trace {
+            let span = tracing::trace_span!("msgpack", kind, len, offset = self.rd.position());
+            let _guard = span.enter();
+            let res = f(self);
+            if let Err(ref err) = res {
+                tracing::debug!(offset = self.rd.position(), error = %err, "failed to read {}", kind);
+            }
+            return res;
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (kind, len);
+        f(self)
+    }
+
+    /// If the next value is an ext, visits it as a `(tag, data)` sequence.
+    ///
+    /// This is how exts are handed to visitors expecting a sequence, which would reject the newtype
+    /// that `deserialize_any` produces.
+    fn deserialize_seq_or_ext<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, Error<R::Error>> {
+        let marker = self.take_or_read_marker()?;
+        if is_ext_marker(marker) {
+            let len = ext_len(&mut self.rd, marker)?;
+            return depth_count!(self.depth, self.visit_ext_seq(len, visitor));
+        }
+        self.marker = Some(marker);
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[inline]
+fn is_ext_marker(marker: Marker) -> bool {
+    matches!(marker,
+        Marker::FixExt1 | Marker::FixExt2 | Marker::FixExt4 | Marker::FixExt8 | Marker::FixExt16 |
+        Marker::Ext8 | Marker::Ext16 | Marker::Ext32)
+}
+
+/// A numeric type, either encoded or requested.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Num {
+    Unsigned(u8),
+    Signed(u8),
+    F32,
+    F64,
+}
+
+impl Num {
+    #[inline]
+    fn from_marker(marker: Marker) -> Option<Self> {
+        match marker {
+            Marker::FixPos(..) | Marker::U8 => Some(Num::Unsigned(8)),
+            Marker::U16 => Some(Num::Unsigned(16)),
+            Marker::U32 => Some(Num::Unsigned(32)),
+            Marker::U64 => Some(Num::Unsigned(64)),
+            Marker::FixNeg(..) | Marker::I8 => Some(Num::Signed(8)),
+            Marker::I16 => Some(Num::Signed(16)),
+            Marker::I32 => Some(Num::Signed(32)),
+            Marker::I64 => Some(Num::Signed(64)),
+            Marker::F32 => Some(Num::F32),
+            Marker::F64 => Some(Num::F64),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if a number with the given marker may be read as this type in strict mode.
+    ///
+    /// The serializer writes non-negative signed integers with unsigned markers, so signed types
+    /// accept both.
+    #[inline]
+    fn accepts(self, marker: Marker) -> bool {
+        match (self, Num::from_marker(marker)) {
+            (Num::Unsigned(bits), Some(Num::Unsigned(width))) => width <= bits,
+            (Num::Signed(bits), Some(Num::Unsigned(width) | Num::Signed(width))) => width <= bits,
+            (Num::F32, Some(Num::F32)) | (Num::F64, Some(Num::F64)) => true,
+            _ => false,
+        }
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $num:expr, $ty:ident => $visit:ident;)*) => {
+        $(
+            #[inline]
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+                where V: Visitor<'de>
+            {
+                if self.options.strict_numbers {
+                    let marker = self.peek_or_read_marker()?;
+                    if !$num.accepts(marker) {
+                        return Err(self.type_mismatch(marker, stringify!($ty)));
+                    }
+                }
+                if let Some(overflow) = self.options.int_overflow {
+                    let marker = self.take_or_read_marker()?;
+                    if let Some(val) = self.read_int(marker)? {
+                        let val = match overflow {
+                            IntOverflow::Saturate => val.clamp($ty::MIN.into(), $ty::MAX.into()) as $ty,
+                            IntOverflow::Wrap => val as $ty,
+                        };
+                        return visitor.$visit(val);
+                    }
+                    self.marker = Some(marker);
+                }
+                self.deserialize_any(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> for &'a mut Deserializer<R, C> {
+    type Error = Error<R::Error>;
+
+    #[inline(always)]
+    fn is_human_readable(&self) -> bool {
+        C::is_human_readable()
+    }
+
+    #[inline(never)]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        let marker = self.take_or_read_marker()?;
+        let last_marker = self.last_marker;
+
+        let res = match marker {
+            Marker::Null => visitor.visit_unit(),
+            Marker::True |
+            Marker::False => visitor.visit_bool(marker == Marker::True),
+            Marker::FixPos(val) => visitor.visit_u8(val),
+            Marker::FixNeg(val) => visitor.visit_i8(val),
+            Marker::U8 => visitor.visit_u8(self.rd.read_data_u8()?),
+            Marker::U16 => visitor.visit_u16(self.rd.read_data_u16()?),
+            Marker::U32 => visitor.visit_u32(self.rd.read_data_u32()?),
+            Marker::U64 => visitor.visit_u64(self.rd.read_data_u64()?),
             Marker::I8 => visitor.visit_i8(self.rd.read_data_i8()?),
             Marker::I16 => visitor.visit_i16(self.rd.read_data_i16()?),
             Marker::I32 => visitor.visit_i32(self.rd.read_data_i32()?),
             Marker::I64 => visitor.visit_i64(self.rd.read_data_i64()?),
-            Marker::F32 => visitor.visit_f32(self.rd.read_data_f32()?),
-            Marker::F64 => visitor.visit_f64(self.rd.read_data_f64()?),
+            Marker::F32 => {
+                let val = self.rd.read_data_f32()?;
+                self.check_float(val.into())?;
+                visitor.visit_f32(val)
+            }
+            Marker::F64 => {
+                let val = self.rd.read_data_f64()?;
+                self.check_float(val)?;
+                visitor.visit_f64(val)
+            }
             Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => {
                 let len = match marker {
                     Marker::FixStr(len) => Ok(len.into()),
@@ -534,14 +1805,7 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> f
                     _ => unreachable!(),
                 };
 
-                depth_count!(self.depth, {
-                    let mut seq = SeqAccess::new(self, len);
-                    let res = visitor.visit_seq(&mut seq)?;
-                    match seq.left {
-                        0 => Ok(res),
-                        excess => Err(Error::LengthMismatch(len - excess)),
-                    }
-                })
+                depth_count!(self.depth, self.visit_array(len, false, visitor))
             }
             Marker::FixMap(_) |
             Marker::Map16 |
@@ -553,14 +1817,7 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> f
                     _ => unreachable!()
                 };
 
-                depth_count!(self.depth, {
-                    let mut seq = MapAccess::new(self, len);
-                    let res = visitor.visit_map(&mut seq)?;
-                    match seq.left {
-                        0 => Ok(res),
-                        excess => Err(Error::LengthMismatch(len - excess)),
-                    }
-                })
+                depth_count!(self.depth, self.visit_map(len, None, visitor))
             }
             Marker::Bin8 | Marker::Bin16 | Marker::Bin32 => {
                 let len = match marker {
@@ -583,10 +1840,12 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> f
             Marker::Ext16 |
             Marker::Ext32 => {
                 let len = ext_len(&mut self.rd, marker)?;
-                depth_count!(self.depth, visitor.visit_newtype_struct(ExtDeserializer::new(self, len)))
+                depth_count!(self.depth, self.visit_ext_newtype(len, visitor))
             }
-            Marker::Reserved => Err(Error::TypeMismatch(Marker::Reserved)),
-        }
+            Marker::Reserved => Err(self.type_mismatch(Marker::Reserved, "a value")),
+        };
+        // Visitors report mismatches without knowing the marker.
+        res.map_err(|err| err.locate(last_marker))
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -605,8 +1864,24 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> f
         // Round trips of Options where `Option<t> = None` such as `Some(None)` will fail because
         // they are just seriialized as `nil`. The serialization format has probably to be changed
         // to solve this. But as serde_json behaves the same, I think it's not worth doing this.
+        // `with_option_as_array` opts into such a format.
         let marker = self.take_or_read_marker()?;
 
+        if self.options.option_as_array {
+            let len = match marker {
+                Marker::Null => 0,
+                Marker::FixArray(len) => len.into(),
+                Marker::Array16 => read_u16(&mut self.rd)?.into(),
+                Marker::Array32 => read_u32(&mut self.rd)?,
+                marker => return Err(self.type_mismatch(marker, "nil or an array of at most one element")),
+            };
+            return match len {
+                0 => visitor.visit_none(),
+                1 => depth_count!(self.depth, visitor.visit_some(&mut *self)),
+                len => Err(Error::LengthMismatch(len)),
+            };
+        }
+
         if marker == Marker::Null {
             visitor.visit_none()
         } else {
@@ -643,8 +1918,20 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> f
             let marker = self.take_or_read_marker()?;
 
             let len = ext_len(&mut self.rd, marker)?;
-            let ext_de = ExtDeserializer::new(self, len);
-            return visitor.visit_newtype_struct(ext_de);
+            return self.visit_ext_newtype(len, visitor);
+        }
+        #[cfg(feature = "std")]
+        if name == crate::bin_stream::BIN_CHUNKS_NAME {
+            let len = match self.take_or_read_marker()? {
+                Marker::Bin8 => read_u8(&mut self.rd)?.into(),
+                Marker::Bin16 => read_u16(&mut self.rd)?.into(),
+                Marker::Bin32 => read_u32(&mut self.rd)?,
+                marker => {
+                    self.marker = Some(marker);
+                    return self.deserialize_any(visitor);
+                }
+            };
+            return visitor.visit_seq(BinChunks { de: self, left: len });
         }
 
         visitor.visit_newtype_struct(self)
@@ -670,8 +1957,7 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> f
     where
         V: Visitor<'de>,
     {
-        let buf = self.read_128()?;
-        visitor.visit_i128(i128::from_be_bytes(buf))
+        visitor.visit_i128(self.read_128()?)
     }
 
     #[inline]
@@ -679,21 +1965,229 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> serde::Deserializer<'de> f
     where
         V: Visitor<'de>,
     {
-        let buf = self.read_128()?;
-        visitor.visit_u128(u128::from_be_bytes(buf))
+        visitor.visit_u128(self.read_128()?)
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_seq_or_ext(visitor)
+    }
+
+    #[inline]
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_seq_or_ext(visitor)
+    }
+
+    #[inline]
+    fn deserialize_tuple_struct<V>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_seq_or_ext(visitor)
+    }
+
+    #[inline]
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        if self.options.numbers_as_str && C::is_human_readable() {
+            let marker = self.take_or_read_marker()?;
+            let mut num = NumStr::new();
+            if self.read_number_str(marker, &mut num)? {
+                return visitor.visit_str(num.as_str());
+            }
+            self.marker = Some(marker);
+        }
+        self.deserialize_any(visitor)
+    }
+
+    #[inline]
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        if self.options.old_spec {
+            let marker = self.take_or_read_marker()?;
+            let len = match marker {
+                Marker::FixStr(len) => len.into(),
+                Marker::Str8 => read_u8(&mut self.rd)?.into(),
+                Marker::Str16 => read_u16(&mut self.rd)?.into(),
+                Marker::Str32 => read_u32(&mut self.rd)?,
+                marker => {
+                    self.marker = Some(marker);
+                    return self.deserialize_any(visitor);
+                }
+            };
+            return match read_bin_data(&mut self.rd, len)? {
+                Reference::Borrowed(buf) => visitor.visit_borrowed_bytes(buf),
+                Reference::Copied(buf) => visitor.visit_bytes(buf),
+            };
+        }
+        #[cfg(feature = "std")]
+        if self.options.int_arrays_as_bytes {
+            let marker = self.take_or_read_marker()?;
+            let len = match marker {
+                Marker::FixArray(len) => len.into(),
+                Marker::Array16 => read_u16(&mut self.rd)?.into(),
+                Marker::Array32 => read_u32(&mut self.rd)?,
+                marker => {
+                    self.marker = Some(marker);
+                    return self.deserialize_any(visitor);
+                }
+            };
+            // Every element takes at least a byte, don't trust the header beyond that.
+            let mut buf = Vec::with_capacity((len as usize).min(4096));
+            for _ in 0..len {
+                buf.push(u8::deserialize(&mut *self)?);
+            }
+            return visitor.visit_byte_buf(buf);
+        }
+        self.deserialize_any(visitor)
+    }
+
+    #[inline]
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_struct<V>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        if self.options.trailing_defaults || self.options.strict_structs || self.options.hashed_fields {
+            let marker = self.take_or_read_marker()?;
+            match marker {
+                Marker::FixArray(_) | Marker::Array16 | Marker::Array32 if self.options.trailing_defaults => {
+                    let len = match marker {
+                        Marker::FixArray(len) => len.into(),
+                        Marker::Array16 => read_u16(&mut self.rd)?.into(),
+                        Marker::Array32 => read_u32(&mut self.rd)?,
+                        _ => unreachable!(),
+                    };
+                    return depth_count!(self.depth, self.visit_array(len, true, visitor));
+                }
+                Marker::FixMap(_) | Marker::Map16 | Marker::Map32 if self.options.strict_structs || self.options.hashed_fields => {
+                    let len = match marker {
+                        Marker::FixMap(len) => len.into(),
+                        Marker::Map16 => read_u16(&mut self.rd)?.into(),
+                        Marker::Map32 => read_u32(&mut self.rd)?,
+                        _ => unreachable!(),
+                    };
+                    return depth_count!(self.depth, self.visit_map(len, Some(fields), visitor));
+                }
+                marker => self.marker = Some(marker),
+            }
+        }
+        self.deserialize_any(visitor)
+    }
+
+    deserialize_number! {
+        deserialize_u8 => Num::Unsigned(8), u8 => visit_u8;
+        deserialize_u16 => Num::Unsigned(16), u16 => visit_u16;
+        deserialize_u32 => Num::Unsigned(32), u32 => visit_u32;
+        deserialize_u64 => Num::Unsigned(64), u64 => visit_u64;
+        deserialize_i8 => Num::Signed(8), i8 => visit_i8;
+        deserialize_i16 => Num::Signed(16), i16 => visit_i16;
+        deserialize_i32 => Num::Signed(32), i32 => visit_i32;
+        deserialize_i64 => Num::Signed(64), i64 => visit_i64;
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        let marker = self.take_or_read_marker()?;
+        if self.options.strict_numbers && !Num::F32.accepts(marker) {
+            return Err(self.type_mismatch(marker, "f32"));
+        }
+        if self.options.exact_f32 && marker == Marker::F64 {
+            let val = self.rd.read_data_f64()?;
+            self.check_float(val)?;
+            let rounded = val as f32;
+            if f64::from(rounded) != val && !val.is_nan() {
+                return Err(de::Error::invalid_value(Unexpected::Float(val), &"a double exactly representable as f32"));
+            }
+            return visitor.visit_f32(rounded);
+        }
+        // Convert integers here, so that visitors only handling floats accept them too.
+        if let Some(val) = self.read_int(marker)? {
+            return visitor.visit_f32(val as f32);
+        }
+        self.marker = Some(marker);
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        let marker = self.take_or_read_marker()?;
+        if self.options.strict_numbers && !Num::F64.accepts(marker) {
+            return Err(self.type_mismatch(marker, "f64"));
+        }
+        if let Some(val) = self.read_int(marker)? {
+            return visitor.visit_f64(val as f64);
+        }
+        self.marker = Some(marker);
+        self.deserialize_any(visitor)
     }
 
     forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32
-        f64 char str string bytes byte_buf unit
-        seq map struct identifier tuple
-        tuple_struct ignored_any
+        bool char unit
+        map identifier
+    }
+}
+
+/// Passes the data of a bin to the visitor in chunks, see [`crate::bin_stream`].
+#[cfg(feature = "std")]
+struct BinChunks<'a, R, C> {
+    de: &'a mut Deserializer<R, C>,
+    left: u32,
+}
+
+#[cfg(feature = "std")]
+impl<'de, 'a, R: ReadSlice<'de> + 'a, C> de::SeqAccess<'de> for BinChunks<'a, R, C> {
+    type Error = Error<R::Error>;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+        where T: DeserializeSeed<'de>
+    {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        let len = self.left.min(crate::bin_stream::CHUNK_SIZE);
+        self.left -= len;
+        match read_bin_data(&mut self.de.rd, len)? {
+            Reference::Borrowed(buf) => seed.deserialize(de::value::BorrowedBytesDeserializer::new(buf)).map(Some),
+            Reference::Copied(buf) => seed.deserialize(de::value::BytesDeserializer::new(buf)).map(Some),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left.div_ceil(crate::bin_stream::CHUNK_SIZE) as usize)
     }
 }
 
 struct SeqAccess<'a, R, C> {
     de: &'a mut Deserializer<R, C>,
     left: u32,
+    /// Whether elements past the end are deserialized from `MissingElement`.
+    fill_missing: bool,
 }
 
 impl<'a, R: 'a, C> SeqAccess<'a, R, C> {
@@ -702,6 +2196,7 @@ impl<'a, R: 'a, C> SeqAccess<'a, R, C> {
         SeqAccess {
             de,
             left: len,
+            fill_missing: false,
         }
     }
 }
@@ -715,7 +2210,14 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::SeqAccess<'de> fo
     {
         if self.left > 0 {
             self.left -= 1;
-            Ok(Some(seed.deserialize(&mut *self.de)?))
+            match seed.deserialize(&mut *self.de) {
+                Ok(val) => Ok(Some(val)),
+                Err(err) => Err(err.locate(self.de.last_marker)),
+            }
+        } else if self.fill_missing {
+            // If the element can't be missing, report the end of the sequence, so that a
+            // `#[serde(default)]` value is used instead.
+            Ok(seed.deserialize(MissingElement).ok())
         } else {
             Ok(None)
         }
@@ -727,9 +2229,42 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::SeqAccess<'de> fo
     }
 }
 
+/// Stands for a struct field missing from the end of an array.
+///
+/// Like serde does for missing keys of a map, this deserializes as `None` and fails for anything
+/// that isn't an `Option`.
+struct MissingElement;
+
+impl<'de> de::Deserializer<'de> for MissingElement {
+    type Error = de::value::Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        Err(de::Error::custom("missing element"))
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit
+        seq bytes byte_buf map unit_struct newtype_struct
+        tuple_struct struct identifier tuple enum ignored_any
+    }
+}
+
 struct MapAccess<'a, R, C> {
     de: &'a mut Deserializer<R, C>,
     left: u32,
+    /// The fields of the struct being deserialized, if unknown string keys must be rejected or
+    /// integer keys are field name hashes.
+    fields: Option<&'static [&'static str]>,
 }
 
 impl<'a, R: 'a, C> MapAccess<'a, R, C> {
@@ -737,8 +2272,94 @@ impl<'a, R: 'a, C> MapAccess<'a, R, C> {
         MapAccess {
             de,
             left: len,
+            fields: None,
+        }
+    }
+}
+
+impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> MapAccess<'a, R, C> {
+    /// Deserializes a key of a struct, replacing field name hashes with the names and checking
+    /// that string keys are one of the given fields, as the options require.
+    fn next_field_seed<K>(&mut self, fields: &'static [&'static str], seed: K) -> Result<K::Value, Error<R::Error>>
+        where K: DeserializeSeed<'de>
+    {
+        let strict = self.de.options.strict_structs;
+        if self.de.options.hashed_fields {
+            if let Some(hash) = self.take_hash()? {
+                return match fields.iter().find(|field| crate::config::field_hash(field) == hash) {
+                    Some(field) => seed.deserialize(de::value::BorrowedStrDeserializer::new(field)),
+                    None => {
+                        let key = hash_key(hash);
+                        let key = crate::from_utf8(&key).map_err(Error::Utf8Error)?;
+                        if strict {
+                            return Err(de::Error::unknown_field(key, fields));
+                        }
+                        seed.deserialize(de::value::StrDeserializer::new(key))
+                    }
+                };
+            }
+        }
+
+        let len = match self.de.take_str_len()? {
+            Some(len) => len,
+            None => return seed.deserialize(&mut *self.de),
+        };
+        // Known keys are passed as the field name, which avoids validating them as UTF-8.
+        match read_bin_data(&mut self.de.rd, len)? {
+            Reference::Borrowed(buf) => match find_field(buf, fields) {
+                Some(field) => seed.deserialize(de::value::BorrowedStrDeserializer::new(field)),
+                None => {
+                    let key = crate::from_utf8(buf).map_err(Error::Utf8Error)?;
+                    if strict {
+                        return Err(de::Error::unknown_field(key, fields));
+                    }
+                    seed.deserialize(de::value::BorrowedStrDeserializer::new(key))
+                }
+            },
+            Reference::Copied(buf) => match find_field(buf, fields) {
+                Some(field) => seed.deserialize(de::value::BorrowedStrDeserializer::new(field)),
+                None => {
+                    let key = crate::from_utf8(buf).map_err(Error::Utf8Error)?;
+                    if strict {
+                        return Err(de::Error::unknown_field(key, fields));
+                    }
+                    seed.deserialize(de::value::StrDeserializer::new(key))
+                }
+            },
         }
     }
+
+    /// Reads an unsigned integer key of up to 32 bits, putting back the marker of anything else.
+    fn take_hash(&mut self) -> Result<Option<u32>, Error<R::Error>> {
+        let marker = self.de.take_or_read_marker()?;
+        Ok(Some(match marker {
+            Marker::FixPos(val) => val.into(),
+            Marker::U8 => read_u8(&mut self.de.rd)?.into(),
+            Marker::U16 => read_u16(&mut self.de.rd)?.into(),
+            Marker::U32 => read_u32(&mut self.de.rd)?,
+            marker => {
+                self.de.marker = Some(marker);
+                return Ok(None);
+            }
+        }))
+    }
+}
+
+/// Formats a field name hash that matches no field, like `0x1234abcd`.
+fn hash_key(hash: u32) -> [u8; 10] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut key = *b"0x00000000";
+    for (i, digit) in key[2..].iter_mut().enumerate() {
+        *digit = DIGITS[(hash >> (28 - 4 * i)) as usize & 0xf];
+    }
+    key
+}
+
+/// Returns the field whose name is the key, comparing bytes so that known keys aren't validated as
+/// UTF-8.
+#[inline]
+fn find_field(key: &[u8], fields: &'static [&'static str]) -> Option<&'static str> {
+    fields.iter().copied().find(|field| field.as_bytes() == key)
 }
 
 impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::MapAccess<'de> for MapAccess<'a, R, C> {
@@ -750,7 +2371,12 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::MapAccess<'de> fo
     {
         if self.left > 0 {
             self.left -= 1;
-            seed.deserialize(&mut *self.de).map(Some)
+            let res = match self.fields {
+                Some(fields) => self.next_field_seed(fields, seed),
+                None if self.de.options.int_keys_as_str => seed.deserialize(KeyDeserializer { de: &mut *self.de }),
+                None => seed.deserialize(&mut *self.de),
+            };
+            res.map(Some).map_err(|err| err.locate(self.de.last_marker))
         } else {
             Ok(None)
         }
@@ -760,7 +2386,7 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::MapAccess<'de> fo
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
         where V: DeserializeSeed<'de>
     {
-        seed.deserialize(&mut *self.de)
+        seed.deserialize(&mut *self.de).map_err(|err| err.locate(self.de.last_marker))
     }
 
     #[inline(always)]
@@ -769,21 +2395,146 @@ impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::MapAccess<'de> fo
     }
 }
 
-struct UnitVariantAccess<'a, R: 'a, C> {
+/// Deserializes a map key, formatting integers if a string is expected.
+struct KeyDeserializer<'a, R, C> {
     de: &'a mut Deserializer<R, C>,
 }
 
-impl<'a, R: 'a, C> UnitVariantAccess<'a, R, C> {
-    pub fn new(de: &'a mut Deserializer<R, C>) -> Self {
-        UnitVariantAccess { de }
-    }
+macro_rules! forward_to_inner {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[inline]
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+                where V: Visitor<'de>
+            {
+                de::Deserializer::$method(self.de, $($arg,)* visitor)
+            }
+        )*
+    };
 }
 
-impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> de::EnumAccess<'de>
-    for UnitVariantAccess<'a, R, C>
-{
+impl<'de, 'a, R: ReadSlice<'de> + 'a, C: SerializerConfig> de::Deserializer<'de> for KeyDeserializer<'a, R, C> {
     type Error = Error<R::Error>;
-    type Variant = Self;
+
+    #[inline(always)]
+    fn is_human_readable(&self) -> bool {
+        C::is_human_readable()
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        let marker = self.de.take_or_read_marker()?;
+        let mut key = NumStr::new();
+        if !matches!(marker, Marker::F32 | Marker::F64) && self.de.read_number_str(marker, &mut key)? {
+            return visitor.visit_str(key.as_str());
+        }
+        self.de.marker = Some(marker);
+        de::Deserializer::deserialize_string(self.de, visitor)
+    }
+
+    #[inline]
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_string(visitor)
+    }
+
+    forward_to_inner! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+}
+
+/// A 128-bit integer type.
+trait Int128: Sized + str::FromStr + TryFrom<i128> {
+    fn from_be_bytes(bytes: [u8; 16]) -> Self;
+}
+
+impl Int128 for i128 {
+    #[inline]
+    fn from_be_bytes(bytes: [u8; 16]) -> Self {
+        i128::from_be_bytes(bytes)
+    }
+}
+
+impl Int128 for u128 {
+    #[inline]
+    fn from_be_bytes(bytes: [u8; 16]) -> Self {
+        u128::from_be_bytes(bytes)
+    }
+}
+
+/// A buffer holding a formatted number, large enough for any 128-bit integer.
+pub(crate) struct NumStr {
+    buf: [u8; 40],
+    len: usize,
+}
+
+impl NumStr {
+    #[inline]
+    pub fn new() -> Self {
+        NumStr { buf: [0; 40], len: 0 }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // Only whole `str`s are written.
+        str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for NumStr {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf.get_mut(self.len..end).ok_or(fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+struct UnitVariantAccess<'a, R: 'a, C> {
+    de: &'a mut Deserializer<R, C>,
+}
+
+impl<'a, R: 'a, C> UnitVariantAccess<'a, R, C> {
+    pub fn new(de: &'a mut Deserializer<R, C>) -> Self {
+        UnitVariantAccess { de }
+    }
+}
+
+impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> de::EnumAccess<'de>
+    for UnitVariantAccess<'a, R, C>
+{
+    type Error = Error<R::Error>;
+    type Variant = Self;
 
     #[inline]
     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Self::Error>
@@ -888,7 +2639,7 @@ impl<'de, 'a, R: ReadSlice<'de>, C: SerializerConfig> de::VariantAccess<'de> for
     fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
-        de::Deserializer::deserialize_tuple(self.de, fields.len(), visitor)
+        de::Deserializer::deserialize_struct(self.de, "", fields, visitor)
     }
 }
 
@@ -907,14 +2658,35 @@ pub enum Reference<'b, 'c, T: ?Sized + 'static> {
 pub trait ReadSlice<'de>: RmpRead {
     /// Reads the exact number of bytes from the underlying byte-array.
     fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, Self::Error>;
+
+    /// Returns how many bytes were read, if the reader keeps track of it.
+    ///
+    /// This is only used to report offsets when tracing.
+    #[inline]
+    fn position(&self) -> Option<u64> {
+        None
+    }
+
+    /// Skips the exact number of bytes.
+    ///
+    /// The default implementation reads them with [`Self::read_slice`], readers that copy data
+    /// should discard it instead.
+    #[inline]
+    fn skip(&mut self, len: usize) -> Result<(), Self::Error> {
+        self.read_slice(len).map(drop)
+    }
 }
 
 /// Owned reader wrapper.
+///
+/// Strings and bytes are copied into a scratch buffer `B` before being passed to the visitor. With
+/// the `allocator_api` feature, the buffer can use any allocator, see [`Deserializer::new_in`].
 #[cfg(feature = "std")]
 #[derive(Debug)]
-pub struct ReadReader<R: RmpRead> {
+pub struct ReadReader<R: RmpRead, B: ScratchBuf = DefaultScratchBuf> {
     rd: R,
-    buf: Vec<u8>,
+    buf: B,
+    position: u64,
 }
 
 #[cfg(feature = "std")]
@@ -923,51 +2695,420 @@ impl<R: RmpRead> ReadReader<R> {
     fn new(rd: R) -> Self {
         ReadReader {
             rd,
-            buf: Vec::with_capacity(128),
+            buf: DefaultScratchBuf::with_capacity(128),
+            position: 0,
+        }
+    }
+}
+
+#[cfg(all(feature = "std", not(feature = "allocator_api")))]
+type DefaultScratchBuf = Vec<u8>;
+#[cfg(feature = "allocator_api")]
+type DefaultScratchBuf = allocator_api2::vec::Vec<u8>;
+
+/// The scratch buffer of a [`ReadReader`]: a `Vec<u8>`, or with the `allocator_api` feature, an
+/// `allocator_api2::vec::Vec<u8, A>` with any allocator.
+///
+/// Implemented as an empty trait depending on a hidden trait, so it can't be implemented outside
+/// of this crate.
+#[cfg(feature = "std")]
+pub trait ScratchBuf: sealed::ScratchBuf {}
+
+#[cfg(feature = "std")]
+impl<T: sealed::ScratchBuf> ScratchBuf for T {}
+
+#[cfg(feature = "std")]
+mod sealed {
+    pub trait ScratchBuf {
+        fn clear(&mut self);
+
+        fn len(&self) -> usize;
+
+        fn resize(&mut self, len: usize);
+
+        fn as_slice(&self) -> &[u8];
+
+        fn as_mut_slice(&mut self) -> &mut [u8];
+    }
+
+    #[cfg(not(feature = "allocator_api"))]
+    impl ScratchBuf for Vec<u8> {
+        #[inline]
+        fn clear(&mut self) {
+            Vec::clear(self);
+        }
+
+        #[inline]
+        fn len(&self) -> usize {
+            Vec::len(self)
+        }
+
+        #[inline]
+        fn resize(&mut self, len: usize) {
+            Vec::resize(self, len, 0);
+        }
+
+        #[inline]
+        fn as_slice(&self) -> &[u8] {
+            self
+        }
+
+        #[inline]
+        fn as_mut_slice(&mut self) -> &mut [u8] {
+            self
+        }
+    }
+
+    // With the `nightly` feature of `allocator-api2`, this is `std::vec::Vec`, so it must be the
+    // only implementation.
+    #[cfg(feature = "allocator_api")]
+    impl<A: allocator_api2::alloc::Allocator> ScratchBuf for allocator_api2::vec::Vec<u8, A> {
+        #[inline]
+        fn clear(&mut self) {
+            allocator_api2::vec::Vec::clear(self);
+        }
+
+        #[inline]
+        fn len(&self) -> usize {
+            allocator_api2::vec::Vec::len(self)
+        }
+
+        #[inline]
+        fn resize(&mut self, len: usize) {
+            allocator_api2::vec::Vec::resize(self, len, 0);
+        }
+
+        #[inline]
+        fn as_slice(&self) -> &[u8] {
+            self
+        }
+
+        #[inline]
+        fn as_mut_slice(&mut self) -> &mut [u8] {
+            self
         }
     }
 }
 
+/// The maximum number of bytes `ReadReader` allocates ahead of actually receiving them.
+///
+/// Lengths in MessagePack headers are attacker-controlled, so the scratch buffer only grows as the
+/// data arrives. A truncated stream claiming a 4 GiB payload fails after at most this many bytes
+/// of speculative allocation.
+#[cfg(feature = "std")]
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads `len` bytes into the scratch buffer, growing it by at most [`READ_CHUNK_SIZE`] at a time.
+#[cfg(feature = "std")]
+fn read_chunked<R: RmpRead, B: ScratchBuf>(rd: &mut R, buf: &mut B, len: usize, position: &mut u64) -> Result<(), R::Error> {
+    buf.clear();
+    while buf.len() < len {
+        let start = buf.len();
+        let chunk = (len - start).min(READ_CHUNK_SIZE);
+        buf.resize(start + chunk);
+        rd.read_exact_buf(&mut buf.as_mut_slice()[start..])?;
+        *position += chunk as u64;
+    }
+    Ok(())
+}
+
 #[cfg(feature = "std")]
-impl<'de, R: RmpRead> ReadSlice<'de> for ReadReader<R> {
+impl<'de, R: RmpRead, B: ScratchBuf> ReadSlice<'de> for ReadReader<R, B> {
+    #[inline(always)]
+    fn position(&self) -> Option<u64> {
+        Some(self.position)
+    }
+
     #[inline]
     fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, R::Error> {
-        self.buf = vec![0u8; len]; // TODO: this shouldn't pre-allocate, since that might be a DoS
-                                   // risk
-        self.rd.read_exact_buf(&mut self.buf)?;
+        read_chunked(&mut self.rd, &mut self.buf, len, &mut self.position)?;
+        Ok(Reference::Copied(self.buf.as_slice()))
+    }
 
-        Ok(Reference::Copied(&self.buf[..]))
+    fn skip(&mut self, mut len: usize) -> Result<(), R::Error> {
+        let mut buf = [0; 1024];
+        while len > 0 {
+            let chunk = len.min(buf.len());
+            self.rd.read_exact_buf(&mut buf[..chunk])?;
+            self.position += chunk as u64;
+            len -= chunk;
+        }
+        Ok(())
     }
 }
 
 #[cfg(feature = "std")]
-impl<R: RmpRead> RmpRead for ReadReader<R> {
+impl<R: RmpRead, B: ScratchBuf> RmpRead for ReadReader<R, B> {
     type Error = R::Error;
 
     fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
-        self.rd.read_exact_buf(buf)
+        self.rd.read_exact_buf(buf)?;
+        self.position += buf.len() as u64;
+        Ok(())
+    }
+}
+
+/// Buffered reader wrapper.
+///
+/// Reads markers and payloads straight out of the `BufRead`'s internal buffer. Payloads that fit
+/// into that buffer are handed to the visitor without being copied; the bytes are consumed lazily
+/// on the next read, or when the reader is dropped. Larger payloads fall back to a scratch buffer,
+/// like `ReadReader`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct BufReadReader<R: BufRead> {
+    /// Only `None` once taken by `into_inner`, which can't move out of a type implementing `Drop`.
+    rd: Option<R>,
+    buf: DefaultScratchBuf,
+    /// Number of bytes of the last borrowed slice that are still to be consumed.
+    pending: usize,
+    position: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> BufReadReader<R> {
+    #[inline]
+    fn new(rd: R) -> Self {
+        BufReadReader {
+            rd: Some(rd),
+            buf: DefaultScratchBuf::new(),
+            pending: 0,
+            position: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn inner(&self) -> &R {
+        self.rd.as_ref().expect(READER_TAKEN)
+    }
+
+    #[inline(always)]
+    fn inner_mut(&mut self) -> &mut R {
+        self.rd.as_mut().expect(READER_TAKEN)
+    }
+
+    #[inline]
+    fn consume_pending(&mut self) {
+        if self.pending > 0 {
+            let pending = self.pending;
+            self.inner_mut().consume(pending);
+            self.pending = 0;
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+const READER_TAKEN: &str = "the reader is only taken by into_inner";
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Drop for BufReadReader<R> {
+    /// Consumes the last borrowed payload, so the reader is left after the value.
+    fn drop(&mut self) {
+        if self.rd.is_some() {
+            self.consume_pending();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> RmpRead for BufReadReader<R> {
+    type Error = io::Error;
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, Self::Error> {
+        self.consume_pending();
+        let byte = match self.inner_mut().fill_buf()?.first() {
+            Some(&byte) => byte,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        self.inner_mut().consume(1);
+        self.position += 1;
+        Ok(byte)
+    }
+
+    #[inline]
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.consume_pending();
+        let available = self.inner_mut().fill_buf()?;
+        if available.len() >= buf.len() {
+            buf.copy_from_slice(&available[..buf.len()]);
+            self.inner_mut().consume(buf.len());
+        } else {
+            self.inner_mut().read_exact(buf)?;
+        }
+        self.position += buf.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: BufRead> ReadSlice<'de> for BufReadReader<R> {
+    #[inline(always)]
+    fn position(&self) -> Option<u64> {
+        Some(self.position)
+    }
+
+    #[inline]
+    fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, io::Error> {
+        self.consume_pending();
+
+        if self.inner_mut().fill_buf()?.len() >= len {
+            self.pending = len;
+            self.position += len as u64;
+            let buf = self.inner_mut().fill_buf()?;
+            return Ok(Reference::Copied(&buf[..len]));
+        }
+
+        let rd = self.rd.as_mut().expect(READER_TAKEN);
+        read_chunked(rd, &mut self.buf, len, &mut self.position)?;
+        Ok(Reference::Copied(self.buf.as_slice()))
+    }
+
+    fn skip(&mut self, mut len: usize) -> Result<(), io::Error> {
+        self.consume_pending();
+        while len > 0 {
+            let available = self.inner_mut().fill_buf()?.len();
+            if available == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let chunk = len.min(available);
+            self.inner_mut().consume(chunk);
+            self.position += chunk as u64;
+            len -= chunk;
+        }
+        Ok(())
+    }
+}
+
+/// Reader wrapper for [`bytes::Buf`] implementations, such as `Bytes` and `BytesMut`.
+///
+/// Payloads that are contiguous in the current chunk are passed to the visitor without being
+/// copied. Payloads spanning several chunks are gathered into a scratch buffer. Lengths are
+/// checked against `Buf::remaining` before anything is allocated.
+#[cfg(feature = "bytes")]
+#[derive(Debug)]
+pub struct BytesReader<B: bytes::Buf> {
+    rd: B,
+    buf: Vec<u8>,
+    /// Number of bytes of the last borrowed slice that are still to be advanced over.
+    pending: usize,
+    position: u64,
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::Buf> BytesReader<B> {
+    #[inline]
+    fn new(rd: B) -> Self {
+        BytesReader {
+            rd,
+            buf: Vec::new(),
+            pending: 0,
+            position: 0,
+        }
+    }
+
+    #[inline]
+    fn advance_pending(&mut self) {
+        if self.pending > 0 {
+            self.rd.advance(self.pending);
+            self.pending = 0;
+        }
+    }
+
+    #[inline]
+    fn check_remaining(&self, len: usize) -> Result<(), BytesReadError> {
+        let remaining = self.rd.remaining();
+        if remaining < len {
+            return Err(BytesReadError::InsufficientBytes { expected: len, actual: remaining, position: self.position });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::Buf> RmpRead for BytesReader<B> {
+    type Error = BytesReadError;
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, Self::Error> {
+        self.advance_pending();
+        self.check_remaining(1)?;
+        self.position += 1;
+        Ok(self.rd.get_u8())
+    }
+
+    #[inline]
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.advance_pending();
+        self.check_remaining(buf.len())?;
+        self.position += buf.len() as u64;
+        self.rd.copy_to_slice(buf);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<'de, B: bytes::Buf> ReadSlice<'de> for BytesReader<B> {
+    #[inline(always)]
+    fn position(&self) -> Option<u64> {
+        Some(self.position)
+    }
+
+    #[inline]
+    fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, Self::Error> {
+        self.advance_pending();
+        self.check_remaining(len)?;
+        self.position += len as u64;
+
+        if self.rd.chunk().len() >= len {
+            self.pending = len;
+            return Ok(Reference::Copied(&self.rd.chunk()[..len]));
+        }
+
+        self.buf.clear();
+        self.buf.resize(len, 0);
+        self.rd.copy_to_slice(&mut self.buf);
+        Ok(Reference::Copied(&self.buf[..]))
+    }
+
+    #[inline]
+    fn skip(&mut self, len: usize) -> Result<(), Self::Error> {
+        self.advance_pending();
+        self.check_remaining(len)?;
+        self.position += len as u64;
+        self.rd.advance(len);
+        Ok(())
     }
 }
 
-/// Borrowed reader wrapper.
+/// Borrowed reader wrapper, used by [`Deserializer::from_bytes`].
+///
+/// Strings and bytes are passed to the visitor as borrowed slices of the input.
 #[derive(Debug)]
-struct ReadRefReader<'a> {
-    //whole_slice: &'a [u8],
+pub struct ReadRefReader<'a> {
+    whole_slice: &'a [u8],
     buf: &'a [u8],
 }
 
 impl<'a> ReadRefReader<'a> {
-    ///// Returns the part that hasn't been consumed yet
-    //pub fn remaining_slice(&self) -> &'a [u8] {
-    //    self.buf
-    //}
+    /// Returns the part that hasn't been consumed yet.
+    #[inline]
+    pub fn remaining_slice(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Returns the number of bytes consumed.
+    #[inline]
+    fn offset(&self) -> usize {
+        self.whole_slice.len() - self.buf.len()
+    }
 }
 
 impl<'a> ReadRefReader<'a,> {
     #[inline]
     fn new(bytes: &'a [u8]) -> Self {
         Self {
-            //whole_slice: bytes,
+            whole_slice: bytes,
             buf: bytes,
         }
     }
@@ -979,7 +3120,7 @@ impl<'a> RmpRead for ReadRefReader<'a> {
     #[inline]
     fn read_exact_buf(&mut self, into: &mut [u8]) -> Result<(), Self::Error> {
         if self.buf.len() < into.len() {
-            return Err(BytesReadError::InsufficientBytes { expected: into.len(), actual: self.buf.len(), position: 0 });
+            return Err(BytesReadError::InsufficientBytes { expected: into.len(), actual: self.buf.len(), position: self.offset() as u64 });
         }
         let (a, b) = self.buf.split_at(into.len());
         self.buf = b;
@@ -989,10 +3130,15 @@ impl<'a> RmpRead for ReadRefReader<'a> {
 }
 
 impl<'de> ReadSlice<'de> for ReadRefReader<'de> {
+    #[inline(always)]
+    fn position(&self) -> Option<u64> {
+        Some(self.offset() as u64)
+    }
+
     #[inline]
     fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, Self::Error> {
         if self.buf.len() < len {
-            return Err(BytesReadError::InsufficientBytes { expected: len, actual: self.buf.len(), position: 0 });
+            return Err(BytesReadError::InsufficientBytes { expected: len, actual: self.buf.len(), position: self.offset() as u64 });
         }
         let (a, b) = self.buf.split_at(len);
         self.buf = b;
@@ -1011,6 +3157,91 @@ fn test_as_ref_reader() {
     assert_eq!(rd.read_slice(4).unwrap(), Reference::Borrowed(&[7, 8, 9, 10][..]));
 }
 
+/// The outcome of [`PushDecoder::decode`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress<T> {
+    /// A complete value was decoded.
+    Done(T),
+    /// The buffered input ends in the middle of a value, and at least this many more bytes are
+    /// needed to complete it.
+    NeedMoreData(usize),
+}
+
+/// An incremental decoder for input that arrives in chunks, e.g. from non-blocking I/O.
+///
+/// Chunks are buffered with [`PushDecoder::feed`]. Only the length headers of newly arrived data
+/// are inspected until a value is complete, which is then deserialized in a single pass.
+///
+/// ```
+/// use rmp_serde::decode::{Progress, PushDecoder};
+///
+/// let mut dec = PushDecoder::new();
+///
+/// dec.feed(&[0x92, 0xa2]);
+/// assert_eq!(Progress::NeedMoreData(3), dec.decode::<(String, u8)>().unwrap());
+///
+/// dec.feed(&[0x6c, 0x65, 0x2a, 0xc0]);
+/// assert_eq!(Progress::Done(("le".to_owned(), 42)), dec.decode().unwrap());
+/// assert_eq!(Progress::Done(()), dec.decode().unwrap());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct PushDecoder {
+    buf: Vec<u8>,
+    /// Start of the first value that was not decoded yet.
+    start: usize,
+    scanner: decode::Scanner,
+}
+
+#[cfg(feature = "std")]
+impl PushDecoder {
+    /// Creates a new decoder with an empty buffer.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a chunk of input.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        // Drop decoded values once they take up most of the buffer, instead of on every decode.
+        if self.start > 0 && self.start >= self.buf.len() / 2 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Returns the input that was fed, but not decoded yet.
+    #[inline]
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+
+    /// Decodes the next value if it is complete.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value can't be deserialized as `T`, see [`from_slice`]. The value is skipped
+    /// anyway, so decoding can continue with the next one.
+    ///
+    /// Input containing the reserved `0xc1` marker can't be skipped and keeps failing with
+    /// `Error::TypeMismatch`.
+    pub fn decode<T>(&mut self) -> Result<Progress<T>, Error<BytesReadError>>
+    where T: DeserializeOwned
+    {
+        let input = &self.buf[self.start..];
+        let len = match self.scanner.scan(input) {
+            Ok(decode::Scan::Complete(len)) => len,
+            Ok(decode::Scan::Incomplete(needed)) => return Ok(Progress::NeedMoreData(needed)),
+            Err(decode::ScanError::ReservedMarker { .. }) => return Err(Error::type_mismatch(Marker::Reserved, "a value")),
+        };
+        self.scanner.reset();
+        self.start += len;
+        from_slice(&input[..len]).map(Progress::Done)
+    }
+}
+
 /// Deserialize an instance of type `T` from an I/O stream of MessagePack.
 ///
 /// # Errors
@@ -1027,6 +3258,141 @@ where R: RmpRead,
     Deserialize::deserialize(&mut Deserializer::new(rd))
 }
 
+/// Deserializes a value from an I/O stream of MessagePack with the given seed, like
+/// [`from_read`], for stateful deserialization with a [`DeserializeSeed`].
+///
+/// The data is read into temporary buffers, so the value can't borrow from it.
+///
+/// # Errors
+///
+/// Fails like [`from_read`].
+#[inline]
+#[cfg(feature = "std")]
+pub fn from_read_seed<'de, R, S>(rd: R, seed: S) -> Result<S::Value, Error<R::Error>>
+where R: RmpRead,
+      S: DeserializeSeed<'de>
+{
+    seed.deserialize(&mut Deserializer::new(rd))
+}
+
+/// Deserialize an instance of type `T` from a [`bytes::Buf`], such as a frame held in `Bytes` or
+/// `BytesMut`.
+///
+/// The buffer does not need to be contiguous.
+///
+/// # Errors
+///
+/// This conversion can fail if the structure of the Value does not match the structure expected
+/// by `T`. It can also fail if the structure is correct but `T`'s implementation of `Deserialize`
+/// decides that something is wrong with the data, for example required struct fields are missing.
+#[cfg(feature = "bytes")]
+#[inline]
+pub fn from_buf<B, T>(buf: B) -> Result<T, Error<BytesReadError>>
+where B: bytes::Buf,
+      T: DeserializeOwned
+{
+    Deserialize::deserialize(&mut Deserializer::from_buf(buf))
+}
+
+/// A read-only memory map of a MessagePack file.
+///
+/// Values can be deserialized directly from the mapped memory with [`MappedFile::deserialize`],
+/// borrowing strings and byte arrays from the file where the target type allows it.
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct MappedFile {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+impl MappedFile {
+    /// Opens and maps the file at the given path.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated (by this or any other process) while it is
+    /// mapped, otherwise reading from it is undefined behavior.
+    pub unsafe fn open<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+        Self::map(&std::fs::File::open(path)?)
+    }
+
+    /// Maps the given file.
+    ///
+    /// # Safety
+    ///
+    /// See [`MappedFile::open`].
+    #[inline]
+    pub unsafe fn map(file: &std::fs::File) -> io::Result<Self> {
+        Ok(MappedFile { map: memmap2::Mmap::map(file)? })
+    }
+
+    /// Returns the mapped file contents.
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Deserializes an instance of type `T` from the mapped file, with zero-copy if possible.
+    ///
+    /// See [`from_slice`] for details.
+    #[inline]
+    pub fn deserialize<'a, T>(&'a self) -> Result<T, Error<BytesReadError>>
+    where T: Deserialize<'a>
+    {
+        from_slice(self.as_bytes())
+    }
+}
+
+/// Deserialize an instance of type `T` from a memory-mapped file.
+///
+/// This is a faster alternative to calling [`from_read`] on the file: the mapped contents are
+/// deserialized like [`from_slice`], without copying them through a reader. Use [`MappedFile`] to
+/// borrow from the file contents instead.
+///
+/// # Safety
+///
+/// See [`MappedFile::open`].
+///
+/// # Errors
+///
+/// This conversion can fail if the structure of the Value does not match the structure expected
+/// by `T`. It can also fail if the structure is correct but `T`'s implementation of `Deserialize`
+/// decides that something is wrong with the data, for example required struct fields are missing.
+///
+/// Errors are reported like [`from_read`] would:
+///
+/// - failing to map the file is an [`InvalidMarkerRead`](ValueReadError::InvalidMarkerRead) of
+///   the I/O error, before anything is decoded,
+/// - a file ending in the middle of a value is an I/O error of kind
+///   [`UnexpectedEof`](io::ErrorKind::UnexpectedEof).
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+pub unsafe fn from_file<T>(file: &std::fs::File) -> Result<T, Error<io::Error>>
+where T: DeserializeOwned
+{
+    let map = MappedFile::map(file).map_err(ValueReadError::InvalidMarkerRead)?;
+    from_slice(map.as_bytes()).map_err(|err| err.map_read_err(|err| io::Error::new(io::ErrorKind::UnexpectedEof, err)))
+}
+
+/// Deserialize an instance of type `T` from the memory-mapped file at the given path.
+///
+/// See [`from_file`] for details. Failing to open the file is reported like failing to map it, as
+/// an [`InvalidMarkerRead`](ValueReadError::InvalidMarkerRead) of the I/O error.
+///
+/// # Safety
+///
+/// See [`MappedFile::open`].
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+pub unsafe fn from_path<P, T>(path: P) -> Result<T, Error<io::Error>>
+where P: AsRef<std::path::Path>,
+      T: DeserializeOwned
+{
+    let file = std::fs::File::open(path).map_err(ValueReadError::InvalidMarkerRead)?;
+    from_file(&file)
+}
+
 /// Deserialize a temporary scope-bound instance of type `T` from a slice, with zero-copy if possible.
 ///
 /// Deserialization will be performed in zero-copy manner whenever it is possible, borrowing the
@@ -1064,6 +3430,132 @@ where
     Deserialize::deserialize(&mut de)
 }
 
+/// Deserializes a value from a byte slice with the given seed, like [`from_slice`], for stateful
+/// deserialization with a [`DeserializeSeed`].
+///
+/// # Errors
+///
+/// Fails like [`from_slice`].
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use rmp_serde::intern::{InternSeed, Interner};
+///
+/// let mut interner = Interner::new();
+/// let a = rmp_serde::from_slice_seed(&[0xa3, b'f', b'o', b'o'], InternSeed(&mut interner)).unwrap();
+/// let b = rmp_serde::from_slice_seed(&[0xa3, b'f', b'o', b'o'], InternSeed(&mut interner)).unwrap();
+/// assert!(Arc::ptr_eq(&a, &b));
+/// ```
+#[inline]
+#[allow(deprecated)]
+pub fn from_slice_seed<'a, S>(bytes: &'a [u8], seed: S) -> Result<S::Value, Error<BytesReadError>>
+where
+    S: DeserializeSeed<'a>
+{
+    seed.deserialize(&mut Deserializer::from_bytes(bytes))
+}
+
+/// Deserializes an instance of type `T` from a byte slice, like [`from_slice`], but with a
+/// deserializer that reports itself as human readable, see [`Deserializer::with_human_readable`].
+///
+/// This reads the data written by [`to_vec_human_readable`](crate::to_vec_human_readable), or by
+/// older versions of rmp-serde.
+///
+/// # Errors
+///
+/// Fails like [`from_slice`].
+#[inline]
+#[allow(deprecated)]
+pub fn from_slice_human_readable<'a, T>(bytes: &'a [u8]) -> Result<T, Error<BytesReadError>>
+where
+    T: Deserialize<'a>
+{
+    let mut de = Deserializer::from_bytes(bytes).with_human_readable();
+    Deserialize::deserialize(&mut de)
+}
+
+/// Deserializes an instance of type `T` from MessagePack data encoded as base64, like the output
+/// of [`to_base64_string`](crate::to_base64_string).
+///
+/// Both padded and unpadded base64 in the standard alphabet are accepted. The data is decoded
+/// into a temporary buffer first, so `T` can't borrow from it.
+///
+/// # Errors
+///
+/// Fails with a [`Error::Syntax`] error if the string isn't valid base64, or like
+/// [`from_slice`].
+#[cfg(feature = "base64")]
+pub fn from_base64_str<T: DeserializeOwned>(s: &str) -> Result<T, Error<BytesReadError>> {
+    use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+    use base64::Engine;
+
+    const ENGINE: GeneralPurpose = GeneralPurpose::new(
+        &base64::alphabet::STANDARD,
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+    let buf = ENGINE.decode(s).map_err(|err| Error::Syntax(format!("invalid base64: {}", err)))?;
+    from_slice(&buf)
+}
+
+/// Deserializes an instance of type `T` from MessagePack data encoded as hex, like the output of
+/// [`to_hex_string`](crate::to_hex_string).
+///
+/// Both lowercase and uppercase digits are accepted, and ASCII whitespace is ignored, so hex dumps
+/// such as `"92 01 a1 61"` can be used directly. The data is decoded into a temporary buffer
+/// first, so `T` can't borrow from it.
+///
+/// # Errors
+///
+/// Fails with a [`Error::Syntax`] error if the string isn't valid hex, or like [`from_slice`].
+///
+/// # Examples
+///
+/// ```
+/// let val: (u8, String) = rmp_serde::from_hex_str("92 01 a1 61").unwrap();
+/// assert_eq!((1, "a".to_owned()), val);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn from_hex_str<T: DeserializeOwned>(s: &str) -> Result<T, Error<BytesReadError>> {
+    let mut buf = Vec::with_capacity(s.len() / 2);
+    let mut high = None;
+    for (pos, c) in s.char_indices() {
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        let Some(digit) = c.to_digit(16) else {
+            return Err(de::Error::custom(format_args!("invalid hex digit {:?} at offset {}", c, pos)));
+        };
+        match high.take() {
+            Some(high) => buf.push((high << 4 | digit) as u8),
+            None => high = Some(digit),
+        }
+    }
+    if high.is_some() {
+        return Err(de::Error::custom("odd number of hex digits"));
+    }
+    from_slice(&buf)
+}
+
+/// Returns the length of the value at `pos` in the buffer, without decoding it.
+#[cfg(feature = "std")]
+pub(crate) fn value_len(buf: &[u8], pos: usize) -> Result<usize, Error<BytesReadError>> {
+    match decode::scan_value(&buf[pos..]) {
+        Ok(decode::Scan::Complete(len)) => Ok(len),
+        Ok(decode::Scan::Incomplete(needed)) => {
+            let actual = buf.len() - pos;
+            Err(Error::InvalidValueRead(ValueReadError::InvalidDataRead(BytesReadError::InsufficientBytes {
+                expected: actual + needed,
+                actual,
+                position: pos as u64,
+            })))
+        }
+        Err(decode::ScanError::ReservedMarker { .. }) => Err(Error::type_mismatch(Marker::Reserved, "a value")),
+    }
+}
+
 pub use rmp::decode::bytes::BytesReadError;
 
 /*
@@ -0,0 +1,288 @@
+//! Adapters for [`embedded-io`](https://docs.rs/embedded-io) readers and writers.
+//!
+//! [`IoReader`] and [`IoWriter`] implement the reader and writer traits of this crate over
+//! [`embedded_io::Read`](::embedded_io::Read) and [`embedded_io::Write`](::embedded_io::Write).
+//! [`write`] streams a value to a UART or other transport without an intermediate buffer. The
+//! deserializer needs `std` to read from a reader directly, with
+//! `rmp_serde::from_read(IoReader::new(rd))`, so [`read`] reads the bytes of one value into a
+//! buffer provided by the caller and deserializes it from there instead.
+//!
+//! ```
+//! let mut out = [0; 16];
+//! rmp_serde::embedded_io::write(&mut out[..], &(42, "a")).unwrap();
+//!
+//! let mut buf = [0; 16];
+//! let val: (u8, &str) = rmp_serde::embedded_io::read(&out[..], &mut buf).unwrap();
+//! assert_eq!((42, "a"), val);
+//! ```
+//!
+//! With the `embedded-io-async` feature, [`read_async`] and [`write_async`] do the same over
+//! [`embedded_io_async`](https://docs.rs/embedded-io-async) readers and writers. As the serializer
+//! isn't async, [`write_async`] also goes through a buffer.
+
+use core::fmt::{self, Debug, Display, Formatter};
+#[cfg(feature = "std")]
+use std::error;
+
+use ::embedded_io::{Read, ReadExactError, Write};
+use rmp::decode::{RmpRead, RmpReadErr, Scan, ScanError, ValueReadError};
+use rmp::encode::{RmpWrite, RmpWriteErr};
+use rmp::Marker;
+use serde::{Deserialize, Serialize};
+
+use crate::{decode, encode};
+
+/// The error of an [`IoReader`] or an [`IoWriter`].
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum IoError<E> {
+    /// The underlying reader or writer failed.
+    Io(E),
+    /// The reader reached EOF in the middle of a value.
+    UnexpectedEof,
+    /// The value doesn't fit in the buffer given to [`read_async`] or [`write_async`].
+    BufferFull,
+}
+
+impl<E: Debug> Display for IoError<E> {
+    #[cold]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            // embedded-io errors are only required to implement `Debug`.
+            IoError::Io(ref err) => write!(f, "I/O error: {:?}", err),
+            IoError::UnexpectedEof => f.write_str("unexpected end of input"),
+            IoError::BufferFull => f.write_str("buffer is full"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Debug> error::Error for IoError<E> {}
+
+impl<E> From<ReadExactError<E>> for IoError<E> {
+    #[cold]
+    fn from(err: ReadExactError<E>) -> Self {
+        match err {
+            ReadExactError::UnexpectedEof => IoError::UnexpectedEof,
+            ReadExactError::Other(err) => IoError::Io(err),
+        }
+    }
+}
+
+impl<E: Debug + 'static> RmpReadErr for IoError<E> {}
+
+impl<E: Debug + 'static> RmpWriteErr for IoError<E> {}
+
+/// Reader wrapper for [`embedded_io::Read`](::embedded_io::Read) implementations.
+#[derive(Debug, Default)]
+pub struct IoReader<R> {
+    rd: R,
+}
+
+impl<R> IoReader<R> {
+    /// Wraps the given reader.
+    #[inline]
+    pub fn new(rd: R) -> Self {
+        IoReader { rd }
+    }
+
+    /// Gets a reference to the underlying reader.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.rd
+    }
+
+    /// Unwraps this `IoReader`, returning the underlying reader.
+    #[inline(always)]
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+}
+
+impl<R: Read> RmpRead for IoReader<R>
+where R::Error: 'static
+{
+    type Error = IoError<R::Error>;
+
+    #[inline]
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.rd.read_exact(buf).map_err(IoError::from)
+    }
+}
+
+/// Writer wrapper for [`embedded_io::Write`](::embedded_io::Write) implementations.
+///
+/// Nothing is buffered, but the underlying writer may be, see [`IoWriter::get_mut`].
+#[derive(Debug, Default)]
+pub struct IoWriter<W> {
+    wr: W,
+}
+
+impl<W> IoWriter<W> {
+    /// Wraps the given writer.
+    #[inline]
+    pub fn new(wr: W) -> Self {
+        IoWriter { wr }
+    }
+
+    /// Gets a reference to the underlying writer.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Gets a mutable reference to the underlying writer, e.g. to flush it.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr
+    }
+
+    /// Unwraps this `IoWriter`, returning the underlying writer.
+    #[inline(always)]
+    pub fn into_inner(self) -> W {
+        self.wr
+    }
+}
+
+impl<W: Write> RmpWrite for IoWriter<W>
+where W::Error: 'static
+{
+    type Error = IoError<W::Error>;
+
+    #[inline]
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.wr.write_all(buf).map_err(IoError::Io)
+    }
+}
+
+/// Reads a value from the reader into `buf`, and deserializes it from there.
+///
+/// Only the bytes of the value are read, the reader is left at the start of the next one. The
+/// value may borrow from `buf`.
+///
+/// # Errors
+///
+/// Fails with [`IoError::BufferFull`] if the value is longer than `buf`, or if the value can't be
+/// read or deserialized as `T`.
+pub fn read<'a, R, T>(mut rd: R, buf: &'a mut [u8]) -> Result<T, decode::Error<IoError<R::Error>>>
+where R: Read,
+      R::Error: 'static,
+      T: Deserialize<'a>
+{
+    let mut len = 0;
+    while let Some(needed) = missing_len(&buf[..len])? {
+        let chunk = buf.get_mut(len..len + needed).ok_or_else(|| read_err(IoError::BufferFull))?;
+        rd.read_exact(chunk).map_err(|err| read_err(err.into()))?;
+        len += needed;
+    }
+    from_complete_slice(&buf[..len])
+}
+
+/// Serializes the value into the writer, using the compact representation like
+/// [`crate::write`].
+///
+/// The writer isn't flushed.
+#[inline]
+pub fn write<W, T>(wr: W, val: &T) -> Result<(), encode::Error<IoError<W::Error>>>
+where W: Write,
+      W::Error: 'static,
+      T: Serialize + ?Sized
+{
+    encode::write(IoWriter::new(wr), val)
+}
+
+/// Reads a value from the async reader into `buf`, and deserializes it from there.
+///
+/// Only the bytes of the value are read, the reader is left at the start of the next one. The
+/// value may borrow from `buf`.
+///
+/// # Errors
+///
+/// Fails with [`IoError::BufferFull`] if the value is longer than `buf`, or if the value can't be
+/// read or deserialized as `T`.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// let rd = &[0x92, 0x2a, 0xa1, b'a'][..];
+/// let mut buf = [0; 16];
+/// let val: (u8, &str) = rmp_serde::embedded_io::read_async(rd, &mut buf).await.unwrap();
+/// assert_eq!((42, "a"), val);
+/// # });
+/// ```
+#[cfg(feature = "embedded-io-async")]
+pub async fn read_async<'a, R, T>(mut rd: R, buf: &'a mut [u8]) -> Result<T, decode::Error<IoError<R::Error>>>
+where R: embedded_io_async::Read,
+      R::Error: 'static,
+      T: Deserialize<'a>
+{
+    let mut len = 0;
+    while let Some(needed) = missing_len(&buf[..len])? {
+        let chunk = buf.get_mut(len..len + needed).ok_or_else(|| read_err(IoError::BufferFull))?;
+        rd.read_exact(chunk).await.map_err(|err| read_err(err.into()))?;
+        len += needed;
+    }
+    from_complete_slice(&buf[..len])
+}
+
+/// Serializes the value into `buf`, using the compact representation like [`crate::write`], and
+/// writes it to the async writer.
+///
+/// The writer isn't flushed.
+///
+/// # Errors
+///
+/// Fails with [`IoError::BufferFull`] if the value is longer than `buf`, or if the value can't be
+/// serialized or written.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// let mut out = [0; 4];
+/// let mut buf = [0; 16];
+/// rmp_serde::embedded_io::write_async(&mut out[..], &mut buf, &(42, "a")).await.unwrap();
+/// assert_eq!([0x92, 0x2a, 0xa1, b'a'], out);
+/// # });
+/// ```
+#[cfg(feature = "embedded-io-async")]
+pub async fn write_async<W, T>(mut wr: W, buf: &mut [u8], val: &T) -> Result<(), encode::Error<IoError<W::Error>>>
+where W: embedded_io_async::Write,
+      W::Error: 'static,
+      T: Serialize + ?Sized
+{
+    use rmp::encode::ValueWriteError;
+
+    let len = encode::write_to_slice(buf, val).map_err(|err| err.map_write_err(|_| IoError::BufferFull))?;
+    wr.write_all(&buf[..len]).await
+        .map_err(|err| encode::Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(IoError::Io(err))))
+}
+
+#[inline]
+fn read_err<E>(err: IoError<E>) -> decode::Error<IoError<E>> {
+    decode::Error::InvalidValueRead(ValueReadError::InvalidDataRead(err))
+}
+
+/// Returns how many more bytes the value at the start of `buf` needs at least, or `None` if it's
+/// complete.
+fn missing_len<E>(buf: &[u8]) -> Result<Option<usize>, decode::Error<IoError<E>>> {
+    match rmp::decode::scan_value(buf) {
+        Ok(Scan::Complete(_)) => Ok(None),
+        Ok(Scan::Incomplete(needed)) => Ok(Some(needed)),
+        Err(ScanError::ReservedMarker { .. }) => Err(decode::Error::type_mismatch(Marker::Reserved, "a value")),
+    }
+}
+
+fn from_complete_slice<'a, T, E>(buf: &'a [u8]) -> Result<T, decode::Error<IoError<E>>>
+where T: Deserialize<'a>
+{
+    // The value is complete, so running out of bytes can't happen.
+    decode::from_slice(buf).map_err(|err| err.map_read_err(|_| IoError::UnexpectedEof))
+}
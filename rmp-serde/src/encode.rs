@@ -3,6 +3,12 @@
 use core::fmt::{self, Display, Debug};
 #[cfg(feature = "std")]
 use std::error;
+#[cfg(feature = "std")]
+use std::io::{self, Seek, SeekFrom, Write};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::{String, ToString};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
 
 use serde::ser::{
     SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
@@ -10,12 +16,18 @@ use serde::ser::{
 };
 use serde::Serialize;
 
+#[cfg(feature = "std")]
+use rmp::decode::{scan_value, Scan};
 use rmp::encode::{self, ValueWriteError, RmpWrite, RmpWriteErr};
+use rmp::Marker;
 
 use crate::config::{
-    BinaryConfig, DefaultConfig, HumanReadableConfig, SerializerConfig, StructMapConfig,
-    StructTupleConfig
+    BinaryConfig, DefaultConfig, HumanReadableConfig, Int128Repr, RuntimeConfig, SerializerConfig,
+    StructHashConfig, StructMapConfig, StructTupleConfig
 };
+use crate::decode::NumStr;
+#[cfg(feature = "std")]
+use crate::hook::{HookWriter, WriteHook};
 use crate::MSGPACK_EXT_STRUCT_NAME;
 
 /// This type represents all possible errors that can occur when serializing or
@@ -31,11 +43,31 @@ pub enum Error<W> {
     /// Depth limit exceeded
     DepthLimitExceeded,
     /// Catchall for syntax error messages.
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     Syntax(String),
-    /// Catchall for syntax error messages.
-    #[cfg(not(feature = "std"))]
-    Syntax(),
+    /// Catchall for syntax error messages, truncated to
+    /// [`ErrorMessage::CAPACITY`](crate::decode::ErrorMessage::CAPACITY) bytes.
+    #[cfg(not(feature = "alloc"))]
+    Syntax(crate::decode::ErrorMessage),
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<W> Error<W> {
+    /// Converts the error of the writer with `f`, keeping the other errors as they are.
+    pub(crate) fn map_write_err<V>(self, f: impl FnOnce(W) -> V) -> Error<V> {
+        match self {
+            Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(err)) => {
+                Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(f(err)))
+            }
+            Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)) => {
+                Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(f(err)))
+            }
+            Error::UnknownLength => Error::UnknownLength,
+            Error::InvalidDataModel(msg) => Error::InvalidDataModel(msg),
+            Error::DepthLimitExceeded => Error::DepthLimitExceeded,
+            Error::Syntax(msg) => Error::Syntax(msg),
+        }
+    }
 }
 
 #[cfg(feature = "std")]
@@ -68,10 +100,29 @@ impl<W: Display> Display for Error<W> {
             }
             Error::InvalidDataModel(r) => write!(f, "serialize data model is invalid: {}", r),
             Error::DepthLimitExceeded => f.write_str("depth limit exceeded"),
-            #[cfg(feature = "std")]
+            #[cfg(feature = "alloc")]
             Error::Syntax(ref msg) => f.write_str(msg),
-            #[cfg(not(feature = "std"))]
-            Error::Syntax() => f.write_str("syntex error"),
+            #[cfg(not(feature = "alloc"))]
+            Error::Syntax(ref msg) => Display::fmt(msg, f),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<W: defmt::Format> defmt::Format for Error<W> {
+    #[cold]
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        match *self {
+            Error::InvalidValueWrite(ref err) => defmt::write!(fmt, "invalid value write: {}", err),
+            Error::UnknownLength => {
+                defmt::write!(fmt, "attempt to serialize struct, sequence or map with unknown length")
+            }
+            Error::InvalidDataModel(r) => defmt::write!(fmt, "serialize data model is invalid: {=str}", r),
+            Error::DepthLimitExceeded => defmt::write!(fmt, "depth limit exceeded"),
+            #[cfg(feature = "alloc")]
+            Error::Syntax(ref msg) => defmt::write!(fmt, "{=str}", msg),
+            #[cfg(not(feature = "alloc"))]
+            Error::Syntax(ref msg) => defmt::write!(fmt, "{}", msg),
         }
     }
 }
@@ -80,14 +131,11 @@ impl<W: RmpWriteErr> serde::ser::Error for Error<W> {
     /// Raised when there is general error when deserializing a type.
     #[cold]
     fn custom<T: Display>(msg: T) -> Error<W> {
-        #[cfg(feature = "std")]
+        #[cfg(feature = "alloc")]
         return Error::Syntax(msg.to_string());
 
-        #[cfg(not(feature = "std"))]
-        {
-            let _ = msg; // TODO
-            return Error::Syntax();
-        }
+        #[cfg(not(feature = "alloc"))]
+        return Error::Syntax(crate::decode::ErrorMessage::new(msg));
     }
 }
 
@@ -127,8 +175,9 @@ pub trait UnderlyingWrite {
 // TODO: Docs. Examples.
 #[derive(Debug)]
 pub struct Serializer<W, C = DefaultConfig> {
-    wr: W,
+    wr: CountingWrite<W>,
     config: C,
+    options: RuntimeConfig,
     depth: usize,
 }
 
@@ -136,21 +185,40 @@ impl<W, C> Serializer<W, C> {
     /// Gets a reference to the underlying writer.
     #[inline(always)]
     pub fn get_ref(&self) -> &W {
-        &self.wr
+        &self.wr.wr
     }
 
     /// Gets a mutable reference to the underlying writer.
     ///
-    /// It is inadvisable to directly write to the underlying writer.
+    /// It is inadvisable to directly write to the underlying writer. Such writes aren't counted
+    /// by [`Self::bytes_written`].
     #[inline(always)]
     pub fn get_mut(&mut self) -> &mut W {
-        &mut self.wr
+        &mut self.wr.wr
     }
 
     /// Unwraps this `Serializer`, returning the underlying writer.
     #[inline(always)]
     pub fn into_inner(self) -> W {
-        self.wr
+        self.wr.wr
+    }
+
+    /// Returns the number of bytes written by this serializer so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Vec::new());
+    /// "hello".serialize(&mut se).unwrap();
+    /// assert_eq!(6, se.bytes_written());
+    /// 42.serialize(&mut se).unwrap();
+    /// assert_eq!(7, se.bytes_written());
+    /// ```
+    #[inline]
+    pub fn bytes_written(&self) -> u64 {
+        self.wr.written
     }
 
     /// Changes the maximum nesting depth that is allowed.
@@ -174,9 +242,15 @@ impl<W: RmpWrite> Serializer<W, DefaultConfig> {
     #[inline]
     pub fn new(wr: W) -> Self {
         Serializer {
-            wr,
+            wr: CountingWrite {
+                wr,
+                written: 0,
+                #[cfg(feature = "std")]
+                backpatch: None,
+            },
             depth: 1024,
             config: DefaultConfig,
+            options: RuntimeConfig::default(),
         }
     }
 }
@@ -187,27 +261,398 @@ impl<'a, W: RmpWrite + 'a, C> Serializer<W, C> {
         let c = Compound { se: self };
         Ok(c)
     }
+
+    /// Writes a 128-bit integer, given as big-endian bytes and formatted in decimal.
+    fn write_int128(&mut self, bytes: [u8; 16], decimal: fmt::Arguments<'_>) -> Result<(), Error<W::Error>> {
+        match self.options.int128_repr {
+            Int128Repr::Bin => {
+                bin_header(16, &self.options).write_with(&mut self.wr, &bytes)?;
+            }
+            Int128Repr::Ext(..) if self.options.old_spec => {
+                return Err(Error::InvalidDataModel("ext types are not supported by the old spec"));
+            }
+            Int128Repr::Ext(tag) => {
+                encode::write_ext_meta(&mut self.wr, 16, tag)?;
+                self.wr.write_bytes(&bytes).map_err(ValueWriteError::InvalidDataWrite)?;
+            }
+            Int128Repr::String => {
+                let mut buf = NumStr::new();
+                // The buffer fits any 128-bit integer.
+                fmt::write(&mut buf, decimal).map_err(|_| Error::InvalidDataModel("integer too long"))?;
+                str_header(buf.as_str().len() as u32, &self.options).write_with(&mut self.wr, buf.as_str().as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the float as an integer if it has an integral value that fits in `i64` or `u64`.
+    ///
+    /// Returns `false` without writing anything otherwise.
+    fn write_integral_float(&mut self, v: f64) -> Result<bool, Error<W::Error>> {
+        // Both bounds are exactly representable, and casts truncate towards zero.
+        if v.is_sign_positive() && v < 18446744073709551616.0 {
+            let n = v as u64;
+            if n as f64 == v {
+                encode::write_uint(&mut self.wr, n)?;
+                return Ok(true);
+            }
+        } else if (-9223372036854775808.0..0.0).contains(&v) {
+            let n = v as i64;
+            if n as f64 == v {
+                encode::write_sint(&mut self.wr, n)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
 }
 
 #[cfg(feature = "std")]
 impl<'a, W: RmpWrite + 'a, C: SerializerConfig> Serializer<W, C> {
     #[inline]
-    fn maybe_unknown_len_compound<F>(&'a mut self, len: Option<usize>, f: F) -> Result<MaybeUnknownLengthCompound<'a, W, C>, Error<W::Error>>
-    where F: Fn(&mut W, u32) -> Result<rmp::Marker, ValueWriteError<W::Error>>
+    fn maybe_unknown_len_compound<F>(&'a mut self, len: Option<usize>, f: F, marker: Marker) -> Result<MaybeUnknownLengthCompound<'a, W, C>, Error<W::Error>>
+    where F: Fn(&mut CountingWrite<W>, u32, &RuntimeConfig) -> Result<(), Error<W::Error>>
     {
-        Ok(MaybeUnknownLengthCompound {
-            compound: match len {
-                Some(len) => {
-                    f(&mut self.wr, len as u32)?;
+        let mut pending = None;
+        let compound = match len {
+            Some(len) => {
+                f(&mut self.wr, len as u32, &self.options)?;
+                None
+            }
+            None => match self.wr.reserve_len(marker)? {
+                Some(pos) => {
+                    pending = Some(PendingLen { pos, count: 0 });
                     None
                 }
                 None => Some(UnknownLengthCompound::from(&*self)),
             },
+        };
+        Ok(MaybeUnknownLengthCompound {
             se: self,
+            compound,
+            pending,
+            struct_map: None,
         })
     }
 }
 
+impl<W: RmpWrite, C: SerializerConfig> Serializer<W, C> {
+    /// Serializes the items of an iterator as an array of `len` elements.
+    ///
+    /// Items are serialized one by one as the iterator yields them, so large datasets don't need
+    /// to be collected into a `Vec` first.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing fails, if an item fails to serialize, or if the iterator doesn't yield
+    /// exactly `len` items. In the latter case the output is incomplete and must be discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut se = rmp_serde::Serializer::new(Vec::new());
+    /// se.serialize_iter(3, (1..=3).map(|n| n * 10)).unwrap();
+    ///
+    /// assert_eq!(vec![0x93, 0x0a, 0x14, 0x1e], se.into_inner());
+    /// ```
+    pub fn serialize_iter<I>(&mut self, len: usize, iter: I) -> Result<(), Error<W::Error>>
+    where I: IntoIterator,
+          I::Item: Serialize
+    {
+        let len32 = u32::try_from(len)
+            .map_err(|_| Error::InvalidDataModel("too many elements for a MessagePack array"))?;
+        write_array_len(&mut self.wr, len32, &self.options)?;
+
+        let mut left = len;
+        for item in iter {
+            left = left.checked_sub(1)
+                .ok_or(Error::InvalidDataModel("iterator yielded more items than its declared length"))?;
+            item.serialize(&mut *self)?;
+        }
+        if left != 0 {
+            return Err(Error::InvalidDataModel("iterator yielded fewer items than its declared length"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write + Seek, C: SerializerConfig> Serializer<W, C> {
+    /// Serializes the items of an iterator as an array, without knowing their count in advance.
+    ///
+    /// An `array32` header is reserved and the count is patched in once the iterator is exhausted,
+    /// so items are written as they come instead of being buffered. This allows streaming e.g. a
+    /// database cursor into a file. Afterwards the writer is positioned at the end of the array.
+    ///
+    /// Sequences and maps of unknown length serialized through serde are back-patched too with
+    /// [`Self::with_backpatch`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing or seeking fails, if an item fails to serialize, or if there are more than
+    /// `u32::MAX` items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Cursor::new(Vec::new()));
+    /// se.collect_seq_backpatched((0..10).filter(|n| n % 4 == 0)).unwrap();
+    ///
+    /// let buf = se.into_inner().into_inner();
+    /// assert_eq!(vec![0xdd, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x08], buf);
+    /// assert_eq!(vec![0, 4, 8], rmp_serde::from_slice::<Vec<u8>>(&buf).unwrap());
+    /// ```
+    pub fn collect_seq_backpatched<I>(&mut self, iter: I) -> Result<(), Error<io::Error>>
+    where I: IntoIterator,
+          I::Item: Serialize
+    {
+        let start = self.wr.wr.stream_position().map_err(ValueWriteError::InvalidMarkerWrite)?;
+        self.wr.write_bytes(&[rmp::Marker::Array32.to_u8(), 0, 0, 0, 0])
+            .map_err(ValueWriteError::InvalidMarkerWrite)?;
+
+        let mut len = 0u32;
+        for item in iter {
+            len = len.checked_add(1)
+                .ok_or(Error::InvalidDataModel("too many elements for a MessagePack array"))?;
+            item.serialize(&mut *self)?;
+        }
+
+        let end = self.wr.wr.stream_position().map_err(ValueWriteError::InvalidDataWrite)?;
+        self.wr.wr.seek(SeekFrom::Start(start + 1)).map_err(ValueWriteError::InvalidDataWrite)?;
+        self.wr.wr.write_all(&len.to_be_bytes()).map_err(ValueWriteError::InvalidDataWrite)?;
+        self.wr.wr.seek(SeekFrom::Start(end)).map_err(ValueWriteError::InvalidDataWrite)?;
+        Ok(())
+    }
+}
+
+/// A slot reserved in the output with [`Serializer::reserve`], to be filled in with
+/// [`Serializer::patch`].
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pos: u64,
+    len: usize,
+}
+
+#[cfg(feature = "std")]
+impl Slot {
+    /// Returns the position of the slot in the output.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the size of the slot.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the slot is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// A writer that allows overwriting data written before, see [`Serializer::reserve`].
+///
+/// Implemented for `Vec<u8>` and for seekable writers.
+#[cfg(feature = "std")]
+pub trait PatchWrite: Write {
+    /// Returns the position where the next write goes.
+    fn position(&mut self) -> io::Result<u64>;
+
+    /// Overwrites the data at `pos`, which must have been written already. Writing continues
+    /// where it was.
+    fn patch(&mut self, pos: u64, data: &[u8]) -> io::Result<()>;
+}
+
+#[cfg(feature = "std")]
+impl PatchWrite for Vec<u8> {
+    #[inline]
+    fn position(&mut self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+
+    fn patch(&mut self, pos: u64, data: &[u8]) -> io::Result<()> {
+        usize::try_from(pos).ok()
+            .and_then(|pos| self.get_mut(pos..pos.checked_add(data.len())?))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "patch beyond the written data"))?
+            .copy_from_slice(data);
+        Ok(())
+    }
+}
+
+/// Patches by seeking back and forth.
+#[cfg(feature = "std")]
+fn seek_patch<W: Write + Seek>(wr: &mut W, pos: u64, data: &[u8]) -> io::Result<()> {
+    let end = wr.stream_position()?;
+    if pos.checked_add(data.len() as u64).is_none_or(|patch_end| patch_end > end) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "patch beyond the written data"));
+    }
+    wr.seek(SeekFrom::Start(pos))?;
+    wr.write_all(data)?;
+    wr.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+impl<T: AsRef<[u8]>> PatchWrite for io::Cursor<T> where io::Cursor<T>: Write {
+    #[inline]
+    fn position(&mut self) -> io::Result<u64> {
+        Ok(io::Cursor::position(self))
+    }
+
+    #[inline]
+    fn patch(&mut self, pos: u64, data: &[u8]) -> io::Result<()> {
+        seek_patch(self, pos, data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl PatchWrite for std::fs::File {
+    #[inline]
+    fn position(&mut self) -> io::Result<u64> {
+        self.stream_position()
+    }
+
+    #[inline]
+    fn patch(&mut self, pos: u64, data: &[u8]) -> io::Result<()> {
+        seek_patch(self, pos, data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write + Seek> PatchWrite for io::BufWriter<W> {
+    #[inline]
+    fn position(&mut self) -> io::Result<u64> {
+        self.stream_position()
+    }
+
+    #[inline]
+    fn patch(&mut self, pos: u64, data: &[u8]) -> io::Result<()> {
+        seek_patch(self, pos, data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: PatchWrite + ?Sized> PatchWrite for &mut T {
+    #[inline]
+    fn position(&mut self) -> io::Result<u64> {
+        (**self).position()
+    }
+
+    #[inline]
+    fn patch(&mut self, pos: u64, data: &[u8]) -> io::Result<()> {
+        (**self).patch(pos, data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: PatchWrite, C> Serializer<W, C> {
+    /// Consumes this serializer returning the new one, which will write sequences and maps of
+    /// unknown length directly, patching their length in once they end.
+    ///
+    /// By default such sequences and maps, e.g. from `serialize_seq(None)`,
+    /// [`serde::Serializer::collect_seq`] on an iterator without an exact size, or structs with
+    /// `#[serde(flatten)]` fields, are buffered in memory until their length is known. With this
+    /// option an `array32` or `map32` header is written instead, so that large sequences can be
+    /// streamed, e.g. from a database cursor into a file. Structs whose fields may be skipped are
+    /// still buffered.
+    ///
+    /// Positions are computed from the position of the writer when the first length is reserved
+    /// and from [`Self::bytes_written`], so the writer must not be written to nor moved through
+    /// [`Self::get_mut`] afterwards. [`Self::with_write_hook`] turns this option off.
+    ///
+    /// Failing to patch the writer is reported as `Error::Syntax`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serializer;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Vec::new()).with_backpatch();
+    /// se.collect_seq((0..10).filter(|n| n % 4 == 0)).unwrap();
+    ///
+    /// let buf = se.into_inner();
+    /// assert_eq!(vec![0xdd, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x08], buf);
+    /// assert_eq!(vec![0, 4, 8], rmp_serde::from_slice::<Vec<u8>>(&buf).unwrap());
+    /// ```
+    #[inline]
+    pub fn with_backpatch(mut self) -> Self {
+        self.wr.backpatch = Some(Backpatch {
+            position: W::position,
+            patch: W::patch,
+            base: None,
+        });
+        self
+    }
+
+    /// Writes `len` zero bytes and returns their slot, to be filled in with [`Self::patch`] once
+    /// its value is known, e.g. a length prefix, a count or a checksum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// // A frame prefixed with the length of the message.
+    /// let mut se = rmp_serde::Serializer::new(Vec::new());
+    /// let slot = se.reserve(4).unwrap();
+    /// ("hello", 42).serialize(&mut se).unwrap();
+    /// let len = se.get_ref().len() - 4;
+    /// se.patch(slot, &(len as u32).to_be_bytes()).unwrap();
+    ///
+    /// assert_eq!(vec![0, 0, 0, 8, 0x92, 0xa5, b'h', b'e', b'l', b'l', b'o', 0x2a], se.into_inner());
+    /// ```
+    pub fn reserve(&mut self, len: usize) -> Result<Slot, Error<io::Error>> {
+        let pos = self.wr.wr.position().map_err(ValueWriteError::InvalidDataWrite)?;
+        let zeros = [0; 64];
+        let mut left = len;
+        while left > 0 {
+            let n = left.min(zeros.len());
+            self.wr.write_bytes(&zeros[..n]).map_err(ValueWriteError::InvalidDataWrite)?;
+            left -= n;
+        }
+        Ok(Slot { pos, len })
+    }
+
+    /// Fills in a slot returned by [`Self::reserve`]. Writing continues where it was.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidDataModel` if `data` doesn't have the size of the slot, or if
+    /// patching the writer fails.
+    pub fn patch(&mut self, slot: Slot, data: &[u8]) -> Result<(), Error<io::Error>> {
+        if data.len() != slot.len {
+            return Err(Error::InvalidDataModel("patch doesn't have the size of the reserved slot"));
+        }
+        self.wr.wr.patch(slot.pos, data).map_err(ValueWriteError::InvalidDataWrite)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: RmpWrite, C> Serializer<W, C> {
+    /// Consumes this serializer returning the new one, which will call the hook for every value
+    /// it writes, see [`crate::hook`].
+    ///
+    /// [`Self::bytes_written`] counts the bytes before the hook replaces any strs.
+    #[inline]
+    pub fn with_write_hook<H: WriteHook>(self, hook: H) -> Serializer<HookWriter<W, H>, C> {
+        let Serializer { wr, depth, config, options } = self;
+        Serializer {
+            wr: CountingWrite { wr: HookWriter::new(wr.wr, hook), written: wr.written, backpatch: None },
+            depth,
+            config,
+            options,
+        }
+    }
+}
+
 impl<W, C> Serializer<W, C> {
     /// Consumes this serializer returning the new one, which will serialize structs as a map.
     ///
@@ -215,11 +660,29 @@ impl<W, C> Serializer<W, C> {
     /// requirements.
     #[inline]
     pub fn with_struct_map(self) -> Serializer<W, StructMapConfig<C>> {
-        let Serializer { wr, depth, config } = self;
+        let Serializer { wr, depth, config, options } = self;
         Serializer {
             wr,
             depth,
             config: StructMapConfig::new(config),
+            options,
+        }
+    }
+
+    /// Consumes this serializer returning the new one, which will serialize structs as a map keyed
+    /// by a 32-bit hash of each field name, see [`StructHashConfig`].
+    ///
+    /// This keeps the flexibility of [`Self::with_struct_map`] at close to the size of the tuple
+    /// representation. Read it back with
+    /// [`Deserializer::with_hashed_fields`](crate::Deserializer::with_hashed_fields).
+    #[inline]
+    pub fn with_struct_hash(self) -> Serializer<W, StructHashConfig<C>> {
+        let Serializer { wr, depth, config, options } = self;
+        Serializer {
+            wr,
+            depth,
+            config: StructHashConfig::new(config),
+            options,
         }
     }
 
@@ -230,11 +693,12 @@ impl<W, C> Serializer<W, C> {
     /// representation.
     #[inline]
     pub fn with_struct_tuple(self) -> Serializer<W, StructTupleConfig<C>> {
-        let Serializer { wr, depth, config } = self;
+        let Serializer { wr, depth, config, options } = self;
         Serializer {
             wr,
             depth,
             config: StructTupleConfig::new(config),
+            options,
         }
     }
 
@@ -247,11 +711,12 @@ impl<W, C> Serializer<W, C> {
     /// versions of `rmp-serde`.
     #[inline]
     pub fn with_human_readable(self) -> Serializer<W, HumanReadableConfig<C>> {
-        let Serializer { wr, depth, config } = self;
+        let Serializer { wr, depth, config, options } = self;
         Serializer {
             wr,
             depth,
             config: HumanReadableConfig::new(config),
+            options,
         }
     }
 
@@ -262,73 +727,519 @@ impl<W, C> Serializer<W, C> {
     /// representation.
     #[inline]
     pub fn with_binary(self) -> Serializer<W, BinaryConfig<C>> {
-        let Serializer { wr, depth, config } = self;
+        let Serializer { wr, depth, config, options } = self;
         Serializer {
             wr,
             depth,
             config: BinaryConfig::new(config),
+            options,
         }
     }
-}
-
-impl<W: RmpWrite, C> UnderlyingWrite for Serializer<W, C> {
-    type Write = W;
 
-    #[inline(always)]
-    fn get_ref(&self) -> &Self::Write {
-        &self.wr
+    /// Consumes this serializer returning the new one, which will serialize `Option`s as an array
+    /// of zero (`None`) or one (`Some`) elements.
+    ///
+    /// By default `None` is written as nil and `Some(v)` as just `v`, so `Some(None)` and `None`
+    /// are indistinguishable, as are `Some(())` and `None`. With this option they round-trip
+    /// losslessly, provided the deserializer is configured with
+    /// [`Deserializer::with_option_as_array`](crate::Deserializer::with_option_as_array) too.
+    #[inline]
+    pub fn with_option_as_array(mut self) -> Self {
+        self.options.option_as_array = true;
+        self
     }
 
-    #[inline(always)]
-    fn get_mut(&mut self) -> &mut Self::Write {
-        &mut self.wr
+    /// Consumes this serializer returning the new one, which will write floats that have an
+    /// integral value as integers, e.g. `2.0` as `2`.
+    ///
+    /// This saves space for data full of round numbers, and matches what several encoders of
+    /// scripting languages do. Negative zero, non-finite values and values out of the range of
+    /// `i64` and `u64` are still written as floats. Integers are accepted when deserializing
+    /// floats.
+    #[inline]
+    pub fn with_floats_as_ints(mut self) -> Self {
+        self.options.floats_as_ints = true;
+        self
     }
 
-    #[inline(always)]
-    fn into_inner(self) -> Self::Write {
-        self.wr
+    /// Consumes this serializer returning the new one, which will fail to serialize NaN and
+    /// infinite floats with `Error::InvalidDataModel`.
+    #[inline]
+    pub fn with_finite_floats(mut self) -> Self {
+        self.options.finite_floats = true;
+        self
     }
-}
-
-/// Part of serde serialization API.
-#[derive(Debug)]
-pub struct Compound<'a, W: 'a, C: 'a> {
-    se: &'a mut Serializer<W, C>,
-}
 
-#[derive(Debug)]
-#[allow(missing_docs)]
-pub struct ExtFieldSerializer<'a, W> {
-    wr: &'a mut W,
-    tag: Option<i8>,
-    finish: bool,
-}
+    /// Consumes this serializer returning the new one, which will write all NaNs with the bit
+    /// pattern of `f32::NAN` or `f64::NAN`, dropping their sign and payload.
+    ///
+    /// This makes the output deterministic, e.g. for hashing or signing.
+    #[inline]
+    pub fn with_canonical_nan(mut self) -> Self {
+        self.options.canonical_nan = true;
+        self
+    }
 
-/// Represents MessagePack serialization implementation for Ext.
-#[derive(Debug)]
-pub struct ExtSerializer<'a, W> {
-    fields_se: ExtFieldSerializer<'a, W>,
-    tuple_received: bool,
-}
+    /// Consumes this serializer returning the new one, which will write `i128` and `u128` values
+    /// with the given representation instead of a 16-byte bin.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use rmp_serde::config::Int128Repr;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Vec::new()).with_int128_repr(Int128Repr::String);
+    /// (-42i128).serialize(&mut se).unwrap();
+    ///
+    /// assert_eq!(vec![0xa3, b'-', b'4', b'2'], se.into_inner());
+    /// ```
+    #[inline]
+    pub fn with_int128_repr(mut self, repr: Int128Repr) -> Self {
+        self.options.int128_repr = repr;
+        self
+    }
 
-#[cfg(not(feature = "std"))]
-impl<'a, W, C> SerializeSeq for Compound<'a, W, C>
-where W: RmpWrite + 'a,
-      C: SerializerConfig,
-{
-    type Ok = ();
-    type Error = Error<W::Error>;
+    /// Consumes this serializer returning the new one, which will write only the types of the
+    /// MessagePack spec before 2013, for decoders that predate the bin, Str8 and ext types.
+    ///
+    /// Strings and bytes are both written with the legacy raw headers, i.e. FixStr, Str16 and
+    /// Str32, so the decoder can't tell them apart. This implies [`Self::with_no_str8`].
+    /// Serializing an ext fails with `Error::InvalidDataModel`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Vec::new()).with_old_spec();
+    /// serde_bytes::Bytes::new(&[1, 2]).serialize(&mut se).unwrap();
+    ///
+    /// assert_eq!(vec![0xa2, 0x01, 0x02], se.into_inner());
+    /// ```
+    #[inline]
+    pub fn with_old_spec(mut self) -> Self {
+        self.options.old_spec = true;
+        self.options.no_str8 = true;
+        self
+    }
 
+    /// Consumes this serializer returning the new one, which will write strings of 32 to 255
+    /// bytes as Str16 instead of Str8.
+    ///
+    /// Str8 was added to the spec together with bin and ext, and some decoders support those but
+    /// still not Str8. Unlike [`Self::with_old_spec`], bin and ext are still written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Vec::new()).with_no_str8();
+    /// "x".repeat(40).serialize(&mut se).unwrap();
+    ///
+    /// assert_eq!([0xda, 0x00, 0x28], se.into_inner()[..3]);
+    /// ```
     #[inline]
-    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
-        value.serialize(&mut *self.se)
+    pub fn with_no_str8(mut self) -> Self {
+        self.options.no_str8 = true;
+        self
     }
 
-    #[inline(always)]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+    /// Consumes this serializer returning the new one, which will write integers with the marker
+    /// matching their Rust type, e.g. a `u32` always as a U32, instead of the smallest marker
+    /// that fits the value.
+    ///
+    /// The encoded size then only depends on the type, which helps decoders with rigid schemas
+    /// and patching values in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Vec::new()).with_fixed_width_ints();
+    /// (1u32, -1i8).serialize(&mut se).unwrap();
+    ///
+    /// assert_eq!(vec![0x92, 0xce, 0x00, 0x00, 0x00, 0x01, 0xd0, 0xff], se.into_inner());
+    /// ```
+    #[inline]
+    pub fn with_fixed_width_ints(mut self) -> Self {
+        self.options.fixed_width_ints = true;
+        self
     }
-}
+
+    /// Consumes this serializer returning the new one, which will write all str, bin, array and
+    /// map headers with a 32-bit length, i.e. as Str32, Bin32, Array32 and Map32.
+    ///
+    /// The size of a header then doesn't depend on the length, so the length can be patched in
+    /// place later, e.g. to append elements, without shifting the data that follows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// let mut se = rmp_serde::Serializer::new(Vec::new()).with_widest_headers();
+    /// ["a"].serialize(&mut se).unwrap();
+    ///
+    /// assert_eq!(vec![0xdd, 0x00, 0x00, 0x00, 0x01, 0xdb, 0x00, 0x00, 0x00, 0x01, b'a'], se.into_inner());
+    /// ```
+    #[inline]
+    pub fn with_widest_headers(mut self) -> Self {
+        self.options.widest_headers = true;
+        self
+    }
+
+    /// Consumes this serializer returning the new one, which will omit struct fields whose value
+    /// is `None` instead of writing them as nil.
+    ///
+    /// This only has an effect when structs are serialized as maps (see
+    /// [`Self::with_struct_map`]), as omitting elements of a tuple would shift the remaining ones.
+    /// Such structs are buffered until all their fields are known. On deserialization, missing
+    /// `Option` fields are `None`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn with_skip_none(mut self) -> Self {
+        self.options.skip_none = true;
+        self
+    }
+
+    /// Consumes this serializer returning the new one, which will omit struct fields whose value
+    /// encodes as an empty value, see [`is_empty_value`].
+    ///
+    /// That's the case for the `Default` of numbers, `bool`, `Option`, strings, and collections,
+    /// and, recursively, for structs made only of such fields. `Some` is always written, even
+    /// `Some(0)`, as it would otherwise deserialize as `None`. Fields must be marked with
+    /// `#[serde(default)]` to deserialize when they are missing.
+    ///
+    /// Like [`Self::with_skip_none`], this only has an effect when structs are serialized as maps.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn with_skip_default(self) -> Self {
+        self.with_skip_field_if(skip_empty_value)
+    }
+
+    /// Consumes this serializer returning the new one, which will omit struct fields for which
+    /// the given function returns `true`.
+    ///
+    /// The function is called with the field name and the field value, already encoded as
+    /// MessagePack. This allows to skip values that [`Self::with_skip_default`] doesn't recognize
+    /// as defaults, e.g. enums. The function isn't called for `Some` values, which are always
+    /// written.
+    ///
+    /// Like [`Self::with_skip_none`], this only has an effect when structs are serialized as maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate serde_derive;
+    /// # fn main() {
+    /// use serde::Serialize;
+    /// use rmp_serde::Serializer;
+    /// use rmp_serde::encode::is_empty_value;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Settings {
+    ///     theme: &'static str,
+    ///     width: u32,
+    /// }
+    ///
+    /// let mut se = Serializer::new(Vec::new())
+    ///     .with_struct_map()
+    ///     .with_skip_field_if(|key, value| key == "theme" && value == b"\xa5light" || is_empty_value(value));
+    /// Settings { theme: "light", width: 0 }.serialize(&mut se).unwrap();
+    ///
+    /// // {}
+    /// assert_eq!(vec![0x80], se.into_inner());
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn with_skip_field_if(mut self, f: fn(&'static str, &[u8]) -> bool) -> Self {
+        self.options.skip_field = Some(f);
+        self
+    }
+
+    /// Consumes this serializer returning the new one, which will write the structs for which the
+    /// given function returns `true` as maps with field names, and the others as arrays.
+    ///
+    /// The function is called with the name of the struct, or of the enum for struct variants.
+    /// This allows a compact encoding for frequent inner types, while the structs that are likely
+    /// to gain fields are written as maps. The config of the serializer, e.g. hashed field names,
+    /// applies to the structs for which the function agrees with it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate serde_derive;
+    /// # fn main() {
+    /// use serde::Serialize;
+    /// use rmp_serde::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Point {
+    ///     x: u8,
+    ///     y: u8,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Envelope {
+    ///     points: Vec<Point>,
+    /// }
+    ///
+    /// let mut se = Serializer::new(Vec::new()).with_struct_map_if(|name| name == "Envelope");
+    /// Envelope { points: vec![Point { x: 1, y: 2 }] }.serialize(&mut se).unwrap();
+    ///
+    /// // {"points": [[1, 2]]}
+    /// assert_eq!(b"\x81\xa6points\x91\x92\x01\x02", &se.into_inner()[..]);
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn with_struct_map_if(mut self, f: fn(&'static str) -> bool) -> Self {
+        self.options.struct_map_if = Some(f);
+        self
+    }
+}
+
+/// Writes a marker followed by a 32-bit length.
+fn write_len32<W: RmpWrite>(wr: &mut W, marker: Marker, len: u32) -> Result<(), Error<W::Error>> {
+    wr.write_bytes(Header::new(marker, len, 4).as_bytes())
+        .map_err(ValueWriteError::InvalidMarkerWrite)?;
+    Ok(())
+}
+
+/// The header of a value with a length: a marker followed by up to 4 bytes of length.
+struct Header {
+    buf: [u8; 5],
+    len: usize,
+}
+
+impl Header {
+    /// Creates a header whose length takes `size` bytes.
+    #[inline]
+    fn new(marker: Marker, len: u32, size: usize) -> Self {
+        let mut buf = [marker.to_u8(), 0, 0, 0, 0];
+        buf[1..=size].copy_from_slice(&len.to_be_bytes()[4 - size..]);
+        Header { buf, len: 1 + size }
+    }
+
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Writes the header followed by the data, in a single call if the writer supports it.
+    #[inline]
+    fn write_with<W: RmpWrite>(&self, wr: &mut W, data: &[u8]) -> Result<(), Error<W::Error>> {
+        wr.write_bytes_vectored(self.as_bytes(), data)
+            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))
+    }
+}
+
+/// Returns the header of a str, with the width chosen by the options. With `no_str8`, lengths
+/// that would take a Str8, which older decoders don't know, take a Str16 instead.
+fn str_header(len: u32, options: &RuntimeConfig) -> Header {
+    if options.widest_headers || len > u32::from(u16::MAX) {
+        Header::new(Marker::Str32, len, 4)
+    } else if len < 32 {
+        Header::new(Marker::FixStr(len as u8), len, 0)
+    } else if len < 256 && !options.no_str8 {
+        Header::new(Marker::Str8, len, 1)
+    } else {
+        Header::new(Marker::Str16, len, 2)
+    }
+}
+
+/// Returns the header of a bin, or of a str with `old_spec`, which has the raw type only.
+fn bin_header(len: u32, options: &RuntimeConfig) -> Header {
+    if options.old_spec {
+        str_header(len, options)
+    } else if options.widest_headers || len > u32::from(u16::MAX) {
+        Header::new(Marker::Bin32, len, 4)
+    } else if len < 256 {
+        Header::new(Marker::Bin8, len, 1)
+    } else {
+        Header::new(Marker::Bin16, len, 2)
+    }
+}
+
+/// Writes the header of a str.
+fn write_str_len<W: RmpWrite>(wr: &mut W, len: u32, options: &RuntimeConfig) -> Result<(), Error<W::Error>> {
+    wr.write_bytes(str_header(len, options).as_bytes())
+        .map_err(ValueWriteError::InvalidMarkerWrite)?;
+    Ok(())
+}
+
+/// Writes the header of a bin.
+#[cfg(feature = "std")]
+fn write_bin_len<W: RmpWrite>(wr: &mut W, len: u32, options: &RuntimeConfig) -> Result<(), Error<W::Error>> {
+    wr.write_bytes(bin_header(len, options).as_bytes())
+        .map_err(ValueWriteError::InvalidMarkerWrite)?;
+    Ok(())
+}
+
+/// Writes the header of an array, with the width chosen by the options.
+fn write_array_len<W: RmpWrite>(wr: &mut W, len: u32, options: &RuntimeConfig) -> Result<(), Error<W::Error>> {
+    if options.widest_headers {
+        write_len32(wr, Marker::Array32, len)
+    } else {
+        encode::write_array_len(wr, len)?;
+        Ok(())
+    }
+}
+
+/// Writes the header of a map, with the width chosen by the options.
+fn write_map_len<W: RmpWrite>(wr: &mut W, len: u32, options: &RuntimeConfig) -> Result<(), Error<W::Error>> {
+    if options.widest_headers {
+        write_len32(wr, Marker::Map32, len)
+    } else {
+        encode::write_map_len(wr, len)?;
+        Ok(())
+    }
+}
+
+/// The writer of a [`Serializer`], counting the bytes written through it.
+#[derive(Debug)]
+struct CountingWrite<W> {
+    wr: W,
+    written: u64,
+    #[cfg(feature = "std")]
+    backpatch: Option<Backpatch<W>>,
+}
+
+/// How the writer of a [`Serializer`] is patched, see [`Serializer::with_backpatch`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct Backpatch<W> {
+    position: fn(&mut W) -> io::Result<u64>,
+    patch: fn(&mut W, u64, &[u8]) -> io::Result<()>,
+    /// The position of the writer before the first byte counted, once it was needed.
+    base: Option<u64>,
+}
+
+#[cfg(feature = "std")]
+impl<W: RmpWrite> CountingWrite<W> {
+    /// Writes the marker of a 32-bit length followed by a zero length when back-patching, and
+    /// returns the position of the length to be patched by [`Self::patch_len`].
+    fn reserve_len(&mut self, marker: Marker) -> Result<Option<u64>, Error<W::Error>> {
+        let backpatch = match self.backpatch.as_mut() {
+            Some(backpatch) => backpatch,
+            None => return Ok(None),
+        };
+        let base = match backpatch.base {
+            Some(base) => base,
+            None => {
+                let pos = (backpatch.position)(&mut self.wr).map_err(backpatch_err)?;
+                let base = pos.checked_sub(self.written)
+                    .ok_or_else(|| backpatch_err(io::Error::new(io::ErrorKind::InvalidInput, "the writer is before the written data")))?;
+                *backpatch.base.insert(base)
+            }
+        };
+        let pos = base + self.written + 1;
+        self.write_bytes(&[marker.to_u8(), 0, 0, 0, 0]).map_err(ValueWriteError::InvalidMarkerWrite)?;
+        Ok(Some(pos))
+    }
+
+    /// Fills in a length reserved by [`Self::reserve_len`].
+    fn patch_len(&mut self, pos: u64, len: u32) -> Result<(), Error<W::Error>> {
+        let backpatch = self.backpatch.as_ref().expect("lengths are only reserved when back-patching");
+        (backpatch.patch)(&mut self.wr, pos, &len.to_be_bytes()).map_err(backpatch_err)
+    }
+}
+
+/// Converts an error from seeking or patching the writer, whose type isn't known to the generic
+/// serializer.
+#[cfg(feature = "std")]
+#[cold]
+fn backpatch_err<E>(err: io::Error) -> Error<E> {
+    Error::Syntax(format!("failed to patch a length: {err}"))
+}
+
+impl<W: RmpWrite> RmpWrite for CountingWrite<W> {
+    type Error = W::Error;
+
+    #[inline]
+    fn write_u8(&mut self, val: u8) -> Result<(), Self::Error> {
+        self.wr.write_u8(val)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.wr.write_bytes(buf)?;
+        self.written += buf.len() as u64;
+        Ok(())
+    }
+
+    #[inline]
+    fn write_bytes_vectored(&mut self, header: &[u8], data: &[u8]) -> Result<(), Self::Error> {
+        self.wr.write_bytes_vectored(header, data)?;
+        self.written += (header.len() + data.len()) as u64;
+        Ok(())
+    }
+}
+
+impl<W: RmpWrite, C> UnderlyingWrite for Serializer<W, C> {
+    type Write = W;
+
+    #[inline(always)]
+    fn get_ref(&self) -> &Self::Write {
+        &self.wr.wr
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self) -> &mut Self::Write {
+        &mut self.wr.wr
+    }
+
+    #[inline(always)]
+    fn into_inner(self) -> Self::Write {
+        self.wr.wr
+    }
+}
+
+/// Part of serde serialization API.
+#[derive(Debug)]
+pub struct Compound<'a, W: 'a, C: 'a> {
+    se: &'a mut Serializer<W, C>,
+}
+
+#[derive(Debug)]
+#[allow(missing_docs)]
+pub struct ExtFieldSerializer<'a, W> {
+    wr: &'a mut CountingWrite<W>,
+    tag: Option<i8>,
+    finish: bool,
+}
+
+/// Represents MessagePack serialization implementation for Ext.
+#[derive(Debug)]
+pub struct ExtSerializer<'a, W> {
+    fields_se: ExtFieldSerializer<'a, W>,
+    tuple_received: bool,
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a, W, C> SerializeSeq for Compound<'a, W, C>
+where W: RmpWrite + 'a,
+      C: SerializerConfig,
+{
+    type Ok = ();
+    type Error = Error<W::Error>;
+
+    #[inline]
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(&mut *self.se)
+    }
+
+    #[inline(always)]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
 
 #[cfg(not(feature = "std"))]
 impl<'a, W, C> SerializeMap for Compound<'a, W, C>
@@ -443,16 +1354,47 @@ struct UnknownLengthCompound<C> {
     se: Serializer<Vec<u8>, C>,
     elem_count: u32,
 }
+#[cfg(feature = "std")]
+impl<C> UnknownLengthCompound<C> {
+    /// Converts an error from serializing into the buffer into an error of the outer serializer.
+    ///
+    /// Writing into a `Vec` doesn't fail, so the I/O error type only needs to be changed.
+    #[cold]
+    fn convert_err<E>(err: Error<io::Error>) -> Error<E> {
+        match err {
+            Error::InvalidValueWrite(err) => Error::Syntax(err.to_string()),
+            Error::UnknownLength => Error::UnknownLength,
+            Error::InvalidDataModel(r) => Error::InvalidDataModel(r),
+            Error::DepthLimitExceeded => Error::DepthLimitExceeded,
+            Error::Syntax(msg) => Error::Syntax(msg),
+        }
+    }
+}
+
 #[cfg(feature = "std")]
 impl<W, C: SerializerConfig> From<&Serializer<W, C>> for UnknownLengthCompound<C> {
     fn from(se: &Serializer<W, C>) -> Self {
         Self {
-            se: Serializer { wr: Vec::with_capacity(128), config: se.config, depth: se.depth },
+            se: Serializer {
+                wr: CountingWrite { wr: Vec::with_capacity(128), written: 0, backpatch: None },
+                config: se.config,
+                options: se.options,
+                depth: se.depth,
+            },
             elem_count: 0
         }
     }
 }
 
+/// A length reserved in the output of a back-patching [`Serializer`], and the number of elements
+/// written since.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct PendingLen {
+    pos: u64,
+    count: u32,
+}
+
 /// Contains a `Serializer` for encoding elements of sequences and maps.
 ///
 /// # Note
@@ -473,6 +1415,10 @@ impl<W, C: SerializerConfig> From<&Serializer<W, C>> for UnknownLengthCompound<C
 pub struct MaybeUnknownLengthCompound<'a, W: 'a, C: 'a> {
     se: &'a mut Serializer<W, C>,
     compound: Option<UnknownLengthCompound<C>>,
+    /// The length to patch in once all the elements were written, when back-patching.
+    pending: Option<PendingLen>,
+    /// Whether the struct is written as a map or as an array, when it differs from the config.
+    struct_map: Option<bool>,
 }
 
 #[cfg(feature = "std")]
@@ -482,19 +1428,29 @@ impl<'a, W: RmpWrite + 'a, C: SerializerConfig> SerializeSeq for MaybeUnknownLen
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
         match self.compound.as_mut() {
-            None => value.serialize(&mut *self.se),
+            None => {
+                value.serialize(&mut *self.se)?;
+                if let Some(pending) = self.pending.as_mut() {
+                    pending.count = pending.count.checked_add(1)
+                        .ok_or(Error::InvalidDataModel("too many elements for a MessagePack array or map"))?;
+                }
+                Ok(())
+            }
             Some(buf) => {
-                // TODO: buf.se uses a Vec<u8> and returns an std::io::Error
-                value.serialize(&mut buf.se).unwrap();
-                buf.elem_count += 1;
+                value.serialize(&mut buf.se).map_err(UnknownLengthCompound::<C>::convert_err)?;
+                buf.elem_count = buf.elem_count.checked_add(1)
+                    .ok_or(Error::InvalidDataModel("too many elements for a MessagePack array or map"))?;
                 Ok(())
             }
         }
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(pending) = self.pending {
+            self.se.wr.patch_len(pending.pos, pending.count)?;
+        }
         if let Some(compound) = self.compound {
-            encode::write_array_len(&mut self.se.wr, compound.elem_count)?;
+            write_array_len(&mut self.se.wr, compound.elem_count, &self.se.options)?;
             self.se.wr.write_bytes(&compound.se.into_inner())
                 .map_err(ValueWriteError::InvalidDataWrite)?;
         }
@@ -516,8 +1472,11 @@ impl<'a, W: RmpWrite + 'a, C: SerializerConfig> SerializeMap for MaybeUnknownLen
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(pending) = self.pending {
+            self.se.wr.patch_len(pending.pos, pending.count / 2)?;
+        }
         if let Some(compound) = self.compound {
-            encode::write_map_len(&mut self.se.wr, compound.elem_count / 2)?;
+            write_map_len(&mut self.se.wr, compound.elem_count / 2, &self.se.options)?;
             self.se.wr.write_bytes(&compound.se.into_inner())
                 .map_err(ValueWriteError::InvalidDataWrite)?;
         }
@@ -525,87 +1484,380 @@ impl<'a, W: RmpWrite + 'a, C: SerializerConfig> SerializeMap for MaybeUnknownLen
     }
 }
 
-impl<'a, W, C> serde::Serializer for &'a mut Serializer<W, C>
-where
-    W: RmpWrite,
-    C: SerializerConfig,
-{
+#[cfg(feature = "std")]
+impl<'a, W: RmpWrite + 'a, C: SerializerConfig> SerializeStruct for MaybeUnknownLengthCompound<'a, W, C> {
     type Ok = ();
     type Error = Error<W::Error>;
 
-    #[cfg(feature = "std")]
-    type SerializeSeq = MaybeUnknownLengthCompound<'a, W, C>;
-    #[cfg(not(feature = "std"))]
-    type SerializeSeq = Compound<'a, W, C>;
-
-    #[cfg(feature = "std")]
-    type SerializeMap = MaybeUnknownLengthCompound<'a, W, C>;
-    #[cfg(not(feature = "std"))]
-    type SerializeMap = Compound<'a, W, C>;
-
-    type SerializeTuple = Compound<'a, W, C>;
-    type SerializeTupleStruct = Compound<'a, W, C>;
-    type SerializeTupleVariant = Compound<'a, W, C>;
-    type SerializeStruct = Compound<'a, W, C>;
-    type SerializeStructVariant = Compound<'a, W, C>;
-
-    fn is_human_readable(&self) -> bool {
-        C::is_human_readable()
-    }
-
-    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        encode::write_bool(&mut self.wr, v)
-            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(err)))
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error> {
+        match self.compound.as_mut() {
+            None => write_struct_field(&mut *self.se, self.struct_map, key, value),
+            Some(buf) => {
+                if self.se.options.skip_none && is_none(value) {
+                    return Ok(());
+                }
+                let start = buf.se.get_ref().len();
+                write_struct_field(&mut buf.se, self.struct_map, key, value).map_err(UnknownLengthCompound::<C>::convert_err)?;
+                // `Some` is written like the value it holds, so `Some(0)` would come back as `None`.
+                let skip_field = self.se.options.skip_field.filter(|_| !is_some(value));
+                if let Some(skip) = skip_field {
+                    let field = &buf.se.get_ref()[start..];
+                    if let Ok(Scan::Complete(key_len)) = scan_value(field) {
+                        if skip(key, &field[key_len..]) {
+                            buf.se.get_mut().truncate(start);
+                            return Ok(());
+                        }
+                    }
+                }
+                buf.elem_count = buf.elem_count.checked_add(1)
+                    .ok_or(Error::InvalidDataModel("too many fields for a MessagePack map"))?;
+                Ok(())
+            }
+        }
     }
 
-    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-        self.serialize_i64(v as i64)
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(compound) = self.compound {
+            write_map_len(&mut self.se.wr, compound.elem_count, &self.se.options)?;
+            self.se.wr.write_bytes(&compound.se.into_inner())
+                .map_err(ValueWriteError::InvalidDataWrite)?;
+        }
+        Ok(())
     }
+}
 
-    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-        self.serialize_i64(v as i64)
-    }
+#[cfg(feature = "std")]
+impl<'a, W: RmpWrite + 'a, C: SerializerConfig> SerializeStructVariant for MaybeUnknownLengthCompound<'a, W, C> {
+    type Ok = ();
+    type Error = Error<W::Error>;
 
-    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        self.serialize_i64(v as i64)
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error> {
+        <Self as SerializeStruct>::serialize_field(self, key, value)
     }
 
-    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        encode::write_sint(&mut self.wr, v)?;
-        Ok(())
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        <Self as SerializeStruct>::end(self)
     }
+}
 
-    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
-        self.serialize_bytes(&v.to_be_bytes())
+/// Returns `true` if the buffer holds a single MessagePack value that is zero or empty.
+///
+/// These are nil, `false`, integers and floats equal to positive zero, and empty strings, binaries,
+/// arrays and maps, as produced by serializing the `Default` of the corresponding Rust types.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::encode::is_empty_value;
+///
+/// assert!(is_empty_value(&rmp_serde::to_vec(&0u64).unwrap()));
+/// assert!(is_empty_value(&rmp_serde::to_vec("").unwrap()));
+/// assert!(!is_empty_value(&rmp_serde::to_vec(&[0u8]).unwrap()));
+/// ```
+pub fn is_empty_value(buf: &[u8]) -> bool {
+    use rmp::Marker;
+
+    let (&marker, rest) = match buf.split_first() {
+        Some(v) => v,
+        None => return false,
+    };
+    match Marker::from_u8(marker) {
+        Marker::Null | Marker::False | Marker::FixPos(0) |
+        Marker::FixStr(0) | Marker::FixArray(0) | Marker::FixMap(0) => rest.is_empty(),
+        // Either the number itself or the length that follows the marker must be zero.
+        Marker::U8 | Marker::U16 | Marker::U32 | Marker::U64 |
+        Marker::I8 | Marker::I16 | Marker::I32 | Marker::I64 |
+        Marker::F32 | Marker::F64 |
+        Marker::Str8 | Marker::Str16 | Marker::Str32 |
+        Marker::Bin8 | Marker::Bin16 | Marker::Bin32 |
+        Marker::Array16 | Marker::Array32 |
+        Marker::Map16 | Marker::Map32 => !rest.is_empty() && rest.iter().all(|&b| b == 0),
+        _ => false,
     }
+}
 
-    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        self.serialize_u64(v as u64)
-    }
+#[cfg(feature = "std")]
+fn skip_empty_value(_key: &'static str, value: &[u8]) -> bool {
+    is_empty_value(value)
+}
 
-    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-        self.serialize_u64(v as u64)
-    }
+/// Returns `true` if the value serializes as `None`.
+#[cfg(feature = "std")]
+fn is_none<T: ?Sized + Serialize>(value: &T) -> bool {
+    value.serialize(OptionProbe { some: false }).is_ok()
+}
+
+/// Returns `true` if the value serializes as `Some`.
+#[cfg(feature = "std")]
+fn is_some<T: ?Sized + Serialize>(value: &T) -> bool {
+    value.serialize(OptionProbe { some: true }).is_ok()
+}
+
+/// Writes a struct field as a map entry or as an array element, or following the config if the
+/// representation of the struct wasn't overridden.
+#[cfg(feature = "std")]
+fn write_struct_field<W, C, T>(se: &mut Serializer<W, C>, struct_map: Option<bool>, key: &'static str, value: &T) -> Result<(), Error<W::Error>>
+where
+    W: RmpWrite,
+    C: SerializerConfig,
+    T: ?Sized + Serialize
+{
+    match struct_map {
+        None => C::write_struct_field(se, key, value),
+        Some(true) => {
+            serde::Serializer::serialize_str(&mut *se, key)?;
+            value.serialize(se)
+        }
+        Some(false) => value.serialize(se),
+    }
+}
+
+/// A serializer that only accepts `None`, or only `Some` if `some` is set, failing right away for
+/// any other value.
+#[cfg(feature = "std")]
+struct OptionProbe {
+    some: bool,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct ProbeMismatch;
+
+#[cfg(feature = "std")]
+impl Display for ProbeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unexpected value")
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ProbeMismatch {}
+
+#[cfg(feature = "std")]
+impl serde::ser::Error for ProbeMismatch {
+    #[inline]
+    fn custom<T: Display>(_msg: T) -> Self {
+        ProbeMismatch
+    }
+}
+
+#[cfg(feature = "std")]
+macro_rules! probe_mismatch {
+    ($($name:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            #[inline]
+            fn $name(self, $(_: $arg),*) -> Result<$ret, ProbeMismatch> {
+                Err(ProbeMismatch)
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "std")]
+impl serde::Serializer for OptionProbe {
+    type Ok = ();
+    type Error = ProbeMismatch;
+    type SerializeSeq = serde::ser::Impossible<(), ProbeMismatch>;
+    type SerializeTuple = serde::ser::Impossible<(), ProbeMismatch>;
+    type SerializeTupleStruct = serde::ser::Impossible<(), ProbeMismatch>;
+    type SerializeTupleVariant = serde::ser::Impossible<(), ProbeMismatch>;
+    type SerializeMap = serde::ser::Impossible<(), ProbeMismatch>;
+    type SerializeStruct = serde::ser::Impossible<(), ProbeMismatch>;
+    type SerializeStructVariant = serde::ser::Impossible<(), ProbeMismatch>;
+
+    #[inline]
+    fn serialize_none(self) -> Result<(), ProbeMismatch> {
+        if self.some { Err(ProbeMismatch) } else { Ok(()) }
+    }
+
+    #[inline]
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), ProbeMismatch> {
+        if self.some { Ok(()) } else { Err(ProbeMismatch) }
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, _value: &T) -> Result<(), ProbeMismatch> {
+        Err(ProbeMismatch)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _idx: u32, _variant: &'static str, _value: &T) -> Result<(), ProbeMismatch> {
+        Err(ProbeMismatch)
+    }
+
+    probe_mismatch! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+impl<'a, W, C> serde::Serializer for &'a mut Serializer<W, C>
+where
+    W: RmpWrite,
+    C: SerializerConfig,
+{
+    type Ok = ();
+    type Error = Error<W::Error>;
+
+    #[cfg(feature = "std")]
+    type SerializeSeq = MaybeUnknownLengthCompound<'a, W, C>;
+    #[cfg(not(feature = "std"))]
+    type SerializeSeq = Compound<'a, W, C>;
+
+    #[cfg(feature = "std")]
+    type SerializeMap = MaybeUnknownLengthCompound<'a, W, C>;
+    #[cfg(not(feature = "std"))]
+    type SerializeMap = Compound<'a, W, C>;
+
+    type SerializeTuple = Compound<'a, W, C>;
+    type SerializeTupleStruct = Compound<'a, W, C>;
+    type SerializeTupleVariant = Compound<'a, W, C>;
+
+    #[cfg(feature = "std")]
+    type SerializeStruct = MaybeUnknownLengthCompound<'a, W, C>;
+    #[cfg(not(feature = "std"))]
+    type SerializeStruct = Compound<'a, W, C>;
+
+    #[cfg(feature = "std")]
+    type SerializeStructVariant = MaybeUnknownLengthCompound<'a, W, C>;
+    #[cfg(not(feature = "std"))]
+    type SerializeStructVariant = Compound<'a, W, C>;
+
+    fn is_human_readable(&self) -> bool {
+        C::is_human_readable()
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        encode::write_bool(&mut self.wr, v)
+            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(err)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        if self.options.fixed_width_ints {
+            encode::write_i8(&mut self.wr, v)?;
+            return Ok(());
+        }
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        if self.options.fixed_width_ints {
+            encode::write_i16(&mut self.wr, v)?;
+            return Ok(());
+        }
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        if self.options.fixed_width_ints {
+            encode::write_i32(&mut self.wr, v)?;
+            return Ok(());
+        }
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        if self.options.fixed_width_ints {
+            encode::write_i64(&mut self.wr, v)?;
+        } else {
+            encode::write_sint(&mut self.wr, v)?;
+        }
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.write_int128(v.to_be_bytes(), format_args!("{}", v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        if self.options.fixed_width_ints {
+            encode::write_u8(&mut self.wr, v)?;
+            return Ok(());
+        }
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        if self.options.fixed_width_ints {
+            encode::write_u16(&mut self.wr, v)?;
+            return Ok(());
+        }
+        self.serialize_u64(v as u64)
+    }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        if self.options.fixed_width_ints {
+            encode::write_u32(&mut self.wr, v)?;
+            return Ok(());
+        }
         self.serialize_u64(v as u64)
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        encode::write_uint(&mut self.wr, v)?;
+        if self.options.fixed_width_ints {
+            encode::write_u64(&mut self.wr, v)?;
+        } else {
+            encode::write_uint(&mut self.wr, v)?;
+        }
         Ok(())
     }
 
     fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
-        self.serialize_bytes(&v.to_be_bytes())
+        self.write_int128(v.to_be_bytes(), format_args!("{}", v))
     }
 
-    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+    fn serialize_f32(self, mut v: f32) -> Result<Self::Ok, Self::Error> {
+        if !v.is_finite() {
+            if self.options.finite_floats {
+                return Err(Error::InvalidDataModel("non-finite float"));
+            }
+            if self.options.canonical_nan && v.is_nan() {
+                v = f32::NAN;
+            }
+        }
+        if self.options.floats_as_ints && self.write_integral_float(v.into())? {
+            return Ok(());
+        }
         encode::write_f32(&mut self.wr, v)?;
         Ok(())
     }
 
-    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+    fn serialize_f64(self, mut v: f64) -> Result<Self::Ok, Self::Error> {
+        if !v.is_finite() {
+            if self.options.finite_floats {
+                return Err(Error::InvalidDataModel("non-finite float"));
+            }
+            if self.options.canonical_nan && v.is_nan() {
+                v = f64::NAN;
+            }
+        }
+        if self.options.floats_as_ints && self.write_integral_float(v)? {
+            return Ok(());
+        }
         encode::write_f64(&mut self.wr, v)?;
         Ok(())
     }
@@ -617,22 +1869,25 @@ where
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        encode::write_str(&mut self.wr, v)?;
-        Ok(())
+        str_header(v.len() as u32, &self.options).write_with(&mut self.wr, v.as_bytes())
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
-        encode::write_bin_len(&mut self.wr, value.len() as u32)?;
-        self.wr
-            .write_bytes(value)
-            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))
+        bin_header(value.len() as u32, &self.options).write_with(&mut self.wr, value)
     }
 
     fn serialize_none(self) -> Result<(), Self::Error> {
+        if self.options.option_as_array {
+            write_array_len(&mut self.wr, 0, &self.options)?;
+            return Ok(());
+        }
         self.serialize_unit()
     }
 
     fn serialize_some<T: ?Sized + serde::Serialize>(self, v: &T) -> Result<(), Self::Error> {
+        if self.options.option_as_array {
+            write_array_len(&mut self.wr, 1, &self.options)?;
+        }
         v.serialize(self)
     }
 
@@ -642,7 +1897,7 @@ where
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        encode::write_array_len(&mut self.wr, 0)?;
+        write_array_len(&mut self.wr, 0, &self.options)?;
         Ok(())
     }
 
@@ -654,11 +1909,21 @@ where
 
     fn serialize_newtype_struct<T: ?Sized + serde::Serialize>(self, name: &'static str, value: &T) -> Result<(), Self::Error> {
         if name == MSGPACK_EXT_STRUCT_NAME {
+            if self.options.old_spec {
+                return Err(Error::InvalidDataModel("ext types are not supported by the old spec"));
+            }
             let mut ext_se = ExtSerializer::new(self);
             value.serialize(&mut ext_se)?;
 
             return ext_se.end();
         }
+        #[cfg(feature = "std")]
+        if name == crate::bin_stream::BIN_CHUNKS_NAME {
+            let mut bin_se = BinChunksSerializer::new(self);
+            value.serialize(&mut bin_se)?;
+
+            return bin_se.end();
+        }
 
         // Encode as if it's inner type.
         value.serialize(self)
@@ -666,7 +1931,7 @@ where
 
     fn serialize_newtype_variant<T: ?Sized + serde::Serialize>(self, _name: &'static str, idx: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error> {
         // encode as a map from variant idx to its attributed data, like: {idx => value}
-        encode::write_map_len(&mut self.wr, 1)?;
+        write_map_len(&mut self.wr, 1, &self.options)?;
         C::write_variant_ident(self, idx, variant)?;
         value.serialize(self)
     }
@@ -674,18 +1939,18 @@ where
     #[cfg(not(feature = "std"))]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         let len = len.ok_or(Error::UnknownLength)?;
-        encode::write_array_len(&mut self.wr, len as u32)?;
+        write_array_len(&mut self.wr, len as u32, &self.options)?;
         self.compound()
     }
 
     #[cfg(feature = "std")]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        self.maybe_unknown_len_compound(len, |wr, len| encode::write_array_len(wr, len))
+        self.maybe_unknown_len_compound(len, write_array_len, Marker::Array32)
     }
 
     //TODO: normal compund
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        encode::write_array_len(&mut self.wr, len as u32)?;
+        write_array_len(&mut self.wr, len as u32, &self.options)?;
 
         self.compound()
     }
@@ -693,7 +1958,7 @@ where
     fn serialize_tuple_struct(self, _name: &'static str, len: usize) ->
         Result<Self::SerializeTupleStruct, Self::Error>
     {
-        encode::write_array_len(&mut self.wr, len as u32)?;
+        write_array_len(&mut self.wr, len as u32, &self.options)?;
 
         self.compound()
     }
@@ -702,7 +1967,7 @@ where
         Result<Self::SerializeTupleVariant, Self::Error>
     {
         // encode as a map from variant idx to a sequence of its attributed data, like: {idx => [v1,...,vN]}
-        encode::write_map_len(&mut self.wr, 1)?;
+        write_map_len(&mut self.wr, 1, &self.options)?;
         C::write_variant_ident(self, idx, variant)?;
         self.serialize_tuple(len)
     }
@@ -710,14 +1975,15 @@ where
     #[cfg(not(feature = "std"))]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
         let len = len.ok_or(Error::UnknownLength)?;
-        encode::write_map_len(&mut self.wr, len as u32)?;
+        write_map_len(&mut self.wr, len as u32, &self.options)?;
         self.compound()
     }
     #[cfg(feature = "std")]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        self.maybe_unknown_len_compound(len, |wr, len| encode::write_map_len(wr, len))
+        self.maybe_unknown_len_compound(len, write_map_len, Marker::Map32)
     }
 
+    #[cfg(not(feature = "std"))]
     fn serialize_struct(self, _name: &'static str, len: usize) ->
         Result<Self::SerializeStruct, Self::Error>
     {
@@ -725,11 +1991,42 @@ where
         self.compound()
     }
 
+    #[cfg(feature = "std")]
+    fn serialize_struct(self, name: &'static str, len: usize) ->
+        Result<Self::SerializeStruct, Self::Error>
+    {
+        let struct_map = self.options.struct_map_if
+            .map(|f| f(name))
+            .filter(|&struct_map| struct_map != C::is_struct_map());
+        // Fields may be skipped, so the map length is only known once all of them were seen.
+        if self.options.skips_fields() && struct_map.unwrap_or(C::is_struct_map()) {
+            return Ok(MaybeUnknownLengthCompound {
+                compound: Some(UnknownLengthCompound::from(&*self)),
+                se: self,
+                pending: None,
+                struct_map,
+            });
+        }
+        match struct_map {
+            None => C::write_struct_len(self, len)?,
+            Some(struct_map) => {
+                let len32 = u32::try_from(len)
+                    .map_err(|_| Error::InvalidDataModel("too many fields for a MessagePack map or array"))?;
+                if struct_map {
+                    write_map_len(&mut self.wr, len32, &self.options)?;
+                } else {
+                    write_array_len(&mut self.wr, len32, &self.options)?;
+                }
+            }
+        }
+        Ok(MaybeUnknownLengthCompound { se: self, compound: None, pending: None, struct_map })
+    }
+
     fn serialize_struct_variant(self, name: &'static str, id: u32, variant: &'static str, len: usize) ->
         Result<Self::SerializeStructVariant, Self::Error>
     {
         // encode as a map from variant idx to a sequence of its attributed data, like: {idx => [v1,...,vN]}
-        encode::write_map_len(&mut self.wr, 1)?;
+        write_map_len(&mut self.wr, 1, &self.options)?;
         C::write_variant_ident(self, id, variant)?;
         self.serialize_struct(name, len)
     }
@@ -752,7 +2049,7 @@ where
 
         let mut counter = LenCounter { len: 0 };
         write!(&mut counter, "{value}").expect("counting length can't fail");
-        encode::write_str_len(&mut self.wr, counter.len as u32)?;
+        write_str_len(&mut self.wr, counter.len as u32, &self.options)?;
 
         struct FmtWriter<'a, W: RmpWrite> {
             r: &'a mut Result<(), W::Error>,
@@ -1155,7 +2452,7 @@ impl<'a, W: RmpWrite + 'a> ExtFieldSerializer<'a, W> {
     #[inline]
     fn new<C>(ser: &'a mut Serializer<W, C>) -> Self {
         Self {
-            wr: UnderlyingWrite::get_mut(ser),
+            wr: &mut ser.wr,
             tag: None,
             finish: false,
         }
@@ -1171,60 +2468,785 @@ impl<'a, W: RmpWrite + 'a> ExtFieldSerializer<'a, W> {
     }
 }
 
-/// Serialize the given data structure as MessagePack into the I/O stream.
-/// This function uses compact representation - structures as arrays
-///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
-#[inline]
-pub fn write<W, T>(wr: W, val: &T) -> Result<(), Error<W::Error>>
-where
-    W: RmpWrite,
-    T: Serialize + ?Sized
-{
-    val.serialize(&mut Serializer::new(wr))
+/// Writes a bin from a length followed by chunks of its data, see [`crate::bin_stream`].
+#[cfg(feature = "std")]
+struct BinChunksSerializer<'a, W> {
+    wr: &'a mut CountingWrite<W>,
+    tuple_received: bool,
+    /// Length of the data still to be written, once the header is.
+    left: Option<u32>,
+    options: RuntimeConfig,
 }
 
-/// Serialize the given data structure as MessagePack into the I/O stream.
+#[cfg(feature = "std")]
+impl<'a, W: RmpWrite + 'a> BinChunksSerializer<'a, W> {
+    #[inline]
+    fn new<C>(ser: &'a mut Serializer<W, C>) -> Self {
+        Self {
+            options: ser.options,
+            wr: &mut ser.wr,
+            tuple_received: false,
+            left: None,
+        }
+    }
 
-///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
-pub fn write_named<W, T>(wr: W, val: &T) -> Result<(), Error<W::Error>>
-where
-    W: RmpWrite,
-    T: Serialize + ?Sized
-{
-    let mut se = Serializer::new(wr).with_struct_map();
-    val.serialize(&mut se)
+    #[inline]
+    fn end(self) -> Result<(), Error<W::Error>> {
+        match self.left {
+            Some(0) => Ok(()),
+            _ => Err(Error::InvalidDataModel("expected bin chunks, received less data than announced")),
+        }
+    }
 }
 
-/// Serialize the given data structure as a MessagePack byte vector.
-/// This method uses compact representation, structs are serialized as arrays
-///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
 #[cfg(feature = "std")]
-#[inline]
-pub fn to_vec<T>(val: &T) -> Result<Vec<u8>, Error<std::io::Error>>
-where
-    T: Serialize + ?Sized
-{
-    let mut wr = Vec::with_capacity(128);
-    write(&mut wr, val)?;
-    Ok(wr)
-}
+impl<'a, W: RmpWrite + 'a> serde::Serializer for &mut BinChunksSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error<W::Error>;
 
-/// Serializes data structure into byte vector as a map
-/// Resulting MessagePack message will contain field names
-///
-/// # Errors
-///
-/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
-#[cfg(feature = "std")]
-#[inline]
-pub fn to_vec_named<T>(val: &T) -> Result<Vec<u8>, Error<std::io::Error>>
-where
-    T: Serialize + ?Sized
-{
-    let mut wr = Vec::with_capacity(128);
-    write_named(&mut wr, val)?;
-    Ok(wr)
+    type SerializeSeq = serde::ser::Impossible<(), Self::Error>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = serde::ser::Impossible<(), Self::Error>;
+    type SerializeTupleVariant = serde::ser::Impossible<(), Self::Error>;
+    type SerializeMap = serde::ser::Impossible<(), Self::Error>;
+    type SerializeStruct = serde::ser::Impossible<(), Self::Error>;
+    type SerializeStructVariant = serde::ser::Impossible<(), Self::Error>;
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        if self.tuple_received {
+            return Err(Error::InvalidDataModel("expected bin chunks, received nested tuple"));
+        }
+        self.tuple_received = true;
+        Ok(self)
+    }
+
+    fn serialize_u32(self, len: u32) -> Result<Self::Ok, Self::Error> {
+        if !self.tuple_received || self.left.is_some() {
+            return Err(Error::InvalidDataModel("expected bin chunks, received unexpected u32"));
+        }
+        write_bin_len(self.wr, len, &self.options)?;
+        self.left = Some(len);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, val: &[u8]) -> Result<Self::Ok, Self::Error> {
+        match self.left {
+            Some(left) if val.len() as u64 <= u64::from(left) => {
+                self.wr.write_bytes(val).map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))?;
+                self.left = Some(left - val.len() as u32);
+                Ok(())
+            }
+            Some(..) => Err(Error::InvalidDataModel("expected bin chunks, received more data than announced")),
+            None => Err(Error::InvalidDataModel("expected bin chunks, received bytes before the length")),
+        }
+    }
+
+    #[cold]
+    fn serialize_bool(self, _val: bool) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received bool"))
+    }
+
+    #[cold]
+    fn serialize_i8(self, _val: i8) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received i8"))
+    }
+
+    #[cold]
+    fn serialize_i16(self, _val: i16) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received i16"))
+    }
+
+    #[cold]
+    fn serialize_i32(self, _val: i32) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received i32"))
+    }
+
+    #[cold]
+    fn serialize_i64(self, _val: i64) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received i64"))
+    }
+
+    #[cold]
+    fn serialize_u8(self, _val: u8) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received u8"))
+    }
+
+    #[cold]
+    fn serialize_u16(self, _val: u16) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received u16"))
+    }
+
+    #[cold]
+    fn serialize_u64(self, _val: u64) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received u64"))
+    }
+
+    #[cold]
+    fn serialize_f32(self, _val: f32) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received f32"))
+    }
+
+    #[cold]
+    fn serialize_f64(self, _val: f64) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received f64"))
+    }
+
+    #[cold]
+    fn serialize_char(self, _val: char) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received char"))
+    }
+
+    #[cold]
+    fn serialize_str(self, _val: &str) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received str"))
+    }
+
+    #[cold]
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received unit"))
+    }
+
+    #[cold]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received unit_struct"))
+    }
+
+    #[cold]
+    fn serialize_unit_variant(self, _name: &'static str, _idx: u32, _variant: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received unit_variant"))
+    }
+
+    #[cold]
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, _value: &T) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received newtype_struct"))
+    }
+
+    #[cold]
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _idx: u32, _variant: &'static str, _value: &T) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received newtype_variant"))
+    }
+
+    #[cold]
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received none"))
+    }
+
+    #[cold]
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received some"))
+    }
+
+    #[cold]
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received seq"))
+    }
+
+    #[cold]
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received tuple_struct"))
+    }
+
+    #[cold]
+    fn serialize_tuple_variant(self, _name: &'static str, _idx: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received tuple_variant"))
+    }
+
+    #[cold]
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received map"))
+    }
+
+    #[cold]
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received struct"))
+    }
+
+    #[cold]
+    fn serialize_struct_variant(self, _name: &'static str, _idx: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received struct_variant"))
+    }
+
+    fn collect_str<T: ?Sized + Display>(self, _value: &T) -> Result<Self::Ok, Self::Error> {
+        Err(Error::InvalidDataModel("expected bin chunks, received str"))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, W: RmpWrite + 'a> SerializeTuple for &mut BinChunksSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error<W::Error>;
+
+    #[inline]
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(&mut **self)
+    }
+
+    #[inline(always)]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+/// Writer wrapper for [`bytes::BufMut`] implementations, such as `BytesMut` or `&mut BytesMut`.
+///
+/// Writes go straight into the buffer, without an intermediate `Vec<u8>`. Running out of
+/// capacity (only possible for fixed-size buffers) results in an
+/// [`ErrorKind::WriteZero`](std::io::ErrorKind::WriteZero) error instead of a panic.
+#[cfg(feature = "bytes")]
+#[derive(Debug, Default)]
+pub struct BytesWriter<B = bytes::BytesMut> {
+    buf: B,
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::BufMut> BytesWriter<B> {
+    /// Wraps the given buffer.
+    #[inline]
+    pub fn new(buf: B) -> Self {
+        BytesWriter { buf }
+    }
+
+    /// Gets a reference to the underlying buffer.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Gets a mutable reference to the underlying buffer.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.buf
+    }
+
+    /// Unwraps this `BytesWriter`, returning the underlying buffer.
+    #[inline(always)]
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+
+    #[inline]
+    fn check_capacity(&self, len: usize) -> Result<(), std::io::Error> {
+        if self.buf.remaining_mut() < len {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::BufMut> RmpWrite for BytesWriter<B> {
+    type Error = std::io::Error;
+
+    #[inline]
+    fn write_u8(&mut self, val: u8) -> Result<(), Self::Error> {
+        self.check_capacity(1)?;
+        self.buf.put_u8(val);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.check_capacity(buf.len())?;
+        self.buf.put_slice(buf);
+        Ok(())
+    }
+}
+
+/// The error of a [`SliceWriter`] whose buffer is too small for the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BufferFull;
+
+impl Display for BufferFull {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("output buffer is full")
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for BufferFull {}
+
+impl RmpWriteErr for BufferFull {}
+
+/// Writer into a fixed `&mut [u8]` buffer, for targets without an allocator.
+///
+/// Writes that don't fit fail with [`BufferFull`] and leave the buffer as it was.
+#[derive(Debug)]
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Wraps the given buffer, writing from its start.
+    #[inline]
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        SliceWriter { buf, len: 0 }
+    }
+
+    /// Returns the number of bytes written.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if nothing was written yet.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the part of the buffer written so far.
+    #[inline(always)]
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Unwraps this `SliceWriter`, returning the part of the buffer written so far.
+    #[inline]
+    pub fn into_written(self) -> &'a mut [u8] {
+        &mut self.buf[..self.len]
+    }
+}
+
+impl RmpWrite for SliceWriter<'_> {
+    type Error = BufferFull;
+
+    #[inline]
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        let end = self.len + buf.len();
+        self.buf.get_mut(self.len..end).ok_or(BufferFull)?.copy_from_slice(buf);
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Writer into a fixed-capacity [`heapless::Vec`], appending to its content.
+///
+/// Writes that don't fit fail with [`BufferFull`] and leave the vector as it was.
+#[cfg(feature = "heapless")]
+#[derive(Debug, Default)]
+pub struct HeaplessWriter<const N: usize> {
+    buf: heapless::Vec<u8, N>,
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> HeaplessWriter<N> {
+    /// Wraps the given vector.
+    #[inline]
+    pub fn new(buf: heapless::Vec<u8, N>) -> Self {
+        HeaplessWriter { buf }
+    }
+
+    /// Gets a reference to the underlying vector.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &heapless::Vec<u8, N> {
+        &self.buf
+    }
+
+    /// Gets a mutable reference to the underlying vector.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut heapless::Vec<u8, N> {
+        &mut self.buf
+    }
+
+    /// Unwraps this `HeaplessWriter`, returning the underlying vector.
+    #[inline(always)]
+    pub fn into_inner(self) -> heapless::Vec<u8, N> {
+        self.buf
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> RmpWrite for HeaplessWriter<N> {
+    type Error = BufferFull;
+
+    #[inline]
+    fn write_u8(&mut self, val: u8) -> Result<(), Self::Error> {
+        self.buf.push(val).map_err(|_| BufferFull)
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.buf.extend_from_slice(buf).map_err(|()| BufferFull)
+    }
+}
+
+/// Serializes the given data structure into a [`heapless::Vec`] of capacity `N`, using the
+/// compact representation like [`to_vec`].
+///
+/// # Errors
+///
+/// Fails with [`BufferFull`] if the output is longer than `N` bytes, or if `T`'s implementation
+/// of `Serialize` decides to fail.
+///
+/// # Examples
+///
+/// ```
+/// let buf = rmp_serde::encode::to_heapless_vec::<4>(&(1, "a")).unwrap();
+/// assert_eq!([0x92, 0x01, 0xa1, b'a'], buf[..]);
+///
+/// assert!(rmp_serde::encode::to_heapless_vec::<4>(&(1, "ab")).is_err());
+/// ```
+#[cfg(feature = "heapless")]
+#[inline]
+pub fn to_heapless_vec<const N: usize>(val: &(impl Serialize + ?Sized)) -> Result<heapless::Vec<u8, N>, Error<BufferFull>> {
+    let mut se = Serializer::new(HeaplessWriter::new(heapless::Vec::new()));
+    val.serialize(&mut se)?;
+    Ok(se.into_inner().into_inner())
+}
+
+/// Serializes the given data structure into the start of the buffer, using the compact
+/// representation like [`to_vec`], and returns the number of bytes written.
+///
+/// This doesn't allocate, so it works without the `alloc` feature.
+///
+/// # Errors
+///
+/// Fails with [`BufferFull`] if the output doesn't fit, in which case the content of the buffer is
+/// unspecified, or if `T`'s implementation of `Serialize` decides to fail.
+///
+/// # Examples
+///
+/// ```
+/// let mut buf = [0; 4];
+/// let len = rmp_serde::write_to_slice(&mut buf, &(1, "a")).unwrap();
+/// assert_eq!([0x92, 0x01, 0xa1, b'a'], buf[..len]);
+///
+/// assert!(rmp_serde::write_to_slice(&mut buf, &(1, "ab")).is_err());
+/// ```
+#[inline]
+pub fn write_to_slice<T>(buf: &mut [u8], val: &T) -> Result<usize, Error<BufferFull>>
+where
+    T: Serialize + ?Sized
+{
+    let mut se = Serializer::new(SliceWriter::new(buf));
+    val.serialize(&mut se)?;
+    Ok(se.into_inner().len())
+}
+
+/// Serialize the given data structure as MessagePack into the I/O stream.
+/// This function uses compact representation - structures as arrays
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[inline]
+pub fn write<W, T>(wr: W, val: &T) -> Result<(), Error<W::Error>>
+where
+    W: RmpWrite,
+    T: Serialize + ?Sized
+{
+    val.serialize(&mut Serializer::new(wr))
+}
+
+/// Serialize the given data structure as MessagePack into the I/O stream.
+
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+pub fn write_named<W, T>(wr: W, val: &T) -> Result<(), Error<W::Error>>
+where
+    W: RmpWrite,
+    T: Serialize + ?Sized
+{
+    let mut se = Serializer::new(wr).with_struct_map();
+    val.serialize(&mut se)
+}
+
+/// The error type of writing into a `Vec<u8>`, which can't actually fail: `io::Error` with the
+/// `std` feature, and `Infallible` without.
+#[cfg(feature = "std")]
+pub type VecWriteError = std::io::Error;
+/// The error type of writing into a `Vec<u8>`, which can't actually fail: `io::Error` with the
+/// `std` feature, and `Infallible` without.
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+pub type VecWriteError = core::convert::Infallible;
+
+/// Serialize the given data structure as a MessagePack byte vector.
+/// This method uses compact representation, structs are serialized as arrays
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_vec<T>(val: &T) -> Result<Vec<u8>, Error<VecWriteError>>
+where
+    T: Serialize + ?Sized
+{
+    let mut wr = Vec::with_capacity(128);
+    write(&mut wr, val)?;
+    Ok(wr)
+}
+
+/// Serializes the given data structure as a MessagePack byte vector allocated with `alloc`.
+///
+/// This is the same as [`to_vec`], e.g. for arena or bump allocators. The allocator API is
+/// provided by the `allocator-api2` crate, whose `nightly` feature switches to the unstable one
+/// of the standard library.
+///
+/// # Examples
+///
+/// ```
+/// use allocator_api2::alloc::Global;
+///
+/// let buf = rmp_serde::encode::to_vec_in(&(1, "a"), Global).unwrap();
+/// assert_eq!([0x92, 0x01, 0xa1, b'a'], buf[..]);
+/// ```
+#[cfg(feature = "allocator_api")]
+#[inline]
+pub fn to_vec_in<T, A>(val: &T, alloc: A) -> Result<allocator_api2::vec::Vec<u8, A>, Error<std::io::Error>>
+where
+    T: Serialize + ?Sized,
+    A: allocator_api2::alloc::Allocator,
+{
+    let mut wr = allocator_api2::vec::Vec::with_capacity_in(128, alloc);
+    write(&mut wr, val)?;
+    Ok(wr)
+}
+
+/// Serializes the given data structure as a MessagePack byte vector, like [`to_vec`], into a
+/// buffer taken from a thread-local pool.
+///
+/// Dropping the returned [`PooledBuf`] gives the buffer back to the pool of the thread it's
+/// dropped on, so encoding in a loop reuses a few buffers instead of allocating each time. New
+/// buffers get the capacity of the last message encoded on the thread. Up to 8 buffers of at
+/// most 1 MiB are kept per thread, larger ones are freed.
+///
+/// # Examples
+///
+/// ```
+/// for i in 0..3 {
+///     let buf = rmp_serde::to_vec_pooled(&(i, "a")).unwrap();
+///     assert_eq!([0x92, i, 0xa1, b'a'], buf[..]);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn to_vec_pooled<T>(val: &T) -> Result<PooledBuf, Error<std::io::Error>>
+where
+    T: Serialize + ?Sized
+{
+    let mut buf = PooledBuf { buf: pool::take() };
+    write(&mut buf.buf, val)?;
+    pool::LAST_LEN.with(|last| last.set(buf.buf.len()));
+    Ok(buf)
+}
+
+/// A buffer returned by [`to_vec_pooled`], going back to the thread-local pool on drop.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct PooledBuf {
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl PooledBuf {
+    /// Takes the buffer out, so that it isn't returned to the pool.
+    #[inline]
+    pub fn into_vec(mut self) -> Vec<u8> {
+        core::mem::take(&mut self.buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::ops::Deref for PooledBuf {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<[u8]> for PooledBuf {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for PooledBuf {
+    #[inline]
+    fn drop(&mut self) {
+        pool::give(core::mem::take(&mut self.buf));
+    }
+}
+
+#[cfg(feature = "std")]
+mod pool {
+    use std::cell::{Cell, RefCell};
+
+    /// Maximum number of buffers kept per thread.
+    const MAX_BUFS: usize = 8;
+    /// Maximum capacity of a kept buffer.
+    const MAX_CAPACITY: usize = 1024 * 1024;
+
+    thread_local! {
+        static BUFS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+        pub(super) static LAST_LEN: Cell<usize> = const { Cell::new(128) };
+    }
+
+    pub(super) fn take() -> Vec<u8> {
+        BUFS.with(|bufs| bufs.borrow_mut().pop())
+            .unwrap_or_else(|| Vec::with_capacity(LAST_LEN.with(Cell::get).clamp(128, MAX_CAPACITY)))
+    }
+
+    pub(super) fn give(mut buf: Vec<u8>) {
+        if buf.capacity() == 0 || buf.capacity() > MAX_CAPACITY {
+            return;
+        }
+        buf.clear();
+        // The pool is gone if the thread is exiting.
+        let _ = BUFS.try_with(|bufs| {
+            let mut bufs = bufs.borrow_mut();
+            if bufs.len() < MAX_BUFS {
+                bufs.push(buf);
+            }
+        });
+    }
+}
+
+/// Serializes data structure into byte vector as a map
+/// Resulting MessagePack message will contain field names
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_vec_named<T>(val: &T) -> Result<Vec<u8>, Error<VecWriteError>>
+where
+    T: Serialize + ?Sized
+{
+    let mut wr = Vec::with_capacity(128);
+    write_named(&mut wr, val)?;
+    Ok(wr)
+}
+
+/// Serializes the given data structure like [`to_vec`], but with a serializer that reports
+/// itself as human readable, see [`Serializer::with_human_readable`].
+///
+/// Types that branch on `is_human_readable`, such as `IpAddr`, then serialize as strings, as they
+/// did with older versions of rmp-serde.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_vec_human_readable<T>(val: &T) -> Result<Vec<u8>, Error<VecWriteError>>
+where
+    T: Serialize + ?Sized
+{
+    let mut se = Serializer::new(Vec::with_capacity(128)).with_human_readable();
+    val.serialize(&mut se)?;
+    Ok(se.into_inner())
+}
+
+/// Serializes the given data structure like [`to_vec_named`], with structs as maps, but with a
+/// serializer that reports itself as human readable, see [`to_vec_human_readable`].
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_vec_named_human_readable<T>(val: &T) -> Result<Vec<u8>, Error<VecWriteError>>
+where
+    T: Serialize + ?Sized
+{
+    let mut se = Serializer::new(Vec::with_capacity(128)).with_struct_map().with_human_readable();
+    val.serialize(&mut se)?;
+    Ok(se.into_inner())
+}
+
+/// Serializes the given data structure like [`to_vec`], and returns the MessagePack data as
+/// lowercase hex, e.g. for debugging or test fixtures.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!("9201a161", rmp_serde::to_hex_string(&(1, "a")).unwrap());
+/// ```
+#[cfg(feature = "alloc")]
+pub fn to_hex_string<T>(val: &T) -> Result<String, Error<VecWriteError>>
+where
+    T: Serialize + ?Sized
+{
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let buf = to_vec(val)?;
+    let mut s = String::with_capacity(2 * buf.len());
+    for byte in buf {
+        s.push(DIGITS[usize::from(byte >> 4)].into());
+        s.push(DIGITS[usize::from(byte & 0xf)].into());
+    }
+    Ok(s)
+}
+
+/// Serializes the given data structure like [`to_vec`], and returns the MessagePack data as base64
+/// with padding in the standard alphabet, e.g. to embed it in a JSON field or an HTTP header.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+///
+/// # Examples
+///
+/// ```
+/// let s = rmp_serde::to_base64_string(&(1, "a")).unwrap();
+/// assert_eq!("kgGhYQ==", s);
+/// assert_eq!((1, "a".to_owned()), rmp_serde::from_base64_str(&s).unwrap());
+/// ```
+#[cfg(feature = "base64")]
+#[inline]
+pub fn to_base64_string<T>(val: &T) -> Result<String, Error<VecWriteError>>
+where
+    T: Serialize + ?Sized
+{
+    use base64::Engine;
+
+    Ok(base64::engine::general_purpose::STANDARD.encode(to_vec(val)?))
+}
+
+/// Serializes the items of an iterator as a MessagePack array of `len` elements into a byte vector.
+/// This method uses compact representation, structs are serialized as arrays
+///
+/// See [`Serializer::serialize_iter`].
+///
+/// # Errors
+///
+/// Serialization can fail if an item's implementation of `Serialize` decides to fail, or if the
+/// iterator doesn't yield exactly `len` items.
+#[cfg(feature = "std")]
+#[inline]
+pub fn to_vec_from_iter<I>(len: usize, iter: I) -> Result<Vec<u8>, Error<std::io::Error>>
+where
+    I: IntoIterator,
+    I::Item: Serialize
+{
+    let mut se = Serializer::new(Vec::with_capacity(128));
+    se.serialize_iter(len, iter)?;
+    Ok(se.into_inner())
+}
+
+/// Serialize the given data structure as MessagePack into a [`bytes::Bytes`] buffer.
+/// This method uses compact representation, structs are serialized as arrays
+///
+/// The buffer is written through `BytesMut` and frozen, so it can be handed to `bytes`-based
+/// networking code without copying.
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[cfg(feature = "bytes")]
+#[inline]
+pub fn to_bytes<T>(val: &T) -> Result<bytes::Bytes, Error<std::io::Error>>
+where
+    T: Serialize + ?Sized
+{
+    let mut se = Serializer::new(BytesWriter::new(bytes::BytesMut::with_capacity(128)));
+    val.serialize(&mut se)?;
+    Ok(se.into_inner().into_inner().freeze())
 }
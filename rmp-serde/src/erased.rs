@@ -0,0 +1,162 @@
+//! Object-safe serializers and deserializers, for use with [`erased_serde`].
+//!
+//! [`ErasedSerializer`] and [`ErasedDeserializer`] hold a MessagePack serializer or deserializer
+//! whose configuration is chosen at runtime with [`Options`], and lend it out as a boxed
+//! `dyn erased_serde::Serializer` or `dyn erased_serde::Deserializer`. Plugins can then encode and
+//! decode MessagePack through a trait object, without depending on the type of the writer, the
+//! reader or the configuration.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! use rmp_serde::erased::{ErasedDeserializer, ErasedSerializer, Options};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Point {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! let value: Box<dyn erased_serde::Serialize> = Box::new(Point { x: 1, y: 2 });
+//!
+//! let mut buf = Vec::new();
+//! let mut se = ErasedSerializer::new(&mut buf, Options::new().with_struct_map());
+//! value.erased_serialize(&mut *se.erase()).unwrap();
+//! assert_eq!(buf, rmp_serde::to_vec_named(&Point { x: 1, y: 2 }).unwrap());
+//!
+//! let mut de = ErasedDeserializer::from_slice(&buf, Options::new());
+//! let point: Point = erased_serde::deserialize(&mut *de.erase()).unwrap();
+//! assert_eq!(Point { x: 1, y: 2 }, point);
+//! # }
+//! ```
+
+use std::io;
+
+use crate::config::{DefaultConfig, HumanReadableConfig, StructMapConfig};
+use crate::decode::{self, ReadReader, ReadRefReader};
+use crate::encode;
+
+/// The configuration of an [`ErasedSerializer`] or [`ErasedDeserializer`].
+///
+/// The default writes structs as arrays, and isn't human readable, like [`to_vec`](crate::to_vec).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    struct_map: bool,
+    human_readable: bool,
+}
+
+impl Options {
+    /// Returns the default options.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes structs as maps with field names, like
+    /// [`Serializer::with_struct_map`](crate::Serializer::with_struct_map).
+    ///
+    /// Deserializers read both forms of structs, so this only affects serializers.
+    #[inline]
+    pub fn with_struct_map(mut self) -> Self {
+        self.struct_map = true;
+        self
+    }
+
+    /// Reports the format as human readable, like
+    /// [`Serializer::with_human_readable`](crate::Serializer::with_human_readable).
+    #[inline]
+    pub fn with_human_readable(mut self) -> Self {
+        self.human_readable = true;
+        self
+    }
+}
+
+/// A serializer writing to a `dyn io::Write`, which can be erased into a
+/// `dyn erased_serde::Serializer`.
+pub struct ErasedSerializer<'a> {
+    inner: SerializerKind<'a>,
+}
+
+enum SerializerKind<'a> {
+    Tuple(encode::Serializer<&'a mut dyn io::Write, DefaultConfig>),
+    Map(encode::Serializer<&'a mut dyn io::Write, StructMapConfig<DefaultConfig>>),
+    HumanTuple(encode::Serializer<&'a mut dyn io::Write, HumanReadableConfig<DefaultConfig>>),
+    HumanMap(encode::Serializer<&'a mut dyn io::Write, HumanReadableConfig<StructMapConfig<DefaultConfig>>>),
+}
+
+impl<'a> ErasedSerializer<'a> {
+    /// Creates a serializer writing to `wr`.
+    pub fn new(wr: &'a mut dyn io::Write, options: Options) -> Self {
+        let se = encode::Serializer::new(wr);
+        let inner = match (options.struct_map, options.human_readable) {
+            (false, false) => SerializerKind::Tuple(se),
+            (true, false) => SerializerKind::Map(se.with_struct_map()),
+            (false, true) => SerializerKind::HumanTuple(se.with_human_readable()),
+            (true, true) => SerializerKind::HumanMap(se.with_struct_map().with_human_readable()),
+        };
+        ErasedSerializer { inner }
+    }
+
+    /// Returns the serializer as a trait object, to serialize one value.
+    ///
+    /// Several values can be written back to back by erasing the serializer again for each.
+    pub fn erase(&mut self) -> Box<dyn erased_serde::Serializer + '_> {
+        match self.inner {
+            SerializerKind::Tuple(ref mut se) => Box::new(<dyn erased_serde::Serializer>::erase(se)),
+            SerializerKind::Map(ref mut se) => Box::new(<dyn erased_serde::Serializer>::erase(se)),
+            SerializerKind::HumanTuple(ref mut se) => Box::new(<dyn erased_serde::Serializer>::erase(se)),
+            SerializerKind::HumanMap(ref mut se) => Box::new(<dyn erased_serde::Serializer>::erase(se)),
+        }
+    }
+}
+
+/// A deserializer reading from a `dyn io::Read` or a byte slice, which can be erased into a
+/// `dyn erased_serde::Deserializer`.
+///
+/// Values read from a byte slice can borrow from it.
+pub struct ErasedDeserializer<'de> {
+    inner: DeserializerKind<'de>,
+}
+
+enum DeserializerKind<'de> {
+    Read(decode::Deserializer<ReadReader<&'de mut dyn io::Read>, DefaultConfig>),
+    HumanRead(decode::Deserializer<ReadReader<&'de mut dyn io::Read>, HumanReadableConfig<DefaultConfig>>),
+    Slice(decode::Deserializer<ReadRefReader<'de>, DefaultConfig>),
+    HumanSlice(decode::Deserializer<ReadRefReader<'de>, HumanReadableConfig<DefaultConfig>>),
+}
+
+impl<'de> ErasedDeserializer<'de> {
+    /// Creates a deserializer reading from `rd`.
+    pub fn from_read(rd: &'de mut dyn io::Read, options: Options) -> Self {
+        let de = decode::Deserializer::new(rd);
+        let inner = if options.human_readable {
+            DeserializerKind::HumanRead(de.with_human_readable())
+        } else {
+            DeserializerKind::Read(de)
+        };
+        ErasedDeserializer { inner }
+    }
+
+    /// Creates a deserializer reading from `buf`.
+    pub fn from_slice(buf: &'de [u8], options: Options) -> Self {
+        let de = decode::Deserializer::from_bytes(buf);
+        let inner = if options.human_readable {
+            DeserializerKind::HumanSlice(de.with_human_readable())
+        } else {
+            DeserializerKind::Slice(de)
+        };
+        ErasedDeserializer { inner }
+    }
+
+    /// Returns the deserializer as a trait object, to deserialize one value.
+    ///
+    /// Several values can be read back to back by erasing the deserializer again for each.
+    pub fn erase(&mut self) -> Box<dyn erased_serde::Deserializer<'de> + '_> {
+        match self.inner {
+            DeserializerKind::Read(ref mut de) => Box::new(<dyn erased_serde::Deserializer>::erase(de)),
+            DeserializerKind::HumanRead(ref mut de) => Box::new(<dyn erased_serde::Deserializer>::erase(de)),
+            DeserializerKind::Slice(ref mut de) => Box::new(<dyn erased_serde::Deserializer>::erase(de)),
+            DeserializerKind::HumanSlice(ref mut de) => Box::new(<dyn erased_serde::Deserializer>::erase(de)),
+        }
+    }
+}
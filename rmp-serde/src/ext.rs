@@ -0,0 +1,572 @@
+//! First-class MessagePack extension types.
+
+use core::fmt::{self, Formatter};
+#[cfg(feature = "std")]
+use core::fmt::{Debug, Display};
+#[cfg(feature = "std")]
+use std::any::Any;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+#[cfg(feature = "std")]
+use serde::de::DeserializeSeed;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::MSGPACK_EXT_STRUCT_NAME;
+
+/// An owned MessagePack extension value, consisting of a type tag and opaque data.
+///
+/// Serializes as a MessagePack ext (via [`MSGPACK_EXT_STRUCT_NAME`]), so there is no need to
+/// hand-write a `#[serde(rename = "_ExtStruct")]` newtype.
+///
+/// ```
+/// use rmp_serde::Ext;
+///
+/// let ext = Ext::new(5, vec![0x01, 0x02]);
+/// let buf = rmp_serde::to_vec(&ext).unwrap();
+///
+/// assert_eq!(vec![0xd5, 0x05, 0x01, 0x02], buf);
+/// assert_eq!(ext, rmp_serde::from_slice(&buf).unwrap());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Ext {
+    /// The application-defined type tag. Negative tags are reserved by the MessagePack spec.
+    pub tag: i8,
+    /// The extension payload.
+    pub data: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl Ext {
+    /// Constructs a new extension value.
+    #[inline]
+    pub fn new(tag: i8, data: Vec<u8>) -> Self {
+        Ext { tag, data }
+    }
+
+    /// Returns the type tag.
+    #[inline]
+    pub fn tag(&self) -> i8 {
+        self.tag
+    }
+
+    /// Returns the payload.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes this value, returning the payload.
+    #[inline]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Borrows this value as an [`ExtRef`].
+    #[inline]
+    pub fn as_ext_ref(&self) -> ExtRef<'_> {
+        ExtRef::new(self.tag, &self.data)
+    }
+
+    /// Deserializes an ext from the deserializer given to `Visitor::visit_newtype_struct`, for
+    /// visitors that accept exts besides other types.
+    #[cfg(any(feature = "bigint", feature = "uuid", feature = "rust_decimal", feature = "bigdecimal"))]
+    #[inline]
+    pub(crate) fn from_newtype<'de, D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let (tag, data) = ExtVisitor::<Vec<u8>>::new().visit_newtype_struct(de)?;
+        Ok(Ext { tag, data })
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<(i8, Vec<u8>)> for Ext {
+    #[inline]
+    fn from((tag, data): (i8, Vec<u8>)) -> Self {
+        Ext::new(tag, data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Ext> for (i8, Vec<u8>) {
+    #[inline]
+    fn from(ext: Ext) -> Self {
+        (ext.tag, ext.data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ExtRef<'_>> for Ext {
+    #[inline]
+    fn from(ext: ExtRef<'_>) -> Self {
+        Ext::new(ext.tag, ext.data.to_vec())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> From<&'a Ext> for ExtRef<'a> {
+    #[inline]
+    fn from(ext: &'a Ext) -> Self {
+        ext.as_ext_ref()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Serialize for Ext {
+    #[inline]
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        self.as_ext_ref().serialize(se)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for Ext {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_newtype_struct(MSGPACK_EXT_STRUCT_NAME, ExtVisitor::<Vec<u8>>::new())
+            .map(|(tag, data)| Ext::new(tag, data))
+    }
+}
+
+/// A borrowed MessagePack extension value, the zero-copy counterpart of [`Ext`].
+///
+/// Deserializing requires the payload to be borrowable from the input, e.g. when using
+/// [`from_slice`](crate::from_slice).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ExtRef<'a> {
+    /// The application-defined type tag. Negative tags are reserved by the MessagePack spec.
+    pub tag: i8,
+    /// The extension payload.
+    pub data: &'a [u8],
+}
+
+impl<'a> ExtRef<'a> {
+    /// Constructs a new extension value.
+    #[inline]
+    pub fn new(tag: i8, data: &'a [u8]) -> Self {
+        ExtRef { tag, data }
+    }
+
+    /// Returns the type tag.
+    #[inline]
+    pub fn tag(&self) -> i8 {
+        self.tag
+    }
+
+    /// Returns the payload.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> From<(i8, &'a [u8])> for ExtRef<'a> {
+    #[inline]
+    fn from((tag, data): (i8, &'a [u8])) -> Self {
+        ExtRef::new(tag, data)
+    }
+}
+
+impl Serialize for ExtRef<'_> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_newtype_struct(MSGPACK_EXT_STRUCT_NAME, &(self.tag, SerBytes(self.data)))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for ExtRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_newtype_struct(MSGPACK_EXT_STRUCT_NAME, ExtVisitor::<&'a [u8]>::new())
+            .map(|(tag, data)| ExtRef::new(tag, data))
+    }
+}
+
+/// An application-specific extension type with a fixed tag.
+///
+/// Wrap values in [`TypedExt`] to serialize them as MessagePack exts with the tag [`Self::TAG`].
+///
+/// ```
+/// use rmp_serde::{MsgPackExtType, TypedExt};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point {
+///     x: i16,
+///     y: i16,
+/// }
+///
+/// impl MsgPackExtType for Point {
+///     const TAG: i8 = 7;
+///     type Error = &'static str;
+///
+///     fn to_bytes(&self) -> Vec<u8> {
+///         let (x, y) = (self.x.to_be_bytes(), self.y.to_be_bytes());
+///         vec![x[0], x[1], y[0], y[1]]
+///     }
+///
+///     fn from_bytes(data: &[u8]) -> Result<Self, Self::Error> {
+///         match *data {
+///             [x0, x1, y0, y1] => Ok(Point {
+///                 x: i16::from_be_bytes([x0, x1]),
+///                 y: i16::from_be_bytes([y0, y1]),
+///             }),
+///             _ => Err("expected 4 bytes"),
+///         }
+///     }
+/// }
+///
+/// let buf = rmp_serde::to_vec(&TypedExt(Point { x: 1, y: -1 })).unwrap();
+/// assert_eq!(vec![0xd6, 0x07, 0x00, 0x01, 0xff, 0xff], buf);
+///
+/// let TypedExt(point) = rmp_serde::from_slice(&buf).unwrap();
+/// assert_eq!(Point { x: 1, y: -1 }, point);
+/// ```
+#[cfg(feature = "std")]
+pub trait MsgPackExtType: Sized {
+    /// The ext type tag. Negative tags are reserved by the MessagePack spec.
+    const TAG: i8;
+
+    /// The error returned when the payload can't be decoded.
+    type Error: Display;
+
+    /// Encodes this value as the ext payload.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Decodes a value from the ext payload.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the payload is invalid.
+    fn from_bytes(data: &[u8]) -> Result<Self, Self::Error>;
+}
+
+/// Serializes the wrapped [`MsgPackExtType`] as a MessagePack ext.
+///
+/// On decode the tag must match [`MsgPackExtType::TAG`], otherwise deserialization fails.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypedExt<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T> TypedExt<T> {
+    /// Consumes this wrapper, returning the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> From<T> for TypedExt<T> {
+    #[inline]
+    fn from(val: T) -> Self {
+        TypedExt(val)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: MsgPackExtType> Serialize for TypedExt<T> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        ExtRef::new(T::TAG, &self.0.to_bytes()).serialize(se)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, T: MsgPackExtType> Deserialize<'de> for TypedExt<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let ext = Ext::deserialize(de)?;
+        if ext.tag != T::TAG {
+            return Err(de::Error::invalid_value(de::Unexpected::Signed(ext.tag.into()), &ExpectedTag(T::TAG)));
+        }
+        T::from_bytes(&ext.data).map(TypedExt).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "std")]
+struct ExpectedTag(i8);
+
+#[cfg(feature = "std")]
+impl de::Expected for ExpectedTag {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        write!(fmt, "ext tag {}", self.0)
+    }
+}
+
+/// A decoding function registered in an [`ExtRegistry`].
+#[cfg(feature = "std")]
+type ExtDecoder = Box<dyn Fn(&[u8]) -> Result<Box<dyn Any + Send + Sync>, String> + Send + Sync>;
+
+/// A runtime mapping of ext tags to decoding functions.
+///
+/// Use it when a document mixes several ext types and the Rust type can only be chosen after
+/// looking at the tag. `&ExtRegistry` is a [`DeserializeSeed`] producing a [`DynExt`].
+///
+/// ```
+/// use rmp_serde::{Ext, ExtRegistry};
+///
+/// let mut registry = ExtRegistry::new();
+/// registry.register(1, |data| std::str::from_utf8(data).map(String::from));
+/// registry.register(2, |data| <[u8; 4]>::try_from(data).map(u32::from_be_bytes));
+///
+/// let ext = registry.decode(Ext::new(2, vec![0, 0, 1, 0]).as_ext_ref()).unwrap();
+/// assert_eq!(Some(&256u32), ext.downcast_ref::<u32>());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct ExtRegistry {
+    decoders: HashMap<i8, ExtDecoder>,
+}
+
+#[cfg(feature = "std")]
+impl ExtRegistry {
+    /// Creates an empty registry.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a function decoding the payload of exts with the given tag.
+    ///
+    /// Replaces the function previously registered for the tag, if any.
+    pub fn register<T, E, F>(&mut self, tag: i8, decode: F) -> &mut Self
+    where T: Any + Send + Sync,
+          E: Display,
+          F: Fn(&[u8]) -> Result<T, E> + Send + Sync + 'static
+    {
+        let decoder: ExtDecoder = Box::new(move |data| match decode(data) {
+            Ok(val) => Ok(Box::new(val)),
+            Err(err) => Err(err.to_string()),
+        });
+        self.decoders.insert(tag, decoder);
+        self
+    }
+
+    /// Registers a [`MsgPackExtType`] under its [`TAG`](MsgPackExtType::TAG).
+    #[inline]
+    pub fn register_type<T: MsgPackExtType + Any + Send + Sync>(&mut self) -> &mut Self {
+        self.register(T::TAG, T::from_bytes)
+    }
+
+    /// Returns `true` if a decoding function is registered for the given tag.
+    #[inline]
+    pub fn contains(&self, tag: i8) -> bool {
+        self.decoders.contains_key(&tag)
+    }
+
+    /// Decodes the given ext with the function registered for its tag.
+    ///
+    /// Exts with unregistered tags are not an error, they are returned as an owned [`Ext`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the registered function rejects the payload.
+    pub fn decode(&self, ext: ExtRef<'_>) -> Result<DynExt, ExtDecodeError> {
+        let value = match self.decoders.get(&ext.tag) {
+            Some(decode) => decode(ext.data).map_err(|message| ExtDecodeError { tag: ext.tag, message })?,
+            None => Box::new(Ext::from(ext)),
+        };
+        Ok(DynExt { tag: ext.tag, value })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Debug for ExtRegistry {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        let mut tags: Vec<_> = self.decoders.keys().collect();
+        tags.sort_unstable();
+        fmt.debug_struct("ExtRegistry").field("tags", &tags).finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> DeserializeSeed<'de> for &ExtRegistry {
+    type Value = DynExt;
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<DynExt, D::Error> {
+        let ext = Ext::deserialize(de)?;
+        self.decode(ext.as_ext_ref()).map_err(de::Error::custom)
+    }
+}
+
+/// An ext decoded by an [`ExtRegistry`], holding a value of the type registered for its tag.
+#[cfg(feature = "std")]
+pub struct DynExt {
+    tag: i8,
+    value: Box<dyn Any + Send + Sync>,
+}
+
+#[cfg(feature = "std")]
+impl DynExt {
+    /// Returns the type tag.
+    #[inline]
+    pub fn tag(&self) -> i8 {
+        self.tag
+    }
+
+    /// Returns `true` if the decoded value is of type `T`.
+    #[inline]
+    pub fn is<T: Any>(&self) -> bool {
+        self.value.is::<T>()
+    }
+
+    /// Returns a reference to the decoded value if it is of type `T`.
+    #[inline]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the decoded value if it is of type `T`.
+    #[inline]
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.value.downcast_mut()
+    }
+
+    /// Returns the decoded value if it is of type `T`, or gives back `self` otherwise.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        match self.value.downcast() {
+            Ok(val) => Ok(*val),
+            Err(value) => Err(DynExt { tag: self.tag, value }),
+        }
+    }
+
+    /// Consumes this ext, returning the decoded value.
+    #[inline]
+    pub fn into_inner(self) -> Box<dyn Any + Send + Sync> {
+        self.value
+    }
+}
+
+#[cfg(feature = "std")]
+impl Debug for DynExt {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("DynExt").field("tag", &self.tag).finish_non_exhaustive()
+    }
+}
+
+/// The error returned when a function registered in an [`ExtRegistry`] rejects a payload.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtDecodeError {
+    tag: i8,
+    message: String,
+}
+
+#[cfg(feature = "std")]
+impl ExtDecodeError {
+    /// Returns the tag of the ext that failed to decode.
+    #[inline]
+    pub fn tag(&self) -> i8 {
+        self.tag
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for ExtDecodeError {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        write!(fmt, "invalid ext with tag {}: {}", self.tag, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExtDecodeError {}
+
+/// Serializes a byte slice as bytes rather than as a sequence.
+struct SerBytes<'a>(&'a [u8]);
+
+impl Serialize for SerBytes<'_> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_bytes(self.0)
+    }
+}
+
+/// Deserializes the `(tag, data)` tuple, with `D` being the payload type.
+struct ExtVisitor<D>(core::marker::PhantomData<D>);
+
+impl<D> ExtVisitor<D> {
+    #[inline]
+    fn new() -> Self {
+        ExtVisitor(core::marker::PhantomData)
+    }
+}
+
+impl<'de, D: ExtData<'de>> Visitor<'de> for ExtVisitor<D> {
+    type Value = (i8, D);
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a MessagePack ext")
+    }
+
+    #[inline]
+    fn visit_newtype_struct<De: Deserializer<'de>>(self, de: De) -> Result<Self::Value, De::Error> {
+        de.deserialize_tuple(2, self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let tag = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let data = seq.next_element::<DeData<D>>()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok((tag, data.0))
+    }
+}
+
+/// Payload types an ext can be deserialized into.
+trait ExtData<'de>: Sized {
+    fn from_borrowed<E: de::Error>(v: &'de [u8]) -> Result<Self, E>;
+    fn from_bytes<E: de::Error>(v: &[u8]) -> Result<Self, E>;
+}
+
+#[cfg(feature = "std")]
+impl<'de> ExtData<'de> for Vec<u8> {
+    #[inline]
+    fn from_borrowed<E: de::Error>(v: &'de [u8]) -> Result<Self, E> {
+        Ok(v.to_vec())
+    }
+
+    #[inline]
+    fn from_bytes<E: de::Error>(v: &[u8]) -> Result<Self, E> {
+        Ok(v.to_vec())
+    }
+}
+
+impl<'de: 'a, 'a> ExtData<'de> for &'a [u8] {
+    #[inline]
+    fn from_borrowed<E: de::Error>(v: &'de [u8]) -> Result<Self, E> {
+        Ok(v)
+    }
+
+    #[cold]
+    fn from_bytes<E: de::Error>(v: &[u8]) -> Result<Self, E> {
+        Err(de::Error::invalid_type(de::Unexpected::Bytes(v), &"borrowed ext data"))
+    }
+}
+
+struct DeData<D>(D);
+
+impl<'de, D: ExtData<'de>> Deserialize<'de> for DeData<D> {
+    #[inline]
+    fn deserialize<De: Deserializer<'de>>(de: De) -> Result<Self, De::Error> {
+        de.deserialize_bytes(DataVisitor(core::marker::PhantomData)).map(DeData)
+    }
+}
+
+struct DataVisitor<D>(core::marker::PhantomData<D>);
+
+impl<'de, D: ExtData<'de>> Visitor<'de> for DataVisitor<D> {
+    type Value = D;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("ext data")
+    }
+
+    #[inline]
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        D::from_borrowed(v)
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        D::from_bytes(v)
+    }
+}
@@ -0,0 +1,146 @@
+//! Precomputed matching of struct field names.
+//!
+//! Derived `Deserialize` implementations compare each key of a map-encoded struct against the
+//! field names one after another. For wide structs written with
+//! [`to_vec_named`](crate::to_vec_named), a hand-written implementation can instead build a
+//! [`FieldMatcher`] once and use [`FieldSeed`] to turn each key into the index of its field. Fields
+//! are bucketed by length, so a lookup compares the key against the few names of the same length.
+//!
+//! ```
+//! use std::fmt::{self, Formatter};
+//! use std::sync::OnceLock;
+//!
+//! use rmp_serde::fields::{FieldMatcher, FieldSeed};
+//! use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+//!
+//! const FIELDS: &[&str] = &["id", "name", "score"];
+//!
+//! #[derive(Debug, Default, PartialEq)]
+//! struct Row {
+//!     id: u32,
+//!     name: String,
+//!     score: f64,
+//! }
+//!
+//! impl<'de> Deserialize<'de> for Row {
+//!     fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+//!         de.deserialize_struct("Row", FIELDS, RowVisitor)
+//!     }
+//! }
+//!
+//! struct RowVisitor;
+//!
+//! impl<'de> Visitor<'de> for RowVisitor {
+//!     type Value = Row;
+//!
+//!     fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+//!         fmt.write_str("a row")
+//!     }
+//!
+//!     fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Row, A::Error> {
+//!         static MATCHER: OnceLock<FieldMatcher> = OnceLock::new();
+//!         let matcher = MATCHER.get_or_init(|| FieldMatcher::new(FIELDS));
+//!
+//!         let mut row = Row::default();
+//!         while let Some(field) = map.next_key_seed(FieldSeed(matcher))? {
+//!             match field {
+//!                 Some(0) => row.id = map.next_value()?,
+//!                 Some(1) => row.name = map.next_value()?,
+//!                 Some(2) => row.score = map.next_value()?,
+//!                 _ => drop(map.next_value::<IgnoredAny>()?),
+//!             }
+//!         }
+//!         Ok(row)
+//!     }
+//! }
+//!
+//! let buf = rmp_serde::to_vec_named(&rmpv::Value::Map(vec![
+//!     ("name".into(), "a".into()),
+//!     ("extra".into(), true.into()),
+//!     ("id".into(), 7.into()),
+//! ])).unwrap();
+//! let row: Row = rmp_serde::from_slice(&buf).unwrap();
+//! assert_eq!(Row { id: 7, name: "a".into(), score: 0.0 }, row);
+//! ```
+
+use core::fmt::{self, Formatter};
+
+use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
+
+/// Maps field names to their index, bucketed by length.
+#[derive(Clone, Debug)]
+pub struct FieldMatcher {
+    fields: &'static [&'static str],
+    /// For each length, the names of that length and their index.
+    buckets: Vec<Vec<(&'static [u8], usize)>>,
+}
+
+impl FieldMatcher {
+    /// Builds the matcher for the given field names.
+    ///
+    /// If a name is repeated, the first index is used.
+    pub fn new(fields: &'static [&'static str]) -> Self {
+        let max_len = fields.iter().map(|field| field.len()).max().unwrap_or(0);
+        let mut buckets = vec![Vec::new(); max_len + 1];
+        for (idx, field) in fields.iter().enumerate() {
+            buckets[field.len()].push((field.as_bytes(), idx));
+        }
+        FieldMatcher { fields, buckets }
+    }
+
+    /// Returns the index of the field with the given name.
+    #[inline]
+    pub fn find(&self, key: &[u8]) -> Option<usize> {
+        self.buckets.get(key.len())?
+            .iter()
+            .find(|(field, _)| *field == key)
+            .map(|&(_, idx)| idx)
+    }
+
+    /// Returns the field names.
+    #[inline]
+    pub fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+}
+
+/// Deserializes a struct key as the index of the field in the wrapped matcher, or `None` if it's
+/// unknown.
+///
+/// Keys may be strings, bytes or integers, which are taken as the index directly like derived
+/// implementations do.
+#[derive(Clone, Copy, Debug)]
+pub struct FieldSeed<'a>(pub &'a FieldMatcher);
+
+impl<'de> DeserializeSeed<'de> for FieldSeed<'_> {
+    type Value = Option<usize>;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<Option<usize>, D::Error> {
+        de.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FieldSeed<'_> {
+    type Value = Option<usize>;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a field identifier")
+    }
+
+    #[inline]
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Option<usize>, E> {
+        Ok(usize::try_from(v).ok().filter(|&idx| idx < self.0.fields.len()))
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Option<usize>, E> {
+        Ok(self.0.find(v))
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Option<usize>, E> {
+        Ok(self.0.find(v.as_bytes()))
+    }
+}
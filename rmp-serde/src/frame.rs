@@ -0,0 +1,56 @@
+//! Framing helpers shared by the codec, stream and body adapters.
+
+use std::io;
+
+#[cfg(any(feature = "tokio-util", feature = "futures"))]
+use bytes::Buf;
+use bytes::BytesMut;
+#[cfg(any(feature = "tokio-util", feature = "futures"))]
+use rmp::decode::Scan;
+use rmp::encode::ValueWriteError;
+#[cfg(any(feature = "tokio-util", feature = "futures"))]
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::encode::{self, BytesWriter};
+use crate::Serializer;
+
+/// The outcome of [`decode`].
+#[cfg(any(feature = "tokio-util", feature = "futures"))]
+pub(crate) enum Decoded<T> {
+    Value(T),
+    /// At least this many more bytes are needed.
+    Incomplete(usize),
+}
+
+/// Decodes a value from the start of `src` and advances past it.
+///
+/// `src` is left untouched if it does not hold a complete value yet.
+#[cfg(any(feature = "tokio-util", feature = "futures"))]
+pub(crate) fn decode<T: DeserializeOwned>(src: &mut BytesMut) -> Result<Decoded<T>, io::Error> {
+    let len = match rmp::decode::scan_value(src) {
+        Ok(Scan::Complete(len)) => len,
+        Ok(Scan::Incomplete(needed)) => return Ok(Decoded::Incomplete(needed)),
+        Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+    };
+
+    let val = crate::from_slice(&src[..len]).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    src.advance(len);
+    Ok(Decoded::Value(val))
+}
+
+/// Appends the encoded value to `dst`.
+///
+/// On error nothing is appended.
+pub(crate) fn encode<T: Serialize + ?Sized>(val: &T, dst: &mut BytesMut) -> Result<(), io::Error> {
+    let len = dst.len();
+    if let Err(err) = val.serialize(&mut Serializer::new(BytesWriter::new(&mut *dst))) {
+        dst.truncate(len);
+        return Err(match err {
+            encode::Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(err)) |
+            encode::Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,386 @@
+//! Observing and rewriting what a serializer writes.
+//!
+//! A [`WriteHook`] is installed with [`Serializer::with_write_hook`](crate::Serializer::with_write_hook),
+//! or by wrapping any writer in a [`HookWriter`]. It's told about every marker and payload as it
+//! goes to the writer, which is enough for metrics and auditing, and can replace the strings
+//! written as map values, e.g. to redact sensitive fields of structs written as maps.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! use rmp_serde::hook::WriteHook;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Login {
+//!     user: String,
+//!     password: String,
+//! }
+//!
+//! #[derive(Default)]
+//! struct Redact {
+//!     strs: usize,
+//! }
+//!
+//! impl WriteHook for Redact {
+//!     fn marker(&mut self, marker: rmp::Marker) {
+//!         if matches!(marker, rmp::Marker::FixStr(..) | rmp::Marker::Str8 | rmp::Marker::Str16 | rmp::Marker::Str32) {
+//!             self.strs += 1;
+//!         }
+//!     }
+//!
+//!     fn replace_str(&mut self, key: &str, _value: &str) -> Option<String> {
+//!         (key == "password").then(|| "***".into())
+//!     }
+//! }
+//!
+//! let mut se = rmp_serde::Serializer::new(Vec::new()).with_struct_map().with_write_hook(Redact::default());
+//! Login { user: "john".into(), password: "hunter2".into() }.serialize(&mut se).unwrap();
+//!
+//! let (buf, hook) = se.into_inner().into_parts();
+//! assert_eq!(4, hook.strs);
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Redacted<'a> {
+//!     user: &'a str,
+//!     password: &'a str,
+//! }
+//! assert_eq!(Redacted { user: "john", password: "***" }, rmp_serde::from_slice(&buf).unwrap());
+//! # }
+//! ```
+
+use core::fmt::{self, Debug, Formatter};
+
+use rmp::encode::RmpWrite;
+use rmp::Marker;
+
+/// Callbacks for the values written through a [`HookWriter`].
+///
+/// All methods do nothing by default.
+pub trait WriteHook {
+    /// Called with the marker of every value, before its header is written.
+    #[inline]
+    fn marker(&mut self, _marker: Marker) {}
+
+    /// Called with the data of every value, after its header is written.
+    ///
+    /// The data of numbers, strs, bins and exts are passed as they are written, possibly in
+    /// several pieces. Ext types and lengths are part of the header, and not passed here.
+    #[inline]
+    fn payload(&mut self, _data: &[u8]) {}
+
+    /// Called with every str written as the value of a map entry with a str key, and returns the
+    /// str to write instead, if any.
+    ///
+    /// The original str is held back until this returns, and [`Self::marker`] and
+    /// [`Self::payload`] see what is written in the end. Keys or values that aren't valid UTF-8
+    /// are written as they are.
+    #[inline]
+    fn replace_str(&mut self, _key: &str, _value: &str) -> Option<String> {
+        None
+    }
+}
+
+impl<H: WriteHook + ?Sized> WriteHook for &mut H {
+    #[inline]
+    fn marker(&mut self, marker: Marker) {
+        (**self).marker(marker);
+    }
+
+    #[inline]
+    fn payload(&mut self, data: &[u8]) {
+        (**self).payload(data);
+    }
+
+    #[inline]
+    fn replace_str(&mut self, key: &str, value: &str) -> Option<String> {
+        (**self).replace_str(key, value)
+    }
+}
+
+/// What happens to the payload of the value being written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Payload {
+    Forward,
+    /// The value is a str map key, kept for the value that follows.
+    Key,
+    /// The value is a str that may be replaced, held back until it's complete.
+    Held,
+}
+
+/// A writer that follows the MessagePack written through it, and calls a [`WriteHook`].
+///
+/// Writes may split values anywhere, but the data must be well-formed MessagePack. The hook is
+/// also called for data written directly to the writer, e.g. with [`RmpWrite::write_bytes`].
+pub struct HookWriter<W, H> {
+    wr: W,
+    hook: H,
+    /// The header of the value being written, up to its marker, length and ext type.
+    header: [u8; 6],
+    header_len: usize,
+    header_size: usize,
+    payload_left: u64,
+    payload: Payload,
+    /// Whether the value being written is the value of a map entry.
+    is_map_value: bool,
+    /// The last str key of a map, if the last key was a str.
+    key: Option<Vec<u8>>,
+    held: Vec<u8>,
+    /// Number of values left in each enclosing array or map, and whether it's a map.
+    stack: Vec<(u64, bool)>,
+}
+
+impl<W, H> HookWriter<W, H> {
+    /// Wraps the writer, calling the hook for everything written to it.
+    #[inline]
+    pub fn new(wr: W, hook: H) -> Self {
+        HookWriter {
+            wr,
+            hook,
+            header: [0; 6],
+            header_len: 0,
+            header_size: 0,
+            payload_left: 0,
+            payload: Payload::Forward,
+            is_map_value: false,
+            key: None,
+            held: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Gets a reference to the underlying writer.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// Data written directly to it isn't seen by the hook.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr
+    }
+
+    /// Gets a reference to the hook.
+    #[inline(always)]
+    pub fn hook(&self) -> &H {
+        &self.hook
+    }
+
+    /// Gets a mutable reference to the hook.
+    #[inline(always)]
+    pub fn hook_mut(&mut self) -> &mut H {
+        &mut self.hook
+    }
+
+    /// Consumes this writer, returning the underlying writer and the hook.
+    ///
+    /// A str that is held back for [`WriteHook::replace_str`] is lost if it wasn't complete.
+    #[inline]
+    pub fn into_parts(self) -> (W, H) {
+        (self.wr, self.hook)
+    }
+}
+
+impl<W: RmpWrite, H: WriteHook> HookWriter<W, H> {
+    fn process(&mut self, mut buf: &[u8]) -> Result<(), W::Error> {
+        while !buf.is_empty() {
+            if self.payload_left > 0 {
+                let len = buf.len().min(self.payload_left as usize);
+                let (data, rest) = buf.split_at(len);
+                buf = rest;
+                self.payload_left -= len as u64;
+                match self.payload {
+                    Payload::Forward => self.forward(data)?,
+                    Payload::Key => {
+                        self.key.get_or_insert_with(Vec::new).extend_from_slice(data);
+                        self.forward(data)?;
+                    }
+                    Payload::Held => self.held.extend_from_slice(data),
+                }
+                if self.payload_left == 0 {
+                    self.end_payload()?;
+                }
+                continue;
+            }
+
+            self.header[self.header_len] = buf[0];
+            self.header_len += 1;
+            buf = &buf[1..];
+            if self.header_len == 1 {
+                self.start_value();
+            }
+            if self.header_len == self.header_size {
+                self.end_header()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the place of the value whose marker was just written.
+    fn start_value(&mut self) {
+        self.header_size = header_size(Marker::from_u8(self.header[0]));
+        self.is_map_value = false;
+        self.payload = Payload::Forward;
+        if let Some((left, is_map)) = self.stack.last_mut() {
+            if *is_map {
+                if *left % 2 == 0 {
+                    self.key = None;
+                    if matches!(Marker::from_u8(self.header[0]), Marker::FixStr(..) | Marker::Str8 | Marker::Str16 | Marker::Str32) {
+                        self.payload = Payload::Key;
+                    }
+                } else {
+                    self.is_map_value = true;
+                }
+            }
+            *left -= 1;
+        }
+    }
+
+    fn end_header(&mut self) -> Result<(), W::Error> {
+        let marker = Marker::from_u8(self.header[0]);
+        let header = &self.header[..self.header_len];
+        self.header_len = 0;
+
+        let len = match header.len() {
+            2 if is_ext(marker) => 0,
+            2 => u64::from(header[1]),
+            3 if is_ext(marker) => u64::from(header[1]),
+            3 => u64::from(u16::from_be_bytes([header[1], header[2]])),
+            4 => u64::from(u16::from_be_bytes([header[1], header[2]])),
+            5 | 6 => u64::from(u32::from_be_bytes([header[1], header[2], header[3], header[4]])),
+            _ => 0,
+        };
+        let (payload_len, elements) = match marker {
+            Marker::U8 | Marker::I8 => (1, None),
+            Marker::U16 | Marker::I16 => (2, None),
+            Marker::U32 | Marker::I32 | Marker::F32 => (4, None),
+            Marker::U64 | Marker::I64 | Marker::F64 => (8, None),
+            Marker::FixStr(len) => (u64::from(len), None),
+            Marker::Str8 | Marker::Str16 | Marker::Str32 |
+            Marker::Bin8 | Marker::Bin16 | Marker::Bin32 |
+            Marker::Ext8 | Marker::Ext16 | Marker::Ext32 => (len, None),
+            Marker::FixExt1 => (1, None),
+            Marker::FixExt2 => (2, None),
+            Marker::FixExt4 => (4, None),
+            Marker::FixExt8 => (8, None),
+            Marker::FixExt16 => (16, None),
+            Marker::FixArray(len) => (0, Some((u64::from(len), false))),
+            Marker::Array16 | Marker::Array32 => (0, Some((len, false))),
+            Marker::FixMap(len) => (0, Some((2 * u64::from(len), true))),
+            Marker::Map16 | Marker::Map32 => (0, Some((2 * len, true))),
+            _ => (0, None),
+        };
+
+        let is_str = matches!(marker, Marker::FixStr(..) | Marker::Str8 | Marker::Str16 | Marker::Str32);
+        if is_str && self.is_map_value && self.key.is_some() {
+            // The header is written once the str is known.
+            self.header_len = header.len();
+            self.payload = Payload::Held;
+            self.held.clear();
+        } else {
+            self.hook.marker(marker);
+            self.wr.write_bytes(header)?;
+            if self.payload == Payload::Key {
+                self.key = Some(Vec::new());
+            }
+        }
+
+        self.payload_left = payload_len;
+        match elements {
+            Some((0, _)) | None if payload_len == 0 => self.end_payload(),
+            Some(elements) => {
+                self.stack.push(elements);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn end_payload(&mut self) -> Result<(), W::Error> {
+        if self.payload == Payload::Held {
+            let header = &self.header[..self.header_len];
+            self.header_len = 0;
+            let replacement = match (self.key.as_deref().map(core::str::from_utf8), core::str::from_utf8(&self.held)) {
+                (Some(Ok(key)), Ok(value)) => self.hook.replace_str(key, value),
+                _ => None,
+            };
+            match replacement {
+                Some(value) => {
+                    let mut header = Vec::with_capacity(5);
+                    rmp::encode::write_str_len(&mut header, value.len() as u32).expect("writing to a Vec can't fail");
+                    self.hook.marker(Marker::from_u8(header[0]));
+                    self.wr.write_bytes(&header)?;
+                    self.forward(value.as_bytes())?;
+                }
+                None => {
+                    self.hook.marker(Marker::from_u8(header[0]));
+                    self.wr.write_bytes(header)?;
+                    let held = core::mem::take(&mut self.held);
+                    let res = self.forward(&held);
+                    self.held = held;
+                    res?;
+                }
+            }
+        }
+        self.payload = Payload::Forward;
+        while let Some(&(0, _)) = self.stack.last() {
+            self.stack.pop();
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn forward(&mut self, data: &[u8]) -> Result<(), W::Error> {
+        if !data.is_empty() {
+            self.hook.payload(data);
+        }
+        self.wr.write_bytes(data)
+    }
+}
+
+/// Returns the size of the marker, and of the length and ext type that follow it.
+fn header_size(marker: Marker) -> usize {
+    match marker {
+        Marker::Str8 | Marker::Bin8 => 2,
+        Marker::Str16 | Marker::Bin16 | Marker::Array16 | Marker::Map16 => 3,
+        Marker::Str32 | Marker::Bin32 | Marker::Array32 | Marker::Map32 => 5,
+        Marker::FixExt1 | Marker::FixExt2 | Marker::FixExt4 | Marker::FixExt8 | Marker::FixExt16 => 2,
+        Marker::Ext8 => 3,
+        Marker::Ext16 => 4,
+        Marker::Ext32 => 6,
+        _ => 1,
+    }
+}
+
+#[inline]
+fn is_ext(marker: Marker) -> bool {
+    matches!(marker,
+        Marker::FixExt1 | Marker::FixExt2 | Marker::FixExt4 | Marker::FixExt8 | Marker::FixExt16 |
+        Marker::Ext8 | Marker::Ext16 | Marker::Ext32)
+}
+
+impl<W: RmpWrite, H: WriteHook> RmpWrite for HookWriter<W, H> {
+    type Error = W::Error;
+
+    #[inline]
+    fn write_u8(&mut self, val: u8) -> Result<(), Self::Error> {
+        self.process(&[val])
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.process(buf)
+    }
+}
+
+impl<W: Debug, H: Debug> Debug for HookWriter<W, H> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("HookWriter")
+            .field("wr", &self.wr)
+            .field("hook", &self.hook)
+            .finish_non_exhaustive()
+    }
+}
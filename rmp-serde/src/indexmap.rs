@@ -0,0 +1,109 @@
+//! Order-preserving maps, backed by the [`indexmap`](https://docs.rs/indexmap) crate.
+//!
+//! MessagePack maps are ordered sequences of key-value pairs, but `HashMap` forgets that order
+//! and `BTreeMap` sorts the keys. Decoding into an [`IndexMap`] keeps the keys in the order they
+//! were written, so that re-encoding a document produces the same bytes. The map is allocated
+//! upfront using the length from the map header.
+//!
+//! ```
+//! use rmp_serde::indexmap::IndexMap;
+//!
+//! let mut map = IndexMap::new();
+//! map.insert("zebra", 1);
+//! map.insert("apple", 2);
+//!
+//! let buf = rmp_serde::to_vec(&map).unwrap();
+//! let decoded: IndexMap<String, u8> = rmp_serde::from_slice(&buf).unwrap();
+//! assert_eq!(vec!["zebra", "apple"], decoded.keys().collect::<Vec<_>>());
+//! ```
+//!
+//! The default `IndexMap` implementation keeps the last value of duplicate keys at the position
+//! of the first one. Use [`unique`] to reject such input instead.
+
+pub use ::indexmap::{IndexMap, IndexSet};
+
+/// Serializes an `IndexMap` as a map, and deserializes it rejecting duplicate keys.
+///
+/// Use with `#[serde(with = "rmp_serde::indexmap::unique")]`.
+///
+/// ```
+/// # #[macro_use] extern crate serde_derive;
+/// # fn main() {
+/// use rmp_serde::indexmap::IndexMap;
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct Headers {
+///     #[serde(with = "rmp_serde::indexmap::unique")]
+///     entries: IndexMap<String, String>,
+/// }
+///
+/// // [{"a": "1", "a": "2"}]
+/// let buf = [0x91, 0x82, 0xa1, 0x61, 0xa1, 0x31, 0xa1, 0x61, 0xa1, 0x32];
+/// assert!(rmp_serde::from_slice::<Headers>(&buf).is_err());
+/// # }
+/// ```
+pub mod unique {
+    use core::fmt::{self, Formatter};
+    use core::hash::{BuildHasher, Hash};
+    use core::marker::PhantomData;
+
+    use indexmap::map::Entry;
+    use indexmap::IndexMap;
+    use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+    use serde::{Serialize, Serializer};
+
+    /// Don't trust the length from the header more than this when allocating.
+    const MAX_PREALLOCATED: usize = 4096;
+
+    /// Serializes the map, in order.
+    #[inline]
+    pub fn serialize<K, V, H, S>(map: &IndexMap<K, V, H>, se: S) -> Result<S::Ok, S::Error>
+    where K: Serialize,
+          V: Serialize,
+          S: Serializer
+    {
+        se.collect_map(map)
+    }
+
+    /// Deserializes a map, failing if a key appears more than once.
+    #[inline]
+    pub fn deserialize<'de, K, V, H, D>(de: D) -> Result<IndexMap<K, V, H>, D::Error>
+    where K: Deserialize<'de> + Eq + Hash + fmt::Debug,
+          V: Deserialize<'de>,
+          H: BuildHasher + Default,
+          D: Deserializer<'de>
+    {
+        de.deserialize_map(UniqueVisitor(PhantomData))
+    }
+
+    struct UniqueVisitor<K, V, H>(PhantomData<IndexMap<K, V, H>>);
+
+    impl<'de, K, V, H> Visitor<'de> for UniqueVisitor<K, V, H>
+    where K: Deserialize<'de> + Eq + Hash + fmt::Debug,
+          V: Deserialize<'de>,
+          H: BuildHasher + Default
+    {
+        type Value = IndexMap<K, V, H>;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a map without duplicate keys")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+            let capacity = access.size_hint().unwrap_or(0).min(MAX_PREALLOCATED);
+            let mut map = IndexMap::with_capacity_and_hasher(capacity, H::default());
+            while let Some(key) = access.next_key()? {
+                match map.entry(key) {
+                    Entry::Occupied(entry) => {
+                        return Err(de::Error::custom(format_args!("duplicate key {:?}", entry.key())));
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(access.next_value()?);
+                    }
+                }
+            }
+            Ok(map)
+        }
+    }
+}
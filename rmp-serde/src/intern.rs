@@ -0,0 +1,170 @@
+//! Interning of repeated strings on decode.
+//!
+//! Decoding a `String` or an `Arc<str>` allocates for each value, even if most of them are the
+//! same few enum tags or categories. An [`Interner`] keeps the strings it has seen, and hands out
+//! clones of the same `Arc<str>` for equal strings.
+//!
+//! Use [`InternSeed`] with an interner of your own, or the [`Interned`] type and the
+//! [`deserialize`] function, which share an interner per thread. Only strings of up to
+//! [`MAX_INTERNED_LEN`] bytes are kept, longer ones are allocated as usual.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! use std::sync::Arc;
+//!
+//! #[derive(Deserialize)]
+//! struct Event {
+//!     #[serde(deserialize_with = "rmp_serde::intern::deserialize")]
+//!     kind: Arc<str>,
+//!     value: u32,
+//! }
+//!
+//! let buf = rmp_serde::to_vec(&[("click", 1), ("click", 2)]).unwrap();
+//! let events: Vec<Event> = rmp_serde::from_slice(&buf).unwrap();
+//! assert!(Arc::ptr_eq(&events[0].kind, &events[1].kind));
+//! # }
+//! ```
+
+use core::fmt::{self, Formatter};
+use core::ops::Deref;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// Maximum length of the strings that are interned.
+pub const MAX_INTERNED_LEN: usize = 64;
+
+/// A set of shared strings.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    /// Creates an empty interner.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of the string, adding it if it's new.
+    ///
+    /// Strings longer than [`MAX_INTERNED_LEN`] are returned in a new `Arc` instead.
+    pub fn intern(&mut self, v: &str) -> Arc<str> {
+        if v.len() > MAX_INTERNED_LEN {
+            return v.into();
+        }
+        if let Some(s) = self.strings.get(v) {
+            return s.clone();
+        }
+        let s: Arc<str> = v.into();
+        self.strings.insert(s.clone());
+        s
+    }
+
+    /// Returns the number of strings kept.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns `true` if no string is kept.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Forgets all strings, the ones handed out stay valid.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.strings.clear();
+    }
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::new());
+}
+
+/// Deserializes a str as an `Arc<str>`, shared with equal strings decoded on the same thread.
+///
+/// Use with `#[serde(deserialize_with = "rmp_serde::intern::deserialize")]`.
+#[inline]
+pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Arc<str>, D::Error> {
+    de.deserialize_str(InternVisitor(|v: &str| INTERNER.with(|interner| interner.borrow_mut().intern(v))))
+}
+
+/// Empties the interner used by [`deserialize`] and [`Interned`] on the current thread.
+#[inline]
+pub fn clear() {
+    INTERNER.with(|interner| interner.borrow_mut().clear());
+}
+
+/// Deserializes a str into an `Arc<str>` from the wrapped interner.
+#[derive(Debug)]
+pub struct InternSeed<'a>(pub &'a mut Interner);
+
+impl<'de> DeserializeSeed<'de> for InternSeed<'_> {
+    type Value = Arc<str>;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<Arc<str>, D::Error> {
+        de.deserialize_str(InternVisitor(|v: &str| self.0.intern(v)))
+    }
+}
+
+/// A string shared with equal strings decoded on the same thread, see [`deserialize`].
+///
+/// Serializes as a plain str.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interned(pub Arc<str>);
+
+impl Deref for Interned {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Serialize for Interned {
+    #[inline]
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interned {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        deserialize(de).map(Interned)
+    }
+}
+
+struct InternVisitor<F>(F);
+
+impl<'de, F: FnOnce(&str) -> Arc<str>> Visitor<'de> for InternVisitor<F> {
+    type Value = Arc<str>;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a string")
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Arc<str>, E> {
+        Ok((self.0)(v))
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Arc<str>, E> {
+        match crate::from_utf8(v) {
+            Ok(v) => self.visit_str(v),
+            Err(..) => Err(de::Error::invalid_value(de::Unexpected::Bytes(v), &self)),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Compression of repeated map keys.
+//!
+//! Arrays of structs written with [`to_vec_named`](crate::to_vec_named) repeat the same field
+//! names in every element. In this mode, each str map key of 3 bytes or more is written once, and
+//! later occurrences are replaced by an ext with the type [`KEY_REF_EXT_TAG`] holding the index of
+//! the key, in the order the keys first appeared. The index takes 1, 2 or 4 bytes, so a reference
+//! is 3 to 6 bytes long.
+//!
+//! The result is still valid MessagePack, but other decoders see the references as exts. Use
+//! [`from_slice`] or [`expand`] to read it back.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+//! struct Point {
+//!     latitude: f32,
+//!     longitude: f32,
+//! }
+//!
+//! let points = vec![Point { latitude: 1.0, longitude: 2.0 }; 100];
+//! let buf = rmp_serde::keydict::to_vec_named(&points).unwrap();
+//! assert!(buf.len() < rmp_serde::to_vec_named(&points).unwrap().len() * 2 / 3);
+//!
+//! assert_eq!(points, rmp_serde::keydict::from_slice::<Vec<Point>>(&buf).unwrap());
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+
+use rmp::decode::{self, Bytes, RmpRead, Scan, ScanError, ValueReadError};
+use rmp::Marker;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::decode::{BytesReadError, Error};
+use crate::encode;
+
+/// Ext type of the key references.
+pub const KEY_REF_EXT_TAG: i8 = 3;
+
+/// Keys shorter than this are never replaced, as the reference wouldn't be smaller.
+const MIN_KEY_LEN: usize = 3;
+
+/// Serializes the value with structs as maps, like [`to_vec_named`](crate::to_vec_named), and
+/// replaces repeated map keys with references.
+///
+/// # Errors
+///
+/// Fails like `to_vec_named`, or if a map key is an ext with the type [`KEY_REF_EXT_TAG`].
+pub fn to_vec_named<T>(val: &T) -> Result<Vec<u8>, encode::Error<io::Error>>
+where T: Serialize + ?Sized
+{
+    let buf = encode::to_vec_named(val)?;
+    compress(&buf).map_err(|_| encode::Error::InvalidDataModel("map key is an ext of the key reference type"))
+}
+
+/// Deserializes a value written with [`to_vec_named`] or [`compress`].
+///
+/// The input is expanded first, so `T` can't borrow from it.
+pub fn from_slice<T: DeserializeOwned>(buf: &[u8]) -> Result<T, Error<BytesReadError>> {
+    crate::from_slice(&expand(buf)?)
+}
+
+/// Replaces repeated str map keys in the MessagePack value at the start of the buffer with
+/// references.
+///
+/// Anything after the first value is ignored.
+///
+/// # Errors
+///
+/// Fails if the buffer doesn't start with a complete value, or if a map key is an ext with the
+/// type [`KEY_REF_EXT_TAG`].
+pub fn compress(buf: &[u8]) -> Result<Vec<u8>, Error<BytesReadError>> {
+    let mut keys = HashMap::new();
+    transform(buf, |key, out| {
+        if let Some(data) = long_str_data(key) {
+            let next = keys.len() as u32;
+            match keys.get(data) {
+                Some(&idx) => write_key_ref(out, idx),
+                None => {
+                    keys.insert(data, next);
+                    out.extend_from_slice(key);
+                }
+            }
+        } else if key_ref(key).is_some() {
+            return Err(Error::Uncategorized("map key is an ext of the key reference type"));
+        } else {
+            out.extend_from_slice(key);
+        }
+        Ok(())
+    })
+}
+
+/// Replaces the key references written by [`compress`] with the keys, in the MessagePack value at
+/// the start of the buffer.
+///
+/// Anything after the first value is ignored.
+///
+/// # Errors
+///
+/// Fails if the buffer doesn't start with a complete value, or if a reference points to a key
+/// that wasn't seen yet.
+pub fn expand(buf: &[u8]) -> Result<Vec<u8>, Error<BytesReadError>> {
+    let mut keys = Vec::new();
+    transform(buf, |key, out| {
+        if long_str_data(key).is_some() {
+            keys.push(key);
+            out.extend_from_slice(key);
+        } else if let Some(idx) = key_ref(key) {
+            let key = keys.get(idx as usize).ok_or(Error::Uncategorized("reference to an unknown map key"))?;
+            out.extend_from_slice(key);
+        } else {
+            out.extend_from_slice(key);
+        }
+        Ok(())
+    })
+}
+
+/// Copies the first value of the buffer, passing each map key to `on_key` along with the output.
+fn transform<'a, F>(buf: &'a [u8], mut on_key: F) -> Result<Vec<u8>, Error<BytesReadError>>
+where F: FnMut(&'a [u8], &mut Vec<u8>) -> Result<(), Error<BytesReadError>>
+{
+    let mut out = Vec::with_capacity(buf.len());
+    // Number of values left in each enclosing array or map, and whether it's a map.
+    let mut stack: Vec<(u64, bool)> = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let is_key = match stack.last_mut() {
+            Some((left, is_map)) => {
+                let is_key = *is_map && *left % 2 == 0;
+                *left -= 1;
+                is_key
+            }
+            None => false,
+        };
+
+        let mut rd = Bytes::new(&buf[pos..]);
+        let container = match decode::read_marker(&mut rd)? {
+            Marker::FixArray(len) => Some((len.into(), false)),
+            Marker::Array16 => Some((rd.read_data_u16()?.into(), false)),
+            Marker::Array32 => Some((rd.read_data_u32()?.into(), false)),
+            Marker::FixMap(len) => Some((2 * u64::from(len), true)),
+            Marker::Map16 => Some((2 * u64::from(rd.read_data_u16()?), true)),
+            Marker::Map32 => Some((2 * u64::from(rd.read_data_u32()?), true)),
+            _ => None,
+        };
+
+        match container {
+            Some((len, is_map)) => {
+                let header_len = buf.len() - pos - rd.remaining_slice().len();
+                out.extend_from_slice(&buf[pos..pos + header_len]);
+                pos += header_len;
+                if len > 0 {
+                    stack.push((len, is_map));
+                }
+            }
+            None => {
+                let value_len = match decode::scan_value(&buf[pos..]) {
+                    Ok(Scan::Complete(len)) => len,
+                    Ok(Scan::Incomplete(needed)) => {
+                        let actual = buf.len() - pos;
+                        return Err(Error::InvalidValueRead(ValueReadError::InvalidDataRead(BytesReadError::InsufficientBytes {
+                            expected: actual + needed,
+                            actual,
+                            position: pos as u64,
+                        })));
+                    }
+                    Err(ScanError::ReservedMarker { .. }) => return Err(Error::type_mismatch(Marker::Reserved, "a value")),
+                };
+                let value = &buf[pos..pos + value_len];
+                if is_key {
+                    on_key(value, &mut out)?;
+                } else {
+                    out.extend_from_slice(value);
+                }
+                pos += value_len;
+            }
+        }
+
+        while let Some(&(0, _)) = stack.last() {
+            stack.pop();
+        }
+        if stack.is_empty() {
+            return Ok(out);
+        }
+    }
+}
+
+/// Returns the data of a str of at least [`MIN_KEY_LEN`] bytes.
+fn long_str_data(key: &[u8]) -> Option<&[u8]> {
+    let header_len = match Marker::from_u8(key[0]) {
+        Marker::FixStr(_) => 1,
+        Marker::Str8 => 2,
+        Marker::Str16 => 3,
+        Marker::Str32 => 5,
+        _ => return None,
+    };
+    Some(&key[header_len..]).filter(|data| data.len() >= MIN_KEY_LEN)
+}
+
+/// Returns the index held by a key reference.
+fn key_ref(key: &[u8]) -> Option<u32> {
+    match *key {
+        [0xd4, tag, idx] if tag as i8 == KEY_REF_EXT_TAG => Some(idx.into()),
+        [0xd5, tag, a, b] if tag as i8 == KEY_REF_EXT_TAG => Some(u16::from_be_bytes([a, b]).into()),
+        [0xd6, tag, a, b, c, d] if tag as i8 == KEY_REF_EXT_TAG => Some(u32::from_be_bytes([a, b, c, d])),
+        _ => None,
+    }
+}
+
+fn write_key_ref(out: &mut Vec<u8>, idx: u32) {
+    let tag = KEY_REF_EXT_TAG as u8;
+    if let Ok(idx) = u8::try_from(idx) {
+        out.extend_from_slice(&[Marker::FixExt1.to_u8(), tag, idx]);
+    } else if let Ok(idx) = u16::try_from(idx) {
+        out.extend_from_slice(&[Marker::FixExt2.to_u8(), tag]);
+        out.extend_from_slice(&idx.to_be_bytes());
+    } else {
+        out.extend_from_slice(&[Marker::FixExt4.to_u8(), tag]);
+        out.extend_from_slice(&idx.to_be_bytes());
+    }
+}
@@ -0,0 +1,343 @@
+//! Views over encoded data that decode values on demand.
+//!
+//! [`LazyMap`] and [`LazyArray`] read the header of a map or an array and nothing else. Their
+//! entries are found by scanning the headers of the keys and values, which is much cheaper than
+//! decoding them, so looking at a few keys of a large envelope or a page of a large array doesn't
+//! cost a full decode.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Serialize)]
+//! struct Envelope {
+//!     route: String,
+//!     body: Vec<u32>,
+//! }
+//!
+//! let buf = rmp_serde::to_vec_named(&Envelope { route: "orders".into(), body: vec![1; 1000] }).unwrap();
+//!
+//! let map = rmp_serde::lazy::LazyMap::new(&buf).unwrap();
+//! assert_eq!(2, map.len());
+//! assert_eq!(Some("orders"), map.get("route").unwrap());
+//! # }
+//! ```
+
+use core::fmt::{self, Debug, Formatter};
+
+use rmp::decode::{Event, EventReader};
+use rmp::Marker;
+use serde::Deserialize;
+
+use crate::decode::{value_len, BytesReadError, Error};
+
+/// Returns the number of values in the buffer, like a stream of concatenated messages.
+///
+/// The values are scanned but not decoded.
+///
+/// # Errors
+///
+/// Fails if the buffer doesn't end with a complete value.
+///
+/// # Examples
+///
+/// ```
+/// let mut buf = rmp_serde::to_vec(&[1, 2, 3]).unwrap();
+/// buf.extend(rmp_serde::to_vec("four").unwrap());
+///
+/// assert_eq!(2, rmp_serde::lazy::count_top_level(&buf).unwrap());
+/// assert_eq!(3, rmp_serde::lazy::seq_len(&buf).unwrap());
+/// ```
+pub fn count_top_level(buf: &[u8]) -> Result<usize, Error<BytesReadError>> {
+    let mut count = 0;
+    let mut pos = 0;
+    while pos < buf.len() {
+        pos += value_len(buf, pos)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Returns the length of the array at the start of the buffer, from its header.
+///
+/// # Errors
+///
+/// Fails with [`Error::TypeMismatch`] if the buffer doesn't start with an array.
+#[inline]
+pub fn seq_len(buf: &[u8]) -> Result<usize, Error<BytesReadError>> {
+    LazyArray::new(buf).map(|array| array.len())
+}
+
+/// A map whose keys and values are kept encoded.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LazyMap<'a> {
+    len: u32,
+    /// The bytes following the map header.
+    entries: &'a [u8],
+}
+
+impl<'a> LazyMap<'a> {
+    /// Reads the header of the map at the start of the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::TypeMismatch`] if the buffer doesn't start with a map.
+    pub fn new(buf: &'a [u8]) -> Result<Self, Error<BytesReadError>> {
+        let mut rd = EventReader::new(buf);
+        match rd.next_event()? {
+            Some(Event::MapStart(len)) => Ok(LazyMap { len, entries: rd.remaining_slice() }),
+            Some(..) => Err(Error::type_mismatch(Marker::from_u8(buf[0]), "map")),
+            None => Err(Error::Uncategorized("empty buffer")),
+        }
+    }
+
+    /// Returns the number of entries, as announced by the header.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the map has no entries.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the encoded keys and values.
+    #[inline]
+    pub fn iter(&self) -> MapIter<'a> {
+        MapIter { remaining: self.len, pos: 0, entries: self.entries }
+    }
+
+    /// Returns the encoded value of the first entry whose key is the given string.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the entries in front of it aren't valid MessagePack.
+    pub fn get_raw(&self, key: &str) -> Result<Option<&'a [u8]>, Error<BytesReadError>> {
+        for entry in self.iter() {
+            let (entry_key, val) = entry?;
+            if let Ok(Some(Event::Str(entry_key))) = EventReader::new(entry_key).next_event() {
+                if entry_key == key.as_bytes() {
+                    return Ok(Some(val));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Deserializes the value of the first entry whose key is the given string.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`Self::get_raw`], or if the value can't be deserialized into `T`.
+    pub fn get<T>(&self, key: &str) -> Result<Option<T>, Error<BytesReadError>>
+    where T: Deserialize<'a>
+    {
+        match self.get_raw(key)? {
+            Some(val) => crate::from_slice(val).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Debug for LazyMap<'_> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("LazyMap")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<'a> IntoIterator for &LazyMap<'a> {
+    type Item = Result<(&'a [u8], &'a [u8]), Error<BytesReadError>>;
+    type IntoIter = MapIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> MapIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the encoded keys and values of a [`LazyMap`].
+///
+/// Iteration stops after the first error.
+#[derive(Clone, Debug)]
+pub struct MapIter<'a> {
+    remaining: u32,
+    pos: usize,
+    entries: &'a [u8],
+}
+
+impl<'a> MapIter<'a> {
+    fn next_entry(&mut self) -> Result<(&'a [u8], &'a [u8]), Error<BytesReadError>> {
+        let key_len = value_len(self.entries, self.pos)?;
+        let key = &self.entries[self.pos..self.pos + key_len];
+        let val_len = value_len(self.entries, self.pos + key_len)?;
+        let val = &self.entries[self.pos + key_len..self.pos + key_len + val_len];
+        self.pos += key_len + val_len;
+        Ok((key, val))
+    }
+}
+
+impl<'a> Iterator for MapIter<'a> {
+    type Item = Result<(&'a [u8], &'a [u8]), Error<BytesReadError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let entry = self.next_entry();
+        if entry.is_err() {
+            self.remaining = 0;
+        }
+        Some(entry)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining as usize))
+    }
+}
+
+/// An array whose elements are kept encoded.
+///
+/// Accessing an element scans the headers of the elements in front of it, so iterating with
+/// [`Self::iter`] is cheaper than calling [`Self::get`] for every index.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LazyArray<'a> {
+    len: u32,
+    /// The bytes following the array header.
+    elements: &'a [u8],
+}
+
+impl<'a> LazyArray<'a> {
+    /// Reads the header of the array at the start of the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::TypeMismatch`] if the buffer doesn't start with an array.
+    pub fn new(buf: &'a [u8]) -> Result<Self, Error<BytesReadError>> {
+        let mut rd = EventReader::new(buf);
+        match rd.next_event()? {
+            Some(Event::ArrayStart(len)) => Ok(LazyArray { len, elements: rd.remaining_slice() }),
+            Some(..) => Err(Error::type_mismatch(Marker::from_u8(buf[0]), "array")),
+            None => Err(Error::Uncategorized("empty buffer")),
+        }
+    }
+
+    /// Returns the number of elements, as announced by the header.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the array has no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the encoded elements.
+    #[inline]
+    pub fn iter(&self) -> ArrayIter<'a> {
+        ArrayIter { remaining: self.len, pos: 0, elements: self.elements }
+    }
+
+    /// Returns the encoded element at the index, or `None` if it's out of bounds.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the elements up to this one aren't valid MessagePack.
+    pub fn get_raw(&self, idx: usize) -> Result<Option<&'a [u8]>, Error<BytesReadError>> {
+        self.iter().nth(idx).transpose()
+    }
+
+    /// Deserializes the element at the index, or returns `None` if it's out of bounds.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`Self::get_raw`], or if the element can't be deserialized into `T`.
+    pub fn get<T>(&self, idx: usize) -> Result<Option<T>, Error<BytesReadError>>
+    where T: Deserialize<'a>
+    {
+        match self.get_raw(idx)? {
+            Some(val) => crate::from_slice(val).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Debug for LazyArray<'_> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("LazyArray")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<'a> IntoIterator for &LazyArray<'a> {
+    type Item = Result<&'a [u8], Error<BytesReadError>>;
+    type IntoIter = ArrayIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> ArrayIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the encoded elements of a [`LazyArray`].
+///
+/// Iteration stops after the first error.
+#[derive(Clone, Debug)]
+pub struct ArrayIter<'a> {
+    remaining: u32,
+    pos: usize,
+    elements: &'a [u8],
+}
+
+impl<'a> Iterator for ArrayIter<'a> {
+    type Item = Result<&'a [u8], Error<BytesReadError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        match value_len(self.elements, self.pos) {
+            Ok(len) => {
+                let val = &self.elements[self.pos..self.pos + len];
+                self.pos += len;
+                Some(Ok(val))
+            }
+            Err(err) => {
+                self.remaining = 0;
+                Some(Err(err))
+            }
+        }
+    }
+
+    /// Skips `n` elements, reporting an error found in them rather than discarding it.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.remaining as usize {
+            self.remaining = 0;
+            return None;
+        }
+        for _ in 0..n {
+            match value_len(self.elements, self.pos) {
+                Ok(len) => self.pos += len,
+                Err(err) => {
+                    self.remaining = 0;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.remaining -= n as u32;
+        self.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining as usize))
+    }
+}
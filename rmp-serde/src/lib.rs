@@ -55,28 +55,133 @@
 //! ```
 //!
 //! [serde]: https://serde.rs/
-#![forbid(unsafe_code)]
+// Memory-mapping a file can't be done without `unsafe`, so it's only allowed under `mmap`.
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 //#![warn(missing_debug_implementations, missing_docs)] // TODO
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 use core::fmt::{self, Display, Formatter};
 use core::str::{self, Utf8Error};
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::{String, ToString};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
 use serde::de;
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "std")]
-pub use crate::decode::{from_read, Deserializer};
-pub use crate::decode::from_slice;
+pub use crate::decode::{from_read, from_read_seed, Deserializer};
+pub use crate::decode::{from_slice, from_slice_human_readable, from_slice_seed};
+#[cfg(feature = "std")]
+pub use crate::decode::ErasedError;
+#[cfg(feature = "alloc")]
+pub use crate::decode::from_hex_str;
+#[cfg(feature = "bytes")]
+pub use crate::decode::from_buf;
+#[cfg(feature = "base64")]
+pub use crate::decode::from_base64_str;
+#[cfg(feature = "mmap")]
+pub use crate::decode::{from_file, from_path};
 
 #[allow(deprecated)]
+#[cfg(feature = "alloc")]
+pub use crate::encode::{to_hex_string, to_vec, to_vec_human_readable, to_vec_named, to_vec_named_human_readable};
+#[cfg(feature = "std")]
+pub use crate::encode::{to_vec_from_iter, to_vec_pooled, PooledBuf, Serializer};
+pub use crate::encode::{write, write_named, write_to_slice};
+#[cfg(feature = "bytes")]
+pub use crate::encode::to_bytes;
+#[cfg(feature = "base64")]
+pub use crate::encode::to_base64_string;
+#[cfg(feature = "allocator_api")]
+pub use crate::encode::to_vec_in;
+#[cfg(feature = "heapless")]
+pub use crate::encode::to_heapless_vec;
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+pub use crate::compress::{from_slice_compressed, to_vec_compressed};
+pub use crate::checksum::{from_slice_checksummed, write_checksummed};
+#[cfg(feature = "std")]
+pub use crate::records::{append_to_file, iter_file};
+
+#[cfg(feature = "std")]
+pub use crate::byte_buf::ByteBuf;
+pub use crate::byte_buf::Bytes;
 #[cfg(feature = "std")]
-pub use crate::encode::{to_vec, to_vec_named, Serializer};
-pub use crate::encode::{write, write_named};
+pub use crate::ext::{DynExt, Ext, ExtDecodeError, ExtRegistry, MsgPackExtType, TypedExt};
+pub use crate::ext::ExtRef;
 
+#[cfg(feature = "bigint")]
+pub mod bigint;
+#[cfg(feature = "std")]
+pub mod bin_stream;
+#[cfg(feature = "bumpalo")]
+pub mod bump;
+#[cfg(feature = "http-body")]
+pub mod body;
+mod byte_buf;
+pub mod checksum;
+#[cfg(feature = "tokio-util")]
+pub mod codec;
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+pub mod compress;
 pub mod config;
 pub mod decode;
+#[cfg(feature = "embedded-io")]
+pub mod embedded_io;
+#[cfg(any(feature = "rust_decimal", feature = "bigdecimal"))]
+pub mod decimal;
 pub mod encode;
+#[cfg(feature = "erased-serde")]
+pub mod erased;
+mod ext;
+#[cfg(feature = "std")]
+pub mod fields;
+#[cfg(any(feature = "tokio-util", feature = "futures", feature = "http-body"))]
+mod frame;
+#[cfg(feature = "std")]
+pub mod hook;
+#[cfg(feature = "indexmap")]
+pub mod indexmap;
+#[cfg(feature = "std")]
+pub mod intern;
+#[cfg(feature = "std")]
+pub mod keydict;
+#[cfg(feature = "std")]
+pub mod lazy;
+#[cfg(feature = "rayon")]
+pub mod par;
+#[cfg(feature = "std")]
+pub mod patch;
+#[cfg(feature = "std")]
+pub mod pointer;
+#[cfg(feature = "std")]
+pub mod records;
+#[cfg(feature = "std")]
+pub mod rpc;
+#[cfg(feature = "futures")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod schema;
+#[cfg(feature = "std")]
+pub mod select;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod strict;
+#[cfg(feature = "std")]
+pub mod table;
+#[cfg(feature = "uuid")]
+pub mod uuid;
+#[cfg(any(feature = "axum", feature = "actix-web"))]
+pub mod web;
+#[cfg(feature = "std")]
+pub mod with;
 
 /// Name of Serde newtype struct to Represent Msgpack's Ext
 /// Msgpack Ext: Ext(tag, binary)
@@ -104,7 +209,7 @@ pub enum Raw<'a> {
         s: Result<&'a str, (&'a [u8], Utf8Error)>,
     },
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     Owned {
         s: Result<String, (Vec<u8>, Utf8Error)>,
     },
@@ -118,7 +223,19 @@ impl<'a> Raw<'a> {
     }
 }
 
-#[cfg(feature = "std")]
+/// Validates UTF-8 like `str::from_utf8`, using SIMD instructions if the `simdutf8` feature is
+/// enabled.
+#[inline]
+pub(crate) fn from_utf8(v: &[u8]) -> Result<&str, Utf8Error> {
+    #[cfg(feature = "simdutf8")]
+    if let Ok(s) = simdutf8::basic::from_utf8(v) {
+        return Ok(s);
+    }
+    // The fast path doesn't say where the error is, so invalid input is validated again.
+    str::from_utf8(v)
+}
+
+#[cfg(feature = "alloc")]
 impl Raw<'_> {
     /// Constructs a new `Raw` from the UTF-8 string.
     #[inline]
@@ -159,7 +276,7 @@ impl Raw<'_> {
     pub fn as_str(&self) -> Option<&str> {
         match self {
             Self::Borrowed { s: Ok(s) } => Some(s),
-            #[cfg(feature = "std")]
+            #[cfg(feature = "alloc")]
             Self::Owned { s: Ok(ref s) } => Some(s.as_str()),
             _ => None,
         }
@@ -171,7 +288,7 @@ impl Raw<'_> {
     pub fn as_err(&self) -> Option<&Utf8Error> {
         match self {
             Self::Borrowed  { s : Err((_, ref err)) } => Some(err),
-            #[cfg(feature = "std")]
+            #[cfg(feature = "alloc")]
             Self::Owned  { s : Err((_, ref err)) } => Some(err),
             _ => None,
         }
@@ -184,15 +301,15 @@ impl Raw<'_> {
             Self::Borrowed  { s : Err(ref err) } => err.0,
             Self::Borrowed { s: Ok(s) } => s.as_bytes(),
 
-            #[cfg(feature = "std")]
+            #[cfg(feature = "alloc")]
             Self::Owned  { s : Err(ref err) } => &err.0,
-            #[cfg(feature = "std")]
+            #[cfg(feature = "alloc")]
             Self::Owned { s: Ok(ref s) } => s.as_bytes(),
         }
     }
 
     /// Consumes this object, yielding the string if the raw is valid UTF-8, or else `None`.
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
     pub fn into_str(self) -> Option<String> {
         match self {
@@ -203,7 +320,7 @@ impl Raw<'_> {
     }
 
     /// Converts a `Raw` into a byte vector.
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
     pub fn into_bytes(self) -> Vec<u8> {
         match self{
@@ -238,13 +355,13 @@ impl<'de> de::Visitor<'de> for RawVisitor {
         "string or bytes".fmt(fmt)
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
     fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
         Ok(Raw::Owned { s: Ok(v) })
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
         where E: de::Error
@@ -258,12 +375,12 @@ impl<'de> de::Visitor<'de> for RawVisitor {
         Ok(Raw::Borrowed { s: Ok(v) })
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
     fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
         where E: de::Error
     {
-        let s = match str::from_utf8(v) {
+        let s = match from_utf8(v) {
             Ok(s) => Ok(s.into()),
             Err(err) => Err((v.into(), err)),
         };
@@ -275,7 +392,7 @@ impl<'de> de::Visitor<'de> for RawVisitor {
         where
             E: de::Error, {
         
-        let s = match str::from_utf8(v) {
+        let s = match from_utf8(v) {
             Ok(s) => Ok(s),
             Err(err) => Err((v, err)),
         };
@@ -283,7 +400,7 @@ impl<'de> de::Visitor<'de> for RawVisitor {
         Ok(Raw::Borrowed { s })
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
     fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
         where E: de::Error
@@ -412,7 +529,7 @@ impl<'de> de::Visitor<'de> for RawRefVisitor {
     fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
         where E: de::Error
     {
-        let s = match str::from_utf8(v) {
+        let s = match from_utf8(v) {
             Ok(s) => Ok(s),
             Err(err) => Err((v, err)),
         };
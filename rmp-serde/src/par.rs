@@ -0,0 +1,115 @@
+//! Parallel encoding and decoding of large arrays, backed by [`rayon`](https://docs.rs/rayon).
+//!
+//! [`to_vec_par`] splits a slice into chunks, serializes each chunk into its own buffer on the
+//! rayon thread pool, and then joins the buffers under a single `Array32` header. The result
+//! decodes like the output of [`to_vec`](crate::to_vec), but the header is always 5 bytes long.
+//!
+//! [`from_slice_par`] goes the other way: it finds where the elements of an array start with the
+//! value scanner, which only looks at the headers, and then deserializes them on the thread pool.
+//!
+//! ```
+//! let rows: Vec<(u32, String)> = (0..10_000).map(|i| (i, i.to_string())).collect();
+//! let buf = rmp_serde::par::to_vec_par(&rows).unwrap();
+//!
+//! assert_eq!(0xdd, buf[0]);
+//! assert_eq!(rows, rmp_serde::par::from_slice_par::<(u32, String)>(&buf).unwrap());
+//! ```
+
+use std::io;
+
+use rayon::prelude::*;
+use rmp::decode::{self, Scan, ScanError, ValueReadError};
+use rmp::Marker;
+use serde::{Deserialize, Serialize};
+
+use crate::decode::BytesReadError;
+use crate::encode::{self, Error};
+
+/// Minimum number of elements handled by a single task.
+const MIN_CHUNK_LEN: usize = 256;
+
+/// Serializes the elements of the slice in parallel into a MessagePack array, using the compact
+/// representation like [`to_vec`](crate::to_vec).
+#[inline]
+pub fn to_vec_par<T: Serialize + Sync>(items: &[T]) -> Result<Vec<u8>, Error<io::Error>> {
+    serialize_par(items, |buf, item| encode::write(buf, item))
+}
+
+/// Serializes the elements of the slice in parallel into a MessagePack array, writing structs as
+/// maps like [`to_vec_named`](crate::to_vec_named).
+#[inline]
+pub fn to_vec_named_par<T: Serialize + Sync>(items: &[T]) -> Result<Vec<u8>, Error<io::Error>> {
+    serialize_par(items, |buf, item| encode::write_named(buf, item))
+}
+
+fn serialize_par<T, F>(items: &[T], write: F) -> Result<Vec<u8>, Error<io::Error>>
+where T: Serialize + Sync,
+      F: Fn(&mut Vec<u8>, &T) -> Result<(), Error<io::Error>> + Sync
+{
+    let len = u32::try_from(items.len())
+        .map_err(|_| Error::InvalidDataModel("too many elements for a MessagePack array"))?;
+    let chunk_len = (items.len() / (4 * rayon::current_num_threads())).max(MIN_CHUNK_LEN);
+
+    let chunks = items.par_chunks(chunk_len)
+        .map(|chunk| {
+            let mut buf = Vec::with_capacity(128);
+            for item in chunk {
+                write(&mut buf, item)?;
+            }
+            Ok(buf)
+        })
+        .collect::<Result<Vec<_>, Error<io::Error>>>()?;
+
+    let mut buf = Vec::with_capacity(5 + chunks.iter().map(Vec::len).sum::<usize>());
+    buf.push(Marker::Array32.to_u8());
+    buf.extend_from_slice(&len.to_be_bytes());
+    for chunk in chunks {
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(buf)
+}
+
+/// Deserializes a MessagePack array from the slice, decoding its elements in parallel.
+///
+/// The elements must be independent values, which is the case for anything but the elements of
+/// a struct or tuple serialized as an array. Each one is decoded like with
+/// [`from_slice`](crate::from_slice), so errors report positions relative to the element.
+///
+/// # Errors
+///
+/// Fails if the slice doesn't start with an array, if the array is truncated, or if any element
+/// can't be deserialized as `T`.
+pub fn from_slice_par<'a, T>(buf: &'a [u8]) -> Result<Vec<T>, crate::decode::Error<BytesReadError>>
+where T: Deserialize<'a> + Send
+{
+    use crate::decode::Error;
+
+    let mut rd = decode::Bytes::new(buf);
+    let len = decode::read_array_len(&mut rd)?;
+    let mut pos = buf.len() - rd.remaining_slice().len();
+
+    // Every element takes at least a byte, so a bogus length can't allocate too much.
+    let mut elems = Vec::with_capacity((len as usize).min(buf.len() - pos));
+    for _ in 0..len {
+        let rest = &buf[pos..];
+        match decode::scan_value(rest) {
+            Ok(Scan::Complete(elem_len)) => {
+                elems.push(&rest[..elem_len]);
+                pos += elem_len;
+            }
+            Ok(Scan::Incomplete(needed)) => {
+                return Err(Error::InvalidValueRead(ValueReadError::InvalidDataRead(BytesReadError::InsufficientBytes {
+                    expected: rest.len() + needed,
+                    actual: rest.len(),
+                    position: pos as u64,
+                })));
+            }
+            Err(ScanError::ReservedMarker { .. }) => return Err(Error::type_mismatch(Marker::Reserved, "a value")),
+        }
+    }
+
+    elems.into_par_iter()
+        .with_min_len(MIN_CHUNK_LEN)
+        .map(crate::from_slice)
+        .collect()
+}
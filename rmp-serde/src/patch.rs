@@ -0,0 +1,153 @@
+//! Merge patches applied directly to MessagePack data.
+//!
+//! [`merge`] follows the semantics of [JSON Merge Patch](https://www.rfc-editor.org/rfc/rfc7386):
+//! a map in the patch is merged into the corresponding map of the base, a nil value in it removes
+//! the key, and any other value replaces the base value. Values that are not touched by the patch
+//! are copied as they are, without being decoded.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     #[serde(default)]
+//!     debug: Option<bool>,
+//!     limits: Limits,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Limits {
+//!     cpu: u32,
+//!     memory: u32,
+//! }
+//!
+//! let base = Config { name: "app".into(), debug: Some(true), limits: Limits { cpu: 1, memory: 512 } };
+//! let base = rmp_serde::to_vec_named(&base).unwrap();
+//!
+//! // {"debug": nil, "limits": {"memory": 1024}}
+//! let patch = [0x82, 0xa5, b'd', b'e', b'b', b'u', b'g', 0xc0,
+//!              0xa6, b'l', b'i', b'm', b'i', b't', b's', 0x81, 0xa6, b'm', b'e', b'm', b'o', b'r', b'y', 0xcd, 0x04, 0x00];
+//!
+//! let merged = rmp_serde::patch::merge(&base, &patch).unwrap();
+//! assert_eq!(Config { name: "app".into(), debug: None, limits: Limits { cpu: 1, memory: 1024 } },
+//!            rmp_serde::from_slice(&merged).unwrap());
+//! # }
+//! ```
+
+use rmp::decode::{self, Bytes, Event, EventReader, RmpRead};
+use rmp::Marker;
+
+use crate::decode::{value_len, BytesReadError, Error};
+
+/// How deeply maps of the patch can be nested.
+const MAX_DEPTH: usize = 1024;
+
+/// Applies the patch to the base, and returns the merged value.
+///
+/// Map keys are matched by value, so a key written as a str8 in the patch matches the same key
+/// written as a fixstr in the base, and integer keys match whatever their width. Merged maps keep
+/// the order of the keys of the base, followed by the keys only found in the patch.
+///
+/// Anything after the first value of either buffer is ignored.
+///
+/// # Errors
+///
+/// Fails if either buffer doesn't start with a complete value, or if the maps of the patch are
+/// nested more than 1024 levels deep.
+pub fn merge(base: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error<BytesReadError>> {
+    let base = &base[..value_len(base, 0)?];
+    let patch = &patch[..value_len(patch, 0)?];
+
+    let mut out = Vec::with_capacity(base.len() + patch.len());
+    merge_into(Some(base), patch, &mut out, MAX_DEPTH)?;
+    Ok(out)
+}
+
+fn merge_into(base: Option<&[u8]>, patch: &[u8], out: &mut Vec<u8>, depth: usize) -> Result<(), Error<BytesReadError>> {
+    let Some(patch_entries) = map_entries(patch)? else {
+        out.extend_from_slice(patch);
+        return Ok(());
+    };
+    let depth = depth.checked_sub(1).ok_or(Error::DepthLimitExceeded)?;
+    let base_entries = match base {
+        Some(base) => map_entries(base)?.unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let mut body = Vec::new();
+    let mut len = 0u32;
+    for &(key, val) in &base_entries {
+        // The last occurrence of a key wins, like when decoding into a map.
+        let patch_val = patch_entries.iter().rev().find(|&&(patch_key, _)| key_eq(key, patch_key)).map(|&(_, val)| val);
+        if patch_val.is_some_and(is_nil) {
+            continue;
+        }
+        body.extend_from_slice(key);
+        match patch_val {
+            Some(patch_val) => merge_into(Some(val), patch_val, &mut body, depth)?,
+            None => body.extend_from_slice(val),
+        }
+        len += 1;
+    }
+    for (idx, &(key, val)) in patch_entries.iter().enumerate() {
+        let skip = is_nil(val) ||
+            base_entries.iter().any(|&(base_key, _)| key_eq(base_key, key)) ||
+            patch_entries[idx + 1..].iter().any(|&(patch_key, _)| key_eq(patch_key, key));
+        if skip {
+            continue;
+        }
+        body.extend_from_slice(key);
+        merge_into(None, val, &mut body, depth)?;
+        len += 1;
+    }
+
+    rmp::encode::write_map_len(out, len).expect("writing to a Vec can't fail");
+    out.extend_from_slice(&body);
+    Ok(())
+}
+
+/// The encoded key and value of a map entry.
+type Entry<'a> = (&'a [u8], &'a [u8]);
+
+/// Splits a map into its entries, or returns `None` if the value isn't a map.
+fn map_entries(buf: &[u8]) -> Result<Option<Vec<Entry<'_>>>, Error<BytesReadError>> {
+    let mut rd = Bytes::new(buf);
+    let len = match decode::read_marker(&mut rd)? {
+        Marker::FixMap(len) => u32::from(len),
+        Marker::Map16 => u32::from(rd.read_data_u16()?),
+        Marker::Map32 => rd.read_data_u32()?,
+        _ => return Ok(None),
+    };
+
+    let mut pos = buf.len() - rd.remaining_slice().len();
+    // Every entry takes at least 2 bytes.
+    let mut entries = Vec::with_capacity((len as usize).min((buf.len() - pos) / 2));
+    for _ in 0..len {
+        let key_len = value_len(buf, pos)?;
+        let key = &buf[pos..pos + key_len];
+        pos += key_len;
+        let val_len = value_len(buf, pos)?;
+        entries.push((key, &buf[pos..pos + val_len]));
+        pos += val_len;
+    }
+    Ok(Some(entries))
+}
+
+#[inline]
+fn is_nil(val: &[u8]) -> bool {
+    val.first() == Some(&Marker::Null.to_u8())
+}
+
+/// Compares map keys by value rather than by encoding.
+fn key_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    let first_event = |buf| EventReader::new(buf).next_event().ok().flatten();
+    match (first_event(lhs), first_event(rhs)) {
+        (Some(Event::UInt(uint)), Some(Event::SInt(sint))) |
+        (Some(Event::SInt(sint)), Some(Event::UInt(uint))) => i128::from(uint) == i128::from(sint),
+        // Arrays and maps are compared by their encoding.
+        (Some(Event::ArrayStart(..) | Event::MapStart(..)), _) | (_, Some(Event::ArrayStart(..) | Event::MapStart(..))) => lhs == rhs,
+        (Some(lhs), Some(rhs)) => lhs == rhs,
+        _ => lhs == rhs,
+    }
+}
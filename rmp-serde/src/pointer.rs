@@ -0,0 +1,135 @@
+//! Lookup of values by path, without decoding the rest of the data.
+//!
+//! Paths follow the [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901) syntax: `/users/3/name`
+//! addresses the `name` field of the element at index 3 of the `users` array. The values in front
+//! of the addressed one are skipped by scanning their headers, so reading one field of a large
+//! document costs a fraction of decoding it.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Serialize)]
+//! struct User {
+//!     name: String,
+//!     tags: Vec<String>,
+//! }
+//!
+//! let users: Vec<User> = (0..100).map(|i| User { name: format!("user{}", i), tags: vec![] }).collect();
+//! let buf = rmp_serde::to_vec_named(&users).unwrap();
+//!
+//! assert_eq!(Some("user3"), rmp_serde::pointer::get(&buf, "/3/name").unwrap());
+//! assert_eq!(None::<&str>, rmp_serde::pointer::get(&buf, "/100/name").unwrap());
+//! # }
+//! ```
+
+use std::borrow::Cow;
+
+use rmp::decode::{Event, EventReader};
+use serde::Deserialize;
+
+use crate::decode::{value_len, BytesReadError, Error};
+
+/// Deserializes the value at the given path.
+///
+/// Returns `Ok(None)` if the path doesn't exist. See [`find`] for how paths are resolved.
+///
+/// # Errors
+///
+/// Fails like [`find`], or if the value can't be deserialized into `T`.
+pub fn get<'a, T>(buf: &'a [u8], pointer: &str) -> Result<Option<T>, Error<BytesReadError>>
+where T: Deserialize<'a>
+{
+    match find(buf, pointer)? {
+        Some(val) => crate::from_slice(val).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Returns the encoded value at the given path.
+///
+/// The empty path addresses the value at the start of the buffer, otherwise each `/`-separated
+/// token selects an element of an array by its index, or a value of a map by its key. Keys match
+/// strings equal to the token, with `~1` standing for `/` and `~0` for `~`, and integers equal to
+/// it. If a map has the key several times, the first occurrence is used.
+///
+/// Returns `Ok(None)` if the path doesn't exist.
+///
+/// # Errors
+///
+/// Fails if the path isn't empty and doesn't start with `/`, or if the data on the way to the
+/// addressed value isn't valid MessagePack.
+pub fn find<'a>(buf: &'a [u8], pointer: &str) -> Result<Option<&'a [u8]>, Error<BytesReadError>> {
+    if pointer.is_empty() {
+        return Ok(Some(&buf[..value_len(buf, 0)?]));
+    }
+    let tokens = pointer.strip_prefix('/').ok_or(Error::Uncategorized("pointer must be empty or start with '/'"))?;
+
+    let mut val = buf;
+    for token in tokens.split('/') {
+        let token = unescape(token);
+        match child(val, &token)? {
+            Some(child) => val = child,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(val))
+}
+
+fn unescape(token: &str) -> Cow<'_, str> {
+    if token.contains('~') {
+        Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+/// Returns the element of the array or map at the start of `buf` selected by the token.
+fn child<'a>(buf: &'a [u8], token: &str) -> Result<Option<&'a [u8]>, Error<BytesReadError>> {
+    let mut rd = EventReader::new(buf);
+    let event = rd.next_event()?;
+    let mut pos = rd.position() as usize;
+
+    match event {
+        Some(Event::ArrayStart(len)) => {
+            let Some(idx) = array_index(token).filter(|&idx| idx < len) else {
+                return Ok(None);
+            };
+            for _ in 0..idx {
+                pos += value_len(buf, pos)?;
+            }
+            let val_len = value_len(buf, pos)?;
+            Ok(Some(&buf[pos..pos + val_len]))
+        }
+        Some(Event::MapStart(len)) => {
+            for _ in 0..len {
+                let key_len = value_len(buf, pos)?;
+                let is_match = key_matches(&buf[pos..pos + key_len], token);
+                pos += key_len;
+                let val_len = value_len(buf, pos)?;
+                if is_match {
+                    return Ok(Some(&buf[pos..pos + val_len]));
+                }
+                pos += val_len;
+            }
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses an array index, which has no sign and no leading zeros.
+fn array_index(token: &str) -> Option<u32> {
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) || token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn key_matches(key: &[u8], token: &str) -> bool {
+    match EventReader::new(key).next_event() {
+        Ok(Some(Event::Str(key))) => key == token.as_bytes(),
+        Ok(Some(Event::UInt(key))) => token.parse() == Ok(key),
+        Ok(Some(Event::SInt(key))) => token.parse() == Ok(key),
+        _ => false,
+    }
+}
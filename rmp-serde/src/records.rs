@@ -0,0 +1,232 @@
+//! Files of back-to-back MessagePack records.
+//!
+//! [`append_to_file`] adds a value at the end of a file, and [`iter_file`] reads the values back
+//! one by one, without loading the whole file. A record that doesn't deserialize into the expected
+//! type is reported and skipped, so one bad record doesn't make the rest of the log unreadable.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Entry {
+//!     level: u8,
+//!     message: String,
+//! }
+//!
+//! let path = std::env::temp_dir().join(format!("rmp-serde-doc-{}.log", std::process::id()));
+//! # let _ = std::fs::remove_file(&path);
+//! rmp_serde::records::append_to_file(&path, &Entry { level: 1, message: "started".into() }).unwrap();
+//! rmp_serde::records::append_to_file(&path, &"not an entry").unwrap();
+//! rmp_serde::records::append_to_file(&path, &Entry { level: 2, message: "stopped".into() }).unwrap();
+//!
+//! let mut records = rmp_serde::records::iter_file::<Entry, _>(&path).unwrap();
+//! assert_eq!(1, records.next().unwrap().unwrap().level);
+//! assert_eq!(10, records.next().unwrap().unwrap_err().position());
+//! assert_eq!(2, records.next().unwrap().unwrap().level);
+//! assert!(records.next().is_none());
+//! # std::fs::remove_file(&path).unwrap();
+//! # }
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+use core::marker::PhantomData;
+use std::error;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use rmp::decode::{Scan, ScanError, Scanner, ValueReadError};
+use rmp::Marker;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::decode::{Error, ErasedError};
+use crate::encode;
+
+/// How many bytes are read from the file at once.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Appends the value at the end of the file, with structs written as arrays like
+/// [`to_vec`](crate::to_vec), creating the file if it doesn't exist.
+///
+/// The record is encoded first and written with a single write, so that a crash leaves at most a
+/// truncated record at the end of the file, which [`iter_file`] reports.
+///
+/// # Errors
+///
+/// Fails if the value can't be serialized, or if the file can't be opened or written.
+pub fn append_to_file<P, T>(path: P, val: &T) -> Result<(), encode::Error<io::Error>>
+where
+    P: AsRef<Path>,
+    T: Serialize + ?Sized
+{
+    let buf = crate::to_vec(val)?;
+    let write = || OpenOptions::new().create(true).append(true).open(path)?.write_all(&buf);
+    write().map_err(|err| encode::Error::InvalidValueWrite(rmp::encode::ValueWriteError::InvalidDataWrite(err)))
+}
+
+/// Opens the file, and returns an iterator over the records in it.
+///
+/// # Errors
+///
+/// Fails if the file can't be opened.
+#[inline]
+pub fn iter_file<T, P>(path: P) -> io::Result<RecordIter<T, File>>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>
+{
+    File::open(path).map(RecordIter::new)
+}
+
+/// An iterator deserializing back-to-back records from a reader.
+///
+/// Records that are valid MessagePack but can't be deserialized into `T` are reported as errors,
+/// and iteration continues with the next record. Iteration stops after a read error, after data
+/// that isn't MessagePack, or after a truncated record at the end.
+#[derive(Debug)]
+pub struct RecordIter<T, R> {
+    rd: R,
+    buf: Vec<u8>,
+    /// Where the next record starts in `buf`.
+    start: usize,
+    /// The offset of the next record in the reader.
+    position: u64,
+    scanner: Scanner,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, R: Read> RecordIter<T, R> {
+    /// Iterates over the records read from `rd`.
+    #[inline]
+    pub fn new(rd: R) -> Self {
+        RecordIter {
+            rd,
+            buf: Vec::new(),
+            start: 0,
+            position: 0,
+            scanner: Scanner::new(),
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the offset of the next record, or of the end of the data once iteration is done.
+    #[inline(always)]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Consumes this iterator, returning the underlying reader.
+    ///
+    /// Data read ahead of the next record is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+
+    /// Reads more data, and returns `false` at the end of the reader.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.buf.drain(..self.start);
+        self.start = 0;
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+        loop {
+            match self.rd.read(&mut self.buf[len..]) {
+                Ok(read) => {
+                    self.buf.truncate(len + read);
+                    return Ok(read > 0);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.buf.truncate(len);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    #[cold]
+    fn fail(&mut self, err: Error<io::Error>) -> Option<Result<T, RecordError>> {
+        self.done = true;
+        Some(Err(RecordError { position: self.position, error: err.into() }))
+    }
+}
+
+impl<T: DeserializeOwned, R: Read> Iterator for RecordIter<T, R> {
+    type Item = Result<T, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            match self.scanner.scan(&self.buf[self.start..]) {
+                Ok(Scan::Complete(len)) => {
+                    let record = &self.buf[self.start..self.start + len];
+                    let res = crate::from_slice(record)
+                        .map_err(|err| RecordError { position: self.position, error: err.into() });
+                    self.start += len;
+                    self.position += len as u64;
+                    self.scanner.reset();
+                    return Some(res);
+                }
+                Ok(Scan::Incomplete(..)) => match self.fill() {
+                    Ok(true) => {}
+                    Ok(false) if self.start == self.buf.len() => {
+                        self.done = true;
+                        return None;
+                    }
+                    Ok(false) => {
+                        let err = io::Error::new(io::ErrorKind::UnexpectedEof, "truncated record");
+                        return self.fail(Error::InvalidValueRead(ValueReadError::InvalidDataRead(err)));
+                    }
+                    Err(err) => return self.fail(Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(err))),
+                },
+                Err(ScanError::ReservedMarker { .. }) => return self.fail(Error::type_mismatch(Marker::Reserved, "a value")),
+            }
+        }
+    }
+}
+
+/// An error reading a record, at the offset of the record.
+#[derive(Debug)]
+pub struct RecordError {
+    position: u64,
+    error: ErasedError,
+}
+
+impl RecordError {
+    /// Returns the offset of the record.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Gets a reference to the underlying error.
+    #[inline]
+    pub fn get_ref(&self) -> &ErasedError {
+        &self.error
+    }
+
+    /// Consumes this error returning the underlying error.
+    #[inline]
+    pub fn into_inner(self) -> ErasedError {
+        self.error
+    }
+}
+
+impl Display for RecordError {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        write!(fmt, "invalid record at offset {}: {}", self.position, self.error)
+    }
+}
+
+impl error::Error for RecordError {
+    #[cold]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,261 @@
+//! Envelope types of the [MessagePack-RPC] protocol, as spoken by Neovim and others.
+//!
+//! Each message is an array starting with its type:
+//!
+//! - `[0, msgid, method, params]` for a [`Request`],
+//! - `[1, msgid, error, result]` for a [`Response`],
+//! - `[2, method, params]` for a [`Notification`].
+//!
+//! The parameter, result and error types are generic, use [`rmpv::Value`] to handle arbitrary
+//! payloads. Parameters should serialize as an array, e.g. a tuple or a `Vec`.
+//!
+//! [MessagePack-RPC]: https://github.com/msgpack-rpc/msgpack-rpc/blob/master/spec.md
+//! [`rmpv::Value`]: https://docs.rs/rmpv/latest/rmpv/enum.Value.html
+
+use core::fmt::{self, Formatter};
+use core::marker::PhantomData;
+use std::io;
+
+use serde::de::{self, DeserializeOwned, Deserializer, IgnoredAny, SeqAccess, Visitor};
+use serde::ser::{SerializeTuple, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::{decode, encode};
+
+const REQUEST: u8 = 0;
+const RESPONSE: u8 = 1;
+const NOTIFICATION: u8 = 2;
+
+/// A request, expecting a [`Response`] with the same `msgid`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request<P> {
+    /// Identifier of the request, used to match it with its response.
+    pub msgid: u32,
+    /// Name of the method to call.
+    pub method: String,
+    /// Method arguments.
+    pub params: P,
+}
+
+/// The response to a [`Request`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response<R, E> {
+    /// Identifier of the request this responds to.
+    pub msgid: u32,
+    /// The result on success, or the error.
+    ///
+    /// On the wire an error is any non-nil value, so `E` should not serialize as nil.
+    pub result: Result<R, E>,
+}
+
+/// A notification, which has no response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification<P> {
+    /// Name of the method to call.
+    pub method: String,
+    /// Method arguments.
+    pub params: P,
+}
+
+/// Any MessagePack-RPC message.
+///
+/// Requests and notifications share the parameter type `P`, responses have result type `R` and
+/// error type `E`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message<P, R, E> {
+    /// A request.
+    Request(Request<P>),
+    /// A response.
+    Response(Response<R, E>),
+    /// A notification.
+    Notification(Notification<P>),
+}
+
+impl<P: Serialize> Serialize for Request<P> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = se.serialize_tuple(4)?;
+        tuple.serialize_element(&REQUEST)?;
+        tuple.serialize_element(&self.msgid)?;
+        tuple.serialize_element(&self.method)?;
+        tuple.serialize_element(&self.params)?;
+        tuple.end()
+    }
+}
+
+impl<R: Serialize, E: Serialize> Serialize for Response<R, E> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = se.serialize_tuple(4)?;
+        tuple.serialize_element(&RESPONSE)?;
+        tuple.serialize_element(&self.msgid)?;
+        match self.result {
+            Ok(ref result) => {
+                tuple.serialize_element(&())?;
+                tuple.serialize_element(result)?;
+            }
+            Err(ref err) => {
+                tuple.serialize_element(err)?;
+                tuple.serialize_element(&())?;
+            }
+        }
+        tuple.end()
+    }
+}
+
+impl<P: Serialize> Serialize for Notification<P> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = se.serialize_tuple(3)?;
+        tuple.serialize_element(&NOTIFICATION)?;
+        tuple.serialize_element(&self.method)?;
+        tuple.serialize_element(&self.params)?;
+        tuple.end()
+    }
+}
+
+impl<P: Serialize, R: Serialize, E: Serialize> Serialize for Message<P, R, E> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Message::Request(ref req) => req.serialize(se),
+            Message::Response(ref res) => res.serialize(se),
+            Message::Notification(ref notification) => notification.serialize(se),
+        }
+    }
+}
+
+/// Deserializes any message, then checks it is of the expected kind.
+struct MessageVisitor<P, R, E> {
+    expected: Option<u8>,
+    marker: PhantomData<Message<P, R, E>>,
+}
+
+impl<P, R, E> MessageVisitor<P, R, E> {
+    #[inline]
+    fn new(expected: Option<u8>) -> Self {
+        MessageVisitor { expected, marker: PhantomData }
+    }
+}
+
+impl<'de, P, R, E> Visitor<'de> for MessageVisitor<P, R, E>
+where P: Deserialize<'de>,
+      R: Deserialize<'de>,
+      E: Deserialize<'de>
+{
+    type Value = Message<P, R, E>;
+
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        match self.expected {
+            Some(REQUEST) => fmt.write_str("a MessagePack-RPC request"),
+            Some(RESPONSE) => fmt.write_str("a MessagePack-RPC response"),
+            Some(NOTIFICATION) => fmt.write_str("a MessagePack-RPC notification"),
+            _ => fmt.write_str("a MessagePack-RPC message"),
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let ty: u8 = next_element(&mut seq, 0, &self)?;
+        if let Some(expected) = self.expected {
+            if ty != expected {
+                return Err(de::Error::invalid_value(de::Unexpected::Unsigned(ty.into()), &self));
+            }
+        }
+
+        match ty {
+            REQUEST => Ok(Message::Request(Request {
+                msgid: next_element(&mut seq, 1, &self)?,
+                method: next_element(&mut seq, 2, &self)?,
+                params: next_element(&mut seq, 3, &self)?,
+            })),
+            RESPONSE => {
+                let msgid = next_element(&mut seq, 1, &self)?;
+                let result = match next_element::<_, Option<E>>(&mut seq, 2, &self)? {
+                    Some(err) => {
+                        next_element::<_, IgnoredAny>(&mut seq, 3, &self)?;
+                        Err(err)
+                    }
+                    None => Ok(next_element(&mut seq, 3, &self)?),
+                };
+                Ok(Message::Response(Response { msgid, result }))
+            }
+            NOTIFICATION => Ok(Message::Notification(Notification {
+                method: next_element(&mut seq, 1, &self)?,
+                params: next_element(&mut seq, 2, &self)?,
+            })),
+            ty => Err(de::Error::invalid_value(de::Unexpected::Unsigned(ty.into()), &self)),
+        }
+    }
+}
+
+#[inline]
+fn next_element<'de, A, T>(seq: &mut A, idx: usize, exp: &dyn de::Expected) -> Result<T, A::Error>
+where A: SeqAccess<'de>,
+      T: Deserialize<'de>
+{
+    seq.next_element()?.ok_or_else(|| de::Error::invalid_length(idx, exp))
+}
+
+impl<'de, P: Deserialize<'de>, R: Deserialize<'de>, E: Deserialize<'de>> Deserialize<'de> for Message<P, R, E> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_seq(MessageVisitor::new(None))
+    }
+}
+
+impl<'de, P: Deserialize<'de>> Deserialize<'de> for Request<P> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        match de.deserialize_seq(MessageVisitor::<P, IgnoredAny, IgnoredAny>::new(Some(REQUEST)))? {
+            Message::Request(req) => Ok(req),
+            _ => Err(de::Error::custom("unexpected msgpack-rpc message type")),
+        }
+    }
+}
+
+impl<'de, R: Deserialize<'de>, E: Deserialize<'de>> Deserialize<'de> for Response<R, E> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        match de.deserialize_seq(MessageVisitor::<IgnoredAny, R, E>::new(Some(RESPONSE)))? {
+            Message::Response(res) => Ok(res),
+            _ => Err(de::Error::custom("unexpected msgpack-rpc message type")),
+        }
+    }
+}
+
+impl<'de, P: Deserialize<'de>> Deserialize<'de> for Notification<P> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        match de.deserialize_seq(MessageVisitor::<P, IgnoredAny, IgnoredAny>::new(Some(NOTIFICATION)))? {
+            Message::Notification(notification) => Ok(notification),
+            _ => Err(de::Error::custom("unexpected msgpack-rpc message type")),
+        }
+    }
+}
+
+/// Writes a single message into the given stream.
+///
+/// The stream is not flushed.
+///
+/// # Errors
+///
+/// Fails if writing fails, or if `T`'s implementation of `Serialize` fails.
+#[inline]
+pub fn write_message<W, T>(wr: &mut W, msg: &T) -> Result<(), encode::Error<io::Error>>
+where W: io::Write + ?Sized,
+      T: Serialize + ?Sized
+{
+    encode::write(wr, msg)
+}
+
+/// Reads a single message from the given stream.
+///
+/// Exactly the bytes of the message are read, so this can be called in a loop on the same stream.
+/// Wrap unbuffered streams, e.g. sockets, in a [`std::io::BufReader`] for efficiency.
+///
+/// # Errors
+///
+/// Fails if reading fails, or if the message does not match the expected types.
+#[inline]
+pub fn read_message<R, P, Res, E>(rd: &mut R) -> Result<Message<P, Res, E>, decode::Error<io::Error>>
+where R: io::Read + ?Sized,
+      P: DeserializeOwned,
+      Res: DeserializeOwned,
+      E: DeserializeOwned
+{
+    decode::from_read(rd)
+}
@@ -0,0 +1,420 @@
+//! Inference of the shape of MessagePack data.
+//!
+//! [`infer_schema`] walks a buffer and describes the values it holds: their types, the fields of
+//! maps with string keys, the element types of arrays, and ext tags. This helps to write the Rust
+//! types for payloads whose format isn't documented.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Serialize)]
+//! struct User {
+//!     id: u32,
+//!     name: Option<String>,
+//! }
+//!
+//! let users = vec![User { id: 1, name: Some("John".into()) }, User { id: 300, name: None }];
+//! let buf = rmp_serde::to_vec_named(&users).unwrap();
+//!
+//! let schema = rmp_serde::schema::infer_schema(&buf).unwrap();
+//! assert_eq!("[{id: int(1..=300), name: str | nil}]", schema.to_string());
+//! # }
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+use core::mem;
+use std::collections::BTreeSet;
+
+use rmp::decode::{Event, EventReader, ValueReadError};
+
+use crate::decode::{BytesReadError, Error};
+
+/// The maximum nesting depth of the values, the same as for [`Deserializer`](crate::Deserializer).
+const MAX_DEPTH: usize = 1024;
+
+/// A structural description of MessagePack values.
+///
+/// Values of different types are described by a [`Schema::Union`], while values of the same type
+/// are merged into one schema, e.g. the ranges of integers are combined.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Schema {
+    /// No value was seen, e.g. for the elements of empty arrays.
+    #[default]
+    Never,
+    /// Nil.
+    Nil,
+    /// Booleans.
+    Bool,
+    /// Integers, between `min` and `max` inclusive.
+    Int {
+        /// The smallest integer seen.
+        min: i128,
+        /// The largest integer seen.
+        max: i128,
+    },
+    /// 32-bit floats.
+    F32,
+    /// 64-bit floats.
+    F64,
+    /// Strings, with the number of them that were not valid UTF-8.
+    Str {
+        /// How many strings were not valid UTF-8.
+        invalid_utf8: usize,
+    },
+    /// Binaries.
+    Bin,
+    /// Arrays whose elements are described by the inner schema.
+    ///
+    /// The array is homogeneous unless that schema is a [`Schema::Union`].
+    Array(Box<Schema>),
+    /// Maps with string keys, described field by field.
+    Struct(Struct),
+    /// Maps with other keys, or a mix of string and other keys.
+    Map {
+        /// The schema of the keys.
+        keys: Box<Schema>,
+        /// The schema of the values.
+        values: Box<Schema>,
+    },
+    /// Exts with the given tags.
+    Ext(BTreeSet<i8>),
+    /// Values of different types.
+    Union(Vec<Schema>),
+}
+
+/// Maps with string keys, as written for structs by [`to_vec_named`](crate::to_vec_named).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Struct {
+    /// How many maps were seen.
+    pub count: usize,
+    /// The fields, in the order they first appeared.
+    pub fields: Vec<Field>,
+}
+
+/// A field of a [`Struct`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// The key.
+    pub name: String,
+    /// How many maps had this key.
+    pub count: usize,
+    /// The schema of the values.
+    pub schema: Schema,
+}
+
+impl Field {
+    /// Returns `true` if some maps didn't have this key.
+    #[inline]
+    pub fn is_optional(&self, parent: &Struct) -> bool {
+        self.count < parent.count
+    }
+}
+
+impl Schema {
+    /// Returns the maximum nesting depth of arrays and maps, which is 0 for scalars.
+    pub fn depth(&self) -> usize {
+        match *self {
+            Schema::Array(ref items) => 1 + items.depth(),
+            Schema::Struct(ref st) => 1 + st.fields.iter().map(|field| field.schema.depth()).max().unwrap_or(0),
+            Schema::Map { ref keys, ref values } => 1 + keys.depth().max(values.depth()),
+            Schema::Union(ref schemas) => schemas.iter().map(Schema::depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` if this schema describes values of a single type.
+    #[inline]
+    pub fn is_homogeneous(&self) -> bool {
+        !matches!(*self, Schema::Union(..))
+    }
+
+    /// Returns the schema describing the values of both `self` and `other`.
+    pub fn merge(self, other: Schema) -> Schema {
+        match (self, other) {
+            (Schema::Never, other) | (other, Schema::Never) => other,
+            (Schema::Union(mut schemas), Schema::Union(others)) => {
+                for other in others {
+                    merge_into_union(&mut schemas, other);
+                }
+                Schema::Union(schemas)
+            }
+            (Schema::Union(mut schemas), other) | (other, Schema::Union(mut schemas)) => {
+                merge_into_union(&mut schemas, other);
+                Schema::Union(schemas)
+            }
+            (lhs, rhs) => match merge_same(lhs, rhs) {
+                Ok(schema) => schema,
+                Err((lhs, rhs)) => Schema::Union(vec![lhs, rhs]),
+            },
+        }
+    }
+}
+
+fn merge_into_union(schemas: &mut Vec<Schema>, mut other: Schema) {
+    for schema in schemas.iter_mut() {
+        match merge_same(mem::take(schema), other) {
+            Ok(merged) => {
+                *schema = merged;
+                return;
+            }
+            Err((lhs, rhs)) => {
+                *schema = lhs;
+                other = rhs;
+            }
+        }
+    }
+    schemas.push(other);
+}
+
+/// Merges two schemas of the same type, or gives them back.
+fn merge_same(lhs: Schema, rhs: Schema) -> Result<Schema, (Schema, Schema)> {
+    let merged = match (lhs, rhs) {
+        (Schema::Nil, Schema::Nil) => Schema::Nil,
+        (Schema::Bool, Schema::Bool) => Schema::Bool,
+        (Schema::Int { min, max }, Schema::Int { min: rmin, max: rmax }) => {
+            Schema::Int { min: min.min(rmin), max: max.max(rmax) }
+        }
+        (Schema::F32 | Schema::F64, Schema::F32 | Schema::F64) => Schema::F64,
+        (Schema::Str { invalid_utf8 }, Schema::Str { invalid_utf8: rhs }) => Schema::Str { invalid_utf8: invalid_utf8 + rhs },
+        (Schema::Bin, Schema::Bin) => Schema::Bin,
+        (Schema::Array(items), Schema::Array(rhs)) => Schema::Array(Box::new(items.merge(*rhs))),
+        (Schema::Struct(mut st), Schema::Struct(rhs)) => {
+            st.count += rhs.count;
+            for field in rhs.fields {
+                match st.fields.iter_mut().find(|f| f.name == field.name) {
+                    Some(f) => {
+                        f.count += field.count;
+                        f.schema = mem::take(&mut f.schema).merge(field.schema);
+                    }
+                    None => st.fields.push(field),
+                }
+            }
+            Schema::Struct(st)
+        }
+        (Schema::Struct(st), Schema::Map { keys, values }) | (Schema::Map { keys, values }, Schema::Struct(st)) => {
+            let (skeys, svalues) = struct_as_map(st);
+            Schema::Map { keys: Box::new(keys.merge(skeys)), values: Box::new(values.merge(svalues)) }
+        }
+        (Schema::Map { keys, values }, Schema::Map { keys: rkeys, values: rvalues }) => {
+            Schema::Map { keys: Box::new(keys.merge(*rkeys)), values: Box::new(values.merge(*rvalues)) }
+        }
+        (Schema::Ext(mut tags), Schema::Ext(rhs)) => {
+            tags.extend(rhs);
+            Schema::Ext(tags)
+        }
+        (lhs, rhs) => return Err((lhs, rhs)),
+    };
+    Ok(merged)
+}
+
+/// Returns the key and value schemas of a struct seen as a map.
+fn struct_as_map(st: Struct) -> (Schema, Schema) {
+    let keys = if st.fields.is_empty() { Schema::Never } else { Schema::Str { invalid_utf8: 0 } };
+    let values = st.fields.into_iter().fold(Schema::Never, |values, field| values.merge(field.schema));
+    (keys, values)
+}
+
+/// An array or map whose elements are being read.
+enum Container {
+    Array {
+        remaining: u32,
+        items: Schema,
+    },
+    Map {
+        remaining: u32,
+        /// Maps are structs until a key that isn't a valid UTF-8 string shows up.
+        st: Option<Struct>,
+        keys: Schema,
+        values: Schema,
+        /// The key whose value is being read.
+        key: Option<Key>,
+    },
+}
+
+enum Key {
+    Name(String),
+    Other,
+}
+
+impl Container {
+    /// Adds the schema of the next element, and returns the schema of the container once
+    /// complete.
+    fn push(&mut self, schema: Schema) -> Option<Schema> {
+        match *self {
+            Container::Array { ref mut remaining, ref mut items } => {
+                *items = mem::take(items).merge(schema);
+                *remaining -= 1;
+                if *remaining == 0 {
+                    return Some(Schema::Array(Box::new(mem::take(items))));
+                }
+            }
+            Container::Map { ref mut remaining, ref mut st, ref mut keys, ref mut values, ref mut key } => {
+                match (key.take(), st.as_mut()) {
+                    (None, _) => {
+                        // A key that is not a field name, which makes this a map.
+                        if let Some(st) = st.take() {
+                            (*keys, *values) = struct_as_map(st);
+                        }
+                        *keys = mem::take(keys).merge(schema);
+                        *key = Some(Key::Other);
+                        return None;
+                    }
+                    (Some(Key::Name(name)), Some(st)) => match st.fields.iter_mut().find(|field| field.name == name) {
+                        // Duplicate keys are counted once.
+                        Some(field) => field.schema = mem::take(&mut field.schema).merge(schema),
+                        None => st.fields.push(Field { name, count: 1, schema }),
+                    },
+                    (Some(..), _) => *values = mem::take(values).merge(schema),
+                }
+                *remaining -= 1;
+                if *remaining == 0 {
+                    return Some(match st.take() {
+                        Some(st) => Schema::Struct(st),
+                        None => Schema::Map { keys: Box::new(mem::take(keys)), values: Box::new(mem::take(values)) },
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Infers the schema of the values in the buffer.
+///
+/// If the buffer holds several concatenated values, like a stream of messages, the returned schema
+/// describes all of them. An empty buffer gives [`Schema::Never`].
+///
+/// # Errors
+///
+/// Fails if the buffer isn't valid MessagePack, or if values are nested more than 1024 levels
+/// deep.
+pub fn infer_schema(buf: &[u8]) -> Result<Schema, Error<BytesReadError>> {
+    let mut rd = EventReader::new(buf);
+    let mut stack: Vec<Container> = Vec::new();
+    let mut schema = Schema::Never;
+
+    loop {
+        let position = rd.position();
+        let event = match rd.next_event()? {
+            Some(event) => event,
+            None if stack.is_empty() => return Ok(schema),
+            None => {
+                let err = BytesReadError::InsufficientBytes { expected: 1, actual: 0, position };
+                return Err(Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(err)));
+            }
+        };
+
+        // Names of struct fields are kept aside until their value is read.
+        if let Some(Container::Map { st: Some(..), key: ref mut key @ None, .. }) = stack.last_mut() {
+            if let Event::Str(name) = event {
+                if let Ok(name) = crate::from_utf8(name) {
+                    *key = Some(Key::Name(name.to_owned()));
+                    continue;
+                }
+            }
+        }
+
+        let mut val = match event {
+            Event::Nil => Schema::Nil,
+            Event::Bool(..) => Schema::Bool,
+            Event::UInt(val) => Schema::Int { min: i128::from(val), max: i128::from(val) },
+            Event::SInt(val) => Schema::Int { min: i128::from(val), max: i128::from(val) },
+            Event::F32(..) => Schema::F32,
+            Event::F64(..) => Schema::F64,
+            Event::Str(val) => Schema::Str { invalid_utf8: usize::from(crate::from_utf8(val).is_err()) },
+            Event::Bin(..) => Schema::Bin,
+            Event::Ext(tag, ..) => Schema::Ext(BTreeSet::from([tag])),
+            Event::ArrayStart(0) => Schema::Array(Box::new(Schema::Never)),
+            Event::MapStart(0) => Schema::Struct(Struct { count: 1, fields: Vec::new() }),
+            Event::ArrayStart(len) => {
+                open(&mut stack, Container::Array { remaining: len, items: Schema::Never })?;
+                continue;
+            }
+            Event::MapStart(len) => {
+                open(&mut stack, Container::Map {
+                    remaining: len,
+                    st: Some(Struct { count: 1, fields: Vec::new() }),
+                    keys: Schema::Never,
+                    values: Schema::Never,
+                    key: None,
+                })?;
+                continue;
+            }
+        };
+
+        // Completes the containers this value was the last element of.
+        loop {
+            match stack.last_mut() {
+                Some(container) => match container.push(val) {
+                    Some(complete) => {
+                        stack.pop();
+                        val = complete;
+                    }
+                    None => break,
+                },
+                None => {
+                    schema = mem::take(&mut schema).merge(val);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn open(stack: &mut Vec<Container>, container: Container) -> Result<(), Error<BytesReadError>> {
+    if stack.len() >= MAX_DEPTH {
+        return Err(Error::DepthLimitExceeded);
+    }
+    stack.push(container);
+    Ok(())
+}
+
+impl Display for Schema {
+    /// Formats the schema in a compact, Rust-like notation, e.g. `[{id: int(1..=9), tags?: [str]}]`.
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Schema::Never => fmt.write_str("never"),
+            Schema::Nil => fmt.write_str("nil"),
+            Schema::Bool => fmt.write_str("bool"),
+            Schema::Int { min, max } if min == max => write!(fmt, "int({})", min),
+            Schema::Int { min, max } => write!(fmt, "int({}..={})", min, max),
+            Schema::F32 => fmt.write_str("f32"),
+            Schema::F64 => fmt.write_str("f64"),
+            Schema::Str { invalid_utf8: 0 } => fmt.write_str("str"),
+            Schema::Str { .. } => fmt.write_str("str(invalid utf8)"),
+            Schema::Bin => fmt.write_str("bin"),
+            Schema::Array(ref items) => write!(fmt, "[{}]", items),
+            Schema::Struct(ref st) => {
+                fmt.write_str("{")?;
+                for (idx, field) in st.fields.iter().enumerate() {
+                    if idx > 0 {
+                        fmt.write_str(", ")?;
+                    }
+                    let optional = if field.is_optional(st) { "?" } else { "" };
+                    write!(fmt, "{}{}: {}", field.name, optional, field.schema)?;
+                }
+                fmt.write_str("}")
+            }
+            Schema::Map { ref keys, ref values } => write!(fmt, "map<{}, {}>", keys, values),
+            Schema::Ext(ref tags) => {
+                fmt.write_str("ext(")?;
+                for (idx, tag) in tags.iter().enumerate() {
+                    if idx > 0 {
+                        fmt.write_str(" | ")?;
+                    }
+                    write!(fmt, "{}", tag)?;
+                }
+                fmt.write_str(")")
+            }
+            Schema::Union(ref schemas) => {
+                for (idx, schema) in schemas.iter().enumerate() {
+                    if idx > 0 {
+                        fmt.write_str(" | ")?;
+                    }
+                    write!(fmt, "{}", schema)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
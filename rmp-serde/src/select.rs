@@ -0,0 +1,147 @@
+//! Deserialization of a few selected values out of a large document.
+//!
+//! A [`Selection`] lists the values to read by their [`pointer`](crate::pointer) paths, and
+//! deserializes them as the fields of a struct. Only the selected values are decoded, everything
+//! else is skipped by scanning the headers, which works for structs written as arrays by
+//! [`to_vec`](crate::to_vec) as well as for maps.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Serialize)]
+//! struct Event {
+//!     id: u64,
+//!     kind: String,
+//!     payload: Vec<u8>,
+//!     user: User,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     name: String,
+//!     country: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Summary<'a> {
+//!     id: u64,
+//!     country: &'a str,
+//!     referrer: Option<&'a str>,
+//! }
+//!
+//! let event = Event {
+//!     id: 7,
+//!     kind: "click".into(),
+//!     payload: vec![0; 1024],
+//!     user: User { name: "John".into(), country: "SE".into() },
+//! };
+//! let buf = rmp_serde::to_vec(&event).unwrap();
+//!
+//! let summary: Summary = rmp_serde::select::Selection::new()
+//!     .field("id", "/0")
+//!     .field("country", "/3/1")
+//!     .field("referrer", "/4")
+//!     .from_slice(&buf)
+//!     .unwrap();
+//! assert_eq!(Summary { id: 7, country: "SE", referrer: None }, summary);
+//! # }
+//! ```
+
+use serde::de::{DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+use crate::decode::{BytesReadError, Error};
+
+/// A list of values to deserialize, each under a field name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selection<'p> {
+    fields: Vec<(&'p str, &'p str)>,
+}
+
+impl<'p> Selection<'p> {
+    /// Creates an empty selection.
+    #[inline]
+    pub fn new() -> Self {
+        Selection { fields: Vec::new() }
+    }
+
+    /// Selects the value at the path, as the field with the given name.
+    #[inline]
+    pub fn field(mut self, name: &'p str, pointer: &'p str) -> Self {
+        self.fields.push((name, pointer));
+        self
+    }
+
+    /// Deserializes the selected values from the document at the start of the buffer.
+    ///
+    /// The values are presented to `T` as a map from the field names to the values. Paths that
+    /// don't exist are left out, so the corresponding fields should be `Option`s or have a default.
+    ///
+    /// Each path is resolved from the start of the buffer, so the cost grows with the number of
+    /// fields, but not with the size of the values that are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a path can't be resolved, see [`pointer::find`](crate::pointer::find), or if the
+    /// values can't be deserialized into `T`.
+    pub fn from_slice<'a, T>(&self, buf: &'a [u8]) -> Result<T, Error<BytesReadError>>
+    where T: Deserialize<'a>
+    {
+        let mut found = Vec::with_capacity(self.fields.len());
+        for &(name, pointer) in &self.fields {
+            if let Some(val) = crate::pointer::find(buf, pointer)? {
+                found.push((name, val));
+            }
+        }
+        T::deserialize(Selected { found: &found })
+    }
+}
+
+/// Deserializes the found values as a map.
+struct Selected<'s, 'p, 'a> {
+    found: &'s [(&'p str, &'a [u8])],
+}
+
+impl<'de> Deserializer<'de> for Selected<'_, '_, 'de> {
+    type Error = Error<BytesReadError>;
+
+    #[inline]
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(SelectedAccess { found: self.found.iter(), val: None })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+struct SelectedAccess<'s, 'p, 'a> {
+    found: core::slice::Iter<'s, (&'p str, &'a [u8])>,
+    val: Option<&'a [u8]>,
+}
+
+impl<'de> MapAccess<'de> for SelectedAccess<'_, '_, 'de> {
+    type Error = Error<BytesReadError>;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        match self.found.next() {
+            Some(&(name, val)) => {
+                self.val = Some(val);
+                seed.deserialize(name.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        let val = self.val.take().ok_or(Error::Uncategorized("value requested before its key"))?;
+        seed.deserialize(&mut crate::Deserializer::from_bytes(val))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.found.len())
+    }
+}
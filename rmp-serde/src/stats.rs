@@ -0,0 +1,180 @@
+//! Statistics about the encoding of MessagePack data.
+//!
+//! [`analyze`] counts the values of each type in a buffer, and how many bytes go to their headers
+//! and to their data. The bytes spent on map keys are totalled as well, which shows what
+//! [`to_vec_named`](crate::to_vec_named) costs compared to [`to_vec`](crate::to_vec).
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Clone, Serialize)]
+//! struct Point {
+//!     latitude: f64,
+//!     longitude: f64,
+//! }
+//!
+//! let points = vec![Point { latitude: 1.0, longitude: 2.0 }; 100];
+//! let stats = rmp_serde::stats::analyze(&rmp_serde::to_vec_named(&points).unwrap()).unwrap();
+//!
+//! assert_eq!(200, stats.float.count);
+//! assert_eq!(100, stats.map.count);
+//! assert_eq!(1900, stats.map_keys.bytes());
+//! assert_eq!(stats.total_bytes, stats.nil.bytes() + stats.bool.bytes() + stats.int.bytes() +
+//!            stats.float.bytes() + stats.str.bytes() + stats.bin.bytes() + stats.array.bytes() +
+//!            stats.map.bytes() + stats.ext.bytes());
+//! # }
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+use std::collections::BTreeMap;
+
+use rmp::decode::{Event, EventReader, ValueReadError};
+
+use crate::decode::{value_len, BytesReadError, Error};
+
+/// The number and size of values of some kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Count {
+    /// How many values there were.
+    pub count: usize,
+    /// The bytes of the markers, and of the lengths and ext types that follow them.
+    pub header_bytes: usize,
+    /// The bytes of the values themselves, which is 0 for arrays and maps, as their elements are
+    /// counted separately.
+    pub data_bytes: usize,
+}
+
+impl Count {
+    /// Returns the total number of bytes.
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.header_bytes + self.data_bytes
+    }
+
+    #[inline]
+    fn add(&mut self, header_bytes: usize, data_bytes: usize) {
+        self.count += 1;
+        self.header_bytes += header_bytes;
+        self.data_bytes += data_bytes;
+    }
+}
+
+/// Counts of the values in a buffer, by type.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Nil values.
+    pub nil: Count,
+    /// Booleans.
+    pub bool: Count,
+    /// Integers, including fixints.
+    pub int: Count,
+    /// 32 and 64-bit floats.
+    pub float: Count,
+    /// Strings.
+    pub str: Count,
+    /// Binaries.
+    pub bin: Count,
+    /// Arrays.
+    pub array: Count,
+    /// Maps.
+    pub map: Count,
+    /// Exts.
+    pub ext: Count,
+    /// Exts, by type.
+    pub ext_types: BTreeMap<i8, Count>,
+    /// Map keys, whatever their type, with the bytes of nested values.
+    ///
+    /// The keys are counted by their type as well.
+    pub map_keys: Count,
+    /// The number of values at the top level.
+    pub values: usize,
+    /// The size of the buffer.
+    pub total_bytes: usize,
+}
+
+/// Counts the values in the buffer, which may hold several concatenated values.
+///
+/// # Errors
+///
+/// Fails if the buffer isn't valid MessagePack or ends in the middle of a value.
+pub fn analyze(buf: &[u8]) -> Result<Stats, Error<BytesReadError>> {
+    let mut stats = Stats { total_bytes: buf.len(), ..Stats::default() };
+    let mut rd = EventReader::new(buf);
+    // Number of values left in each enclosing array or map, and whether it's a map.
+    let mut stack: Vec<(u64, bool)> = Vec::new();
+
+    loop {
+        let pos = rd.position() as usize;
+        match stack.last_mut() {
+            Some((left, is_map)) => {
+                if *is_map && *left % 2 == 0 {
+                    let key_len = value_len(buf, pos)?;
+                    stats.map_keys.add(0, key_len);
+                }
+                *left -= 1;
+            }
+            None if pos == buf.len() => return Ok(stats),
+            None => stats.values += 1,
+        }
+
+        let Some(event) = rd.next_event()? else {
+            let err = BytesReadError::InsufficientBytes { expected: 1, actual: 0, position: pos as u64 };
+            return Err(Error::InvalidValueRead(ValueReadError::InvalidMarkerRead(err)));
+        };
+        let len = rd.position() as usize - pos;
+        match event {
+            Event::Nil => stats.nil.add(len, 0),
+            Event::Bool(..) => stats.bool.add(len, 0),
+            Event::UInt(..) | Event::SInt(..) => stats.int.add(1, len - 1),
+            Event::F32(..) | Event::F64(..) => stats.float.add(1, len - 1),
+            Event::Str(data) => stats.str.add(len - data.len(), data.len()),
+            Event::Bin(data) => stats.bin.add(len - data.len(), data.len()),
+            Event::Ext(ty, data) => {
+                stats.ext.add(len - data.len(), data.len());
+                stats.ext_types.entry(ty).or_default().add(len - data.len(), data.len());
+            }
+            Event::ArrayStart(n) => {
+                stats.array.add(len, 0);
+                stack.push((u64::from(n), false));
+            }
+            Event::MapStart(n) => {
+                stats.map.add(len, 0);
+                stack.push((2 * u64::from(n), true));
+            }
+        }
+
+        while let Some(&(0, _)) = stack.last() {
+            stack.pop();
+        }
+    }
+}
+
+impl Display for Stats {
+    /// Formats the counts as a table, leaving out the types that don't occur.
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(fmt, "{:<10} {:>10} {:>12} {:>12}", "type", "count", "header bytes", "data bytes")?;
+        let rows = [
+            ("nil", &self.nil),
+            ("bool", &self.bool),
+            ("int", &self.int),
+            ("float", &self.float),
+            ("str", &self.str),
+            ("bin", &self.bin),
+            ("array", &self.array),
+            ("map", &self.map),
+            ("ext", &self.ext),
+        ];
+        for (name, count) in rows {
+            if count.count > 0 {
+                writeln!(fmt, "{:<10} {:>10} {:>12} {:>12}", name, count.count, count.header_bytes, count.data_bytes)?;
+            }
+        }
+        for (ty, count) in &self.ext_types {
+            writeln!(fmt, "{:<10} {:>10} {:>12} {:>12}", format!("ext({})", ty), count.count, count.header_bytes, count.data_bytes)?;
+        }
+        if self.map_keys.count > 0 {
+            writeln!(fmt, "{:<10} {:>10} {:>12} {:>12}", "map keys", self.map_keys.count, "", self.map_keys.data_bytes)?;
+        }
+        write!(fmt, "{} values in {} bytes", self.values, self.total_bytes)
+    }
+}
@@ -0,0 +1,286 @@
+//! [`futures`](https://docs.rs/futures) adapters between async byte streams and streams of values.
+//!
+//! [`MsgPackStream`] decodes values from an [`AsyncRead`], and [`MsgPackSink`] encodes values into
+//! an [`AsyncWrite`]. Values are plain concatenated MessagePack values, the same format as produced
+//! by [`crate::to_vec`] and read by [`crate::from_read`].
+//!
+//! Errors are reported as [`io::Error`]s. Decoding and encoding errors have the
+//! [`io::ErrorKind::InvalidData`] kind, wrapping the original error.
+
+use core::fmt::{self, Debug, Formatter};
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+
+use bytes::{Buf, BytesMut};
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::frame::{self, Decoded};
+
+/// How many bytes are read from the underlying reader at once.
+const READ_SIZE: usize = 8 * 1024;
+
+/// Once this many bytes are buffered, the sink writes them out before accepting more values.
+const WRITE_HIGH_WATER_MARK: usize = 8 * 1024;
+
+/// A [`Stream`] of values of type `T` decoded from an [`AsyncRead`].
+///
+/// The stream ends when the reader reaches EOF between two values. EOF in the middle of a value
+/// is reported as an [`io::ErrorKind::UnexpectedEof`] error. After data that can't be decoded, the
+/// error is reported once and the stream ends, as the position of the next value is unknown.
+pub struct MsgPackStream<R, T> {
+    rd: R,
+    buf: BytesMut,
+    max_frame_length: usize,
+    /// How many bytes must be buffered before it's worth trying to decode again.
+    min_len: usize,
+    eof: bool,
+    failed: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> MsgPackStream<R, T> {
+    /// Creates a new stream decoding values from the given reader.
+    #[inline]
+    pub fn new(rd: R) -> Self {
+        MsgPackStream {
+            rd,
+            buf: BytesMut::new(),
+            max_frame_length: usize::MAX,
+            min_len: 0,
+            eof: false,
+            failed: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Limits how many bytes may be buffered while waiting for an incomplete value.
+    ///
+    /// Once the limit is exceeded the stream fails, instead of buffering a value of unbounded size.
+    #[inline]
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Returns the maximum frame length.
+    #[inline(always)]
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    /// Gets a reference to the underlying reader.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly may corrupt the stream of values.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.rd
+    }
+
+    /// Returns the bytes that were read, but not decoded yet.
+    #[inline(always)]
+    pub fn read_buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Consumes this stream returning the underlying reader.
+    ///
+    /// Bytes that were read but not decoded yet are lost, see [`Self::read_buffer`].
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+}
+
+impl<R, T> Debug for MsgPackStream<R, T>
+where R: Debug
+{
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("MsgPackStream")
+            .field("rd", &self.rd)
+            .field("buffered", &self.buf.len())
+            .field("max_frame_length", &self.max_frame_length)
+            .field("eof", &self.eof)
+            .field("failed", &self.failed)
+            .finish()
+    }
+}
+
+impl<R, T> Stream for MsgPackStream<R, T>
+where R: AsyncRead + Unpin,
+      T: DeserializeOwned
+{
+    type Item = Result<T, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.failed {
+            return Poll::Ready(None);
+        }
+        loop {
+            if this.buf.len() >= this.min_len {
+                match frame::decode(&mut this.buf) {
+                    Ok(Decoded::Value(val)) => {
+                        this.min_len = 0;
+                        return Poll::Ready(Some(Ok(val)));
+                    }
+                    Ok(Decoded::Incomplete(needed)) => {
+                        this.min_len = this.buf.len().saturating_add(needed);
+                        if this.min_len > this.max_frame_length {
+                            this.failed = true;
+                            let err = io::Error::new(io::ErrorKind::InvalidData, "frame length limit exceeded");
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                    }
+                    Err(err) => {
+                        this.failed = true;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+            }
+            if this.eof {
+                if this.buf.is_empty() {
+                    return Poll::Ready(None);
+                }
+                this.buf.clear();
+                return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+            }
+
+            let len = this.buf.len();
+            this.buf.resize(len + READ_SIZE, 0);
+            let res = Pin::new(&mut this.rd).poll_read(cx, &mut this.buf[len..]);
+            let n = match res {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(err)) => {
+                    this.buf.truncate(len);
+                    return Poll::Ready(Some(Err(err)));
+                }
+                Poll::Pending => {
+                    this.buf.truncate(len);
+                    return Poll::Pending;
+                }
+            };
+            this.buf.truncate(len + n);
+            if n == 0 {
+                this.eof = true;
+            }
+        }
+    }
+}
+
+/// A [`Sink`] encoding values of type `T` into an [`AsyncWrite`].
+///
+/// Values are buffered and written out when the buffer fills up, on flush, or on close.
+pub struct MsgPackSink<W, T> {
+    wr: W,
+    buf: BytesMut,
+    marker: PhantomData<fn(T)>,
+}
+
+impl<W, T> MsgPackSink<W, T> {
+    /// Creates a new sink encoding values into the given writer.
+    #[inline]
+    pub fn new(wr: W) -> Self {
+        MsgPackSink {
+            wr,
+            buf: BytesMut::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Gets a reference to the underlying writer.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly may corrupt the stream of values.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr
+    }
+
+    /// Consumes this sink returning the underlying writer.
+    ///
+    /// Values that were not flushed yet are lost.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.wr
+    }
+}
+
+impl<W: AsyncWrite + Unpin, T> MsgPackSink<W, T> {
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        while !self.buf.is_empty() {
+            let n = match Pin::new(&mut self.wr).poll_write(cx, &self.buf) {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            };
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.buf.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W, T> Debug for MsgPackSink<W, T>
+where W: Debug
+{
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("MsgPackSink")
+            .field("wr", &self.wr)
+            .field("buffered", &self.buf.len())
+            .finish()
+    }
+}
+
+impl<W, T> Sink<T> for MsgPackSink<W, T>
+where W: AsyncWrite + Unpin,
+      T: Serialize
+{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        if this.buf.len() >= WRITE_HIGH_WATER_MARK {
+            return this.poll_write_buf(cx);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), io::Error> {
+        frame::encode(&item, &mut self.get_mut().buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.wr).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.wr).poll_close(cx),
+            other => other,
+        }
+    }
+}
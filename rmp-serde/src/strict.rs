@@ -0,0 +1,128 @@
+//! Field helpers that keep MessagePack bin and str apart.
+//!
+//! serde's own `String` accepts byte buffers, and `serde_bytes` accepts strings. That's
+//! convenient, but breaks `#[serde(untagged)]` enums, which try each variant in order: a bin value
+//! happily matches a `String` variant if it's valid UTF-8, and a str matches a bytes variant.
+//!
+//! The helpers in this module only accept their own type, so the first variant that matches is
+//! the right one.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! #[serde(untagged)]
+//! enum Field {
+//!     Text(#[serde(with = "rmp_serde::strict::string")] String),
+//!     Blob(#[serde(with = "rmp_serde::strict::bytes")] Vec<u8>),
+//! }
+//!
+//! let buf = rmp_serde::to_vec(&Field::Blob(b"hi".to_vec())).unwrap();
+//! assert_eq!(Field::Blob(b"hi".to_vec()), rmp_serde::from_slice(&buf).unwrap());
+//!
+//! let buf = rmp_serde::to_vec(&Field::Text("hi".into())).unwrap();
+//! assert_eq!(Field::Text("hi".into()), rmp_serde::from_slice(&buf).unwrap());
+//! # }
+//! ```
+
+/// Serializes as bin, and deserializes only from bin.
+///
+/// Use with `#[serde(with = "rmp_serde::strict::bytes")]` on fields of a type implementing
+/// `AsRef<[u8]>` and `From<Vec<u8>>`, e.g. `Vec<u8>`.
+pub mod bytes {
+    use core::fmt::{self, Formatter};
+    use core::marker::PhantomData;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    /// Serializes the bytes as bin.
+    #[inline]
+    pub fn serialize<T, S>(val: &T, se: S) -> Result<S::Ok, S::Error>
+    where T: AsRef<[u8]> + ?Sized,
+          S: Serializer
+    {
+        se.serialize_bytes(val.as_ref())
+    }
+
+    /// Deserializes bin, rejecting any other type.
+    #[inline]
+    pub fn deserialize<'de, T, D>(de: D) -> Result<T, D::Error>
+    where T: From<Vec<u8>>,
+          D: Deserializer<'de>
+    {
+        de.deserialize_byte_buf(BinVisitor(PhantomData))
+    }
+
+    struct BinVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: From<Vec<u8>>> Visitor<'de> for BinVisitor<T> {
+        type Value = T;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a MessagePack bin")
+        }
+
+        #[inline]
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+            Ok(v.to_vec().into())
+        }
+
+        #[inline]
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<T, E> {
+            Ok(v.into())
+        }
+    }
+}
+
+/// Serializes as str, and deserializes only from str.
+///
+/// Use with `#[serde(with = "rmp_serde::strict::string")]` on fields of a type implementing
+/// `AsRef<str>` and `From<String>`, e.g. `String`.
+pub mod string {
+    use core::fmt::{self, Formatter};
+    use core::marker::PhantomData;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    /// Serializes the string as str.
+    #[inline]
+    pub fn serialize<T, S>(val: &T, se: S) -> Result<S::Ok, S::Error>
+    where T: AsRef<str> + ?Sized,
+          S: Serializer
+    {
+        se.serialize_str(val.as_ref())
+    }
+
+    /// Deserializes str, rejecting any other type.
+    #[inline]
+    pub fn deserialize<'de, T, D>(de: D) -> Result<T, D::Error>
+    where T: From<String>,
+          D: Deserializer<'de>
+    {
+        de.deserialize_string(StrVisitor(PhantomData))
+    }
+
+    struct StrVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: From<String>> Visitor<'de> for StrVisitor<T> {
+        type Value = T;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a MessagePack str")
+        }
+
+        #[inline]
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            Ok(String::from(v).into())
+        }
+
+        #[inline]
+        fn visit_string<E: de::Error>(self, v: String) -> Result<T, E> {
+            Ok(v.into())
+        }
+    }
+}
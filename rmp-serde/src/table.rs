@@ -0,0 +1,222 @@
+//! Tables of structs, with the field names written once.
+//!
+//! [`to_vec`](crate::to_vec) writes structs as arrays, which is compact but doesn't say which
+//! value is which, while [`to_vec_named`](crate::to_vec_named) repeats the field names in every
+//! struct. [`to_vec`] writes a slice of structs as an array whose first element holds the field
+//! names, followed by one array of values per row:
+//!
+//! ```text
+//! [["id", "name"], [1, "a"], [2, "b"]]
+//! ```
+//!
+//! [`from_slice`] and [`from_read`] match the values to the fields by name, so fields can be
+//! reordered, and fields added at the end of the rows can be missing or ignored like with
+//! `to_vec_named` data.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Row {
+//!     id: u32,
+//!     name: String,
+//! }
+//!
+//! let rows = vec![Row { id: 1, name: "a".into() }, Row { id: 2, name: "b".into() }];
+//! let buf = rmp_serde::table::to_vec(&rows).unwrap();
+//! assert_eq!([0x93, 0x92, 0xa2, b'i', b'd', 0xa4], buf[..6]);
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Name {
+//!     name: String,
+//! }
+//!
+//! let names: Vec<Name> = rmp_serde::table::from_slice(&buf).unwrap();
+//! assert_eq!(vec![Name { name: "a".into() }, Name { name: "b".into() }], names);
+//! # }
+//! ```
+
+use core::fmt::{self, Formatter};
+use core::marker::PhantomData;
+use std::io;
+
+use rmp::decode::{self, Bytes, RmpRead, Scan};
+use rmp::encode::{write_array_len, RmpWrite};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use serde::{Deserialize, Serialize};
+
+use crate::decode::{BytesReadError, Error as DecodeError};
+use crate::encode::{self, Error};
+
+/// Serializes the rows as a table.
+///
+/// The field names are taken from the first row, all rows must be structs with the same fields,
+/// so fields skipped with `skip_serializing_if` are not supported.
+pub fn to_vec<T: Serialize>(rows: &[T]) -> Result<Vec<u8>, Error<io::Error>> {
+    let len = u32::try_from(rows.len())
+        .ok()
+        .and_then(|len| len.checked_add(1))
+        .ok_or(Error::InvalidDataModel("too many elements for a MessagePack array"))?;
+
+    let mut buf = Vec::with_capacity(128);
+    write_array_len(&mut buf, len)?;
+    match rows.first() {
+        Some(row) => write_names(&mut buf, &crate::to_vec_named(row)?)?,
+        None => write_array_len(&mut buf, 0).map(drop)?,
+    }
+    for row in rows {
+        encode::write(&mut buf, row)?;
+    }
+    Ok(buf)
+}
+
+/// Copies the keys of the map in `named` as an array.
+fn write_names(buf: &mut Vec<u8>, named: &[u8]) -> Result<(), Error<io::Error>> {
+    let not_struct = || Error::InvalidDataModel("table rows must be structs");
+    let mut rd = Bytes::new(named);
+    let len = decode::read_map_len(&mut rd).map_err(|_| not_struct())?;
+    write_array_len(&mut *buf, len)?;
+
+    let mut rest = rd.remaining_slice();
+    for _ in 0..len {
+        let Ok(Scan::Complete(key_len)) = decode::scan_value(rest) else { return Err(not_struct()) };
+        buf.write_bytes(&rest[..key_len]).map_err(rmp::encode::ValueWriteError::InvalidDataWrite)?;
+        rest = &rest[key_len..];
+        let Ok(Scan::Complete(value_len)) = decode::scan_value(rest) else { return Err(not_struct()) };
+        rest = &rest[value_len..];
+    }
+    Ok(())
+}
+
+/// Deserializes the rows of a table from a slice.
+#[inline]
+pub fn from_slice<'a, T: Deserialize<'a>>(buf: &'a [u8]) -> Result<Vec<T>, DecodeError<BytesReadError>> {
+    crate::from_slice(buf).map(|Table(rows)| rows)
+}
+
+/// Deserializes the rows of a table from a reader.
+#[inline]
+pub fn from_read<R, T>(rd: R) -> Result<Vec<T>, DecodeError<R::Error>>
+where R: RmpRead,
+      T: DeserializeOwned
+{
+    crate::from_read(rd).map(|Table(rows)| rows)
+}
+
+struct Table<T>(Vec<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Table<T> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_seq(TableVisitor(PhantomData)).map(Table)
+    }
+}
+
+struct TableVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for TableVisitor<T> {
+    type Value = Vec<T>;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a table of field names and rows")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+        let names: Vec<String> = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+        let mut rows = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(row) = seq.next_element_seed(RowSeed { names: &names, row: PhantomData })? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+struct RowSeed<'a, T> {
+    names: &'a [String],
+    row: PhantomData<T>,
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for RowSeed<'_, T> {
+    type Value = T;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<T, D::Error> {
+        T::deserialize(RowDeserializer { de, names: self.names })
+    }
+}
+
+/// Presents a row as a map from the field names to its values.
+struct RowDeserializer<'a, D> {
+    de: D,
+    names: &'a [String],
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for RowDeserializer<'_, D> {
+    type Error = D::Error;
+
+    #[inline]
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.de.deserialize_seq(RowVisitor { visitor, names: self.names })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct RowVisitor<'a, V> {
+    visitor: V,
+    names: &'a [String],
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for RowVisitor<'_, V> {
+    type Value = V::Value;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a table row")
+    }
+
+    #[inline]
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.visitor.visit_map(RowAccess { seq, names: self.names.iter() })
+    }
+}
+
+/// Pairs the values of a row with the field names.
+struct RowAccess<'a, A> {
+    seq: A,
+    names: core::slice::Iter<'a, String>,
+}
+
+impl<'de, A: SeqAccess<'de>> MapAccess<'de> for RowAccess<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        // Rows shorter than the header are allowed, their last fields are missing.
+        if self.seq.size_hint() == Some(0) {
+            return Ok(None);
+        }
+        match self.names.next() {
+            Some(name) => seed.deserialize(de::value::StrDeserializer::new(name)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, A::Error> {
+        self.seq.next_element_seed(seed)?
+            .ok_or_else(|| de::Error::custom("table row is shorter than its header"))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.names.len())
+    }
+}
@@ -0,0 +1,121 @@
+//! UUIDs as MessagePack exts, backed by the [`uuid`](https://docs.rs/uuid) crate.
+//!
+//! A [`Uuid`] is serialized as a FixExt16 holding its 16 bytes, or as a hyphenated string if the
+//! serializer is human-readable. Decoding accepts the ext, a 16-byte bin and a string in any
+//! format `Uuid::parse_str` understands.
+//!
+//! MessagePack doesn't assign an ext type to UUIDs, so the functions in this module use
+//! [`UUID_EXT_TAG`]. Use [`UuidExt`] to pick another one.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! use rmp_serde::uuid::{Uuid, UuidExt};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Order {
+//!     #[serde(with = "rmp_serde::uuid")]
+//!     id: Uuid,
+//!     customer: UuidExt<5>,
+//! }
+//!
+//! let order = Order { id: Uuid::from_u128(1), customer: UuidExt(Uuid::from_u128(2)) };
+//! let buf = rmp_serde::to_vec(&order).unwrap();
+//! assert_eq!([0x92, 0xd8, 0x02], buf[..3]);
+//! assert_eq!([0xd8, 0x05], buf[19..21]);
+//! assert_eq!(order, rmp_serde::from_slice(&buf).unwrap());
+//! # }
+//! ```
+
+use core::fmt::{self, Formatter};
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::{Ext, ExtRef};
+
+pub use ::uuid::Uuid;
+
+/// Ext type used by [`serialize`] and [`deserialize`].
+pub const UUID_EXT_TAG: i8 = 2;
+
+/// Serializes the UUID as an ext with [`UUID_EXT_TAG`], or as a hyphenated string if the
+/// serializer is human-readable.
+///
+/// Use with `#[serde(with = "rmp_serde::uuid")]`.
+#[inline]
+pub fn serialize<S: Serializer>(val: &Uuid, se: S) -> Result<S::Ok, S::Error> {
+    UuidExt::<UUID_EXT_TAG>(*val).serialize(se)
+}
+
+/// Deserializes an ext with [`UUID_EXT_TAG`], a 16-byte bin or a string.
+#[inline]
+pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Uuid, D::Error> {
+    UuidExt::<UUID_EXT_TAG>::deserialize(de).map(|UuidExt(uuid)| uuid)
+}
+
+/// Serializes the wrapped UUID as an ext with the type `TAG`, or as a hyphenated string if the
+/// serializer is human-readable.
+///
+/// Decoding accepts the ext with the same type, a 16-byte bin or a string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UuidExt<const TAG: i8 = UUID_EXT_TAG>(pub Uuid);
+
+impl<const TAG: i8> From<Uuid> for UuidExt<TAG> {
+    #[inline]
+    fn from(uuid: Uuid) -> Self {
+        UuidExt(uuid)
+    }
+}
+
+impl<const TAG: i8> Serialize for UuidExt<TAG> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        if se.is_human_readable() {
+            se.collect_str(&self.0.hyphenated())
+        } else {
+            ExtRef::new(TAG, self.0.as_bytes()).serialize(se)
+        }
+    }
+}
+
+impl<'de, const TAG: i8> Deserialize<'de> for UuidExt<TAG> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_any(UuidVisitor::<TAG>).map(UuidExt)
+    }
+}
+
+struct UuidVisitor<const TAG: i8>;
+
+impl<const TAG: i8> UuidVisitor<TAG> {
+    fn parse_bytes<E: de::Error>(&self, v: &[u8]) -> Result<Uuid, E> {
+        Uuid::from_slice(v).map_err(|_| de::Error::invalid_length(v.len(), self))
+    }
+}
+
+impl<'de, const TAG: i8> Visitor<'de> for UuidVisitor<TAG> {
+    type Value = Uuid;
+
+    #[cold]
+    fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        write!(fmt, "a UUID as an ext of type {}, 16 bytes or a string", TAG)
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Uuid, E> {
+        Uuid::parse_str(v).map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+    }
+
+    #[inline]
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Uuid, E> {
+        self.parse_bytes(v)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, de: D) -> Result<Uuid, D::Error> {
+        let ext = Ext::from_newtype(de)?;
+        if ext.tag() != TAG {
+            return Err(de::Error::invalid_value(de::Unexpected::Signed(ext.tag().into()), &self));
+        }
+        self.parse_bytes(ext.data())
+    }
+}
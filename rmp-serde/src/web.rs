@@ -0,0 +1,317 @@
+//! A [`MsgPack`] extractor and responder for web frameworks.
+//!
+//! With the `axum` feature, [`MsgPack<T>`] implements axum's `FromRequest` and `IntoResponse`,
+//! and with the `actix-web` feature, actix-web's `FromRequest` and `Responder`, like the `Json`
+//! types of these frameworks:
+//!
+//! ```ignore
+//! async fn create_user(MsgPack(user): MsgPack<NewUser>) -> MsgPack<User> {
+//!     MsgPack(db.insert(user).await)
+//! }
+//! ```
+//!
+//! Requests are rejected unless their `Content-Type` is `application/msgpack`,
+//! `application/x-msgpack`, `application/vnd.msgpack` or ends with `+msgpack`. Their body is
+//! limited in size and nesting depth by a [`MsgPackConfig`], which can be set with an `Extension`
+//! layer in axum or as `app_data` in actix-web. Responses write structs as maps, like
+//! [`to_vec_named`](crate::to_vec_named), as clients in other languages usually expect field
+//! names.
+
+use core::fmt::{self, Display, Formatter};
+use core::ops::{Deref, DerefMut};
+use std::error;
+
+use serde::de::DeserializeOwned;
+
+use crate::decode::{BytesReadError, Error};
+use crate::Deserializer;
+
+/// The `Content-Type` of MessagePack responses.
+pub const CONTENT_TYPE: &str = "application/msgpack";
+
+/// A value read from or written as a MessagePack request or response body.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MsgPack<T>(pub T);
+
+impl<T> MsgPack<T> {
+    /// Unwraps the value.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for MsgPack<T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for MsgPack<T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for MsgPack<T> {
+    #[inline(always)]
+    fn from(val: T) -> Self {
+        MsgPack(val)
+    }
+}
+
+/// Limits applied by the [`MsgPack`] extractor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MsgPackConfig {
+    limit: usize,
+    max_depth: usize,
+}
+
+impl MsgPackConfig {
+    /// Creates the default config, with a limit of 2 MiB and a maximum depth of 128.
+    #[inline]
+    pub const fn new() -> Self {
+        MsgPackConfig { limit: 2 * 1024 * 1024, max_depth: 128 }
+    }
+
+    /// Limits the size of request bodies, in bytes.
+    ///
+    /// Bodies are read up to the limit, which replaces the `DefaultBodyLimit` of axum routers.
+    #[inline]
+    pub const fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Limits how deeply arrays and maps can be nested in request bodies.
+    #[inline]
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Returns the size limit of request bodies.
+    #[inline(always)]
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the maximum nesting depth of request bodies.
+    #[inline(always)]
+    pub const fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+}
+
+impl Default for MsgPackConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error of the [`MsgPack`] extractor.
+#[derive(Debug)]
+pub enum MsgPackRejection {
+    /// The request doesn't have a MessagePack `Content-Type`.
+    UnsupportedContentType,
+    /// The body is longer than the limit.
+    PayloadTooLarge,
+    /// The body couldn't be read.
+    Body(Box<dyn error::Error + Send + Sync>),
+    /// The body isn't a MessagePack value of the expected type, or has bytes after it.
+    Decode(Error<BytesReadError>),
+}
+
+impl MsgPackRejection {
+    /// Returns the HTTP status code of the response sent for this error.
+    ///
+    /// This is 415 for [`Self::UnsupportedContentType`], 413 for [`Self::PayloadTooLarge`], and 400
+    /// otherwise.
+    #[inline]
+    pub fn status(&self) -> u16 {
+        match *self {
+            MsgPackRejection::UnsupportedContentType => 415,
+            MsgPackRejection::PayloadTooLarge => 413,
+            MsgPackRejection::Body(..) | MsgPackRejection::Decode(..) => 400,
+        }
+    }
+}
+
+impl Display for MsgPackRejection {
+    #[cold]
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            MsgPackRejection::UnsupportedContentType => {
+                write!(fmt, "expected request with `Content-Type: {}`", CONTENT_TYPE)
+            }
+            MsgPackRejection::PayloadTooLarge => fmt.write_str("request body is too large"),
+            MsgPackRejection::Body(ref err) => write!(fmt, "failed to read the request body: {}", err),
+            MsgPackRejection::Decode(ref err) => write!(fmt, "failed to deserialize the request body: {}", err),
+        }
+    }
+}
+
+impl error::Error for MsgPackRejection {
+    #[cold]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            MsgPackRejection::Body(ref err) => Some(&**err),
+            MsgPackRejection::Decode(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Returns `true` if the `Content-Type` is a MessagePack one, ignoring its parameters.
+fn is_msgpack(content_type: Option<&[u8]>) -> bool {
+    let Some(content_type) = content_type else { return false };
+    let essence = content_type.split(|&b| b == b';').next().unwrap_or_default().trim_ascii();
+    let Some(slash) = essence.iter().position(|&b| b == b'/') else { return false };
+    let (ty, subtype) = (&essence[..slash], &essence[slash + 1..]);
+
+    ty.eq_ignore_ascii_case(b"application") &&
+        (subtype.eq_ignore_ascii_case(b"msgpack") ||
+         subtype.eq_ignore_ascii_case(b"x-msgpack") ||
+         subtype.eq_ignore_ascii_case(b"vnd.msgpack") ||
+         subtype.len() > 8 && subtype[subtype.len() - 8..].eq_ignore_ascii_case(b"+msgpack"))
+}
+
+/// Rejects requests whose `Content-Length` is above the limit before reading their body.
+fn check_content_length(content_length: Option<&[u8]>, config: &MsgPackConfig) -> Result<(), MsgPackRejection> {
+    let len = content_length
+        .and_then(|len| core::str::from_utf8(len).ok())
+        .and_then(|len| len.parse::<u64>().ok());
+    match len {
+        Some(len) if len > config.limit as u64 => Err(MsgPackRejection::PayloadTooLarge),
+        _ => Ok(()),
+    }
+}
+
+fn decode<T: DeserializeOwned>(body: &[u8], config: &MsgPackConfig) -> Result<T, MsgPackRejection> {
+    if body.len() > config.limit {
+        return Err(MsgPackRejection::PayloadTooLarge);
+    }
+    let mut de = Deserializer::from_bytes(body);
+    de.set_max_depth(config.max_depth);
+    let val = T::deserialize(&mut de).map_err(MsgPackRejection::Decode)?;
+    if de.remaining() != 0 {
+        return Err(MsgPackRejection::Decode(Error::Uncategorized("trailing bytes after the value")));
+    }
+    Ok(val)
+}
+
+#[cfg(feature = "axum")]
+mod axum {
+    use axum_core::extract::{FromRequest, Request};
+    use axum_core::response::{IntoResponse, Response};
+    use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+    use http::{HeaderValue, StatusCode};
+    use http_body_util::{BodyExt, LengthLimitError, Limited};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use super::{MsgPack, MsgPackConfig, MsgPackRejection};
+
+    impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for MsgPack<T> {
+        type Rejection = MsgPackRejection;
+
+        async fn from_request(req: Request, _state: &S) -> Result<Self, MsgPackRejection> {
+            let headers = req.headers();
+            if !super::is_msgpack(headers.get(CONTENT_TYPE).map(HeaderValue::as_bytes)) {
+                return Err(MsgPackRejection::UnsupportedContentType);
+            }
+            let config = req.extensions().get::<MsgPackConfig>().copied().unwrap_or_default();
+            super::check_content_length(headers.get(CONTENT_LENGTH).map(HeaderValue::as_bytes), &config)?;
+
+            let body = Limited::new(req.into_body(), config.limit).collect().await.map_err(|err| {
+                if err.is::<LengthLimitError>() {
+                    MsgPackRejection::PayloadTooLarge
+                } else {
+                    MsgPackRejection::Body(err)
+                }
+            })?;
+            super::decode(&body.to_bytes(), &config).map(MsgPack)
+        }
+    }
+
+    impl IntoResponse for MsgPackRejection {
+        fn into_response(self) -> Response {
+            let status = StatusCode::from_u16(self.status()).unwrap_or(StatusCode::BAD_REQUEST);
+            (status, self.to_string()).into_response()
+        }
+    }
+
+    impl<T: Serialize> IntoResponse for MsgPack<T> {
+        fn into_response(self) -> Response {
+            match crate::to_vec_named(&self.0) {
+                Ok(buf) => ([(CONTENT_TYPE, HeaderValue::from_static(super::CONTENT_TYPE))], buf).into_response(),
+                Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "actix-web")]
+mod actix {
+    use core::future::Future;
+    use core::pin::Pin;
+
+    use actix_web::body::BoxBody;
+    use actix_web::dev::Payload;
+    use actix_web::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+    use actix_web::http::StatusCode;
+    use actix_web::{web, FromRequest, HttpRequest, HttpResponse, Responder, ResponseError};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use super::{MsgPack, MsgPackConfig, MsgPackRejection};
+
+    impl<T: DeserializeOwned + 'static> FromRequest for MsgPack<T> {
+        type Error = MsgPackRejection;
+        type Future = Pin<Box<dyn Future<Output = Result<Self, MsgPackRejection>>>>;
+
+        fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+            let headers = req.headers();
+            let content_type = headers.get(CONTENT_TYPE).map(|val| val.as_bytes());
+            let config = req.app_data::<MsgPackConfig>().copied().unwrap_or_default();
+            let checked = if super::is_msgpack(content_type) {
+                super::check_content_length(headers.get(CONTENT_LENGTH).map(|val| val.as_bytes()), &config)
+            } else {
+                Err(MsgPackRejection::UnsupportedContentType)
+            };
+
+            let payload = web::Payload::from_request(req, payload);
+            Box::pin(async move {
+                checked?;
+                let payload = payload.await.map_err(|err| MsgPackRejection::Body(err.to_string().into()))?;
+                let body = payload.to_bytes_limited(config.limit).await
+                    .map_err(|_| MsgPackRejection::PayloadTooLarge)?
+                    // actix-web errors aren't `Send`.
+                    .map_err(|err| MsgPackRejection::Body(err.to_string().into()))?;
+                super::decode(&body, &config).map(MsgPack)
+            })
+        }
+    }
+
+    impl ResponseError for MsgPackRejection {
+        fn status_code(&self) -> StatusCode {
+            StatusCode::from_u16(self.status()).unwrap_or(StatusCode::BAD_REQUEST)
+        }
+    }
+
+    impl<T: Serialize> Responder for MsgPack<T> {
+        type Body = BoxBody;
+
+        fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+            match crate::to_vec_named(&self.0) {
+                Ok(buf) => HttpResponse::Ok().content_type(super::CONTENT_TYPE).body(buf),
+                Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+            }
+        }
+    }
+}
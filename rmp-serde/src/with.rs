@@ -0,0 +1,597 @@
+//! Helpers for `#[serde(with = "...")]` attributes covering common MessagePack idioms.
+//!
+//! - [`bytes`] writes bin instead of an array of integers, and reads bin or str.
+//! - [`str_lossy`] reads str, replacing invalid UTF-8 instead of failing.
+//! - [`cow_str`] and [`cow_bytes`] borrow from the input instead of always allocating.
+//! - [`timestamp`] maps `SystemTime` to the timestamp ext type defined by the MessagePack spec.
+//! - [`ext`] writes a [`MsgPackExtType`](crate::MsgPackExtType) as its ext without a wrapper.
+//! - [`base64_over_human_readable`] writes bin, or a base64 str if the serializer is
+//!   human-readable (with the `base64` feature).
+//! - [`zstd_bytes`] writes bytes or a string compressed with zstd (with the `zstd` feature).
+//!
+//! The [`strict`](crate::strict) helpers keep bin and str apart, and the `bigint`, `uuid` and
+//! `decimal` modules cover the types of the optional dependencies with the same names.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! # fn main() {
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(with = "rmp_serde::with::timestamp")]
+//!     at: SystemTime,
+//!     #[serde(with = "rmp_serde::with::bytes")]
+//!     payload: Vec<u8>,
+//! }
+//!
+//! let event = Event { at: UNIX_EPOCH + Duration::from_secs(1), payload: vec![1, 2] };
+//! let buf = rmp_serde::to_vec(&event).unwrap();
+//! assert_eq!(vec![0x92, 0xd6, 0xff, 0, 0, 0, 1, 0xc4, 2, 1, 2], buf);
+//! assert_eq!(event, rmp_serde::from_slice(&buf).unwrap());
+//! # }
+//! ```
+
+/// Serializes as bin, and deserializes from bin or str.
+///
+/// Use with `#[serde(with = "rmp_serde::with::bytes")]` on fields of a type implementing
+/// `AsRef<[u8]>` and `From<Vec<u8>>`, e.g. `Vec<u8>`. Unlike [`strict::bytes`](crate::strict::bytes)
+/// this also accepts str, which older encoders used for binary data.
+pub mod bytes {
+    use core::fmt::{self, Formatter};
+    use core::marker::PhantomData;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    /// Serializes the bytes as bin.
+    #[inline]
+    pub fn serialize<T, S>(val: &T, se: S) -> Result<S::Ok, S::Error>
+    where T: AsRef<[u8]> + ?Sized,
+          S: Serializer
+    {
+        se.serialize_bytes(val.as_ref())
+    }
+
+    /// Deserializes bin or str.
+    #[inline]
+    pub fn deserialize<'de, T, D>(de: D) -> Result<T, D::Error>
+    where T: From<Vec<u8>>,
+          D: Deserializer<'de>
+    {
+        de.deserialize_byte_buf(BytesVisitor(PhantomData))
+    }
+
+    struct BytesVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: From<Vec<u8>>> Visitor<'de> for BytesVisitor<T> {
+        type Value = T;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a MessagePack bin or str")
+        }
+
+        #[inline]
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+            Ok(v.to_vec().into())
+        }
+
+        #[inline]
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<T, E> {
+            Ok(v.into())
+        }
+
+        #[inline]
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            Ok(v.as_bytes().to_vec().into())
+        }
+
+        #[inline]
+        fn visit_string<E: de::Error>(self, v: String) -> Result<T, E> {
+            Ok(v.into_bytes().into())
+        }
+    }
+}
+
+/// Serializes as str, and deserializes str or bin, replacing invalid UTF-8 with `U+FFFD`.
+///
+/// Use with `#[serde(with = "rmp_serde::with::str_lossy")]` on fields of a type implementing
+/// `AsRef<str>` and `From<String>`, e.g. `String`.
+pub mod str_lossy {
+    use core::fmt::{self, Formatter};
+    use core::marker::PhantomData;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    /// Serializes the string as str.
+    #[inline]
+    pub fn serialize<T, S>(val: &T, se: S) -> Result<S::Ok, S::Error>
+    where T: AsRef<str> + ?Sized,
+          S: Serializer
+    {
+        se.serialize_str(val.as_ref())
+    }
+
+    /// Deserializes str or bin, replacing invalid UTF-8 sequences.
+    #[inline]
+    pub fn deserialize<'de, T, D>(de: D) -> Result<T, D::Error>
+    where T: From<String>,
+          D: Deserializer<'de>
+    {
+        de.deserialize_string(LossyVisitor(PhantomData))
+    }
+
+    struct LossyVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: From<String>> Visitor<'de> for LossyVisitor<T> {
+        type Value = T;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a MessagePack str or bin")
+        }
+
+        #[inline]
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            Ok(String::from(v).into())
+        }
+
+        #[inline]
+        fn visit_string<E: de::Error>(self, v: String) -> Result<T, E> {
+            Ok(v.into())
+        }
+
+        #[inline]
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+            Ok(String::from_utf8_lossy(v).into_owned().into())
+        }
+
+        #[inline]
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<T, E> {
+            match String::from_utf8(v) {
+                Ok(v) => Ok(v.into()),
+                Err(err) => self.visit_bytes(err.as_bytes()),
+            }
+        }
+    }
+}
+
+/// Deserializes `Cow<str>` borrowing from the input when possible.
+///
+/// serde's own implementation for `Cow` always returns `Cow::Owned`. Use
+/// `#[serde(borrow, with = "rmp_serde::with::cow_str")]` to get `Cow::Borrowed` when decoding from a
+/// slice, falling back to an owned string otherwise. Valid UTF-8 bin is accepted too.
+///
+/// ```
+/// # #[macro_use] extern crate serde_derive;
+/// # fn main() {
+/// use std::borrow::Cow;
+///
+/// #[derive(Deserialize)]
+/// struct Name<'a>(#[serde(borrow, with = "rmp_serde::with::cow_str")] Cow<'a, str>);
+///
+/// let buf = [0xa2, b'h', b'i'];
+/// let name: Name<'_> = rmp_serde::from_slice(&buf).unwrap();
+/// assert!(matches!(name.0, Cow::Borrowed("hi")));
+/// # }
+/// ```
+pub mod cow_str {
+    use core::fmt::{self, Formatter};
+    use std::borrow::Cow;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    /// Serializes the string as str.
+    #[inline]
+    pub fn serialize<S: Serializer>(val: &str, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_str(val)
+    }
+
+    /// Deserializes str, borrowing it if possible.
+    #[inline]
+    pub fn deserialize<'de: 'a, 'a, D: Deserializer<'de>>(de: D) -> Result<Cow<'a, str>, D::Error> {
+        de.deserialize_str(CowStrVisitor)
+    }
+
+    struct CowStrVisitor;
+
+    impl<'de> Visitor<'de> for CowStrVisitor {
+        type Value = Cow<'de, str>;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a string")
+        }
+
+        #[inline]
+        fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+            Ok(Cow::Borrowed(v))
+        }
+
+        #[inline]
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v.into()))
+        }
+
+        #[inline]
+        fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v))
+        }
+
+        #[inline]
+        fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+            match core::str::from_utf8(v) {
+                Ok(v) => Ok(Cow::Borrowed(v)),
+                Err(..) => Err(de::Error::invalid_value(de::Unexpected::Bytes(v), &self)),
+            }
+        }
+
+        #[inline]
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            match core::str::from_utf8(v) {
+                Ok(v) => Ok(Cow::Owned(v.into())),
+                Err(..) => Err(de::Error::invalid_value(de::Unexpected::Bytes(v), &self)),
+            }
+        }
+    }
+}
+
+/// Deserializes `Cow<[u8]>` borrowing from the input when possible.
+///
+/// Use with `#[serde(borrow, with = "rmp_serde::with::cow_bytes")]`. Serializes as bin, and
+/// deserializes bin or str, as `Cow::Borrowed` when decoding from a slice.
+pub mod cow_bytes {
+    use core::fmt::{self, Formatter};
+    use std::borrow::Cow;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    /// Serializes the bytes as bin.
+    #[inline]
+    pub fn serialize<S: Serializer>(val: &[u8], se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_bytes(val)
+    }
+
+    /// Deserializes bin or str, borrowing it if possible.
+    #[inline]
+    pub fn deserialize<'de: 'a, 'a, D: Deserializer<'de>>(de: D) -> Result<Cow<'a, [u8]>, D::Error> {
+        de.deserialize_bytes(CowBytesVisitor)
+    }
+
+    struct CowBytesVisitor;
+
+    impl<'de> Visitor<'de> for CowBytesVisitor {
+        type Value = Cow<'de, [u8]>;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a MessagePack bin or str")
+        }
+
+        #[inline]
+        fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+            Ok(Cow::Borrowed(v))
+        }
+
+        #[inline]
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v.into()))
+        }
+
+        #[inline]
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v))
+        }
+
+        #[inline]
+        fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+            Ok(Cow::Borrowed(v.as_bytes()))
+        }
+
+        #[inline]
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v.as_bytes().into()))
+        }
+
+        #[inline]
+        fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v.into_bytes()))
+        }
+    }
+}
+
+/// Serializes `SystemTime` as a MessagePack timestamp.
+///
+/// Use with `#[serde(with = "rmp_serde::with::timestamp")]`. The smallest of the timestamp 32, 64
+/// and 96 formats that holds the time is written, all three are accepted when decoding.
+pub mod timestamp {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::{self, Serialize, Serializer};
+
+    use crate::{Ext, ExtRef};
+
+    /// The ext type of timestamps, reserved by the MessagePack spec.
+    pub const TIMESTAMP_EXT_TAG: i8 = -1;
+
+    const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+    /// Serializes the time as a timestamp ext.
+    pub fn serialize<S: Serializer>(val: &SystemTime, se: S) -> Result<S::Ok, S::Error> {
+        let (secs, nanos) = match val.duration_since(UNIX_EPOCH) {
+            Ok(since) => {
+                let secs = i64::try_from(since.as_secs())
+                    .map_err(|_| ser::Error::custom("time out of range of a MessagePack timestamp"))?;
+                (secs, since.subsec_nanos())
+            }
+            Err(err) => {
+                let until = err.duration();
+                let secs = i64::try_from(until.as_secs()).ok().and_then(|secs| {
+                    if until.subsec_nanos() == 0 { Some(-secs) } else { (-secs).checked_sub(1) }
+                });
+                let secs = secs.ok_or_else(|| ser::Error::custom("time out of range of a MessagePack timestamp"))?;
+                (secs, (NANOS_PER_SEC - until.subsec_nanos()) % NANOS_PER_SEC)
+            }
+        };
+
+        if secs >> 34 == 0 {
+            let data = (u64::from(nanos) << 34) | secs as u64;
+            if data >> 32 == 0 {
+                ExtRef::new(TIMESTAMP_EXT_TAG, &(data as u32).to_be_bytes()).serialize(se)
+            } else {
+                ExtRef::new(TIMESTAMP_EXT_TAG, &data.to_be_bytes()).serialize(se)
+            }
+        } else {
+            let mut data = [0; 12];
+            data[..4].copy_from_slice(&nanos.to_be_bytes());
+            data[4..].copy_from_slice(&secs.to_be_bytes());
+            ExtRef::new(TIMESTAMP_EXT_TAG, &data).serialize(se)
+        }
+    }
+
+    /// Deserializes a timestamp ext.
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<SystemTime, D::Error> {
+        let ext = Ext::deserialize(de)?;
+        if ext.tag() != TIMESTAMP_EXT_TAG {
+            return Err(de::Error::invalid_value(de::Unexpected::Signed(ext.tag().into()), &"a timestamp ext"));
+        }
+        let (secs, nanos) = match *ext.data() {
+            [a, b, c, d] => (u32::from_be_bytes([a, b, c, d]).into(), 0),
+            [a, b, c, d, e, f, g, h] => {
+                let data = u64::from_be_bytes([a, b, c, d, e, f, g, h]);
+                ((data & ((1 << 34) - 1)) as i64, (data >> 34) as u32)
+            }
+            [a, b, c, d, ref secs @ ..] if secs.len() == 8 => {
+                let mut buf = [0; 8];
+                buf.copy_from_slice(secs);
+                (i64::from_be_bytes(buf), u32::from_be_bytes([a, b, c, d]))
+            }
+            ref data => return Err(de::Error::invalid_length(data.len(), &"a timestamp of 4, 8 or 12 bytes")),
+        };
+        if nanos >= NANOS_PER_SEC {
+            return Err(de::Error::invalid_value(de::Unexpected::Unsigned(nanos.into()), &"less than 10^9 nanoseconds"));
+        }
+
+        let time = if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+                .and_then(|time| time.checked_add(Duration::from_nanos(nanos.into())))
+        };
+        time.ok_or_else(|| de::Error::custom("timestamp out of range of SystemTime"))
+    }
+}
+
+/// Serializes a [`MsgPackExtType`](crate::MsgPackExtType) as its ext, like
+/// [`TypedExt`](crate::TypedExt) but without wrapping the field's type.
+///
+/// Use with `#[serde(with = "rmp_serde::with::ext")]`.
+pub mod ext {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{ExtRef, MsgPackExtType, TypedExt};
+
+    /// Serializes the value as an ext with the tag [`MsgPackExtType::TAG`].
+    #[inline]
+    pub fn serialize<T: MsgPackExtType, S: Serializer>(val: &T, se: S) -> Result<S::Ok, S::Error> {
+        ExtRef::new(T::TAG, &val.to_bytes()).serialize(se)
+    }
+
+    /// Deserializes an ext with the tag [`MsgPackExtType::TAG`].
+    #[inline]
+    pub fn deserialize<'de, T: MsgPackExtType, D: Deserializer<'de>>(de: D) -> Result<T, D::Error> {
+        TypedExt::deserialize(de).map(TypedExt::into_inner)
+    }
+}
+
+/// Serializes as bin, or as a base64 str if the serializer is human-readable.
+///
+/// Use with `#[serde(with = "rmp_serde::with::base64_over_human_readable")]` on fields of a type
+/// implementing `AsRef<[u8]>` and `From<Vec<u8>>`, e.g. `Vec<u8>`. Decoding accepts bin, and str
+/// holding base64 with padding in the standard alphabet.
+#[cfg(feature = "base64")]
+pub mod base64_over_human_readable {
+    use core::fmt::{self, Formatter};
+    use core::marker::PhantomData;
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    /// Serializes the bytes as bin, or base64 if the serializer is human-readable.
+    #[inline]
+    pub fn serialize<T, S>(val: &T, se: S) -> Result<S::Ok, S::Error>
+    where T: AsRef<[u8]> + ?Sized,
+          S: Serializer
+    {
+        if se.is_human_readable() {
+            se.serialize_str(&STANDARD.encode(val))
+        } else {
+            se.serialize_bytes(val.as_ref())
+        }
+    }
+
+    /// Deserializes bin or a base64 str.
+    #[inline]
+    pub fn deserialize<'de, T, D>(de: D) -> Result<T, D::Error>
+    where T: From<Vec<u8>>,
+          D: Deserializer<'de>
+    {
+        if de.is_human_readable() {
+            de.deserialize_str(Base64Visitor(PhantomData))
+        } else {
+            de.deserialize_byte_buf(Base64Visitor(PhantomData))
+        }
+    }
+
+    struct Base64Visitor<T>(PhantomData<T>);
+
+    impl<'de, T: From<Vec<u8>>> Visitor<'de> for Base64Visitor<T> {
+        type Value = T;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("bytes or a base64 string")
+        }
+
+        #[inline]
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+            Ok(v.to_vec().into())
+        }
+
+        #[inline]
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<T, E> {
+            Ok(v.into())
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            STANDARD.decode(v)
+                .map(T::from)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+}
+
+/// Serializes bytes or a string as bin holding a zstd frame, so that a single large field can be
+/// stored compressed in an otherwise uncompressed message.
+///
+/// Use with `#[serde(with = "rmp_serde::with::zstd_bytes")]` on fields of a type implementing
+/// [`Decompressed`](zstd_bytes::Decompressed), e.g. `Vec<u8>` or `String`. Decoding stops at
+/// [`DEFAULT_MAX_SIZE`](zstd_bytes::DEFAULT_MAX_SIZE) bytes of decompressed data, other limits
+/// can be set with [`deserialize_with_limit`](zstd_bytes::deserialize_with_limit):
+///
+/// ```
+/// # #[macro_use] extern crate serde_derive;
+/// # fn main() {
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Page {
+///     url: String,
+///     #[serde(serialize_with = "rmp_serde::with::zstd_bytes::serialize")]
+///     #[serde(deserialize_with = "rmp_serde::with::zstd_bytes::deserialize_with_limit::<_, _, 65536>")]
+///     html: String,
+/// }
+///
+/// let page = Page { url: "/".into(), html: "<p>hello</p>".repeat(1000) };
+/// let buf = rmp_serde::to_vec(&page).unwrap();
+/// assert!(buf.len() < 100);
+/// assert_eq!(page, rmp_serde::from_slice(&buf).unwrap());
+/// # }
+/// ```
+#[cfg(feature = "zstd")]
+pub mod zstd_bytes {
+    use core::fmt::{self, Formatter};
+    use core::marker::PhantomData;
+    use std::io::{Read, Write};
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::ser::{self, Serializer};
+
+    use crate::compress::{CompressWriter, Compression, DecompressReader};
+
+    /// The largest size of the decompressed data accepted by [`deserialize`], 64 MiB.
+    pub const DEFAULT_MAX_SIZE: usize = 64 << 20;
+
+    /// Types that can be rebuilt from their decompressed bytes.
+    pub trait Decompressed: Sized {
+        /// Converts the bytes, or returns an error message if they aren't valid for this type.
+        fn from_decompressed(buf: Vec<u8>) -> Result<Self, &'static str>;
+    }
+
+    impl Decompressed for Vec<u8> {
+        #[inline]
+        fn from_decompressed(buf: Vec<u8>) -> Result<Self, &'static str> {
+            Ok(buf)
+        }
+    }
+
+    impl Decompressed for Box<[u8]> {
+        #[inline]
+        fn from_decompressed(buf: Vec<u8>) -> Result<Self, &'static str> {
+            Ok(buf.into_boxed_slice())
+        }
+    }
+
+    impl Decompressed for String {
+        #[inline]
+        fn from_decompressed(buf: Vec<u8>) -> Result<Self, &'static str> {
+            String::from_utf8(buf).map_err(|_| "invalid UTF-8 in compressed string")
+        }
+    }
+
+    /// Serializes the bytes as bin holding a zstd frame, compressed at the default level.
+    pub fn serialize<T, S>(val: &T, se: S) -> Result<S::Ok, S::Error>
+    where T: AsRef<[u8]> + ?Sized,
+          S: Serializer
+    {
+        let compress = || {
+            let mut wr = CompressWriter::new(Vec::new(), Compression::Zstd(0))?;
+            wr.write_all(val.as_ref())?;
+            wr.finish()
+        };
+        let buf = compress().map_err(ser::Error::custom)?;
+        se.serialize_bytes(&buf)
+    }
+
+    /// Deserializes bin holding a zstd frame of at most [`DEFAULT_MAX_SIZE`] bytes.
+    #[inline]
+    pub fn deserialize<'de, T, D>(de: D) -> Result<T, D::Error>
+    where T: Decompressed,
+          D: Deserializer<'de>
+    {
+        deserialize_with_limit::<T, D, DEFAULT_MAX_SIZE>(de)
+    }
+
+    /// Deserializes bin holding a zstd frame of at most `MAX_SIZE` bytes.
+    #[inline]
+    pub fn deserialize_with_limit<'de, T, D, const MAX_SIZE: usize>(de: D) -> Result<T, D::Error>
+    where T: Decompressed,
+          D: Deserializer<'de>
+    {
+        de.deserialize_bytes(ZstdVisitor::<T, MAX_SIZE>(PhantomData))
+    }
+
+    struct ZstdVisitor<T, const MAX_SIZE: usize>(PhantomData<T>);
+
+    impl<'de, T: Decompressed, const MAX_SIZE: usize> Visitor<'de> for ZstdVisitor<T, MAX_SIZE> {
+        type Value = T;
+
+        #[cold]
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("a MessagePack bin holding a zstd frame")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+            let mut buf = Vec::new();
+            DecompressReader::new(v, MAX_SIZE as u64)
+                .and_then(|mut rd| rd.read_to_end(&mut buf))
+                .map_err(E::custom)?;
+            T::from_decompressed(buf).map_err(E::custom)
+        }
+    }
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "http-body")]
+
+use core::pin::Pin;
+use std::io;
+
+use futures::executor::block_on;
+use futures::future::poll_fn;
+use http_body::Body;
+use serde_derive::{Deserialize, Serialize};
+
+use rmp_serde::body::{MsgPackArrayBody, MsgPackStreamBody};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Message {
+    id: u32,
+    body: String,
+}
+
+fn message(id: u32) -> Message {
+    Message { id, body: "x".repeat(100) }
+}
+
+/// Collects the data frames of the body, stopping at the first error.
+fn collect<B>(mut body: B) -> (Vec<Vec<u8>>, Option<io::Error>)
+where B: Body<Data = bytes::Bytes, Error = io::Error> + Unpin
+{
+    let mut chunks = Vec::new();
+    while let Some(frame) = block_on(poll_fn(|cx| Pin::new(&mut body).poll_frame(cx))) {
+        match frame {
+            Ok(frame) => chunks.push(frame.into_data().unwrap().to_vec()),
+            Err(err) => return (chunks, Some(err)),
+        }
+    }
+    assert!(body.is_end_stream());
+    (chunks, None)
+}
+
+#[test]
+fn pass_array_body() {
+    let (chunks, err) = collect(MsgPackArrayBody::new((0..1000).map(message)));
+    assert!(err.is_none());
+    assert!(chunks.len() > 1);
+    assert!(chunks.iter().all(|chunk| chunk.len() < 9 * 1024));
+
+    let expected = rmp_serde::to_vec(&(0..1000).map(message).collect::<Vec<_>>()).unwrap();
+    assert_eq!(expected, chunks.concat());
+}
+
+#[test]
+fn pass_array_body_empty() {
+    let (chunks, err) = collect(MsgPackArrayBody::new(Vec::<Message>::new()));
+    assert!(err.is_none());
+    assert_eq!(vec![vec![0x90]], chunks);
+}
+
+#[test]
+fn fail_array_body_wrong_len() {
+    struct Lying(u32);
+
+    impl Iterator for Lying {
+        type Item = u32;
+
+        fn next(&mut self) -> Option<u32> {
+            self.0 = self.0.checked_sub(1)?;
+            Some(self.0)
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (2, Some(2))
+        }
+    }
+
+    impl ExactSizeIterator for Lying {}
+
+    let (_, err) = collect(MsgPackArrayBody::new(Lying(1)));
+    assert_eq!(io::ErrorKind::InvalidData, err.unwrap().kind());
+    let (_, err) = collect(MsgPackArrayBody::new(Lying(3)));
+    assert_eq!(io::ErrorKind::InvalidData, err.unwrap().kind());
+}
+
+#[test]
+fn pass_stream_body() {
+    let (chunks, err) = collect(MsgPackStreamBody::new(futures::stream::iter((0..1000).map(message))));
+    assert!(err.is_none());
+    assert!(chunks.len() > 1);
+
+    let mut buf = &chunks.concat()[..];
+    for id in 0..1000 {
+        assert_eq!(message(id), rmp_serde::from_read::<_, Message>(&mut buf).unwrap());
+    }
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn pass_stream_body_empty() {
+    let (chunks, err) = collect(MsgPackStreamBody::new(futures::stream::empty::<Message>()));
+    assert!(err.is_none());
+    assert!(chunks.is_empty());
+}
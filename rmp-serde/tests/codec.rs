@@ -0,0 +1,65 @@
+#![cfg(feature = "tokio-util")]
+
+use std::io;
+
+use bytes::BytesMut;
+use serde_derive::{Deserialize, Serialize};
+use tokio_util::codec::{Decoder, Encoder};
+
+use rmp_serde::codec::MsgPackCodec;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Message {
+    id: u32,
+    body: String,
+}
+
+#[test]
+fn pass_round_trip_multiple_frames() {
+    let mut codec = MsgPackCodec::<Message>::new();
+    let mut buf = BytesMut::new();
+
+    codec.encode(Message { id: 1, body: "foo".into() }, &mut buf).unwrap();
+    codec.encode(&Message { id: 2, body: "bar".into() }, &mut buf).unwrap();
+
+    assert_eq!(Some(Message { id: 1, body: "foo".into() }), codec.decode(&mut buf).unwrap());
+    assert_eq!(Some(Message { id: 2, body: "bar".into() }), codec.decode(&mut buf).unwrap());
+    assert_eq!(None, codec.decode(&mut buf).unwrap());
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn pass_decode_partial_frame() {
+    let mut codec = MsgPackCodec::<Message>::new();
+    let mut encoded = BytesMut::new();
+    codec.encode(Message { id: 42, body: "le message".into() }, &mut encoded).unwrap();
+
+    let mut buf = BytesMut::new();
+    for (i, &byte) in encoded.iter().enumerate() {
+        assert_eq!(None, codec.decode(&mut buf).unwrap(), "decoded after {} bytes", i);
+        buf.extend_from_slice(&[byte]);
+    }
+
+    assert_eq!(Some(Message { id: 42, body: "le message".into() }), codec.decode(&mut buf).unwrap());
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn fail_decode_invalid_frame() {
+    let mut codec = MsgPackCodec::<Message>::new();
+    // A string where the message array is expected.
+    let mut buf = BytesMut::from(&[0xa3, 0x66, 0x6f, 0x6f][..]);
+
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+}
+
+#[test]
+fn fail_decode_frame_too_long() {
+    let mut codec = MsgPackCodec::<serde_bytes::ByteBuf>::new().with_max_frame_length(4);
+    // bin32 with a length of 2^24 bytes, of which only a few have arrived.
+    let mut buf = BytesMut::from(&[0xc6, 0x01, 0x00, 0x00, 0x00, 0x00][..]);
+
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+}
@@ -0,0 +1,90 @@
+#![cfg(any(feature = "zstd", feature = "lz4"))]
+
+use std::io::Read;
+
+use serde::Serialize;
+use serde_derive::{Deserialize, Serialize};
+
+use rmp_serde::compress::{self, CompressWriter, Compression, DecompressReader};
+use rmp_serde::decode::Error;
+use rmp_serde::Serializer;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    id: u32,
+    tags: Vec<String>,
+}
+
+fn compressions() -> Vec<Compression> {
+    vec![
+        #[cfg(feature = "zstd")]
+        Compression::Zstd(0),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd(19),
+        #[cfg(feature = "lz4")]
+        Compression::Lz4,
+    ]
+}
+
+#[test]
+fn pass_compressed_round_trip() {
+    let records: Vec<Record> = (0..100).map(|id| Record { id, tags: vec!["tag".into(); 10] }).collect();
+    let plain = rmp_serde::to_vec(&records).unwrap();
+
+    for compression in compressions() {
+        let buf = rmp_serde::to_vec_compressed(&records, compression).unwrap();
+        assert!(buf.len() < plain.len() / 4, "{:?}", compression);
+        assert_eq!(records, rmp_serde::from_slice_compressed::<Vec<Record>>(&buf, plain.len() as u64).unwrap());
+
+        let mut decompressed = Vec::new();
+        DecompressReader::new(&buf[..], plain.len() as u64).unwrap().read_to_end(&mut decompressed).unwrap();
+        assert_eq!(plain, decompressed);
+    }
+}
+
+#[test]
+fn pass_compress_writer() {
+    let record = Record { id: 1, tags: vec!["a".into()] };
+
+    for compression in compressions() {
+        let mut se = Serializer::new(CompressWriter::new(Vec::new(), compression).unwrap()).with_struct_map();
+        record.serialize(&mut se).unwrap();
+        let buf = se.into_inner().finish().unwrap();
+
+        let mut rd = DecompressReader::new(&buf[..], 1024).unwrap();
+        let mut plain = Vec::new();
+        rd.read_to_end(&mut plain).unwrap();
+        assert_eq!(rmp_serde::to_vec_named(&record).unwrap(), plain);
+    }
+}
+
+#[test]
+fn fail_decompressed_size_limit() {
+    let tags = vec!["x".repeat(100); 1000];
+    let plain = rmp_serde::to_vec(&tags).unwrap();
+
+    for compression in compressions() {
+        let buf = compress::to_vec_compressed(&tags, compression).unwrap();
+        assert_eq!(tags, compress::from_slice_compressed::<Vec<String>>(&buf, plain.len() as u64).unwrap());
+
+        let mut decompressed = Vec::new();
+        let err = DecompressReader::new(&buf[..], plain.len() as u64 - 1).unwrap().read_to_end(&mut decompressed).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+
+        match compress::from_slice_compressed::<Vec<String>>(&buf, 1000) {
+            Err(Error::InvalidValueRead(..)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn fail_unknown_compression() {
+    let buf = rmp_serde::to_vec(&[1, 2, 3, 4]).unwrap();
+
+    match compress::from_slice_compressed::<Vec<u8>>(&buf, 1024) {
+        Err(Error::InvalidValueRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(DecompressReader::new(&[0x28, 0xb5][..], 1024).is_err());
+}
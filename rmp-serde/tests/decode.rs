@@ -1,7 +1,7 @@
 extern crate rmp_serde as rmps;
 
 use std::fmt::{self, Formatter};
-use std::io::Cursor;
+use std::io::{self, Cursor};
 
 use serde::de;
 use serde::Deserialize;
@@ -22,9 +22,9 @@ fn fail_nil_from_reserved() {
     let buf = [0xc1];
     let mut de = Deserializer::new(&buf[..]);
 
-    let res: Result<(), Error> = Deserialize::deserialize(&mut de);
+    let res: Result<(), Error<io::Error>> = Deserialize::deserialize(&mut de);
     match res.err() {
-        Some(Error::TypeMismatch(Marker::Reserved)) => (),
+        Some(Error::TypeMismatch(err)) if err.marker() == Some(Marker::Reserved) => (),
         other => panic!("unexpected result: {:?}", other),
     }
 }
@@ -38,6 +38,23 @@ fn pass_bool() {
     assert_eq!(false, Deserialize::deserialize(&mut de).unwrap());
 }
 
+#[test]
+fn fail_type_mismatch_reported_by_visitor() {
+    // [1, "x"]
+    let buf = [0x92, 0x01, 0xa1, 0x78];
+
+    let err = rmps::from_slice::<(u8, u8)>(&buf).unwrap_err();
+    match err {
+        Error::TypeMismatch(ref err) => {
+            assert_eq!(Some(Marker::FixStr(1)), err.marker());
+            assert_eq!("u8", err.expected());
+            assert_eq!(Some(2), err.position());
+        }
+        ref other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!("wrong msgpack marker FixStr(1), expected u8 at offset 2", err.to_string());
+}
+
 #[test]
 fn fail_bool_from_fixint() {
     let buf = [0x00];
@@ -45,9 +62,9 @@ fn fail_bool_from_fixint() {
 
     let mut deserializer = Deserializer::new(cur);
 
-    let res: Result<bool, Error> = Deserialize::deserialize(&mut deserializer);
+    let res: Result<bool, Error<io::Error>> = Deserialize::deserialize(&mut deserializer);
     match res.err().unwrap() {
-        Error::Syntax(..) => (),
+        Error::TypeMismatch(err) if err.marker() == Some(Marker::FixPos(0)) => (),
         other => panic!("unexpected result: {:?}", other),
     }
 }
@@ -79,7 +96,7 @@ fn fail_u32_from_u64() {
 
     let mut de = Deserializer::new(cur);
 
-    let res: Result<u32, Error> = Deserialize::deserialize(&mut de);
+    let res: Result<u32, Error<io::Error>> = Deserialize::deserialize(&mut de);
     match res.err().unwrap() {
         Error::Syntax(..) => (),
         other => panic!("unexpected result: {:?}", other),
@@ -247,7 +264,7 @@ fn fail_tuple_len_mismatch() {
     let cur = Cursor::new(&buf[..]);
 
     let mut de = Deserializer::new(cur);
-    let actual: Result<(u32,), Error> = Deserialize::deserialize(&mut de);
+    let actual: Result<(u32,), Error<io::Error>> = Deserialize::deserialize(&mut de);
 
     match actual.err().unwrap() {
         Error::LengthMismatch(1) => (),
@@ -297,9 +314,9 @@ fn fail_option_u8_from_reserved() {
     let cur = Cursor::new(&buf[..]);
 
     let mut de = Deserializer::new(cur);
-    let actual: Result<Option<u8>, Error> = Deserialize::deserialize(&mut de);
+    let actual: Result<Option<u8>, Error<io::Error>> = Deserialize::deserialize(&mut de);
     match actual.err() {
-        Some(Error::TypeMismatch(Marker::Reserved)) => (),
+        Some(Error::TypeMismatch(err)) if err.marker() == Some(Marker::Reserved) => (),
         other => panic!("unexpected result: {:?}", other),
     }
 }
@@ -391,6 +408,39 @@ fn pass_bin8_into_bytebuf_regression_growing_buffer() {
     assert_eq!((b"quux".to_vec(), b"bar".to_vec()), (large, small));
 }
 
+#[test]
+fn pass_bin32_larger_than_read_chunk_into_bytebuf() {
+    use serde_bytes::ByteBuf;
+
+    let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+    let mut buf = vec![0xc6];
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&data);
+
+    let mut de = Deserializer::new(Cursor::new(&buf[..]));
+    let actual: ByteBuf = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(data, actual.into_vec());
+}
+
+#[test]
+fn fail_bin32_with_bogus_length_does_not_preallocate() {
+    use serde_bytes::ByteBuf;
+
+    // Claims a 4 GiB payload, but only 3 bytes follow.
+    let buf = [0xc6, 0xff, 0xff, 0xff, 0xff, 0x01, 0x02, 0x03];
+
+    let mut de = Deserializer::new(Cursor::new(&buf[..]));
+    let res: Result<ByteBuf, Error<io::Error>> = Deserialize::deserialize(&mut de);
+
+    match res.err().unwrap() {
+        Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidDataRead(err)) => {
+            assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
 #[test]
 fn test_deserialize_numeric() {
     #[derive(Debug, PartialEq)]
@@ -480,11 +530,140 @@ fn pass_deserializer_cursor_position() {
     assert_eq!(5, de.position());
 }
 
+#[test]
+fn pass_deserializer_position() {
+    // ["abc", 42], nil
+    let buf = [0x92, 0xa3, b'a', b'b', b'c', 0x2a, 0xc0];
+
+    let mut de = Deserializer::new(&buf[..]);
+    assert_eq!(0, de.position());
+    let _: (String, u8) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(6, de.position());
+
+    let mut de = Deserializer::from_buf_read(io::BufReader::with_capacity(4, &buf[..]));
+    let _: (String, u8) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(6, de.position());
+
+    let mut de = Deserializer::from_bytes(&buf);
+    let _: (&str, u8) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(6, de.position());
+    let _: () = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(7, de.position());
+
+    // Truncated input reports where the missing bytes start.
+    let mut de = Deserializer::from_bytes(&buf[..3]);
+    match <(&str, u8)>::deserialize(&mut de) {
+        Err(Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidDataRead(decode::BytesReadError::InsufficientBytes { position, .. }))) => {
+            assert_eq!(2, position);
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
 #[test]
 fn pass_from() {
     assert_eq!(2147483647, decode::from_read(&[0xd2, 0x7f, 0xff, 0xff, 0xff][..]).unwrap());
 }
 
+#[test]
+fn pass_from_buf_read() {
+    // ["le message", 42] followed by nil.
+    let buf = [0x92, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65, 0x2a, 0xc0];
+
+    let mut de = Deserializer::from_buf_read(io::BufReader::new(&buf[..]));
+    let actual: (String, u8) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(("le message".to_string(), 42), actual);
+    assert_eq!((), Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_from_buf_read_payload_larger_than_buffer() {
+    use serde_bytes::ByteBuf;
+
+    let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+    let mut buf = vec![0x92, 0xc5, 0x03, 0xe8];
+    buf.extend_from_slice(&data);
+    buf.extend_from_slice(&[0xa3, 0x6b, 0x65, 0x79]);
+
+    let rd = io::BufReader::with_capacity(16, &buf[..]);
+    let mut de = Deserializer::from_buf_read(rd);
+    let (bin, key): (ByteBuf, String) = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(data, bin.into_vec());
+    assert_eq!("key", key);
+}
+
+#[test]
+fn pass_from_buf_read_into_inner_consumes_last_value() {
+    use std::io::Read;
+
+    let buf = [0xa3, 0x6b, 0x65, 0x79, 0x01, 0x02];
+
+    let mut de = Deserializer::from_buf_read(io::BufReader::new(&buf[..]));
+    let key: String = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!("key", key);
+
+    let mut rest = Vec::new();
+    de.into_inner().read_to_end(&mut rest).unwrap();
+    assert_eq!(vec![0x01, 0x02], rest);
+}
+
+#[test]
+fn pass_from_buf_read_with_separate_deserializers() {
+    // "key" followed by "val".
+    let buf = [0xa3, 0x6b, 0x65, 0x79, 0xa3, 0x76, 0x61, 0x6c];
+    let mut rd = io::BufReader::new(&buf[..]);
+
+    let key: String = Deserialize::deserialize(&mut Deserializer::from_buf_read(&mut rd)).unwrap();
+    let val: String = Deserialize::deserialize(&mut Deserializer::from_buf_read(&mut rd)).unwrap();
+    assert_eq!(("key", "val"), (&key[..], &val[..]));
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn pass_from_buf() {
+    use bytes::Bytes;
+
+    // ["le message", 42]
+    let buf = Bytes::from_static(&[0x92, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65, 0x2a]);
+
+    let actual: (String, u8) = rmps::from_buf(buf).unwrap();
+    assert_eq!(("le message".to_string(), 42), actual);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn pass_from_buf_non_contiguous() {
+    use bytes::{Buf, Bytes};
+
+    // ["le message", 42] split in the middle of the string, followed by nil.
+    let head = Bytes::from_static(&[0x92, 0xaa, 0x6c, 0x65, 0x20, 0x6d]);
+    let tail = Bytes::from_static(&[0x65, 0x73, 0x73, 0x61, 0x67, 0x65, 0x2a, 0xc0]);
+
+    let mut de = Deserializer::from_buf(head.chain(tail));
+    let actual: (String, u8) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(("le message".to_string(), 42), actual);
+    assert_eq!(13, de.position());
+    assert_eq!(1, de.into_inner().remaining());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn fail_from_buf_truncated() {
+    use bytes::Bytes;
+
+    // Claims a 4 GiB payload, but only 3 bytes follow.
+    let buf = Bytes::from_static(&[0xc6, 0xff, 0xff, 0xff, 0xff, 0x01, 0x02, 0x03]);
+
+    let res: Result<serde_bytes::ByteBuf, _> = rmps::from_buf(buf);
+    match res.err().unwrap() {
+        Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidDataRead(decode::BytesReadError::InsufficientBytes { expected, actual, position })) => {
+            assert_eq!((0xffffffff, 3, 5), (expected, actual, position));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
 #[test]
 fn pass_raw_valid_utf8() {
     let buf = vec![0xa3, 0x6b, 0x65, 0x79];
@@ -532,7 +711,7 @@ fn pass_raw_ref_invalid_utf8() {
 #[test]
 fn fail_str_invalid_utf8() {
     let buf = vec![0xa4, 0x92, 0xcc, 0xc8, 0x90];
-    let err: Result<String, decode::Error> = rmps::from_slice(&buf[..]);
+    let err: Result<String, decode::Error<decode::BytesReadError>> = rmps::from_slice(&buf[..]);
 
     assert!(err.is_err());
     match err.err().unwrap() {
@@ -542,6 +721,20 @@ fn fail_str_invalid_utf8() {
     }
 }
 
+#[test]
+fn fail_long_str_invalid_utf8() {
+    // Long enough for vectorized validation, with the error near the end.
+    let mut buf = rmps::to_vec(&"é".repeat(500)).unwrap();
+    buf[990] = 0xff;
+
+    match rmps::from_slice::<String>(&buf).unwrap_err() {
+        decode::Error::Utf8Error(err) => assert_eq!(986, err.valid_up_to()),
+        err => panic!("unexpected error: {:?}", err),
+    }
+    let raw: RawRef<'_> = rmps::from_slice(&buf).unwrap();
+    assert_eq!(986, raw.as_err().unwrap().valid_up_to());
+}
+
 #[test]
 fn fail_depth_limit() {
     #[allow(dead_code)]
@@ -569,3 +762,959 @@ fn fail_depth_limit() {
         other => panic!("unexpected result: {:?}", other),
     }
 }
+
+#[cfg(feature = "mmap")]
+fn temp_file_with(name: &str, contents: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("rmp-serde-{}-{}.msgpack", name, std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn pass_from_path() {
+    // ["le message", 42]
+    let path = temp_file_with("from-path", &[0x92, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65, 0x2a]);
+
+    let actual: (String, u8) = unsafe { rmps::from_path(&path) }.unwrap();
+    assert_eq!(("le message".to_string(), 42), actual);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn pass_mapped_file_borrows() {
+    let path = temp_file_with("mapped-borrow", &[0x92, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65, 0x2a]);
+
+    let map = unsafe { decode::MappedFile::open(&path) }.unwrap();
+    let (msg, num): (&str, u8) = map.deserialize().unwrap();
+    assert_eq!(("le message", 42), (msg, num));
+    assert!(map.as_bytes().as_ptr_range().contains(&msg.as_ptr()));
+
+    drop(map);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn fail_from_path_missing_file() {
+    let path = std::env::temp_dir().join("rmp-serde-this-file-does-not-exist.msgpack");
+
+    match unsafe { rmps::from_path::<_, u8>(&path) } {
+        Err(Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidMarkerRead(err))) => {
+            assert_eq!(io::ErrorKind::NotFound, err.kind());
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn fail_from_file_truncated() {
+    let path = temp_file_with("truncated", &[0x92, 0xaa, 0x6c, 0x65]);
+
+    let file = std::fs::File::open(&path).unwrap();
+    match unsafe { rmps::from_file::<(String, u8)>(&file) } {
+        Err(Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidDataRead(err))) => {
+            assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    drop(file);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn pass_push_decoder_byte_by_byte() {
+    let mut buf = rmps::to_vec(&("le message", 42u32)).unwrap();
+    buf.extend(rmps::to_vec(&("tail", 1u32)).unwrap());
+
+    let mut dec = decode::PushDecoder::new();
+    let mut vals = Vec::new();
+    for &byte in &buf[..] {
+        dec.feed(&[byte]);
+        match dec.decode::<(String, u32)>().unwrap() {
+            decode::Progress::Done(val) => vals.push(val),
+            decode::Progress::NeedMoreData(needed) => assert!(needed > 0),
+        }
+    }
+
+    assert_eq!(vec![("le message".to_owned(), 42), ("tail".to_owned(), 1)], vals);
+    assert!(dec.buffered().is_empty());
+}
+
+#[test]
+fn pass_push_decoder_reports_bytes_needed() {
+    let mut dec = decode::PushDecoder::new();
+
+    // bin16 header announcing 300 bytes.
+    dec.feed(&[0xc5, 0x01, 0x2c]);
+    assert_eq!(decode::Progress::NeedMoreData(300), dec.decode::<serde_bytes::ByteBuf>().unwrap());
+
+    dec.feed(&[0x42; 299]);
+    assert_eq!(decode::Progress::NeedMoreData(1), dec.decode::<serde_bytes::ByteBuf>().unwrap());
+
+    dec.feed(&[0x42]);
+    assert_eq!(decode::Progress::Done(serde_bytes::ByteBuf::from(vec![0x42; 300])), dec.decode().unwrap());
+}
+
+#[test]
+fn fail_push_decoder_skips_invalid_value() {
+    let mut dec = decode::PushDecoder::new();
+    dec.feed(&[0xa3, 0x66, 0x6f, 0x6f, 0x2a]);
+
+    match dec.decode::<u32>() {
+        Err(Error::TypeMismatch(..)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(decode::Progress::Done(42), dec.decode::<u32>().unwrap());
+}
+
+#[test]
+fn pass_map_with_int_keys_as_str() {
+    use std::collections::{BTreeMap, HashMap};
+
+    // {1: "a", -2: "b", 300: "c", "k": "d"}
+    let buf = [0x84, 0x01, 0xa1, 0x61, 0xfe, 0xa1, 0x62, 0xcd, 0x01, 0x2c, 0xa1, 0x63, 0xa1, 0x6b, 0xa1, 0x64];
+
+    let mut de = Deserializer::new(&buf[..]);
+    HashMap::<String, String>::deserialize(&mut de).unwrap_err();
+
+    let mut de = Deserializer::new(Cursor::new(&buf[..])).with_int_keys_as_str();
+    let map = BTreeMap::<String, String>::deserialize(&mut de).unwrap();
+    let expected: BTreeMap<String, String> = [("1", "a"), ("-2", "b"), ("300", "c"), ("k", "d")].iter()
+        .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+        .collect();
+    assert_eq!(expected, map);
+
+    // {i64::MIN: 1}
+    let buf = [0x81, 0xd3, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+    let mut de = Deserializer::new(&buf[..]).with_int_keys_as_str();
+    let map = HashMap::<String, u8>::deserialize(&mut de).unwrap();
+    assert_eq!(Some(&1), map.get("-9223372036854775808"));
+
+    // Integer-keyed maps are not affected.
+    let buf = [0x81, 0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    let mut de = Deserializer::new(&buf[..]).with_int_keys_as_str();
+    let map = HashMap::<String, u8>::deserialize(&mut de).unwrap();
+    assert_eq!(Some(&1), map.get("18446744073709551615"));
+    let mut de = Deserializer::new(&buf[..]).with_int_keys_as_str();
+    let map = HashMap::<u64, u8>::deserialize(&mut de).unwrap();
+    assert_eq!(Some(&1), map.get(&u64::MAX));
+}
+
+#[test]
+fn pass_numbers_as_str_in_human_readable_mode() {
+    // A type that represents itself as a decimal string in human-readable formats.
+    #[derive(Debug, PartialEq)]
+    struct Amount(String);
+
+    impl<'de> Deserialize<'de> for Amount {
+        fn deserialize<D: de::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            assert!(de.is_human_readable());
+            String::deserialize(de).map(Amount)
+        }
+    }
+
+    // [42, -1, 1.5, 1e300, "7"]
+    let buf = [0x95, 0x2a, 0xff, 0xca, 0x3f, 0xc0, 0x00, 0x00,
+               0xcb, 0x7e, 0x37, 0xe4, 0x3c, 0x88, 0x00, 0x75, 0x9c, 0xa1, 0x37];
+    let mut de = Deserializer::new(&buf[..]).with_human_readable().with_numbers_as_str();
+    let amounts = Vec::<Amount>::deserialize(&mut de).unwrap();
+    let expected = ["42", "-1", "1.5", "1e300", "7"].iter().map(|s| Amount(s.to_string())).collect::<Vec<_>>();
+    assert_eq!(expected, amounts);
+
+    // Without the option, or in binary mode, numbers are rejected.
+    let mut de = Deserializer::new(&buf[..]).with_human_readable();
+    Vec::<Amount>::deserialize(&mut de).unwrap_err();
+    let mut de = Deserializer::new(&[0x2a][..]).with_numbers_as_str();
+    String::deserialize(&mut de).unwrap_err();
+}
+
+#[test]
+fn fail_strict_numbers_type_mismatch() {
+    #[track_caller]
+    fn strict<'de, T: Deserialize<'de>>(buf: &'de [u8]) -> Result<T, Error<io::Error>> {
+        let mut de = Deserializer::new(buf).with_strict_numbers();
+        T::deserialize(&mut de)
+    }
+
+    // U16 0x0001 is in range of u8, but wider.
+    assert_eq!(1u8, rmps::from_slice(&[0xcd, 0x00, 0x01]).unwrap());
+    match strict::<u8>(&[0xcd, 0x00, 0x01]) {
+        Err(Error::TypeMismatch(err)) if err.marker() == Some(Marker::U16) && err.expected() == "u8" => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(
+        "wrong msgpack marker U16, expected u8 at offset 0",
+        strict::<u8>(&[0xcd, 0x00, 0x01]).unwrap_err().to_string()
+    );
+    assert_eq!(1u16, strict(&[0xcd, 0x00, 0x01]).unwrap());
+
+    // Signed markers are rejected for unsigned types.
+    match strict::<u32>(&[0xd0, 0x05]) {
+        Err(Error::TypeMismatch(err)) if err.marker() == Some(Marker::I8) && err.expected() == "u32" => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(5i16, strict(&[0xd0, 0x05]).unwrap());
+    assert_eq!(-1i8, strict(&[0xff]).unwrap());
+    assert_eq!(200i16, strict(&[0xcc, 0xc8]).unwrap());
+
+    // Floats must match exactly, and are never integers.
+    assert_eq!(1.5f32, strict(&[0xca, 0x3f, 0xc0, 0x00, 0x00]).unwrap());
+    strict::<f64>(&[0xca, 0x3f, 0xc0, 0x00, 0x00]).unwrap_err();
+    strict::<f64>(&[0x01]).unwrap_err();
+    strict::<u8>(&[0xca, 0x3f, 0xc0, 0x00, 0x00]).unwrap_err();
+
+    // Everything the serializer writes is accepted.
+    let val = (u8::MAX, u16::MAX, u32::MAX, u64::MAX, i8::MIN, i16::MIN, i32::MIN, i64::MIN, i64::MAX, 1.5f32, 2.5f64);
+    let buf = rmps::to_vec(&val).unwrap();
+    assert_eq!(val, strict(&buf).unwrap());
+}
+
+#[test]
+fn pass_saturating_and_wrapping_ints() {
+    // [300, -1, -200, u64::MAX]
+    let buf = [0x94, 0xcd, 0x01, 0x2c, 0xff, 0xd1, 0xff, 0x38, 0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+    rmps::from_slice::<(u8, u8, i8, i64)>(&buf).unwrap_err();
+
+    let mut de = Deserializer::new(&buf[..]).with_saturating_ints();
+    assert_eq!((255u8, 0u8, -128i8, i64::MAX), Deserialize::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::new(&buf[..]).with_wrapping_ints();
+    assert_eq!((44u8, 255u8, 56i8, -1i64), Deserialize::deserialize(&mut de).unwrap());
+
+    // Values in range and other types are unchanged.
+    let buf = rmps::to_vec(&(1u8, -1i64, 1.5f32, "x")).unwrap();
+    let mut de = Deserializer::new(&buf[..]).with_saturating_ints();
+    assert_eq!((1u8, -1i64, 1.5f32, "x".to_owned()), Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_f32_from_f64() {
+    // Doubles are rounded by default.
+    let buf = rmps::to_vec(&0.1f64).unwrap();
+    assert_eq!(0.1f32, rmps::from_slice(&buf).unwrap());
+
+    let mut de = Deserializer::new(&buf[..]).with_exact_f32();
+    match f32::deserialize(&mut de).unwrap_err() {
+        Error::Syntax(msg) => assert!(msg.contains("exactly representable"), "{}", msg),
+        err => panic!("unexpected error: {}", err),
+    }
+
+    for val in [0.5f64, -1e10, f64::INFINITY] {
+        let buf = rmps::to_vec(&val).unwrap();
+        let mut de = Deserializer::new(&buf[..]).with_exact_f32();
+        assert_eq!(val as f32, f32::deserialize(&mut de).unwrap());
+    }
+    let buf = rmps::to_vec(&f64::NAN).unwrap();
+    let mut de = Deserializer::new(&buf[..]).with_exact_f32();
+    assert!(f32::deserialize(&mut de).unwrap().is_nan());
+}
+
+#[test]
+fn pass_float_from_int() {
+    // Only handles floats, like many hand-written implementations.
+    #[derive(Debug, PartialEq)]
+    struct Meters(f64);
+
+    impl<'de> Deserialize<'de> for Meters {
+        fn deserialize<D: de::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            struct MetersVisitor;
+
+            impl<'de> de::Visitor<'de> for MetersVisitor {
+                type Value = Meters;
+
+                fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+                    fmt.write_str("a float")
+                }
+
+                fn visit_f64<E: de::Error>(self, v: f64) -> Result<Meters, E> {
+                    Ok(Meters(v))
+                }
+            }
+
+            de.deserialize_f64(MetersVisitor)
+        }
+    }
+
+    // [3, -2, 1.5, u64::MAX]
+    let buf = [0x94, 0x03, 0xfe, 0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+               0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    let expected = vec![Meters(3.0), Meters(-2.0), Meters(1.5), Meters(u64::MAX as f64)];
+    assert_eq!(expected, rmps::from_slice::<Vec<Meters>>(&buf).unwrap());
+    assert_eq!((3.0f32, -2.0f32), rmps::from_slice(&[0x92, 0x03, 0xfe]).unwrap());
+
+    // Except in strict mode.
+    let mut de = Deserializer::new(&buf[..]).with_strict_numbers();
+    Vec::<Meters>::deserialize(&mut de).unwrap_err();
+}
+
+#[test]
+fn fail_non_finite_floats() {
+    let buf = rmps::to_vec(&(1.5f64, f64::NAN)).unwrap();
+    let (_, nan): (f64, f64) = rmps::from_slice(&buf).unwrap();
+    assert!(nan.is_nan());
+
+    let mut de = Deserializer::new(&buf[..]).with_finite_floats();
+    <(f64, f64)>::deserialize(&mut de).unwrap_err();
+
+    let buf = rmps::to_vec(&(1.5f32, f32::NEG_INFINITY)).unwrap();
+    let mut de = Deserializer::new(&buf[..]).with_finite_floats();
+    <(f32, f32)>::deserialize(&mut de).unwrap_err();
+
+    let buf = rmps::to_vec(&f64::INFINITY).unwrap();
+    let mut de = Deserializer::new(&buf[..]).with_finite_floats().with_exact_f32();
+    f32::deserialize(&mut de).unwrap_err();
+
+    let buf = rmps::to_vec(&(1.5f32, 2.5f64)).unwrap();
+    let mut de = Deserializer::new(&buf[..]).with_finite_floats();
+    assert_eq!((1.5f32, 2.5f64), Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_int_arrays_as_bytes() {
+    // [1, 200, 3], written by generic serde code for a `Vec<u8>`.
+    let buf = [0x93, 0x01, 0xcc, 0xc8, 0x03];
+    let mut de = Deserializer::new(&buf[..]);
+    rmps::ByteBuf::deserialize(&mut de).unwrap_err();
+
+    let mut de = Deserializer::new(&buf[..]).with_int_arrays_as_bytes();
+    assert_eq!(vec![1, 200, 3], rmps::ByteBuf::deserialize(&mut de).unwrap().into_vec());
+
+    // Bin and str still work, and other sequences are unaffected.
+    let mut de = Deserializer::new(&[0x92, 0xc4, 0x01, 0x07, 0xa1, 0x61][..]).with_int_arrays_as_bytes();
+    assert_eq!((rmps::ByteBuf(vec![7]), rmps::ByteBuf(b"a".to_vec())), Deserialize::deserialize(&mut de).unwrap());
+    let mut de = Deserializer::new(&buf[..]).with_int_arrays_as_bytes();
+    assert_eq!(vec![1u16, 200, 3], Vec::<u16>::deserialize(&mut de).unwrap());
+
+    // Elements must be bytes.
+    let mut de = Deserializer::new(&[0x92, 0x01, 0xcd, 0x01, 0x00][..]).with_int_arrays_as_bytes();
+    rmps::ByteBuf::deserialize(&mut de).unwrap_err();
+    let mut de = Deserializer::new(&[0x91, 0xa1, 0x61][..]).with_int_arrays_as_bytes();
+    rmps::ByteBuf::deserialize(&mut de).unwrap_err();
+}
+
+#[test]
+fn pass_bin_sink_in_chunks() {
+    use rmps::bin_stream::BinSink;
+    use serde::de::DeserializeSeed;
+
+    /// Records the size of each write.
+    #[derive(Default)]
+    struct Chunks(Vec<usize>);
+
+    impl io::Write for Chunks {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let data: Vec<u8> = (0..150_000u32).map(|i| i as u8).collect();
+    let buf = rmps::to_vec(&serde_bytes::Bytes::new(&data)).unwrap();
+
+    let mut chunks = Chunks::default();
+    let mut de = Deserializer::new(&buf[..]);
+    assert_eq!(150_000, BinSink(&mut chunks).deserialize(&mut de).unwrap());
+    assert_eq!(vec![65536, 65536, 18928], chunks.0);
+
+    #[derive(serde_derive::Deserialize)]
+    struct Upload(#[serde(deserialize_with = "rmps::bin_stream::deserialize")] Vec<u8>);
+
+    let upload: Upload = rmps::from_slice(&buf).unwrap();
+    assert_eq!(data, upload.0);
+
+    // Str and empty bin, but nothing else.
+    let mut out = Vec::new();
+    BinSink(&mut out).deserialize(&mut Deserializer::new(&[0xa2, b'h', b'i'][..])).unwrap();
+    BinSink(&mut out).deserialize(&mut Deserializer::new(&[0xc4, 0x00][..])).unwrap();
+    assert_eq!(b"hi", &out[..]);
+    BinSink(&mut out).deserialize(&mut Deserializer::new(&[0x91, 0x01][..])).unwrap_err();
+
+    // Truncated data.
+    BinSink(io::sink()).deserialize(&mut Deserializer::new(&buf[..100_000])).unwrap_err();
+}
+
+#[test]
+fn pass_old_spec_raw_as_bytes() {
+    /// Accepts bytes only, not strings.
+    #[derive(Debug, PartialEq)]
+    struct OnlyBytes(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for OnlyBytes {
+        fn deserialize<D: de::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            struct OnlyBytesVisitor;
+
+            impl<'de> de::Visitor<'de> for OnlyBytesVisitor {
+                type Value = OnlyBytes;
+
+                fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+                    fmt.write_str("bytes")
+                }
+
+                fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<OnlyBytes, E> {
+                    Ok(OnlyBytes(v.to_vec()))
+                }
+            }
+
+            de.deserialize_bytes(OnlyBytesVisitor)
+        }
+    }
+
+    // ["ab", <raw "cd">, <raw 0xff>]
+    let buf = [0x93, 0xa2, b'a', b'b', 0xa2, b'c', b'd', 0xa1, 0xff];
+
+    let mut de = Deserializer::new(&buf[..]);
+    <(String, OnlyBytes, OnlyBytes)>::deserialize(&mut de).unwrap_err();
+
+    let mut de = Deserializer::new(&buf[..]).with_old_spec();
+    let val = <(String, OnlyBytes, OnlyBytes)>::deserialize(&mut de).unwrap();
+    assert_eq!(("ab".to_owned(), OnlyBytes(b"cd".to_vec()), OnlyBytes(vec![0xff])), val);
+
+    // Bin is still accepted.
+    let mut de = Deserializer::new(&[0xc4, 0x01, 0x07][..]).with_old_spec();
+    assert_eq!(OnlyBytes(vec![7]), OnlyBytes::deserialize(&mut de).unwrap());
+}
+
+#[cfg(feature = "bumpalo")]
+#[test]
+fn pass_bump_seeds() {
+    use rmps::bump::{Bump, BumpBytes, BumpStr};
+    use serde::de::DeserializeSeed;
+
+    let bump = Bump::new();
+    let buf = [0xa2, b'h', b'i', 0xc4, 0x02, 0x01, 0x02, 0xc4, 0x01, b'x', 0xa1, b'y'];
+
+    let mut de = Deserializer::new(&buf[..]);
+    assert_eq!("hi", BumpStr(&bump).deserialize(&mut de).unwrap());
+    assert_eq!([1, 2], BumpBytes(&bump).deserialize(&mut de).unwrap());
+    assert_eq!("x", BumpStr(&bump).deserialize(&mut de).unwrap());
+    assert_eq!(b"y", BumpBytes(&bump).deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::new(&[0xc4, 0x01, 0xff][..]);
+    BumpStr(&bump).deserialize(&mut de).unwrap_err();
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn pass_from_slice_par() {
+    let rows: Vec<(u32, String)> = (0..5000).map(|i| (i, i.to_string())).collect();
+    let buf = rmps::to_vec(&rows).unwrap();
+    assert_eq!(rows, rmps::par::from_slice_par::<(u32, String)>(&buf).unwrap());
+
+    // Borrowing from the input.
+    let buf = [0x92, 0xa1, b'a', 0xa2, b'b', b'c'];
+    assert_eq!(vec!["a", "bc"], rmps::par::from_slice_par::<&str>(&buf).unwrap());
+
+    assert_eq!(Vec::<u8>::new(), rmps::par::from_slice_par::<u8>(&[0x90]).unwrap());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn fail_from_slice_par() {
+    // Not an array.
+    match rmps::par::from_slice_par::<u8>(&[0x01]) {
+        Err(Error::InvalidValueRead(rmp::decode::ValueReadError::TypeMismatch(Marker::FixPos(1)))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Truncated.
+    match rmps::par::from_slice_par::<u8>(&[0x93, 0x01, 0xa2, b'a']) {
+        Err(Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidDataRead(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // A mismatched element.
+    match rmps::par::from_slice_par::<u8>(&[0x92, 0x01, 0xc0]) {
+        Err(Error::TypeMismatch(err)) if err.marker() == Some(Marker::Null) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_field_matcher() {
+    use rmps::fields::{FieldMatcher, FieldSeed};
+    use serde::de::DeserializeSeed;
+
+    let matcher = FieldMatcher::new(&["id", "name", "ab", "name"]);
+    assert_eq!(Some(0), matcher.find(b"id"));
+    assert_eq!(Some(1), matcher.find(b"name"));
+    assert_eq!(Some(2), matcher.find(b"ab"));
+    assert_eq!(None, matcher.find(b"xy"));
+    assert_eq!(None, matcher.find(b"a long key"));
+    assert_eq!(None, matcher.find(b""));
+
+    // "ab", 3, 9, <invalid UTF-8>
+    let buf = [0xa2, b'a', b'b', 0x03, 0x09, 0xa2, 0xff, 0xfe];
+    let mut de = Deserializer::new(&buf[..]);
+    assert_eq!(Some(2), FieldSeed(&matcher).deserialize(&mut de).unwrap());
+    assert_eq!(Some(3), FieldSeed(&matcher).deserialize(&mut de).unwrap());
+    assert_eq!(None, FieldSeed(&matcher).deserialize(&mut de).unwrap());
+    assert_eq!(None, FieldSeed(&matcher).deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_interned_strings() {
+    use rmps::intern::{InternSeed, Interned, Interner, MAX_INTERNED_LEN};
+    use serde::de::DeserializeSeed;
+    use std::sync::Arc;
+
+    let long = "x".repeat(MAX_INTERNED_LEN + 1);
+    let buf = rmps::to_vec(&["a", "b", "a", &long, &long]).unwrap();
+
+    let vals: Vec<Interned> = rmps::from_slice(&buf).unwrap();
+    assert_eq!(["a", "b", "a", &long, &long], *vals.iter().map(|v| &*v.0).collect::<Vec<_>>());
+    assert!(Arc::ptr_eq(&vals[0].0, &vals[2].0));
+    assert!(!Arc::ptr_eq(&vals[3].0, &vals[4].0));
+
+    // Shared with later decodes on the same thread, until cleared.
+    let val: Interned = rmps::from_slice(&buf[1..]).unwrap();
+    assert!(Arc::ptr_eq(&vals[0].0, &val.0));
+    rmps::intern::clear();
+    let val: Interned = rmps::from_slice(&buf[1..]).unwrap();
+    assert!(!Arc::ptr_eq(&vals[0].0, &val.0));
+
+    let mut interner = Interner::new();
+    let mut de = Deserializer::new(&buf[1..]);
+    let a = InternSeed(&mut interner).deserialize(&mut de).unwrap();
+    InternSeed(&mut interner).deserialize(&mut de).unwrap();
+    let again = InternSeed(&mut interner).deserialize(&mut de).unwrap();
+    assert!(Arc::ptr_eq(&a, &again));
+    assert_eq!(2, interner.len());
+}
+
+#[test]
+fn pass_checkpoint_rewind() {
+    // [[1, 2], "x"]: try the first element as a string, then as a pair.
+    let buf = [0x92, 0x92, 0x01, 0x02, 0xa1, b'x'];
+    let mut de = Deserializer::from_bytes(&buf);
+    assert_eq!(0, de.checkpoint().offset());
+
+    let mut de2 = Deserializer::from_bytes(&buf[1..]);
+    let checkpoint = de2.checkpoint();
+    assert!(<&str>::deserialize(&mut de2).is_err());
+    de2.rewind(checkpoint);
+    assert_eq!((1u8, 2u8), Deserialize::deserialize(&mut de2).unwrap());
+
+    let checkpoint = de2.checkpoint();
+    assert_eq!(3, checkpoint.offset());
+    assert_eq!("x", <&str>::deserialize(&mut de2).unwrap());
+    de2.rewind(checkpoint);
+    assert_eq!('x', char::deserialize(&mut de2).unwrap());
+
+    let checkpoint = de.checkpoint();
+    assert_eq!(((1u8, 2u8), "x"), <((u8, u8), &str)>::deserialize(&mut de).unwrap());
+    de.rewind(checkpoint);
+    assert_eq!((vec![1u8, 2], 'x'), Deserialize::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn pass_try_deserialize() {
+    #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    // An untagged-style dispatch over a sequence of values, without buffering them.
+    let buf = [0x92, 0x01, 0x02, 0xa3, b'a', b'b', b'c', 0x92, 0x01, 0xa1, b'x'];
+    let mut de = Deserializer::from_bytes(&buf);
+
+    assert!(de.try_deserialize::<&str>().is_err());
+    assert_eq!(Point { x: 1, y: 2 }, de.try_deserialize().unwrap());
+
+    assert!(de.try_deserialize::<Point>().is_err());
+    assert_eq!("abc", de.try_deserialize::<&str>().unwrap());
+
+    // Fails halfway through the array.
+    assert!(de.try_deserialize::<Point>().is_err());
+    assert_eq!((1, "x"), de.try_deserialize::<(u8, &str)>().unwrap());
+
+    match de.try_deserialize::<u8>() {
+        Err(Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidMarkerRead(..))) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn pass_remaining_slice() {
+    // Two frames back to back, followed by the start of a third one.
+    let mut buf = rmps::to_vec(&(1, "a")).unwrap();
+    buf.extend(rmps::to_vec(&[true, false]).unwrap());
+    buf.push(0x92);
+
+    let mut de = Deserializer::from_bytes(&buf);
+    assert_eq!(buf.len(), de.remaining());
+    let (id, name): (u8, &str) = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!((1, "a"), (id, name));
+    assert_eq!(&buf[4..], de.remaining_slice());
+
+    let flags: [bool; 2] = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!([true, false], flags);
+    assert_eq!([0x92], de.remaining_slice());
+
+    // The remaining input of a failed attempt is restored with it.
+    let checkpoint = de.checkpoint();
+    de.try_deserialize::<(u8, u8)>().unwrap_err();
+    assert_eq!(checkpoint, de.checkpoint());
+    assert_eq!(1, de.remaining());
+}
+
+#[test]
+fn pass_erased_error() {
+    use std::error::Error as _;
+
+    use rmps::decode::AnyReadError;
+    use rmps::ErasedError;
+
+    fn erased<R: rmp::decode::RmpReadErr + Send + Sync>(res: Result<u32, Error<R>>) -> ErasedError {
+        res.unwrap_err().into()
+    }
+
+    // Reader errors are kept and can be recovered.
+    let err = erased(rmps::from_slice::<u32>(&[0xce, 0x00]));
+    let source = err.source().unwrap().downcast_ref::<AnyReadError>().unwrap();
+    match source.downcast_ref::<decode::BytesReadError>() {
+        Some(decode::BytesReadError::InsufficientBytes { expected: 4, actual: 1, position: 1 }) => {}
+        other => panic!("unexpected error: {other:?}"),
+    }
+
+    let err = erased(rmps::from_read::<_, u32>(&[0xce, 0x00][..]));
+    match err.get_ref() {
+        Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidDataRead(err)) => {
+            assert_eq!(io::ErrorKind::UnexpectedEof, err.downcast_ref::<io::Error>().unwrap().kind());
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+
+    // Other errors are unchanged.
+    let err = erased(rmps::from_slice::<u32>(&[0xa1, b'x']));
+    assert_eq!(rmps::from_slice::<u32>(&[0xa1, b'x']).unwrap_err().to_string(), err.to_string());
+    assert!(matches!(err.into_inner(), Error::TypeMismatch(..)));
+}
+
+#[cfg(feature = "defmt")]
+#[test]
+fn pass_defmt_format() {
+    // Logging needs a defmt global logger, which host tests don't have.
+    fn assert_format<T: defmt::Format>() {}
+    assert_format::<Error<decode::BytesReadError>>();
+    assert_format::<rmps::encode::Error<rmps::encode::BufferFull>>();
+    assert_format::<Marker>();
+}
+
+#[test]
+fn pass_infer_schema() {
+    use std::collections::BTreeMap;
+
+    use rmps::schema::{infer_schema, Schema};
+
+    #[derive(serde_derive::Serialize)]
+    struct Event {
+        id: i64,
+        payload: rmpv::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tag: Option<&'static str>,
+    }
+
+    let events = vec![
+        Event { id: -1, payload: rmpv::Value::from(1.5), tag: Some("a") },
+        Event { id: 70000, payload: rmpv::Value::Ext(3, vec![1]), tag: None },
+        Event { id: 2, payload: rmpv::Value::Ext(-1, vec![]), tag: None },
+    ];
+    let schema = infer_schema(&rmps::to_vec_named(&events).unwrap()).unwrap();
+    assert_eq!("[{id: int(-1..=70000), payload: f64 | ext(-1 | 3), tag?: str}]", schema.to_string());
+    assert_eq!(2, schema.depth());
+    match schema {
+        Schema::Array(ref items) => assert!(items.is_homogeneous()),
+        ref other => panic!("unexpected schema: {other:?}"),
+    }
+
+    // Maps with non-string keys, and arrays of mixed types.
+    let map: BTreeMap<u8, Vec<rmpv::Value>> = [(1, vec![rmpv::Value::Nil, rmpv::Value::from("x")]), (2, vec![])].into();
+    let schema = infer_schema(&rmps::to_vec(&map).unwrap()).unwrap();
+    assert_eq!("map<int(1..=2), [nil | str]>", schema.to_string());
+
+    // {"a": 1, 2: "x"}
+    let schema = infer_schema(&[0x82, 0xa1, b'a', 0x01, 0x02, 0xa1, b'x']).unwrap();
+    assert_eq!("map<str | int(2), int(1) | str>", schema.to_string());
+
+    // Concatenated values are merged.
+    let mut buf = rmps::to_vec(&1u8).unwrap();
+    buf.extend(rmps::to_vec(&true).unwrap());
+    assert_eq!(Schema::Union(vec![Schema::Int { min: 1, max: 1 }, Schema::Bool]), infer_schema(&buf).unwrap());
+    assert_eq!(Schema::Never, infer_schema(&[]).unwrap());
+}
+
+#[test]
+fn fail_infer_schema() {
+    use rmps::schema::infer_schema;
+
+    // Truncated array.
+    assert!(matches!(infer_schema(&[0x92, 0x01]), Err(Error::InvalidValueRead(..))));
+    assert!(matches!(infer_schema(&[0x91; 2000]), Err(Error::DepthLimitExceeded)));
+}
+
+#[test]
+fn pass_pointer_get() {
+    use rmps::pointer::{find, get};
+
+    // {"users": [{"name": "a"}, {"name": "b", "a/b": 1, "m~n": 2}], 7: true, -1: nil}
+    let buf = [
+        0x83,
+        0xa5, b'u', b's', b'e', b'r', b's', 0x92,
+        0x81, 0xa4, b'n', b'a', b'm', b'e', 0xa1, b'a',
+        0x83, 0xa4, b'n', b'a', b'm', b'e', 0xa1, b'b', 0xa3, b'a', b'/', b'b', 0x01, 0xa3, b'm', b'~', b'n', 0x02,
+        0x07, 0xc3,
+        0xff, 0xc0,
+    ];
+
+    assert_eq!(Some("b"), get::<&str>(&buf, "/users/1/name").unwrap());
+    assert_eq!(Some(1), get::<u8>(&buf, "/users/1/a~1b").unwrap());
+    assert_eq!(Some(2), get::<u8>(&buf, "/users/1/m~0n").unwrap());
+    assert_eq!(Some(true), get::<bool>(&buf, "/7").unwrap());
+    assert_eq!(Some(&[0xc0][..]), find(&buf, "/-1").unwrap());
+    assert_eq!(Some(&buf[..]), find(&buf, "").unwrap());
+    let user: std::collections::BTreeMap<String, String> = get(&buf, "/users/0").unwrap().unwrap();
+    assert_eq!(Some(&"a".to_owned()), user.get("name"));
+
+    // Missing paths.
+    assert_eq!(None, find(&buf, "/users/2").unwrap());
+    assert_eq!(None, find(&buf, "/users/01").unwrap());
+    assert_eq!(None, find(&buf, "/users/-").unwrap());
+    assert_eq!(None, find(&buf, "/users/0/name/x").unwrap());
+    assert_eq!(None, find(&buf, "/nope").unwrap());
+}
+
+#[test]
+fn fail_pointer_get() {
+    use rmps::pointer::{find, get};
+
+    let buf = [0x92, 0xa1, b'a'];
+    assert!(matches!(find(&buf, "0"), Err(Error::Uncategorized(..))));
+    assert!(matches!(find(&buf, "/1"), Err(Error::InvalidValueRead(..))));
+    get::<u8>(&buf, "/0").unwrap_err();
+}
+
+#[test]
+fn pass_select_fields() {
+    use std::collections::BTreeMap;
+
+    use rmps::select::Selection;
+
+    #[derive(serde_derive::Serialize)]
+    struct Record {
+        id: u32,
+        attrs: BTreeMap<String, u32>,
+        blob: serde_bytes::ByteBuf,
+    }
+
+    #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+    struct Picked {
+        id: u32,
+        #[serde(default)]
+        size: u32,
+        color: Option<u32>,
+    }
+
+    let record = Record {
+        id: 3,
+        attrs: [("size".to_owned(), 10), ("weight".to_owned(), 20)].into(),
+        blob: serde_bytes::ByteBuf::from(vec![0; 4096]),
+    };
+    let buf = rmps::to_vec_named(&record).unwrap();
+
+    let selection = Selection::new().field("id", "/id").field("size", "/attrs/size").field("color", "/attrs/color");
+    assert_eq!(Picked { id: 3, size: 10, color: None }, selection.from_slice(&buf).unwrap());
+
+    // Missing fields that are required, and values of the wrong type.
+    Selection::new().field("size", "/attrs/size").from_slice::<Picked>(&buf).unwrap_err();
+    Selection::new().field("id", "/blob").from_slice::<Picked>(&buf).unwrap_err();
+
+    // Selections can be deserialized into maps, too.
+    let map: BTreeMap<String, u32> = Selection::new().field("a", "/attrs/weight").from_slice(&buf).unwrap();
+    assert_eq!(BTreeMap::from([("a".to_owned(), 20)]), map);
+}
+
+#[test]
+fn pass_lazy_map() {
+    use rmps::lazy::LazyMap;
+
+    // {"route": "a", 1: [1, 2], "route": "b"}
+    let buf = [0x83, 0xa5, b'r', b'o', b'u', b't', b'e', 0xa1, b'a', 0x01, 0x92, 0x01, 0x02, 0xa5, b'r', b'o', b'u', b't', b'e', 0xa1, b'b'];
+    let map = LazyMap::new(&buf).unwrap();
+    assert_eq!(3, map.len());
+
+    let entries = map.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!((&[0x01][..], &[0x92, 0x01, 0x02][..]), entries[1]);
+    assert_eq!(Some("a"), map.get("route").unwrap());
+    assert_eq!(Some(&[0xa1, b'a'][..]), map.get_raw("route").unwrap());
+    assert_eq!(None::<u8>, map.get("nope").unwrap());
+    assert!(LazyMap::new(&[0x80]).unwrap().is_empty());
+}
+
+#[test]
+fn fail_lazy_map() {
+    use rmps::lazy::LazyMap;
+
+    assert!(matches!(LazyMap::new(&[0x91, 0x01]), Err(Error::TypeMismatch(err)) if err.marker() == Some(Marker::FixArray(1)) && err.expected() == "map"));
+    LazyMap::new(&[]).unwrap_err();
+
+    // Truncated entries are only found when reached.
+    let map = LazyMap::new(&[0x82, 0xa1, b'a', 0x01, 0xa1, b'b']).unwrap();
+    assert_eq!(Some(1), map.get::<u8>("a").unwrap());
+    map.get::<u8>("b").unwrap_err();
+    let mut iter = map.iter();
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn pass_lazy_array() {
+    use rmps::lazy::LazyArray;
+
+    let rows: Vec<(u32, String)> = (0..1000).map(|i| (i, "x".repeat(i as usize % 7))).collect();
+    let buf = rmps::to_vec(&rows).unwrap();
+    let array = LazyArray::new(&buf).unwrap();
+    assert_eq!(1000, array.len());
+
+    assert_eq!(Some((500, "xxx")), array.get::<(u32, &str)>(500).unwrap());
+    assert_eq!(None, array.get::<(u32, &str)>(1000).unwrap());
+    let page: Vec<(u32, String)> = array.iter()
+        .skip(990)
+        .map(|val| rmps::from_slice(val.unwrap()).unwrap())
+        .collect();
+    assert_eq!(rows[990..], page[..]);
+    assert!(LazyArray::new(&[0x90]).unwrap().is_empty());
+}
+
+#[test]
+fn fail_lazy_array() {
+    use rmps::lazy::LazyArray;
+
+    assert!(matches!(LazyArray::new(&[0x80]), Err(Error::TypeMismatch(err)) if err.marker() == Some(Marker::FixMap(0)) && err.expected() == "array"));
+
+    // Errors in skipped elements are reported.
+    let array = LazyArray::new(&[0x93, 0x01, 0xc1, 0x03]).unwrap();
+    assert_eq!(Some(1), array.get::<u8>(0).unwrap());
+    array.get::<u8>(1).unwrap_err();
+    array.get::<u8>(2).unwrap_err();
+    assert!(array.iter().nth(2).unwrap().is_err());
+}
+
+#[test]
+fn pass_count_top_level() {
+    use rmps::lazy::{count_top_level, seq_len};
+
+    let mut buf = Vec::new();
+    for i in 0..10u32 {
+        buf.extend(rmps::to_vec(&vec![i; i as usize]).unwrap());
+    }
+    assert_eq!(10, count_top_level(&buf).unwrap());
+    assert_eq!(0, count_top_level(&[]).unwrap());
+    assert_eq!(0, seq_len(&buf).unwrap());
+    assert_eq!(70000, seq_len(&rmps::to_vec(&vec![0u8; 70000]).unwrap()).unwrap());
+
+    // Truncated values, and values that aren't arrays.
+    assert!(matches!(count_top_level(&buf[..buf.len() - 1]), Err(Error::InvalidValueRead(..))));
+    assert!(matches!(seq_len(&[0x01]), Err(Error::TypeMismatch(..))));
+}
+
+#[test]
+fn pass_analyze_stats() {
+    use rmps::stats::{analyze, Count};
+
+    // [nil, true, 300, 1.5, "abc", bin[2], {"k": ext(5, [1, 2])}, {[1]: -1}]
+    let buf = [
+        0x98, 0xc0, 0xc3, 0xcd, 0x01, 0x2c, 0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xa3, b'a', b'b', b'c',
+        0xc4, 0x02, 0x00, 0x00, 0x81, 0xa1, b'k', 0xd5, 0x05, 0x01, 0x02, 0x81, 0x91, 0x01, 0xff,
+    ];
+    let stats = analyze(&buf).unwrap();
+
+    assert_eq!(Count { count: 1, header_bytes: 1, data_bytes: 0 }, stats.nil);
+    assert_eq!(Count { count: 1, header_bytes: 1, data_bytes: 0 }, stats.bool);
+    assert_eq!(Count { count: 3, header_bytes: 3, data_bytes: 2 }, stats.int);
+    assert_eq!(Count { count: 1, header_bytes: 1, data_bytes: 8 }, stats.float);
+    assert_eq!(Count { count: 2, header_bytes: 2, data_bytes: 4 }, stats.str);
+    assert_eq!(Count { count: 1, header_bytes: 2, data_bytes: 2 }, stats.bin);
+    assert_eq!(Count { count: 2, header_bytes: 2, data_bytes: 0 }, stats.array);
+    assert_eq!(Count { count: 2, header_bytes: 2, data_bytes: 0 }, stats.map);
+    assert_eq!(Count { count: 1, header_bytes: 2, data_bytes: 2 }, stats.ext);
+    assert_eq!(Some(&stats.ext), stats.ext_types.get(&5));
+    assert_eq!(Count { count: 2, header_bytes: 0, data_bytes: 4 }, stats.map_keys);
+    assert_eq!(1, stats.values);
+    assert_eq!(buf.len(), stats.total_bytes);
+    assert!(stats.to_string().contains("ext(5)"));
+
+    // Concatenated values, and truncated ones.
+    let mut concat = buf.to_vec();
+    concat.extend_from_slice(&buf);
+    assert_eq!(2, analyze(&concat).unwrap().values);
+    assert!(matches!(analyze(&buf[..buf.len() - 1]), Err(Error::InvalidValueRead(..))));
+    assert!(matches!(analyze(&buf[..1]), Err(Error::InvalidValueRead(..))));
+}
+
+#[test]
+fn pass_iter_records() {
+    let path = std::env::temp_dir().join(format!("rmp-serde-records-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let long = "x".repeat(20_000);
+    rmps::append_to_file(&path, &(1, "one")).unwrap();
+    rmps::append_to_file(&path, &"not a tuple").unwrap();
+    rmps::append_to_file(&path, &(2, long.as_str())).unwrap();
+
+    let mut records = rmps::iter_file::<(u32, String), _>(&path).unwrap();
+    assert_eq!((1, "one".to_owned()), records.next().unwrap().unwrap());
+    let err = records.next().unwrap().unwrap_err();
+    assert_eq!(6, err.position());
+    assert_eq!(18, records.position());
+    assert_eq!((2, long), records.next().unwrap().unwrap());
+    assert!(records.next().is_none());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), records.position());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn fail_iter_records_truncated() {
+    // A complete record, then a str8 of 5 bytes with only 2 of them.
+    let buf = [0x01, 0xd9, 0x05, b'a', b'b'];
+    let mut records = rmps::records::RecordIter::<u8, _>::new(&buf[..]);
+    assert_eq!(1, records.next().unwrap().unwrap());
+    let err = records.next().unwrap().unwrap_err();
+    assert_eq!(1, err.position());
+    match err.into_inner().into_inner() {
+        Error::InvalidValueRead(rmp::decode::ValueReadError::InvalidDataRead(..)) => {}
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert!(records.next().is_none());
+
+    let buf = [0x02, 0xc1, 0x03];
+    let mut records = rmps::records::RecordIter::<u8, _>::new(&buf[..]);
+    assert_eq!(2, records.next().unwrap().unwrap());
+    assert_eq!(1, records.next().unwrap().unwrap_err().position());
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn pass_seed_entry_points() {
+    use rmps::intern::{InternSeed, Interner};
+    use std::sync::Arc;
+
+    let buf = rmps::to_vec(&"shared").unwrap();
+    let mut interner = Interner::new();
+    let a = rmps::from_slice_seed(&buf, InternSeed(&mut interner)).unwrap();
+    let b = rmps::from_read_seed(Cursor::new(&buf), InternSeed(&mut interner)).unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(1, interner.len());
+
+    assert!(rmps::from_read_seed(Cursor::new(&[0xc3]), InternSeed(&mut interner)).is_err());
+}
@@ -3,7 +3,7 @@ extern crate serde_derive;
 
 extern crate rmp_serde as rmps;
 
-use std::io::Cursor;
+use std::io::{self, Cursor};
 
 use serde::Deserialize;
 
@@ -189,7 +189,7 @@ fn fail_enum_overflow() {
     }
 
     let mut de = Deserializer::new(cur);
-    let actual: Result<Enum, Error> = Deserialize::deserialize(&mut de);
+    let actual: Result<Enum, Error<io::Error>> = Deserialize::deserialize(&mut de);
 
     match actual.err().unwrap() {
         Error::Syntax(..) => (),
@@ -342,7 +342,7 @@ fn fail_internally_tagged_enum_tuple() {
     }
 
     let mut de = Deserializer::new(cur);
-    let actual: Result<Enum, Error> = Deserialize::deserialize(&mut de);
+    let actual: Result<Enum, Error<io::Error>> = Deserialize::deserialize(&mut de);
 
     assert!(actual.is_ok())
 }
@@ -360,7 +360,7 @@ fn pass_internally_tagged_enum_struct() {
     }
 
     let mut de = Deserializer::new(cur);
-    let actual: Result<Enum, Error> = Deserialize::deserialize(&mut de);
+    let actual: Result<Enum, Error<io::Error>> = Deserialize::deserialize(&mut de);
 
     assert!(actual.is_ok());
     assert_eq!(Enum::Foo { value: 123 }, actual.unwrap())
@@ -523,7 +523,6 @@ fn pass_from_slice() {
 }
 
 #[test]
-#[allow(deprecated)]
 fn pass_from_ref() {
     let buf = [0x92, 0xa5, 0x42, 0x6f, 0x62, 0x62, 0x79, 0x8];
 
@@ -533,5 +532,296 @@ fn pass_from_ref() {
         age: u8,
     }
 
-    assert_eq!(Dog { name: "Bobby", age: 8 }, rmps::from_read_ref(&buf).unwrap());
+    assert_eq!(Dog { name: "Bobby", age: 8 }, rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_ignored_field_with_ext() {
+    #[derive(Debug, PartialEq, Serialize)]
+    struct Full {
+        id: u32,
+        extra: rmps::Ext,
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Partial {
+        id: u32,
+        name: String,
+    }
+
+    let full = Full { id: 7, extra: rmps::Ext::new(3, vec![1, 2, 3]), name: "x".into() };
+    let buf = rmps::encode::to_vec_named(&full).unwrap();
+
+    assert_eq!(Partial { id: 7, name: "x".into() }, rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_ignored_fields_skipped() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Partial {
+        id: u32,
+        name: String,
+    }
+
+    // {"id": 7, "blob": <invalid UTF-8 str>, "deep": [[[...]]], "nested": {"a": [1.5, nil]},
+    //  "name": "x"}
+    let mut buf = vec![0x85, 0xa2, b'i', b'd', 0x07, 0xa4, b'b', b'l', b'o', b'b', 0xa2, 0xff, 0xfe];
+    buf.extend([0xa4, b'd', b'e', b'e', b'p']);
+    buf.extend([0x91; 2000]);
+    buf.push(0x90);
+    buf.extend([0xa6, b'n', b'e', b's', b't', b'e', b'd', 0x81, 0xa1, b'a', 0x92, 0xca, 0x3f, 0xc0, 0x00, 0x00, 0xc0]);
+    buf.extend([0xa4, b'n', b'a', b'm', b'e', 0xa1, b'x']);
+
+    let expected = Partial { id: 7, name: "x".into() };
+    assert_eq!(expected, rmps::from_slice(&buf).unwrap());
+    assert_eq!(expected, rmps::from_read(&buf[..]).unwrap());
+    assert_eq!(expected, Partial::deserialize(&mut Deserializer::from_buf_read(&buf[..])).unwrap());
+
+    // Truncated ignored data still fails.
+    rmps::from_slice::<Partial>(&buf[..20]).unwrap_err();
+}
+
+#[test]
+fn pass_identifier_to_str_visitor() {
+    use serde::de::{self, Visitor};
+    use std::fmt::{self, Formatter};
+
+    /// A field visitor implementing only `visit_str`, like the example in the serde docs.
+    #[derive(Debug, PartialEq)]
+    enum Field {
+        Secs,
+        Other(String),
+    }
+
+    impl<'de> Deserialize<'de> for Field {
+        fn deserialize<D: de::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            de.deserialize_identifier(FieldVisitor)
+        }
+    }
+
+    struct FieldVisitor;
+
+    impl<'de> Visitor<'de> for FieldVisitor {
+        type Value = Field;
+
+        fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+            fmt.write_str("`secs`")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Field, E> {
+            Ok(match v {
+                "secs" => Field::Secs,
+                other => Field::Other(other.into()),
+            })
+        }
+    }
+
+    /// Deserializes a struct through the `visit_str`-only field visitor.
+    #[derive(Debug, PartialEq)]
+    struct Duration {
+        secs: u64,
+    }
+
+    impl<'de> Deserialize<'de> for Duration {
+        fn deserialize<D: de::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            struct DurationVisitor;
+
+            impl<'de> Visitor<'de> for DurationVisitor {
+                type Value = Duration;
+
+                fn expecting(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+                    fmt.write_str("struct Duration")
+                }
+
+                fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Duration, A::Error> {
+                    let mut secs = None;
+                    while let Some(key) = map.next_key()? {
+                        match key {
+                            Field::Secs => secs = Some(map.next_value()?),
+                            Field::Other(..) => {
+                                map.next_value::<de::IgnoredAny>()?;
+                            }
+                        }
+                    }
+                    Ok(Duration { secs: secs.ok_or_else(|| de::Error::missing_field("secs"))? })
+                }
+            }
+
+            de.deserialize_struct("Duration", &["secs"], DurationVisitor)
+        }
+    }
+
+    let buf = [0xa4, b's', b'e', b'c', b's'];
+    assert_eq!(Field::Secs, rmps::from_slice(&buf).unwrap());
+    assert_eq!(Field::Secs, rmps::from_read(&buf[..]).unwrap());
+
+    // {"secs": 5, "nanos": 1}
+    let buf = [0x82, 0xa4, b's', b'e', b'c', b's', 0x05, 0xa5, b'n', b'a', b'n', b'o', b's', 0x01];
+    assert_eq!(Duration { secs: 5 }, rmps::from_slice(&buf).unwrap());
+    assert_eq!(Duration { secs: 5 }, rmps::from_read(&buf[..]).unwrap());
+}
+
+#[test]
+fn pass_flatten_keys_as_str() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Dog {
+        name: String,
+        #[serde(flatten)]
+        extra: rmpv::Value,
+    }
+
+    // {"name": "Rex", "age": 3}
+    let buf = [0x82, 0xa4, b'n', b'a', b'm', b'e', 0xa3, b'R', b'e', b'x', 0xa3, b'a', b'g', b'e', 0x03];
+    let dog: Dog = rmps::from_slice(&buf).unwrap();
+    assert_eq!(rmpv::Value::Map(vec![(rmpv::Value::from("age"), rmpv::Value::from(3))]), dog.extra);
+}
+
+#[test]
+fn pass_ext_as_tuple() {
+    let buf = rmps::to_vec(&(rmps::Ext::new(-2, vec![0xaa, 0xbb]), 5u8)).unwrap();
+
+    let ((tag, data), next): ((i8, serde_bytes::ByteBuf), u8) = rmps::from_slice(&buf).unwrap();
+    assert_eq!((-2, &[0xaa, 0xbb][..], 5), (tag, &data[..], next));
+
+    // The data that isn't visited is skipped, so decoding continues with the next value.
+    assert_eq!(((-2,), 5), rmps::from_slice::<((i8,), u8)>(&buf).unwrap());
+}
+
+#[test]
+fn pass_ext_in_untagged_enum() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Field {
+        Int(i64),
+        Text(String),
+        Ext(rmps::Ext),
+    }
+
+    let buf = rmps::to_vec(&(1, "two", rmps::Ext::new(3, vec![4]))).unwrap();
+
+    let fields: Vec<Field> = rmps::from_slice(&buf).unwrap();
+    assert_eq!(vec![Field::Int(1), Field::Text("two".into()), Field::Ext(rmps::Ext::new(3, vec![4]))], fields);
+}
+
+#[test]
+fn pass_ext_in_dynamic_map() {
+    use std::collections::HashMap;
+
+    let mut map = HashMap::new();
+    map.insert("ext", rmpv::Value::Ext(5, vec![6, 7]));
+    map.insert("int", rmpv::Value::from(8));
+    let buf = rmps::to_vec(&map).unwrap();
+
+    let decoded: HashMap<String, rmpv::Value> = rmps::from_slice(&buf).unwrap();
+    assert_eq!(rmpv::Value::Ext(5, vec![6, 7]), decoded["ext"]);
+    assert_eq!(rmpv::Value::from(8), decoded["int"]);
+}
+
+#[test]
+fn pass_struct_with_trailing_defaults() {
+    fn ten() -> u8 {
+        10
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        id: u8,
+        name: Option<String>,
+        #[serde(default = "ten")]
+        retries: u8,
+        tags: Option<Vec<String>>,
+    }
+
+    // [1]
+    let buf = [0x91, 0x01];
+    let mut de = Deserializer::new(&buf[..]).with_trailing_defaults();
+    assert_eq!(Config { id: 1, name: None, retries: 10, tags: None },
+               Config::deserialize(&mut de).unwrap());
+
+    // [1, "a", 3]
+    let buf = [0x93, 0x01, 0xa1, 0x61, 0x03];
+    let mut de = Deserializer::new(&buf[..]).with_trailing_defaults();
+    assert_eq!(Config { id: 1, name: Some("a".into()), retries: 3, tags: None },
+               Config::deserialize(&mut de).unwrap());
+
+    // Without the option, the missing `Option` fields are an error.
+    let buf = [0x91, 0x01];
+    let mut de = Deserializer::new(&buf[..]);
+    Config::deserialize(&mut de).unwrap_err();
+}
+
+#[test]
+fn fail_struct_with_trailing_defaults_missing_required() {
+    #[derive(Debug, Deserialize)]
+    struct Point {
+        _x: u8,
+        _y: u8,
+    }
+
+    // [1]
+    let buf = [0x91, 0x01];
+    let mut de = Deserializer::new(&buf[..]).with_trailing_defaults();
+    Point::deserialize(&mut de).unwrap_err();
+
+    // Plain sequences are not affected.
+    let mut de = Deserializer::new(&buf[..]).with_trailing_defaults();
+    <(u8, Option<u8>)>::deserialize(&mut de).unwrap_err();
+}
+
+#[test]
+fn fail_strict_struct_with_unknown_key() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Point {
+        x: u8,
+        y: u8,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Dot { at: Point },
+    }
+
+    // {"x": 1, "y": 2}
+    let buf = [0x82, 0xa1, 0x78, 0x01, 0xa1, 0x79, 0x02];
+    let mut de = Deserializer::new(&buf[..]).with_strict_structs();
+    assert_eq!(Point { x: 1, y: 2 }, Point::deserialize(&mut de).unwrap());
+
+    // {"x": 1, "y": 2, "z": 3}
+    let buf = [0x83, 0xa1, 0x78, 0x01, 0xa1, 0x79, 0x02, 0xa1, 0x7a, 0x03];
+    let mut de = Deserializer::new(&buf[..]);
+    assert_eq!(Point { x: 1, y: 2 }, Point::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::new(Cursor::new(&buf[..])).with_strict_structs();
+    match Point::deserialize(&mut de).unwrap_err() {
+        Error::Syntax(msg) => assert!(msg.contains("unknown field `z`"), "{}", msg),
+        err => panic!("unexpected error: {}", err),
+    }
+
+    // {"Dot": {"at": {"x": 1, "y": 2, "z": 3}}}
+    let mut buf = vec![0x81, 0xa3, 0x44, 0x6f, 0x74, 0x81, 0xa2, 0x61, 0x74];
+    buf.extend_from_slice(&[0x83, 0xa1, 0x78, 0x01, 0xa1, 0x79, 0x02, 0xa1, 0x7a, 0x03]);
+    let mut de = Deserializer::new(&buf[..]).with_strict_structs();
+    Shape::deserialize(&mut de).unwrap_err();
+
+    // Maps that aren't structs are not affected.
+    let mut de = Deserializer::new(&buf[..]).with_strict_structs();
+    std::collections::BTreeMap::<String, rmpv::Value>::deserialize(&mut de).unwrap();
+}
+
+#[test]
+fn fail_struct_with_excess_elements() {
+    #[derive(Debug, Deserialize)]
+    struct Point {
+        _x: u8,
+        _y: u8,
+    }
+
+    // [1, 2, 3]
+    let buf = [0x93, 0x01, 0x02, 0x03];
+    let mut de = Deserializer::new(&buf[..]).with_strict_structs();
+    match Point::deserialize(&mut de).unwrap_err() {
+        Error::LengthMismatch(2) => {}
+        err => panic!("unexpected error: {}", err),
+    }
 }
@@ -0,0 +1,105 @@
+#![cfg(feature = "embedded-io")]
+
+use rmp::decode::ValueReadError;
+use rmp::encode::ValueWriteError;
+use serde_derive::{Deserialize, Serialize};
+
+use rmp_serde::decode;
+use rmp_serde::embedded_io::{IoError, IoReader, IoWriter};
+use rmp_serde::encode;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Message<'a> {
+    id: u32,
+    body: &'a str,
+}
+
+#[test]
+fn pass_write_and_read_consecutive_values() {
+    let mut out = [0; 32];
+    let mut wr = &mut out[..];
+    rmp_serde::embedded_io::write(&mut wr, &Message { id: 1, body: "foo" }).unwrap();
+    rmp_serde::embedded_io::write(&mut wr, &Message { id: 2, body: "bar" }).unwrap();
+    let len = 32 - wr.len();
+
+    let mut rd = &out[..len];
+    let mut buf = [0; 8];
+    let msg: Message<'_> = rmp_serde::embedded_io::read(&mut rd, &mut buf).unwrap();
+    assert_eq!(Message { id: 1, body: "foo" }, msg);
+    assert_eq!(len / 2, rd.len());
+    let msg: Message<'_> = rmp_serde::embedded_io::read(&mut rd, &mut buf).unwrap();
+    assert_eq!(Message { id: 2, body: "bar" }, msg);
+    assert!(rd.is_empty());
+}
+
+#[test]
+fn pass_from_read_io_reader() {
+    let buf = rmp_serde::to_vec(&(1, "a")).unwrap();
+    let mut out = [0; 8];
+    let mut se = rmp_serde::Serializer::new(IoWriter::new(&mut out[..]));
+    serde::Serialize::serialize(&(1, "a"), &mut se).unwrap();
+    assert_eq!(4, se.into_inner().into_inner().len());
+    assert_eq!(buf, out[..4]);
+
+    let val: (u8, String) = rmp_serde::from_read(IoReader::new(&buf[..])).unwrap();
+    assert_eq!((1, "a".into()), val);
+}
+
+#[test]
+fn fail_read_buffer_full() {
+    let out = rmp_serde::to_vec(&Message { id: 1, body: "foo" }).unwrap();
+    let mut buf = [0; 4];
+    match rmp_serde::embedded_io::read::<_, Message<'_>>(&out[..], &mut buf) {
+        Err(decode::Error::InvalidValueRead(ValueReadError::InvalidDataRead(IoError::BufferFull))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_read_unexpected_eof() {
+    let out = rmp_serde::to_vec(&Message { id: 1, body: "foo" }).unwrap();
+    let mut buf = [0; 16];
+    match rmp_serde::embedded_io::read::<_, Message<'_>>(&out[..out.len() - 1], &mut buf) {
+        Err(decode::Error::InvalidValueRead(ValueReadError::InvalidDataRead(IoError::UnexpectedEof))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_write_io_error() {
+    let mut out = [0; 4];
+    match rmp_serde::embedded_io::write(&mut out[..], &Message { id: 1, body: "foo" }) {
+        Err(encode::Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(IoError::Io(err)))) |
+        Err(encode::Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(IoError::Io(err)))) => {
+            assert_eq!(embedded_io::ErrorKind::WriteZero, embedded_io::Error::kind(&err));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+#[test]
+fn pass_async_round_trip() {
+    use futures::executor::block_on;
+
+    let mut out = [0; 32];
+    let mut buf = [0; 16];
+    let mut wr = &mut out[..];
+    block_on(rmp_serde::embedded_io::write_async(&mut wr, &mut buf, &Message { id: 1, body: "foo" })).unwrap();
+    block_on(rmp_serde::embedded_io::write_async(&mut wr, &mut buf, &Message { id: 2, body: "bar" })).unwrap();
+    let len = 32 - wr.len();
+
+    let mut rd = &out[..len];
+    let msg: Message<'_> = block_on(rmp_serde::embedded_io::read_async(&mut rd, &mut buf)).unwrap();
+    assert_eq!(Message { id: 1, body: "foo" }, msg);
+    let msg: Message<'_> = block_on(rmp_serde::embedded_io::read_async(&mut rd, &mut buf)).unwrap();
+    assert_eq!(Message { id: 2, body: "bar" }, msg);
+    assert!(rd.is_empty());
+
+    let mut small = [0; 4];
+    match block_on(rmp_serde::embedded_io::write_async(&mut out[..], &mut small, &Message { id: 1, body: "foo" })) {
+        Err(encode::Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(IoError::BufferFull))) |
+        Err(encode::Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(IoError::BufferFull))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
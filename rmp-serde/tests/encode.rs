@@ -2,6 +2,7 @@ extern crate rmp_serde as rmps;
 
 use std::io::Cursor;
 
+use rmp::encode::ValueWriteError;
 use serde::Serialize;
 
 use crate::rmps::encode::{self, Error};
@@ -368,3 +369,485 @@ fn pass_raw_ref_invalid_utf8() {
 fn serializer_one_type_arg() {
     let _s: rmp_serde::Serializer<&mut dyn std::io::Write>;
 }
+
+#[cfg(feature = "bytes")]
+#[test]
+fn pass_to_bytes() {
+    let val = (42u8, "le message");
+    let buf = rmps::to_bytes(&val).unwrap();
+
+    assert_eq!(rmps::to_vec(&val).unwrap(), &buf[..]);
+    assert_eq!(val, rmps::from_slice::<(u8, &str)>(&buf).unwrap());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn pass_bytes_writer_appends_to_bytes_mut() {
+    let mut buf = bytes::BytesMut::new();
+
+    1u8.serialize(&mut Serializer::new(encode::BytesWriter::new(&mut buf))).unwrap();
+    "foo".serialize(&mut Serializer::new(encode::BytesWriter::new(&mut buf))).unwrap();
+
+    assert_eq!([0x01, 0xa3, 0x66, 0x6f, 0x6f], &buf[..]);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn fail_bytes_writer_out_of_capacity() {
+    let mut buf = [0u8; 2];
+
+    let err = "foo".serialize(&mut Serializer::new(encode::BytesWriter::new(&mut buf[..]))).unwrap_err();
+    match err {
+        Error::InvalidValueWrite(rmp::encode::ValueWriteError::InvalidDataWrite(err)) => {
+            assert_eq!(std::io::ErrorKind::WriteZero, err.kind());
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[test]
+fn pass_collect_seq_backpatched() {
+    let mut se = Serializer::new(Cursor::new(Vec::new()));
+    "head".serialize(&mut se).unwrap();
+    se.collect_seq_backpatched(["a", "bc"]).unwrap();
+    se.collect_seq_backpatched(std::iter::empty::<u8>()).unwrap();
+    "tail".serialize(&mut se).unwrap();
+
+    let buf = se.into_inner().into_inner();
+    let mut rd = &buf[..];
+    assert_eq!("head", rmps::decode::from_read::<_, String>(&mut rd).unwrap());
+    assert_eq!(vec!["a", "bc"], rmps::decode::from_read::<_, Vec<String>>(&mut rd).unwrap());
+    assert_eq!(Vec::<u8>::new(), rmps::decode::from_read::<_, Vec<u8>>(&mut rd).unwrap());
+    assert_eq!("tail", rmps::decode::from_read::<_, String>(&mut rd).unwrap());
+    assert!(rd.is_empty());
+}
+
+#[test]
+fn pass_collect_seq_backpatched_nested() {
+    let mut se = Serializer::new(Cursor::new(Vec::new())).with_struct_map();
+    se.collect_seq_backpatched((0..3u8).map(|n| vec![n; n as usize])).unwrap();
+
+    let buf = se.into_inner().into_inner();
+    assert_eq!([0xdd, 0x00, 0x00, 0x00, 0x03], buf[..5]);
+    assert_eq!(vec![vec![], vec![1], vec![2, 2]], rmps::from_slice::<Vec<Vec<u8>>>(&buf).unwrap());
+}
+
+#[test]
+fn pass_serialize_seq_backpatched() {
+    use serde::Serializer as _;
+
+    let mut se = Serializer::new(Cursor::new(Vec::new())).with_backpatch();
+    "head".serialize(&mut se).unwrap();
+    se.collect_seq((0..5u8).filter(|n| n % 2 == 0)).unwrap();
+    se.collect_map([("a", 1u8), ("b", 2)].into_iter().filter(|_| true)).unwrap();
+
+    let buf = se.into_inner().into_inner();
+    assert_eq!([0xa4, b'h', b'e', b'a', b'd', 0xdd, 0x00, 0x00, 0x00, 0x03, 0x00, 0x02, 0x04], buf[..13]);
+    assert_eq!([0xdf, 0x00, 0x00, 0x00, 0x02], buf[13..18]);
+
+    let mut rd = &buf[..];
+    assert_eq!("head", rmps::decode::from_read::<_, String>(&mut rd).unwrap());
+    assert_eq!(vec![0, 2, 4], rmps::decode::from_read::<_, Vec<u8>>(&mut rd).unwrap());
+    let map: std::collections::BTreeMap<String, u8> = rmps::decode::from_read(&mut rd).unwrap();
+    assert_eq!(vec![("a".to_owned(), 1), ("b".to_owned(), 2)], map.into_iter().collect::<Vec<_>>());
+    assert!(rd.is_empty());
+}
+
+#[test]
+fn pass_serialize_flatten_backpatched() {
+    #[derive(serde_derive::Serialize)]
+    struct Inner {
+        b: u8,
+    }
+
+    #[derive(serde_derive::Serialize)]
+    struct Outer {
+        a: u8,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    let mut buf = vec![0xc0];
+    Outer { a: 1, inner: Inner { b: 2 } }.serialize(&mut Serializer::new(&mut buf).with_backpatch()).unwrap();
+
+    assert_eq!(vec![0xc0, 0xdf, 0x00, 0x00, 0x00, 0x02, 0xa1, b'a', 0x01, 0xa1, b'b', 0x02], buf);
+}
+
+#[test]
+fn pass_serialize_iter() {
+    let buf = rmps::to_vec_from_iter(2, ["le", "message"].iter()).unwrap();
+    assert_eq!(rmps::to_vec(&["le", "message"]).unwrap(), buf);
+
+    let mut se = Serializer::new(Vec::new()).with_struct_map();
+    se.serialize_iter(0, std::iter::empty::<u8>()).unwrap();
+    se.serialize_iter(20, 0..20u8).unwrap();
+
+    let buf = se.into_inner();
+    let mut rd = &buf[..];
+    assert_eq!(Vec::<u8>::new(), rmps::decode::from_read::<_, Vec<u8>>(&mut rd).unwrap());
+    assert_eq!((0..20).collect::<Vec<u8>>(), rmps::decode::from_read::<_, Vec<u8>>(&mut rd).unwrap());
+}
+
+#[test]
+fn fail_serialize_iter_length_mismatch() {
+    match rmps::to_vec_from_iter(3, 0..2) {
+        Err(Error::InvalidDataModel(..)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+    match rmps::to_vec_from_iter(1, 0..2) {
+        Err(Error::InvalidDataModel(..)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn pass_floats_as_ints() {
+    fn encode<T: Serialize>(val: T) -> Vec<u8> {
+        let mut se = Serializer::new(Vec::new()).with_floats_as_ints();
+        val.serialize(&mut se).unwrap();
+        se.into_inner()
+    }
+
+    assert_eq!(vec![0x02], encode(2.0f64));
+    assert_eq!(vec![0xfe], encode(-2.0f32));
+    assert_eq!(vec![0xcd, 0x01, 0x00], encode(256.0f64));
+    assert_eq!(vec![0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xf8, 0x00], encode(18446744073709549568.0f64));
+    assert_eq!(vec![0xd3, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], encode(-9223372036854775808.0f64));
+
+    // Values that are not integral, or don't fit, stay floats.
+    assert_eq!(vec![0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], encode(1.5f64));
+    assert_eq!(vec![0xca, 0x80, 0x00, 0x00, 0x00], encode(-0.0f32));
+    assert_eq!(vec![0xcb, 0x43, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], encode(18446744073709551616.0f64));
+    assert_eq!(vec![0xcb, 0xc3, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01], encode(-9223372036854777856.0f64));
+    assert_eq!(vec![0xca, 0x7f, 0x80, 0x00, 0x00], encode(f32::INFINITY));
+    assert_eq!(9, encode(f64::NAN).len());
+
+    // The integers are accepted back as floats.
+    let buf = encode((2.0f32, 3.0f64));
+    assert_eq!((2.0f32, 3.0f64), rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_canonical_nan() {
+    let nan = f64::from_bits(0xfff8_0000_dead_beef);
+    assert!(nan.is_nan());
+
+    let mut se = Serializer::new(Vec::new()).with_canonical_nan();
+    (nan, -f32::NAN, f64::INFINITY).serialize(&mut se).unwrap();
+    assert_eq!(vec![0x93, 0xcb, 0x7f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xca, 0x7f, 0xc0, 0x00, 0x00,
+                    0xcb, 0x7f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], se.into_inner());
+}
+
+#[test]
+fn fail_non_finite_floats() {
+    for val in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let mut se = Serializer::new(Vec::new()).with_finite_floats();
+        match val.serialize(&mut se) {
+            Err(Error::InvalidDataModel(..)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut se = Serializer::new(Vec::new()).with_finite_floats();
+        (val as f32).serialize(&mut se).unwrap_err();
+    }
+
+    let mut se = Serializer::new(Vec::new()).with_finite_floats();
+    (1.5f32, f64::MAX).serialize(&mut se).unwrap();
+}
+
+#[test]
+fn pass_bin_reader_in_chunks() {
+    use rmps::bin_stream::BinReader;
+
+    let data: Vec<u8> = (0..150_000u32).map(|i| i as u8).collect();
+    let expected = rmps::to_vec(&("blob", serde_bytes::Bytes::new(&data))).unwrap();
+    let buf = rmps::to_vec(&("blob", BinReader::new(150_000, &data[..]))).unwrap();
+    assert_eq!(expected, buf);
+
+    // Only the given length is read.
+    let rd = BinReader::new(3, &data[..]);
+    assert_eq!(vec![0xc4, 0x03, 0x00, 0x01, 0x02], rmps::to_vec(&rd).unwrap());
+    assert_eq!(&data[3..], rd.into_inner());
+    assert_eq!(vec![0xc4, 0x00], rmps::to_vec(&BinReader::new(0, std::io::empty())).unwrap());
+
+    // The reader is too short.
+    rmps::to_vec(&BinReader::new(10, &data[..5])).unwrap_err();
+}
+
+#[test]
+fn pass_old_spec() {
+    let long = "x".repeat(40);
+    let mut se = Serializer::new(Vec::new()).with_old_spec();
+    ("ab", serde_bytes::Bytes::new(&[1, 2]), long.as_str(), serde_bytes::Bytes::new(&[7; 300])).serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+
+    assert_eq!([0x94, 0xa2, b'a', b'b', 0xa2, 0x01, 0x02, 0xda, 0x00, 0x28], buf[..10]);
+    assert_eq!([0xda, 0x01, 0x2c], buf[50..53]);
+    assert_eq!(353, buf.len());
+
+    // Field names and collected strings don't use Str8 either.
+    #[derive(serde_derive::Serialize)]
+    struct S {
+        a_rather_long_field_name_over_32_bytes: u8,
+    }
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_old_spec();
+    S { a_rather_long_field_name_over_32_bytes: 1 }.serialize(&mut se).unwrap();
+    assert_eq!([0x81, 0xda, 0x00, 0x26], se.into_inner()[..4]);
+
+    let mut se = Serializer::new(Vec::new()).with_old_spec();
+    serde::Serializer::collect_str(&mut se, &format_args!("{}", long)).unwrap();
+    assert_eq!([0xda, 0x00, 0x28], se.into_inner()[..3]);
+}
+
+#[test]
+fn fail_old_spec_ext() {
+    let mut se = Serializer::new(Vec::new()).with_old_spec();
+    match rmps::ExtRef::new(1, &[0]).serialize(&mut se) {
+        Err(Error::InvalidDataModel(..)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut se = Serializer::new(Vec::new())
+        .with_int128_repr(rmps::config::Int128Repr::Ext(3))
+        .with_old_spec();
+    1i128.serialize(&mut se).unwrap_err();
+}
+
+#[test]
+fn pass_no_str8() {
+    let long = "x".repeat(255);
+    let mut se = Serializer::new(Vec::new()).with_no_str8();
+    ("a", &long[..32], long.as_str(), serde_bytes::Bytes::new(&[1])).serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+
+    assert_eq!([0x94, 0xa1, b'a', 0xda, 0x00, 0x20], buf[..6]);
+    assert_eq!([0xda, 0x00, 0xff], buf[38..41]);
+    // Bin is unaffected.
+    assert_eq!([0xc4, 0x01, 0x01], buf[296..]);
+
+    let mut se = Serializer::new(Vec::new()).with_no_str8();
+    "x".repeat(256).serialize(&mut se).unwrap();
+    assert_eq!([0xda, 0x01, 0x00], se.into_inner()[..3]);
+}
+
+#[test]
+fn pass_fixed_width_ints() {
+    let mut se = Serializer::new(Vec::new()).with_fixed_width_ints();
+    (0u8, 1u16, 2u32, 3u64, -1i8, 4i16, -5i32, 6i64).serialize(&mut se).unwrap();
+    assert_eq!(vec![0x98, 0xcc, 0x00, 0xcd, 0x00, 0x01, 0xce, 0x00, 0x00, 0x00, 0x02,
+                    0xcf, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+                    0xd0, 0xff, 0xd1, 0x00, 0x04, 0xd2, 0xff, 0xff, 0xff, 0xfb,
+                    0xd3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06], se.into_inner());
+
+    let buf = rmps::to_vec(&(0u8, 1u16, 2u32, 3u64, -1i8, 4i16, -5i32, 6i64)).unwrap();
+    assert_eq!(vec![0x98, 0x00, 0x01, 0x02, 0x03, 0xff, 0x04, 0xfb, 0x06], buf);
+}
+
+#[test]
+fn pass_widest_headers() {
+    use std::collections::BTreeMap;
+
+    let mut map = BTreeMap::new();
+    map.insert(1u8, serde_bytes::ByteBuf::from(vec![2]));
+    let mut se = Serializer::new(Vec::new()).with_widest_headers();
+    ((), map, "").serialize(&mut se).unwrap();
+    assert_eq!(vec![0xdd, 0x00, 0x00, 0x00, 0x03,
+                    0xc0,
+                    0xdf, 0x00, 0x00, 0x00, 0x01, 0x01, 0xc6, 0x00, 0x00, 0x00, 0x01, 0x02,
+                    0xdb, 0x00, 0x00, 0x00, 0x00], se.into_inner());
+
+    // Structs, unknown lengths and field names.
+    #[derive(serde_derive::Serialize)]
+    struct S {
+        a: Vec<u8>,
+    }
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_widest_headers();
+    S { a: vec![] }.serialize(&mut se).unwrap();
+    assert_eq!(vec![0xdf, 0x00, 0x00, 0x00, 0x01, 0xdb, 0x00, 0x00, 0x00, 0x01, b'a',
+                    0xdd, 0x00, 0x00, 0x00, 0x00], se.into_inner());
+
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_skip_none().with_widest_headers();
+    S { a: vec![] }.serialize(&mut se).unwrap();
+    assert_eq!([0xdf, 0x00, 0x00, 0x00, 0x01], se.into_inner()[..5]);
+
+    let mut se = Serializer::new(Vec::new()).with_widest_headers();
+    serde::Serializer::collect_seq(&mut se, (0..2).filter(|_| true)).unwrap();
+    assert_eq!(vec![0xdd, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01], se.into_inner());
+}
+
+#[test]
+fn pass_reserve_and_patch() {
+    // A count unknown until the items were written.
+    let mut se = Serializer::new(Cursor::new(Vec::new()));
+    let slot = se.reserve(5).unwrap();
+    assert_eq!((0, 5), (slot.position(), slot.len()));
+    1u8.serialize(&mut se).unwrap();
+    2u8.serialize(&mut se).unwrap();
+    se.patch(slot, &[0xdd, 0x00, 0x00, 0x00, 0x02]).unwrap();
+    (3u8,).serialize(&mut se).unwrap();
+    assert_eq!(vec![0xdd, 0x00, 0x00, 0x00, 0x02, 0x01, 0x02, 0x91, 0x03], se.into_inner().into_inner());
+
+    // A trailing slot, through a mutable reference.
+    let mut buf = vec![0xc0];
+    let mut se = Serializer::new(&mut buf);
+    let slot = se.reserve(2).unwrap();
+    assert_eq!(1, slot.position());
+    se.patch(slot, &[0xc2, 0xc3]).unwrap();
+    assert_eq!(vec![0xc0, 0xc2, 0xc3], buf);
+
+    // The size must match.
+    let mut se = Serializer::new(Vec::new());
+    let slot = se.reserve(2).unwrap();
+    match se.patch(slot, &[0]) {
+        Err(Error::InvalidDataModel(..)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_bytes_written() {
+    let mut se = Serializer::new(Vec::new()).with_struct_map();
+    assert_eq!(0, se.bytes_written());
+
+    #[derive(serde_derive::Serialize)]
+    struct S {
+        a: Option<u8>,
+        b: rmps::ExtRef<'static>,
+    }
+    S { a: None, b: rmps::ExtRef::new(1, &[0; 3]) }.serialize(&mut se).unwrap();
+    se.serialize_iter(2, [1, 2]).unwrap();
+    assert_eq!(se.get_ref().len() as u64, se.bytes_written());
+
+    // Buffered structs and reserved slots are counted once.
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_skip_none();
+    let slot = se.reserve(3).unwrap();
+    S { a: None, b: rmps::ExtRef::new(1, &[0; 3]) }.serialize(&mut se).unwrap();
+    se.patch(slot, &[1, 2, 3]).unwrap();
+    assert_eq!(se.get_ref().len() as u64, se.bytes_written());
+
+    // Writing into a slice.
+    let mut buf = [0u8; 8];
+    let mut se = Serializer::new(&mut buf[..]);
+    (1, "ab").serialize(&mut se).unwrap();
+    assert_eq!(5, se.bytes_written());
+}
+
+#[test]
+fn pass_vectored_writes() {
+    use std::io::{self, IoSlice, Write};
+
+    /// Records the size of each call.
+    struct Calls(Vec<usize>);
+
+    impl Write for Calls {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.len());
+            Ok(buf.len())
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+            let len = bufs.iter().map(|buf| buf.len()).sum();
+            self.0.push(len);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let data = vec![0; 100_000];
+    let mut se = Serializer::new(Calls(Vec::new()));
+    ("x".repeat(300), serde_bytes::Bytes::new(&data)).serialize(&mut se).unwrap();
+    assert_eq!(100_309, se.bytes_written());
+    assert_eq!(vec![1, 303, 100_005], se.into_inner().0);
+}
+
+#[test]
+fn pass_to_vec_pooled() {
+    let buf = rmps::to_vec_pooled(&"x".repeat(1000)).unwrap();
+    assert_eq!(rmps::to_vec(&"x".repeat(1000)).unwrap(), *buf);
+    let ptr = buf.as_ptr();
+    drop(buf);
+
+    // The dropped buffer is reused.
+    let buf = rmps::to_vec_pooled(&(1, 2)).unwrap();
+    assert_eq!([0x92, 0x01, 0x02], *buf);
+    assert_eq!(ptr, buf.as_ptr());
+
+    // Taken out buffers aren't.
+    let vec = buf.into_vec();
+    let buf = rmps::to_vec_pooled(&[0u8; 0]).unwrap();
+    assert_ne!(vec.as_ptr(), buf.as_ptr());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn pass_to_vec_par() {
+    #[derive(serde_derive::Serialize)]
+    struct Row {
+        id: u32,
+        name: String,
+    }
+
+    let rows: Vec<Row> = (0..5000).map(|id| Row { id, name: id.to_string() }).collect();
+
+    let buf = rmps::par::to_vec_par(&rows).unwrap();
+    let mut expected = vec![0xdd, 0x00, 0x00, 0x13, 0x88];
+    for row in &rows {
+        expected.extend(rmps::to_vec(row).unwrap());
+    }
+    assert_eq!(expected, buf);
+
+    let buf = rmps::par::to_vec_named_par(&rows[..1]).unwrap();
+    assert_eq!([0xdd, 0x00, 0x00, 0x00, 0x01, 0x82, 0xa2, b'i', b'd', 0x00], buf[..10]);
+
+    assert_eq!([0xdd, 0x00, 0x00, 0x00, 0x00], *rmps::par::to_vec_par::<Row>(&[]).unwrap());
+}
+
+#[test]
+fn pass_write_to_slice() {
+    let val = (42u8, "abc", [1u8, 2]);
+    let expected = rmps::to_vec(&val).unwrap();
+
+    let mut buf = [0xff; 16];
+    let len = rmps::write_to_slice(&mut buf, &val).unwrap();
+    assert_eq!(expected, buf[..len]);
+    assert_eq!([0xff; 16][len..], buf[len..]);
+
+    match rmps::write_to_slice(&mut buf[..len - 1], &val) {
+        Err(Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(encode::BufferFull)))
+        | Err(Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(encode::BufferFull))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // The header fits, the data that doesn't isn't written at all.
+    let mut se = Serializer::new(encode::SliceWriter::new(&mut buf[..3]));
+    assert!("abc".serialize(&mut se).is_err());
+    let writer = se.into_inner();
+    assert_eq!(1, writer.len());
+    assert_eq!([0xa3], *writer.into_written());
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn pass_to_heapless_vec() {
+    let val = (42u8, "abc", [1u8, 2]);
+    let expected = rmps::to_vec(&val).unwrap();
+
+    let buf = rmps::to_heapless_vec::<16>(&val).unwrap();
+    assert_eq!(expected, *buf);
+
+    match rmps::to_heapless_vec::<8>(&val) {
+        Err(Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(encode::BufferFull)))
+        | Err(Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(encode::BufferFull))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Writes append to what's already there.
+    let mut prefix = heapless::Vec::<u8, 16>::new();
+    prefix.push(0xc0).unwrap();
+    let mut se = Serializer::new(encode::HeaplessWriter::new(prefix));
+    val.serialize(&mut se).unwrap();
+    assert_eq!([0xc0], se.get_ref().get_ref()[..1]);
+    assert_eq!(expected, se.into_inner().into_inner()[1..]);
+}
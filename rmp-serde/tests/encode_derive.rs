@@ -263,6 +263,74 @@ fn serialize_struct_with_flattened_struct_field() {
     }
 }
 
+#[test]
+fn serialize_struct_with_nested_flattened_fields() {
+    #[derive(Serialize)]
+    struct Outer {
+        a: u8,
+        #[serde(flatten)]
+        middle: Middle,
+    }
+
+    #[derive(Serialize)]
+    struct Middle {
+        b: u8,
+        #[serde(flatten)]
+        inner: std::collections::BTreeMap<&'static str, u8>,
+    }
+
+    let val = Outer {
+        a: 1,
+        middle: Middle { b: 2, inner: [("c", 3)].into_iter().collect() },
+    };
+
+    // Expect: { "a": 1, "b": 2, "c": 3 }.
+    assert_eq!(
+        vec![0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02, 0xa1, 0x63, 0x03],
+        rmps::to_vec(&val).unwrap()
+    );
+}
+
+#[test]
+fn fail_flattened_field_error_is_returned() {
+    struct Failing;
+
+    impl Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, _se: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("nope"))
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Inner {
+        failing: Failing,
+    }
+
+    #[derive(Serialize)]
+    struct Struct {
+        a: u8,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    let err = rmps::to_vec(&Struct { a: 1, inner: Inner { failing: Failing } }).unwrap_err();
+    assert_eq!("nope", err.to_string());
+}
+
+#[test]
+fn serialize_seq_with_unknown_length() {
+    struct Evens(u8);
+
+    impl Serialize for Evens {
+        fn serialize<S: serde::Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+            // The filter makes the length unknown to serde.
+            se.collect_seq((0..self.0).filter(|n| n % 2 == 0))
+        }
+    }
+
+    assert_eq!(vec![0x93, 0x00, 0x02, 0x04], rmps::to_vec(&Evens(6)).unwrap());
+}
+
 #[test]
 fn pass_struct_as_map_using_ext() {
     #[derive(Serialize)]
@@ -360,3 +428,84 @@ fn pass_struct_as_map_using_triple_ext_many_times() {
     assert_eq!(vec![0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa5, 0x42, 0x6f, 0x62, 0x62, 0x79, 0xa3, 0x61, 0x67, 0x65, 0x08],
                se.into_inner());
 }
+
+#[test]
+fn pass_write_hook() {
+    use std::collections::BTreeMap;
+
+    use rmp::encode::RmpWrite;
+    use rmp::Marker;
+    use rmps::hook::{HookWriter, WriteHook};
+
+    #[derive(Serialize)]
+    struct Account {
+        name: String,
+        token: String,
+        #[serde(flatten)]
+        extra: BTreeMap<String, String>,
+        keys: Vec<String>,
+        inner: Option<Box<Account>>,
+    }
+
+    #[derive(Debug, Default)]
+    struct Audit {
+        markers: Vec<Marker>,
+        payload: usize,
+    }
+
+    impl WriteHook for Audit {
+        fn marker(&mut self, marker: Marker) {
+            self.markers.push(marker);
+        }
+
+        fn payload(&mut self, data: &[u8]) {
+            self.payload += data.len();
+        }
+
+        fn replace_str(&mut self, key: &str, value: &str) -> Option<String> {
+            (key == "token" || key == "secret").then(|| "*".repeat(value.len().min(3)))
+        }
+    }
+
+    let account = Account {
+        name: "root".into(),
+        token: "t".repeat(300),
+        extra: [("secret".to_string(), "s3cr3t".to_string())].into(),
+        keys: vec!["token".into()],
+        inner: Some(Box::new(Account {
+            name: "token".into(),
+            token: "abcdef".into(),
+            extra: BTreeMap::new(),
+            keys: vec![],
+            inner: None,
+        })),
+    };
+
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_write_hook(Audit::default());
+    account.serialize(&mut se).unwrap();
+    let (buf, audit) = se.into_inner().into_parts();
+
+    let mut plain = Serializer::new(Vec::new()).with_struct_map();
+    Account {
+        token: "***".into(),
+        extra: [("secret".to_string(), "***".to_string())].into(),
+        inner: Some(Box::new(Account { token: "***".into(), ..*account.inner.unwrap() })),
+        ..account
+    }.serialize(&mut plain).unwrap();
+    assert_eq!(plain.into_inner(), buf);
+
+    assert_eq!(Marker::FixMap(5), audit.markers[0]);
+    assert_eq!(buf.len(), audit.markers.iter().map(|&m| match m {
+        Marker::Str8 => 2,
+        _ => 1,
+    }).sum::<usize>() + audit.payload);
+
+    // Values split across writes are followed as well.
+    let mut wr = HookWriter::new(Vec::new(), Audit::default());
+    for &byte in &buf {
+        wr.write_u8(byte).unwrap();
+    }
+    let (split, split_audit) = wr.into_parts();
+    assert_eq!(buf, split);
+    assert_eq!(audit.markers, split_audit.markers);
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "erased-serde")]
+
+use std::io::Cursor;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_derive::{Deserialize, Serialize};
+
+use rmp_serde::erased::{ErasedDeserializer, ErasedSerializer, Options};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    id: u32,
+    name: String,
+}
+
+/// Serializes as whether the format is human readable.
+struct Readable;
+
+impl Serialize for Readable {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        let readable = se.is_human_readable();
+        se.serialize_bool(readable)
+    }
+}
+
+#[test]
+fn pass_erased_roundtrip() {
+    let values: Vec<Box<dyn erased_serde::Serialize>> = vec![
+        Box::new(Record { id: 1, name: "one".into() }),
+        Box::new("two"),
+        Box::new(3u8),
+    ];
+
+    let mut buf = Vec::new();
+    let mut se = ErasedSerializer::new(&mut buf, Options::new());
+    for value in &values {
+        value.erased_serialize(&mut *se.erase()).unwrap();
+    }
+    assert_eq!(&buf[..2], [0x92, 0x01]);
+
+    let mut de = ErasedDeserializer::from_slice(&buf, Options::new());
+    let record: Record = erased_serde::deserialize(&mut *de.erase()).unwrap();
+    let name: &str = erased_serde::deserialize(&mut *de.erase()).unwrap();
+    assert_eq!(Record { id: 1, name: "one".into() }, record);
+    assert_eq!("two", name);
+
+    let mut rd = Cursor::new(&buf);
+    let mut de = ErasedDeserializer::from_read(&mut rd, Options::new());
+    let record: Record = erased_serde::deserialize(&mut *de.erase()).unwrap();
+    let name: String = erased_serde::deserialize(&mut *de.erase()).unwrap();
+    let three: u8 = erased_serde::deserialize(&mut *de.erase()).unwrap();
+    assert_eq!((Record { id: 1, name: "one".into() }, "two", 3), (record, &*name, three));
+    assert!(erased_serde::deserialize::<u8>(&mut *de.erase()).is_err());
+}
+
+#[test]
+fn pass_erased_options() {
+    let mut buf = Vec::new();
+    let mut se = ErasedSerializer::new(&mut buf, Options::new().with_struct_map().with_human_readable());
+    let values: [&dyn erased_serde::Serialize; 2] = [&Record { id: 1, name: "one".into() }, &Readable];
+    for value in values {
+        value.erased_serialize(&mut *se.erase()).unwrap();
+    }
+    let mut expected = rmp_serde::to_vec_named(&Record { id: 1, name: "one".into() }).unwrap();
+    expected.push(0xc3);
+    assert_eq!(expected, buf);
+
+    /// Deserializes as whether the format is human readable.
+    struct IsReadable(bool);
+
+    impl<'de> Deserialize<'de> for IsReadable {
+        fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            let readable = de.is_human_readable();
+            serde::de::IgnoredAny::deserialize(de)?;
+            Ok(IsReadable(readable))
+        }
+    }
+
+    let mut de = ErasedDeserializer::from_slice(&[0xc0], Options::new().with_human_readable());
+    assert!(erased_serde::deserialize::<IsReadable>(&mut *de.erase()).unwrap().0);
+    let mut de = ErasedDeserializer::from_slice(&[0xc0], Options::new());
+    assert!(!erased_serde::deserialize::<IsReadable>(&mut *de.erase()).unwrap().0);
+}
@@ -609,6 +609,873 @@ fn checked_seq_access_len()
         .expect_err("Input round tripped into Output; this shouldn't happen");
 }
 
+#[test]
+fn roundtrip_ext() {
+    assert_roundtrips(rmps::Ext::new(1, vec![]));
+    assert_roundtrips(rmps::Ext::new(-5, vec![42]));
+    assert_roundtrips(rmps::Ext::new(127, vec![0; 300]));
+    assert_roundtrips(vec![rmps::Ext::new(2, vec![1, 2, 3, 4]), rmps::Ext::new(3, vec![5; 16])]);
+}
+
+#[test]
+fn roundtrip_ext_ref() {
+    let data = [0x01, 0x02, 0x03, 0x04];
+    let ext = rmps::ExtRef::new(9, &data);
+
+    let buf = rmps::to_vec(&ext).unwrap();
+    assert_eq!(vec![0xd6, 0x09, 0x01, 0x02, 0x03, 0x04], buf);
+    assert_eq!(ext, rmps::from_slice::<rmps::ExtRef<'_>>(&buf).unwrap());
+    assert_eq!(rmps::Ext::from(ext), rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn ext_ref_requires_borrowed_data() {
+    let buf = rmps::to_vec(&rmps::Ext::new(1, vec![0xaa, 0xbb, 0xcc])).unwrap();
+    assert_eq!(vec![0xc7, 0x03, 0x01, 0xaa, 0xbb, 0xcc], buf);
+
+    let mut de = Deserializer::new(Cursor::new(&buf[..]));
+    rmps::ExtRef::deserialize(&mut de).unwrap_err();
+}
+
+#[test]
+fn ext_interops_with_rmpv() {
+    let buf = rmps::to_vec(&rmps::Ext::new(4, vec![0x10, 0x20])).unwrap();
+    assert_eq!(rmpv::Value::Ext(4, vec![0x10, 0x20]), rmpv::decode::read_value(&mut &buf[..]).unwrap());
+
+    let mut buf = Vec::new();
+    rmpv::encode::write_value(&mut buf, &rmpv::Value::Ext(-1, vec![0; 8])).unwrap();
+    assert_eq!(rmps::Ext::new(-1, vec![0; 8]), rmps::from_slice(&buf).unwrap());
+}
+
+#[derive(Debug, PartialEq)]
+struct Celsius(i16);
+
+impl rmps::MsgPackExtType for Celsius {
+    const TAG: i8 = 42;
+    type Error = String;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let bytes = data.try_into().map_err(|_| format!("expected 2 bytes, got {}", data.len()))?;
+        Ok(Celsius(i16::from_be_bytes(bytes)))
+    }
+}
+
+#[test]
+fn roundtrip_typed_ext() {
+    assert_roundtrips(rmps::TypedExt(Celsius(-40)));
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Reading {
+        sensor: u8,
+        temperature: rmps::TypedExt<Celsius>,
+    }
+    assert_roundtrips(Reading { sensor: 3, temperature: Celsius(21).into() });
+}
+
+#[test]
+fn typed_ext_rejects_other_tag() {
+    let buf = rmps::to_vec(&rmps::Ext::new(41, vec![0x00, 0x15])).unwrap();
+    let err = rmps::from_slice::<rmps::TypedExt<Celsius>>(&buf).unwrap_err();
+    assert!(err.to_string().contains("ext tag 42"), "{}", err);
+}
+
+#[test]
+fn typed_ext_reports_invalid_payload() {
+    let buf = rmps::to_vec(&rmps::Ext::new(42, vec![0x00])).unwrap();
+    let err = rmps::from_slice::<rmps::TypedExt<Celsius>>(&buf).unwrap_err();
+    assert!(err.to_string().contains("expected 2 bytes, got 1"), "{}", err);
+}
+
+#[test]
+fn ext_registry_dispatches_on_tag() {
+    use serde::de::DeserializeSeed;
+
+    let mut registry = rmps::ExtRegistry::new();
+    registry.register_type::<Celsius>();
+    registry.register(1, |data: &[u8]| String::from_utf8(data.to_vec()));
+    assert!(registry.contains(42));
+    assert!(!registry.contains(2));
+
+    let mut buf = rmps::to_vec(&rmps::TypedExt(Celsius(-3))).unwrap();
+    buf.extend(rmps::to_vec(&rmps::Ext::new(1, b"hi".to_vec())).unwrap());
+    buf.extend(rmps::to_vec(&rmps::Ext::new(2, vec![0xff])).unwrap());
+
+    let mut de = Deserializer::new(Cursor::new(&buf[..]));
+    let celsius = (&registry).deserialize(&mut de).unwrap();
+    assert_eq!(42, celsius.tag());
+    assert_eq!(Some(&Celsius(-3)), celsius.downcast_ref::<Celsius>());
+
+    let string = (&registry).deserialize(&mut de).unwrap();
+    assert!(string.is::<String>());
+    assert_eq!("hi", string.downcast::<String>().unwrap());
+
+    let unknown = (&registry).deserialize(&mut de).unwrap();
+    let unknown = unknown.downcast::<String>().unwrap_err();
+    assert_eq!(rmps::Ext::new(2, vec![0xff]), unknown.downcast::<rmps::Ext>().unwrap());
+}
+
+#[test]
+fn ext_registry_reports_decoding_errors() {
+    let mut registry = rmps::ExtRegistry::new();
+    registry.register_type::<Celsius>();
+
+    let err = registry.decode(rmps::ExtRef::new(42, &[1, 2, 3])).unwrap_err();
+    assert_eq!(42, err.tag());
+    assert_eq!("invalid ext with tag 42: expected 2 bytes, got 3", err.to_string());
+}
+
+#[test]
+fn roundtrip_internally_tagged_enum_with_bin_and_ext() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Attachment {
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+        ext: rmps::Ext,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    enum Message {
+        Blob {
+            #[serde(with = "serde_bytes")]
+            data: Vec<u8>,
+        },
+        Ext {
+            ext: rmps::Ext,
+            nested: Vec<Option<rmps::Ext>>,
+        },
+        Value {
+            value: rmpv::Value,
+        },
+        Attachment(Attachment),
+    }
+
+    assert_roundtrips(Message::Blob { data: vec![0, 1, 255] });
+    assert_roundtrips(Message::Ext {
+        ext: rmps::Ext::new(1, vec![2, 3]),
+        nested: vec![Some(rmps::Ext::new(-4, vec![0; 20])), None],
+    });
+    assert_roundtrips(Message::Value { value: rmpv::Value::Ext(5, vec![6]) });
+    assert_roundtrips(Message::Value { value: rmpv::Value::Binary(vec![7, 8]) });
+    assert_roundtrips(Message::Attachment(Attachment { data: vec![9], ext: rmps::Ext::new(10, vec![]) }));
+}
+
+#[test]
+fn roundtrip_adjacently_tagged_enum_with_bin_and_ext() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "t", content = "c")]
+    enum Message {
+        Blob(#[serde(with = "serde_bytes")] Vec<u8>),
+        Ext(rmps::Ext),
+    }
+
+    assert_roundtrips(Message::Blob(vec![1, 2]));
+    assert_roundtrips(Message::Ext(rmps::Ext::new(3, vec![4, 5, 6, 7])));
+}
+
+#[test]
+fn roundtrip_internally_tagged_enum_with_borrowed_ext() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    enum Message<'a> {
+        Ext {
+            #[serde(borrow)]
+            ext: rmps::ExtRef<'a>,
+        },
+        Blob {
+            #[serde(with = "serde_bytes")]
+            data: &'a [u8],
+        },
+    }
+
+    let data = [1, 2, 3];
+    for msg in [Message::Ext { ext: rmps::ExtRef::new(4, &data) }, Message::Blob { data: &data }] {
+        let buf = rmps::to_vec_named(&msg).unwrap();
+        assert_eq!(msg, rmps::from_slice(&buf).unwrap());
+    }
+}
+
+#[test]
+fn roundtrip_untagged_enum_with_bin_and_ext() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum TextFirst {
+        Text(#[serde(with = "rmps::strict::string")] String),
+        Blob(#[serde(with = "rmps::strict::bytes")] Vec<u8>),
+        Ext(rmps::Ext),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum BlobFirst {
+        Blob(#[serde(with = "rmps::strict::bytes")] Vec<u8>),
+        Text(#[serde(with = "rmps::strict::string")] String),
+        Ext(rmps::Ext),
+    }
+
+    assert_roundtrips(TextFirst::Text("abc".into()));
+    assert_roundtrips(TextFirst::Blob(b"abc".to_vec()));
+    assert_roundtrips(TextFirst::Ext(rmps::Ext::new(1, b"abc".to_vec())));
+    assert_roundtrips(BlobFirst::Blob(b"abc".to_vec()));
+    assert_roundtrips(BlobFirst::Text("abc".into()));
+    assert_roundtrips(BlobFirst::Ext(rmps::Ext::new(1, b"abc".to_vec())));
+}
+
+#[test]
+fn strict_helpers_reject_other_types() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Blob(#[serde(with = "rmps::strict::bytes")] Vec<u8>);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Text(#[serde(with = "rmps::strict::string")] String);
+
+    let bin = [0xc4, 0x01, 0x61];
+    let str = [0xa1, 0x61];
+    assert_eq!(Blob(b"a".to_vec()), rmps::from_slice(&bin).unwrap());
+    assert_eq!(Text("a".into()), rmps::from_slice(&str).unwrap());
+    rmps::from_slice::<Blob>(&str).unwrap_err();
+    rmps::from_slice::<Text>(&bin).unwrap_err();
+}
+
+#[test]
+fn roundtrip_option_as_array() {
+    #[track_caller]
+    fn assert_roundtrips_as_array<T>(val: T, expected: &[u8])
+    where T: PartialEq + std::fmt::Debug + Serialize + for<'a> Deserialize<'a>
+    {
+        let mut se = Serializer::new(Vec::new()).with_option_as_array();
+        val.serialize(&mut se).unwrap();
+        let buf = se.into_inner();
+        assert_eq!(expected, &buf[..]);
+
+        let mut de = Deserializer::new(&buf[..]).with_option_as_array();
+        assert_eq!(val, T::deserialize(&mut de).unwrap());
+    }
+
+    assert_roundtrips_as_array(None::<u8>, &[0x90]);
+    assert_roundtrips_as_array(Some(5u8), &[0x91, 0x05]);
+    assert_roundtrips_as_array(Some(None::<u8>), &[0x91, 0x90]);
+    assert_roundtrips_as_array(Some(Some(5u8)), &[0x91, 0x91, 0x05]);
+    assert_roundtrips_as_array(Some(()), &[0x91, 0xc0]);
+    assert_roundtrips_as_array(Some(Some(())), &[0x91, 0x91, 0xc0]);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Patch {
+        name: Option<Option<String>>,
+        age: Option<Option<u8>>,
+    }
+    assert_roundtrips_as_array(Patch { name: Some(None), age: None }, &[0x92, 0x91, 0x90, 0x90]);
+}
+
+#[test]
+fn option_as_array_accepts_nil() {
+    let buf = [0x92, 0xc0, 0x91, 0x91, 0x01];
+    let mut de = Deserializer::new(&buf[..]).with_option_as_array();
+    assert_eq!((None, Some(Some(1))), <(Option<u8>, Option<Option<u8>>)>::deserialize(&mut de).unwrap());
+
+    let buf = [0x92, 0x01, 0x02];
+    let mut de = Deserializer::new(&buf[..]).with_option_as_array();
+    Option::<u8>::deserialize(&mut de).unwrap_err();
+}
+
+#[test]
+fn roundtrip_option_as_array_widest_headers() {
+    let mut se = Serializer::new(Vec::new()).with_option_as_array().with_widest_headers();
+    (Some(5u8), None::<u8>).serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    assert_eq!(&[0xdd, 0x00, 0x00, 0x00, 0x02, 0xdd, 0x00, 0x00, 0x00, 0x01, 0x05, 0xdd, 0x00, 0x00, 0x00, 0x00][..], &buf[..]);
+
+    let mut de = Deserializer::new(&buf[..]).with_option_as_array();
+    assert_eq!((Some(5), None), <(Option<u8>, Option<u8>)>::deserialize(&mut de).unwrap());
+
+    let buf = [0xdc, 0x00, 0x02, 0x01, 0x02];
+    let mut de = Deserializer::new(&buf[..]).with_option_as_array();
+    match Option::<u8>::deserialize(&mut de).unwrap_err() {
+        rmps::decode::Error::LengthMismatch(2) => {}
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn roundtrip_skip_none() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Person {
+        name: String,
+        nick: Option<String>,
+        age: Option<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Event {
+        Joined { who: Option<u32>, at: u32 },
+    }
+
+    let val = Person { name: "Bob".into(), nick: None, age: Some(8) };
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_skip_none();
+    val.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    // {"name": "Bob", "age": 8}
+    assert_eq!(&[0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa3, 0x42, 0x6f, 0x62, 0xa3, 0x61, 0x67, 0x65, 0x08][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice(&buf).unwrap());
+
+    let val = Event::Joined { who: None, at: 1 };
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_skip_none();
+    val.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    // {"Joined": {"at": 1}}
+    assert_eq!(&[0x81, 0xa6, 0x4a, 0x6f, 0x69, 0x6e, 0x65, 0x64, 0x81, 0xa2, 0x61, 0x74, 0x01][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice(&buf).unwrap());
+
+    // Tuple structs can't omit fields, so `None` is still written there.
+    let val = Person { name: "Bob".into(), nick: None, age: None };
+    let mut se = Serializer::new(Vec::new()).with_skip_none();
+    val.serialize(&mut se).unwrap();
+    assert_eq!(&[0x93, 0xa3, 0x42, 0x6f, 0x62, 0xc0, 0xc0][..], &se.into_inner()[..]);
+}
+
+#[test]
+fn roundtrip_skip_default() {
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    struct Window {
+        title: String,
+        width: u32,
+        scale: f64,
+        visible: bool,
+        tags: Vec<String>,
+        inner: Inner,
+    }
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    struct Inner {
+        depth: i64,
+        name: Option<String>,
+    }
+
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_skip_default();
+    Window::default().serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    assert_eq!(&[0x80][..], &buf[..]);
+    assert_eq!(Window::default(), rmps::from_slice(&buf).unwrap());
+
+    let val = Window { width: 640, inner: Inner { depth: -1, name: None }, ..Default::default() };
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_skip_default();
+    val.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    // {"width": 640, "inner": {"depth": -1}}
+    assert_eq!(&[0x82, 0xa5, 0x77, 0x69, 0x64, 0x74, 0x68, 0xcd, 0x02, 0x80,
+                 0xa5, 0x69, 0x6e, 0x6e, 0x65, 0x72, 0x81, 0xa5, 0x64, 0x65, 0x70, 0x74, 0x68, 0xff][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn roundtrip_skip_default_keeps_some() {
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    struct Limits {
+        max: Option<u32>,
+        strict: Option<bool>,
+        name: Option<String>,
+    }
+
+    let val = Limits { max: Some(0), strict: Some(false), name: Some(String::new()) };
+    let mut se = Serializer::new(Vec::new()).with_struct_map().with_skip_default();
+    val.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    // {"max": 0, "strict": false, "name": ""}
+    assert_eq!(&[0x83, 0xa3, 0x6d, 0x61, 0x78, 0x00, 0xa6, 0x73, 0x74, 0x72, 0x69, 0x63, 0x74, 0xc2,
+                 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa0][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn skip_field_if_callback() {
+    #[derive(Serialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    let mut se = Serializer::new(Vec::new())
+        .with_struct_map()
+        .with_skip_field_if(|key, value| key == "y" && value == [0x01]);
+    Point { x: 1, y: 1 }.serialize(&mut se).unwrap();
+    // {"x": 1}
+    assert_eq!(vec![0x81, 0xa1, 0x78, 0x01], se.into_inner());
+}
+
+#[test]
+fn roundtrip_human_readable() {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Peer {
+        addr: IpAddr,
+    }
+
+    let val = Peer { addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)) };
+    let buf = rmps::to_vec_human_readable(&val).unwrap();
+    // ["10.0.0.1"]
+    assert_eq!(&[0x91, 0xa8, 0x31, 0x30, 0x2e, 0x30, 0x2e, 0x30, 0x2e, 0x31][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice_human_readable(&buf).unwrap());
+    assert!(rmps::from_slice::<Peer>(&buf).is_err());
+
+    let buf = rmps::to_vec_named_human_readable(&val).unwrap();
+    // {"addr": "10.0.0.1"}
+    assert_eq!(&[0x81, 0xa4, 0x61, 0x64, 0x64, 0x72, 0xa8, 0x31, 0x30, 0x2e, 0x30, 0x2e, 0x30, 0x2e, 0x31][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice_human_readable(&buf).unwrap());
+}
+
+#[test]
+fn roundtrip_struct_map_if() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Point {
+        x: u8,
+        y: u8,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Line { from: Point, to: Point },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Envelope {
+        id: Option<u8>,
+        shape: Shape,
+    }
+
+    let val = Envelope {
+        id: None,
+        shape: Shape::Line { from: Point { x: 1, y: 2 }, to: Point { x: 3, y: 4 } },
+    };
+
+    let mut se = Serializer::new(Vec::new()).with_struct_map_if(|name| name == "Envelope");
+    val.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    // {"id": nil, "shape": {"Line": [[1, 2], [3, 4]]}}
+    assert_eq!(&[0x82, 0xa2, 0x69, 0x64, 0xc0, 0xa5, 0x73, 0x68, 0x61, 0x70, 0x65,
+                 0x81, 0xa4, 0x4c, 0x69, 0x6e, 0x65, 0x92, 0x92, 0x01, 0x02, 0x92, 0x03, 0x04][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice(&buf).unwrap());
+
+    // Overrides the config the other way, and fields are only skipped in structs written as maps.
+    let mut se = Serializer::new(Vec::new())
+        .with_struct_map()
+        .with_skip_none()
+        .with_struct_map_if(|name| name != "Point");
+    val.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    // {"shape": {"Line": {"from": [1, 2], "to": [3, 4]}}}
+    assert_eq!(&[0x81, 0xa5, 0x73, 0x68, 0x61, 0x70, 0x65, 0x81, 0xa4, 0x4c, 0x69, 0x6e, 0x65,
+                 0x82, 0xa4, 0x66, 0x72, 0x6f, 0x6d, 0x92, 0x01, 0x02, 0xa2, 0x74, 0x6f, 0x92, 0x03, 0x04][..], &buf[..]);
+    assert_eq!(val, rmps::from_slice(&buf).unwrap());
+}
+
+#[cfg(feature = "indexmap")]
+#[test]
+fn roundtrip_indexmap_preserves_order() {
+    use rmps::indexmap::IndexMap;
+
+    let mut map = IndexMap::new();
+    for key in ["zebra", "apple", "mango", "kiwi"] {
+        map.insert(key.to_owned(), key.len() as u8);
+    }
+
+    let buf = rmps::to_vec(&map).unwrap();
+    let decoded: IndexMap<String, u8> = rmps::from_slice(&buf).unwrap();
+    assert_eq!(map.keys().collect::<Vec<_>>(), decoded.keys().collect::<Vec<_>>());
+    assert_eq!(buf, rmps::to_vec(&decoded).unwrap());
+}
+
+#[cfg(feature = "indexmap")]
+#[test]
+fn indexmap_unique_rejects_duplicate_keys() {
+    use rmps::indexmap::IndexMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        #[serde(with = "rmps::indexmap::unique")]
+        fields: IndexMap<u8, bool>,
+    }
+
+    let doc = Doc { fields: [(3, true), (1, false)].into_iter().collect() };
+    let buf = rmps::to_vec(&doc).unwrap();
+    assert_eq!(&[0x91, 0x82, 0x03, 0xc3, 0x01, 0xc2][..], &buf[..]);
+    assert_eq!(doc, rmps::from_slice(&buf).unwrap());
+
+    // [{3: true, 3: false}]
+    let buf = [0x91, 0x82, 0x03, 0xc3, 0x03, 0xc2];
+    let err = rmps::from_slice::<Doc>(&buf).unwrap_err();
+    assert_eq!("duplicate key 3", err.to_string());
+}
+
+#[test]
+fn roundtrip_int128_reprs() {
+    use rmps::config::Int128Repr;
+
+    let vals = (i128::MIN, -1i128, u128::MAX, 0u128);
+    for &repr in &[Int128Repr::Bin, Int128Repr::Ext(-3), Int128Repr::String] {
+        let mut se = Serializer::new(Vec::new()).with_int128_repr(repr);
+        vals.serialize(&mut se).unwrap();
+        let buf = se.into_inner();
+        assert_eq!(vals, rmps::from_slice(&buf).unwrap(), "{:?}", repr);
+    }
+
+    let mut se = Serializer::new(Vec::new()).with_int128_repr(Int128Repr::Ext(-3));
+    1u128.serialize(&mut se).unwrap();
+    let mut expected = vec![0xd8, 0xfd];
+    expected.extend_from_slice(&1u128.to_be_bytes());
+    assert_eq!(expected, se.into_inner());
+
+    // Plain integers and decimal strings written by other encoders.
+    assert_eq!((-5i128, u64::MAX as u128), rmps::from_slice(&rmps::to_vec(&(-5i8, u64::MAX)).unwrap()).unwrap());
+    assert_eq!(12345678901234567890123u128, rmps::from_slice(&rmps::to_vec("12345678901234567890123").unwrap()).unwrap());
+    rmps::from_slice::<u128>(&rmps::to_vec(&-1i8).unwrap()).unwrap_err();
+    rmps::from_slice::<u128>(&rmps::to_vec("-1").unwrap()).unwrap_err();
+    rmps::from_slice::<i128>(&rmps::to_vec("x").unwrap()).unwrap_err();
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn roundtrip_bigint_ext() {
+    use rmps::bigint::BigInt;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Amounts {
+        #[serde(with = "rmps::bigint::int128")]
+        signed: i128,
+        #[serde(with = "rmps::bigint::uint128")]
+        unsigned: u128,
+        #[serde(with = "rmps::bigint::big_int")]
+        big: BigInt,
+    }
+
+    let big = "-123456789012345678901234567890123456789012345678901234567890".parse().unwrap();
+    assert_roundtrips(Amounts { signed: i128::MIN, unsigned: u128::MAX, big });
+    assert_roundtrips(Amounts { signed: -1, unsigned: 0, big: BigInt::from(42) });
+
+    // Safe integers are nested ints, anything larger a nested decimal str.
+    let buf = rmps::to_vec(&Amounts { signed: -1, unsigned: 1 << 53, big: BigInt::from(42) }).unwrap();
+    assert_eq!(&[0x93, 0xd4, 0x00, 0xff, 0xc7, 0x11, 0x00, 0xb0][..], &buf[..8]);
+    assert_eq!(b"9007199254740992", &buf[8..24]);
+    assert_eq!(&[0xd4, 0x00, 0x2a][..], &buf[24..]);
+
+    // Plain integers and strings, and the wrong ext type.
+    let buf = rmps::to_vec(&(-7i8, u64::MAX, "18446744073709551616")).unwrap();
+    let amounts: Amounts = rmps::from_slice(&buf).unwrap();
+    assert_eq!(Amounts { signed: -7, unsigned: u64::MAX.into(), big: BigInt::from(u64::MAX) + 1 }, amounts);
+    let buf = [0x93, 0xd4, 0x01, 0x01, 0x00, 0x00];
+    rmps::from_slice::<Amounts>(&buf).unwrap_err();
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn roundtrip_uuid_ext() {
+    use rmps::uuid::{Uuid, UuidExt};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ids {
+        #[serde(with = "rmps::uuid")]
+        default: Uuid,
+        custom: UuidExt<42>,
+    }
+
+    let uuid = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+    let ids = Ids { default: uuid, custom: UuidExt(uuid) };
+    assert_roundtrips(Ids { default: uuid, custom: UuidExt(uuid) });
+
+    let buf = rmps::to_vec(&ids).unwrap();
+    let mut expected = vec![0x92, 0xd8, 0x02];
+    expected.extend_from_slice(uuid.as_bytes());
+    expected.extend_from_slice(&[0xd8, 0x2a]);
+    expected.extend_from_slice(uuid.as_bytes());
+    assert_eq!(expected, buf);
+
+    let mut se = Serializer::new(Vec::new()).with_human_readable();
+    ids.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    let strs: (&str, &str) = rmps::from_slice(&buf).unwrap();
+    assert_eq!(("67e55044-10b1-426f-9247-bb680e5fe0c8", "67e55044-10b1-426f-9247-bb680e5fe0c8"), strs);
+    assert_eq!(ids, rmps::from_slice(&buf).unwrap());
+
+    // Bin and unhyphenated strings are accepted, other ext types and lengths aren't.
+    let buf = rmps::to_vec(&(serde_bytes::Bytes::new(uuid.as_bytes()), "67e5504410b1426f9247bb680e5fe0c8")).unwrap();
+    assert_eq!(ids, rmps::from_slice(&buf).unwrap());
+    let mut buf = vec![0x92, 0xd8, 0x02];
+    buf.extend_from_slice(uuid.as_bytes());
+    buf.extend_from_slice(&[0xd8, 0x02]);
+    buf.extend_from_slice(uuid.as_bytes());
+    rmps::from_slice::<Ids>(&buf).unwrap_err();
+    let buf = rmps::to_vec(&(serde_bytes::Bytes::new(&[0; 15]), "")).unwrap();
+    rmps::from_slice::<Ids>(&buf).unwrap_err();
+}
+
+#[cfg(all(feature = "rust_decimal", feature = "bigdecimal"))]
+#[test]
+fn roundtrip_decimals() {
+    use rmps::decimal::{BigDecimal, Decimal};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Amounts {
+        #[serde(with = "rmps::decimal::ext")]
+        ext: Decimal,
+        #[serde(with = "rmps::decimal::string")]
+        string: Decimal,
+        #[serde(with = "rmps::decimal::ext")]
+        big: BigDecimal,
+    }
+
+    let amounts = Amounts {
+        ext: "-0.10".parse().unwrap(),
+        string: "79228162514264337593543950335".parse().unwrap(),
+        big: "123456789012345678901234567890.000000000000000000001".parse().unwrap(),
+    };
+    assert_roundtrips(Amounts { ext: amounts.ext, string: amounts.string, big: amounts.big.clone() });
+
+    let buf = rmps::to_vec(&amounts).unwrap();
+    assert_eq!(&[0x93, 0xc7, 0x05, 0x01][..], &buf[..4]);
+    assert_eq!(b"-0.10", &buf[4..9]);
+    assert_eq!(0xbd, buf[9]);
+    assert_eq!(b"79228162514264337593543950335", &buf[10..39]);
+
+    // The other form, and integers.
+    let buf = rmps::to_vec(&("1.5", 7u8, -3i8)).unwrap();
+    let decoded: Amounts = rmps::from_slice(&buf).unwrap();
+    assert_eq!(("1.5", "7", "-3"), (&*decoded.ext.to_string(), &*decoded.string.to_string(), &*decoded.big.to_string()));
+
+    // The wrong ext type, or not a number.
+    let buf = [0x93, 0xd4, 0x02, 0x31, 0x01, 0x01];
+    rmps::from_slice::<Amounts>(&buf).unwrap_err();
+    let buf = rmps::to_vec(&("x", 1, 1)).unwrap();
+    rmps::from_slice::<Amounts>(&buf).unwrap_err();
+}
+
+#[test]
+fn roundtrip_with_timestamp() {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Time(#[serde(with = "rmps::with::timestamp")] SystemTime);
+
+    let cases: &[(SystemTime, &[u8])] = &[
+        (UNIX_EPOCH, &[0xd6, 0xff, 0, 0, 0, 0]),
+        (UNIX_EPOCH + Duration::from_secs(u32::MAX.into()), &[0xd6, 0xff, 0xff, 0xff, 0xff, 0xff]),
+        (UNIX_EPOCH + Duration::new(1, 1), &[0xd7, 0xff, 0, 0, 0, 0x04, 0, 0, 0, 0x01]),
+        (UNIX_EPOCH + Duration::from_secs(1 << 34), &[0xc7, 12, 0xff, 0, 0, 0, 0, 0, 0, 0, 0x04, 0, 0, 0, 0]),
+        (UNIX_EPOCH - Duration::from_nanos(1), &[0xc7, 12, 0xff, 0x3b, 0x9a, 0xc9, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+    ];
+    for &(time, expected) in cases {
+        let buf = rmps::to_vec(&Time(time)).unwrap();
+        assert_eq!(expected, &buf[..]);
+        assert_eq!(Time(time), rmps::from_slice(&buf).unwrap());
+    }
+
+    // Wrong ext type, length, or nanoseconds out of range.
+    rmps::from_slice::<Time>(&[0xd6, 0x01, 0, 0, 0, 0]).unwrap_err();
+    rmps::from_slice::<Time>(&[0xd5, 0xff, 0, 0]).unwrap_err();
+    rmps::from_slice::<Time>(&[0xd7, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]).unwrap_err();
+}
+
+#[test]
+fn roundtrip_with_helpers() {
+    use rmps::MsgPackExtType;
+
+    #[derive(Debug, PartialEq)]
+    struct Flag(bool);
+
+    impl MsgPackExtType for Flag {
+        const TAG: i8 = 9;
+        type Error = &'static str;
+
+        fn to_bytes(&self) -> Vec<u8> {
+            vec![self.0 as u8]
+        }
+
+        fn from_bytes(data: &[u8]) -> Result<Self, Self::Error> {
+            match *data {
+                [b] => Ok(Flag(b != 0)),
+                _ => Err("expected one byte"),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        #[serde(with = "rmps::with::bytes")]
+        data: Vec<u8>,
+        #[serde(with = "rmps::with::str_lossy")]
+        name: String,
+        #[serde(with = "rmps::with::ext")]
+        flag: Flag,
+    }
+
+    let doc = Doc { data: vec![1, 2], name: "naïve".into(), flag: Flag(true) };
+    let buf = rmps::to_vec(&doc).unwrap();
+    assert_eq!(&[0x93, 0xc4, 0x02, 0x01, 0x02, 0xa6][..], &buf[..6]);
+    assert_eq!(&[0xd4, 0x09, 0x01][..], &buf[12..]);
+    assert_eq!(doc, rmps::from_slice(&buf).unwrap());
+
+    // [str "ab", str with invalid UTF-8, ext]
+    let buf = [0x93, 0xa2, 0x61, 0x62, 0xa3, 0x61, 0xff, 0x62, 0xd4, 0x09, 0x00];
+    let doc = Doc { data: b"ab".to_vec(), name: "a\u{fffd}b".into(), flag: Flag(false) };
+    assert_eq!(doc, rmps::from_slice(&buf).unwrap());
+    let buf = [0x93, 0xc4, 0x00, 0xc4, 0x02, 0xff, 0x62, 0xd4, 0x09, 0x01];
+    assert_eq!("\u{fffd}b", rmps::from_slice::<Doc>(&buf).unwrap().name);
+    let buf = [0x93, 0xc4, 0x00, 0xa0, 0xd4, 0x08, 0x01];
+    rmps::from_slice::<Doc>(&buf).unwrap_err();
+}
+
+#[test]
+fn with_cow_helpers_borrow_from_slices() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc<'a> {
+        #[serde(borrow, with = "rmps::with::cow_str")]
+        name: Cow<'a, str>,
+        #[serde(borrow, with = "rmps::with::cow_bytes")]
+        data: Cow<'a, [u8]>,
+    }
+
+    let doc = Doc { name: Cow::Owned("name".into()), data: Cow::Owned(vec![1, 2, 3]) };
+    let buf = rmps::to_vec(&doc).unwrap();
+    assert_eq!(&[0x92, 0xa4][..], &buf[..2]);
+    assert_eq!(&[0xc4, 0x03, 0x01, 0x02, 0x03][..], &buf[6..]);
+
+    let borrowed: Doc<'_> = rmps::from_slice(&buf).unwrap();
+    assert_eq!(doc, borrowed);
+    assert!(matches!(borrowed.name, Cow::Borrowed(..)));
+    assert!(matches!(borrowed.data, Cow::Borrowed(..)));
+
+    let owned = Doc::deserialize(&mut Deserializer::new(&buf[..])).unwrap();
+    assert_eq!(doc, owned);
+    assert!(matches!(owned.name, Cow::Owned(..)));
+
+    // [bin "hi", str "hi"], swapped.
+    let buf = [0x92, 0xc4, 0x02, b'h', b'i', 0xa2, b'h', b'i'];
+    let swapped: Doc<'_> = rmps::from_slice(&buf).unwrap();
+    assert!(matches!(swapped.name, Cow::Borrowed("hi")));
+    assert!(matches!(swapped.data, Cow::Borrowed(b"hi")));
+    rmps::from_slice::<Doc<'_>>(&[0x92, 0xc4, 0x01, 0xff, 0xc4, 0x00]).unwrap_err();
+}
+
+#[test]
+fn roundtrip_bytes_wrappers() {
+    use rmps::{ByteBuf, Bytes};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc<'a> {
+        #[serde(borrow)]
+        borrowed: Bytes<'a>,
+        owned: ByteBuf,
+    }
+
+    let doc = Doc { borrowed: Bytes(&[1, 2]), owned: ByteBuf(vec![3]) };
+    let buf = rmps::to_vec(&doc).unwrap();
+    assert_eq!(vec![0x92, 0xc4, 0x02, 0x01, 0x02, 0xc4, 0x01, 0x03], buf);
+    assert_eq!(doc, rmps::from_slice(&buf).unwrap());
+    assert_roundtrips(ByteBuf(vec![0; 300]));
+
+    // Strings are accepted too.
+    let buf = [0x92, 0xa2, b'h', b'i', 0xa1, b'!'];
+    assert_eq!(Doc { borrowed: Bytes(b"hi"), owned: ByteBuf(b"!".to_vec()) }, rmps::from_slice(&buf).unwrap());
+
+    // Borrowing needs a slice.
+    Bytes::deserialize(&mut Deserializer::new(&buf[1..])).unwrap_err();
+    assert_eq!(b"hi".to_vec(), ByteBuf::deserialize(&mut Deserializer::new(&buf[1..])).unwrap().into_vec());
+}
+
+#[cfg(feature = "base64")]
+#[test]
+fn roundtrip_with_base64_over_human_readable() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Blob(#[serde(with = "rmps::with::base64_over_human_readable")] Vec<u8>);
+
+    let blob = Blob(b"hello".to_vec());
+    assert_eq!(vec![0xc4, 0x05, b'h', b'e', b'l', b'l', b'o'], rmps::to_vec(&blob).unwrap());
+
+    let mut se = Serializer::new(Vec::new()).with_human_readable();
+    blob.serialize(&mut se).unwrap();
+    let buf = se.into_inner();
+    assert_eq!(b"\xa8aGVsbG8=", &buf[..]);
+    let mut de = Deserializer::new(&buf[..]).with_human_readable();
+    assert_eq!(blob, Blob::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::new(&[0xa2, b'!', b'!'][..]).with_human_readable();
+    Blob::deserialize(&mut de).unwrap_err();
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn roundtrip_with_zstd_bytes() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        id: u32,
+        #[serde(with = "rmps::with::zstd_bytes")]
+        body: String,
+        #[serde(with = "rmps::with::zstd_bytes")]
+        raw: Vec<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct SmallDoc {
+        id: u32,
+        #[serde(deserialize_with = "rmps::with::zstd_bytes::deserialize_with_limit::<_, _, 1000>")]
+        body: String,
+        #[serde(with = "rmps::with::zstd_bytes")]
+        raw: Vec<u8>,
+    }
+
+    let doc = Doc { id: 7, body: "lorem ipsum ".repeat(1000), raw: vec![0; 1000] };
+    let buf = rmps::to_vec(&doc).unwrap();
+    assert!(buf.len() < 200);
+    assert_eq!(doc, rmps::from_slice(&buf).unwrap());
+
+    let small = Doc { id: 7, body: doc.body[..1000].into(), raw: vec![] };
+    assert_eq!(SmallDoc { id: 7, body: small.body.clone(), raw: vec![] },
+               rmps::from_slice(&rmps::to_vec(&small).unwrap()).unwrap());
+    rmps::from_slice::<SmallDoc>(&buf).unwrap_err();
+
+    // An uncompressed bin is rejected.
+    rmps::from_slice::<Doc>(&[0x93, 0x07, 0xc4, 0x01, b'x', 0xc4, 0x00]).unwrap_err();
+}
+
+#[test]
+fn roundtrip_checksummed() {
+    use rmps::checksum::{self, Checksum, ChecksumError};
+
+    assert_eq!(0xcbf4_3926, Checksum::Crc32.compute(b"123456789"));
+    assert_eq!(0x02cc_5d05, Checksum::Xxh32.compute(b""));
+    assert_eq!(0x32d1_53ff, Checksum::Xxh32.compute(b"abc"));
+    assert_eq!(0xe229_3b2f, Checksum::Xxh32.compute(b"Nobody inspects the spammish repetition"));
+
+    let val = (42u32, "x".repeat(100), vec![1.5f64; 10]);
+    for checksum in [Checksum::Crc32, Checksum::Xxh32] {
+        let mut buf = Vec::new();
+        rmps::write_checksummed(&mut buf, &val, checksum).unwrap();
+        assert_eq!(rmps::to_vec(&val).unwrap(), checksum::verify_checksum(&buf, checksum).unwrap());
+        assert_eq!(val, rmps::from_slice_checksummed(&buf, checksum).unwrap());
+
+        for idx in [0, 50, buf.len() - 1] {
+            let mut corrupt = buf.clone();
+            corrupt[idx] ^= 0x10;
+            match checksum::from_slice_checksummed::<(u32, String, Vec<f64>)>(&corrupt, checksum) {
+                Err(ChecksumError::ChecksumMismatch { expected, actual }) => assert_ne!(expected, actual),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        match checksum::from_slice_checksummed::<u32>(&buf[..3], checksum) {
+            Err(ChecksumError::Decode(..)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
+
 #[ignore]
 #[test]
 fn roundtrip_some_failures() {
@@ -698,3 +1565,271 @@ fn assert_roundtrips_config<T, CSF, SC, CDF, DC>(
 
     assert_eq!(val, &val2, "Config: {}", desc);
 }
+
+#[cfg(feature = "allocator_api")]
+#[test]
+fn round_trip_in_allocator() {
+    use allocator_api2::alloc::{AllocError, Allocator, Global, Layout};
+    use std::cell::Cell;
+    use std::ptr::NonNull;
+
+    /// Counts the allocations.
+    struct Counting<'a>(&'a Cell<usize>);
+
+    unsafe impl Allocator for Counting<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    let count = Cell::new(0);
+    let val = ("hello".to_owned(), vec![1, 2, 3]);
+    let buf = rmps::to_vec_in(&val, Counting(&count)).unwrap();
+    assert_eq!(rmps::to_vec(&val).unwrap(), buf[..]);
+    assert_eq!(1, count.get());
+
+    let mut de = Deserializer::new_in(&buf[..], Counting(&count));
+    assert_eq!(val, Deserialize::deserialize(&mut de).unwrap());
+    assert_eq!(2, count.get());
+}
+
+#[test]
+fn round_trip_key_dictionary() {
+    use rmps::keydict;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        id: u32,
+        name: String,
+        tags: BTreeMap<String, u8>,
+    }
+
+    let rows: Vec<Row> = (0..300)
+        .map(|id| Row { id, name: "n".into(), tags: [(format!("tag{}", id % 2), 1)].into() })
+        .collect();
+    let buf = keydict::to_vec_named(&rows).unwrap();
+    assert_eq!(rows, keydict::from_slice::<Vec<Row>>(&buf).unwrap());
+
+    // [{"id": 0, "name": "n", "tags": {"tag0": 1}}, {"id": 1, "name": <ref 0>, "tags": <ref 1>...
+    let second = &buf[3 + 24..];
+    assert_eq!([0x83, 0xa2, b'i', b'd', 0x01, 0xd4, 0x03, 0x00, 0xa1, b'n', 0xd4, 0x03, 0x01, 0x81], second[..14]);
+    assert_eq!(keydict::compress(&rmps::to_vec_named(&rows).unwrap()).unwrap(), buf);
+    assert_eq!(rmps::to_vec_named(&rows).unwrap(), keydict::expand(&buf).unwrap());
+
+    // Two-byte indices.
+    let keys: BTreeMap<String, ()> = (0..300).map(|i| (format!("key{:03}", i), ())).collect();
+    let maps = vec![keys.clone(), keys];
+    let buf = keydict::to_vec_named(&maps).unwrap();
+    assert_eq!([0xd5, 0x03, 0x01, 0x2b, 0xc0], buf[buf.len() - 5..]);
+    assert_eq!(maps, keydict::from_slice::<Vec<BTreeMap<String, ()>>>(&buf).unwrap());
+
+    // Keys that are already references, and references to unknown keys.
+    keydict::compress(&[0x81, 0xd4, 0x03, 0x00, 0xc0]).unwrap_err();
+    keydict::expand(&[0x81, 0xd4, 0x03, 0x00, 0xc0]).unwrap_err();
+    keydict::expand(&[0x82, 0xa2, b'i', b'd']).unwrap_err();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn round_trip_table() {
+    use rmps::table;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        id: u32,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    let rows = vec![
+        Row { id: 1, name: "a".into(), tags: vec![] },
+        Row { id: 2, name: "b".into(), tags: vec!["x".into()] },
+    ];
+    let buf = table::to_vec(&rows).unwrap();
+    assert_eq!(rows, table::from_slice::<Row>(&buf).unwrap());
+    assert_eq!(rows, table::from_read::<_, Row>(&buf[..]).unwrap());
+
+    // Fields are matched by name, the ones missing from the rows use their default.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Reordered {
+        tags: Vec<String>,
+        id: u32,
+        #[serde(default)]
+        score: f32,
+    }
+    let reordered: Vec<Reordered> = table::from_slice(&buf).unwrap();
+    assert_eq!(vec!["x".to_string()], reordered[1].tags);
+    assert_eq!(2, reordered[1].id);
+
+    // Rows shorter than the header.
+    let buf = [0x93, 0x92, 0xa2, b'i', b'd', 0xa4, b'n', b'a', b'm', b'e', 0x91, 0x01, 0x92, 0x02, 0xa1, b'b'];
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Named {
+        id: u32,
+        #[serde(default)]
+        name: String,
+    }
+    assert_eq!(
+        vec![Named { id: 1, name: String::new() }, Named { id: 2, name: "b".into() }],
+        table::from_slice::<Named>(&buf).unwrap()
+    );
+
+    // Rows longer than the header.
+    table::from_slice::<Named>(&[0x92, 0x91, 0xa2, b'i', b'd', 0x92, 0x01, 0x02]).unwrap_err();
+
+    let empty: Vec<Row> = Vec::new();
+    let buf = table::to_vec(&empty).unwrap();
+    assert_eq!([0x91, 0x90], buf[..]);
+    assert_eq!(empty, table::from_slice::<Row>(&buf).unwrap());
+
+    table::to_vec(&[1, 2]).unwrap_err();
+}
+
+#[test]
+fn round_trip_struct_hash() {
+    use rmps::config::field_hash;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Event {
+        timestamp: u64,
+        description: String,
+    }
+
+    assert_eq!(0x811c9dc5, field_hash(""));
+    assert_eq!(0xe40c292c, field_hash("a"));
+
+    let event = Event { timestamp: 1, description: "start".into() };
+    let mut buf = Vec::new();
+    event.serialize(&mut Serializer::new(&mut buf).with_struct_hash()).unwrap();
+    let hash = field_hash("timestamp").to_be_bytes();
+    assert_eq!([0x82, 0xce, hash[0], hash[1], hash[2], hash[3], 0x01], buf[..7]);
+    assert_eq!(18, buf.len());
+
+    let mut de = Deserializer::new(&buf[..]).with_hashed_fields();
+    assert_eq!(event, Event::deserialize(&mut de).unwrap());
+
+    // Fields can be added and removed.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct NewEvent {
+        description: String,
+        #[serde(default)]
+        level: u8,
+    }
+    let mut de = Deserializer::new(&buf[..]).with_hashed_fields();
+    assert_eq!(NewEvent { description: "start".into(), level: 0 }, NewEvent::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::new(&buf[..]).with_hashed_fields().with_strict_structs();
+    let err = NewEvent::deserialize(&mut de).unwrap_err();
+    assert!(err.to_string().contains(&format!("unknown field `{:#010x}`", field_hash("timestamp"))), "{err}");
+
+    // String keys are still accepted.
+    let buf = rmps::to_vec_named(&event).unwrap();
+    let mut de = Deserializer::new(&buf[..]).with_hashed_fields();
+    assert_eq!(event, Event::deserialize(&mut de).unwrap());
+}
+
+#[cfg(feature = "base64")]
+#[test]
+fn round_trip_base64() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Token {
+        user: String,
+        expires: u64,
+    }
+
+    let token = Token { user: "bob".into(), expires: 1_700_000_000 };
+    let s = rmps::to_base64_string(&token).unwrap();
+    assert!(s.ends_with('='));
+    assert_eq!(token, rmps::from_base64_str(&s).unwrap());
+    assert_eq!(token, rmps::from_base64_str(s.trim_end_matches('=')).unwrap());
+
+    match rmps::from_base64_str::<Token>("not base64!") {
+        Err(rmps::decode::Error::Syntax(msg)) => assert!(msg.starts_with("invalid base64"), "{}", msg),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn round_trip_hex() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    let point = Point { x: 1, y: -1 };
+    let s = rmps::to_hex_string(&point).unwrap();
+    assert_eq!("9201ff", s);
+    assert_eq!(point, rmps::from_hex_str(&s).unwrap());
+    assert_eq!(point, rmps::from_hex_str("92 01\nFF").unwrap());
+
+    match rmps::from_hex_str::<Point>("92 0x") {
+        Err(rmps::decode::Error::Syntax(msg)) => assert_eq!("invalid hex digit 'x' at offset 4", msg),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match rmps::from_hex_str::<Point>("9201f") {
+        Err(rmps::decode::Error::Syntax(msg)) => assert_eq!("odd number of hex digits", msg),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_merge_patch() {
+    use rmps::patch::merge;
+    use rmpv::Value;
+
+    fn map(entries: Vec<(Value, Value)>) -> Value {
+        Value::Map(entries)
+    }
+
+    fn apply(base: &Value, patch: &Value) -> Value {
+        let (mut base_buf, mut patch_buf) = (Vec::new(), Vec::new());
+        rmpv::encode::write_value(&mut base_buf, base).unwrap();
+        rmpv::encode::write_value(&mut patch_buf, patch).unwrap();
+        rmpv::decode::read_value(&mut &merge(&base_buf, &patch_buf).unwrap()[..]).unwrap()
+    }
+
+    let base = map(vec![
+        ("a".into(), 1.into()),
+        ("b".into(), map(vec![("c".into(), 2.into()), ("d".into(), 3.into())])),
+        (7.into(), "seven".into()),
+    ]);
+    let patch = map(vec![
+        ("a".into(), Value::Nil),
+        ("b".into(), map(vec![("c".into(), Value::Nil), ("e".into(), map(vec![("f".into(), Value::Nil)]))])),
+        ("g".into(), vec![Value::Nil].into()),
+    ]);
+    assert_eq!(map(vec![
+        ("b".into(), map(vec![("d".into(), 3.into()), ("e".into(), map(vec![]))])),
+        (7.into(), "seven".into()),
+        ("g".into(), vec![Value::Nil].into()),
+    ]), apply(&base, &patch));
+
+    // Values that aren't maps replace the base.
+    assert_eq!(Value::from(5), apply(&base, &5.into()));
+    assert_eq!(Value::Nil, apply(&base, &Value::Nil));
+    assert_eq!(map(vec![("x".into(), 1.into())]), apply(&vec![Value::from(1)].into(), &map(vec![("x".into(), 1.into())])));
+
+    // Keys are matched by value, and the last occurrence wins.
+    let base = [0x82, 0xd9, 0x01, b'k', 0x01, 0xcd, 0x00, 0x07, 0x02];
+    let patch = [0x83, 0xa1, b'k', 0x03, 0x07, 0xc0, 0xa1, b'k', 0x04];
+    assert_eq!(vec![0x81, 0xd9, 0x01, b'k', 0x04], merge(&base, &patch).unwrap());
+
+    // Truncated input.
+    merge(&base[..5], &patch).unwrap_err();
+    merge(&base, &patch[..5]).unwrap_err();
+
+    // Deeply nested patches.
+    let mut patch = [0x81, 0xa1, b'a'].repeat(1024);
+    patch.push(0xc0);
+    assert!(merge(&[0xc0], &patch).is_ok());
+    let mut patch = [0x81, 0xa1, b'a'].repeat(1025);
+    patch.push(0xc0);
+    assert!(matches!(merge(&[0xc0], &patch), Err(rmps::decode::Error::DepthLimitExceeded)));
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use rmpv::Value;
+
+use rmp_serde::rpc::{self, Message, Notification, Request, Response};
+
+#[test]
+fn pass_request_round_trip() {
+    let req = Request { msgid: 7, method: "nvim_command".to_owned(), params: ("echo 1",) };
+
+    let buf = rmp_serde::to_vec(&req).unwrap();
+    // [0, 7, "nvim_command", ["echo 1"]]
+    assert_eq!(&[0x94, 0x00, 0x07, 0xac][..], &buf[..4]);
+
+    assert_eq!(req, rmp_serde::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_response_ok_and_err() {
+    let ok: Response<u32, String> = Response { msgid: 1, result: Ok(42) };
+    let buf = rmp_serde::to_vec(&ok).unwrap();
+    assert_eq!(vec![0x94, 0x01, 0x01, 0xc0, 0x2a], buf);
+    assert_eq!(ok, rmp_serde::from_slice(&buf).unwrap());
+
+    let err: Response<u32, String> = Response { msgid: 2, result: Err("nope".to_owned()) };
+    let buf = rmp_serde::to_vec(&err).unwrap();
+    assert_eq!(vec![0x94, 0x01, 0x02, 0xa4, 0x6e, 0x6f, 0x70, 0x65, 0xc0], buf);
+    assert_eq!(err, rmp_serde::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_response_ok_nil_result() {
+    let ok: Response<(), String> = Response { msgid: 3, result: Ok(()) };
+    let buf = rmp_serde::to_vec(&ok).unwrap();
+
+    assert_eq!(ok, rmp_serde::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_notification_round_trip() {
+    let notification = Notification { method: "redraw".to_owned(), params: vec![Value::from(1)] };
+
+    let buf = rmp_serde::to_vec(&notification).unwrap();
+    assert_eq!(&[0x93, 0x02, 0xa6][..], &buf[..3]);
+
+    assert_eq!(notification, rmp_serde::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_read_write_messages_over_stream() {
+    let mut buf = Vec::new();
+    rpc::write_message(&mut buf, &Request { msgid: 1, method: "add".to_owned(), params: (1, 2) }).unwrap();
+    rpc::write_message(&mut buf, &Notification { method: "ping".to_owned(), params: () }).unwrap();
+    rpc::write_message(&mut buf, &Response::<i32, Value> { msgid: 1, result: Ok(3) }).unwrap();
+
+    let mut rd = Cursor::new(buf);
+    let msgs: Vec<Message<Value, i32, Value>> = (0..3).map(|_| rpc::read_message(&mut rd).unwrap()).collect();
+
+    assert_eq!(vec![
+        Message::Request(Request { msgid: 1, method: "add".to_owned(), params: Value::from(vec![Value::from(1), Value::from(2)]) }),
+        Message::Notification(Notification { method: "ping".to_owned(), params: Value::Nil }),
+        Message::Response(Response { msgid: 1, result: Ok(3) }),
+    ], msgs);
+}
+
+#[test]
+fn fail_request_from_notification() {
+    let buf = rmp_serde::to_vec(&Notification { method: "ping".to_owned(), params: () }).unwrap();
+
+    assert!(rmp_serde::from_slice::<Request<()>>(&buf).is_err());
+}
+
+#[test]
+fn fail_unknown_message_type() {
+    // [3, "ping", []]
+    let buf = [0x93, 0x03, 0xa4, 0x70, 0x69, 0x6e, 0x67, 0x90];
+
+    assert!(rmp_serde::from_slice::<Message<Value, Value, Value>>(&buf).is_err());
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "futures")]
+
+use std::io;
+
+use futures::executor::block_on;
+use futures::io::Cursor;
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use serde_derive::{Deserialize, Serialize};
+
+use rmp_serde::stream::{MsgPackSink, MsgPackStream};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Message {
+    id: u32,
+    body: String,
+}
+
+#[test]
+fn pass_stream_decodes_concatenated_values() {
+    let mut buf = rmp_serde::to_vec(&Message { id: 1, body: "foo".into() }).unwrap();
+    buf.extend(rmp_serde::to_vec(&Message { id: 2, body: "bar".into() }).unwrap());
+
+    let stream = MsgPackStream::<_, Message>::new(Cursor::new(buf));
+    let actual: Vec<Message> = block_on(stream.try_collect()).unwrap();
+
+    assert_eq!(vec![
+        Message { id: 1, body: "foo".into() },
+        Message { id: 2, body: "bar".into() },
+    ], actual);
+}
+
+#[test]
+fn pass_stream_values_spanning_reads() {
+    let vals: Vec<String> = (0..100).map(|i| "x".repeat(i * 50)).collect();
+    let mut buf = Vec::new();
+    for val in &vals {
+        buf.extend(rmp_serde::to_vec(val).unwrap());
+    }
+
+    let stream = MsgPackStream::<_, String>::new(Cursor::new(buf));
+    let actual: Vec<String> = block_on(stream.try_collect()).unwrap();
+
+    assert_eq!(vals, actual);
+}
+
+#[test]
+fn fail_stream_truncated_value() {
+    let mut buf = rmp_serde::to_vec(&Message { id: 1, body: "foo".into() }).unwrap();
+    buf.pop();
+
+    let mut stream = MsgPackStream::<_, Message>::new(Cursor::new(buf));
+    let err = block_on(stream.next()).unwrap().unwrap_err();
+
+    assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+    assert!(block_on(stream.next()).is_none());
+}
+
+#[test]
+fn fail_stream_ends_after_invalid_value() {
+    // A reserved marker, followed by 1.
+    let buf = vec![0xc1, 0x01];
+
+    let mut stream = MsgPackStream::<_, u8>::new(Cursor::new(buf));
+    let err = block_on(stream.next()).unwrap().unwrap_err();
+
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    assert!(block_on(stream.next()).is_none());
+}
+
+#[test]
+fn fail_stream_frame_length_limit() {
+    // The header of a 4 GiB string.
+    let buf = vec![0xdb, 0xff, 0xff, 0xff, 0xff];
+
+    let mut stream = MsgPackStream::<_, String>::new(Cursor::new(buf)).with_max_frame_length(16);
+    let err = block_on(stream.next()).unwrap().unwrap_err();
+
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    assert!(block_on(stream.next()).is_none());
+}
+
+#[test]
+fn pass_sink_round_trip() {
+    let mut sink = MsgPackSink::<_, Message>::new(Cursor::new(Vec::new()));
+    block_on(async {
+        sink.send(Message { id: 1, body: "foo".into() }).await.unwrap();
+        sink.send(Message { id: 2, body: "bar".into() }).await.unwrap();
+        sink.close().await.unwrap();
+    });
+
+    let buf = sink.into_inner().into_inner();
+    let stream = MsgPackStream::<_, Message>::new(Cursor::new(buf));
+    let actual: Vec<Message> = block_on(stream.try_collect()).unwrap();
+
+    assert_eq!(vec![
+        Message { id: 1, body: "foo".into() },
+        Message { id: 2, body: "bar".into() },
+    ], actual);
+}
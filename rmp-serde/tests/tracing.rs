@@ -0,0 +1,104 @@
+#![cfg(feature = "tracing")]
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use serde_derive::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use rmp_serde::Deserializer;
+
+/// Records the fields of spans and events as `name=value` lines.
+#[derive(Clone, Default)]
+struct Recorder {
+    lines: Arc<Mutex<Vec<String>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+#[derive(Default)]
+struct Fields(Vec<String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        self.lines.lock().unwrap().push(format!("span {}", fields.0.join(" ")));
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.lines.lock().unwrap().push(format!("event {}", fields.0.join(" ")));
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+fn record<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    let recorder = Recorder::default();
+    let res = tracing::subscriber::with_default(recorder.clone(), f);
+    let lines = recorder.lines.lock().unwrap().clone();
+    (res, lines)
+}
+
+#[derive(Debug, PartialEq, Serialize, serde_derive::Deserialize)]
+struct Point {
+    x: u32,
+    tags: Vec<String>,
+}
+
+#[test]
+fn pass_trace_containers() {
+    let buf = rmp_serde::to_vec_named(&Point { x: 1, tags: vec!["a".into()] }).unwrap();
+
+    let (point, lines) = record(|| Point::deserialize(&mut Deserializer::new(&buf[..]).with_tracing()));
+    assert_eq!(Point { x: 1, tags: vec!["a".into()] }, point.unwrap());
+    assert_eq!(vec![
+        "span kind=\"map\" len=2 offset=1".to_string(),
+        "span kind=\"array\" len=1 offset=10".to_string(),
+    ], lines);
+}
+
+#[test]
+fn pass_trace_disabled_by_default() {
+    let buf = rmp_serde::to_vec(&vec![vec![1u8]]).unwrap();
+
+    let (res, lines) = record(|| Vec::<Vec<u8>>::deserialize(&mut Deserializer::from_bytes(&buf)));
+    assert_eq!(vec![vec![1]], res.unwrap());
+    assert!(lines.is_empty());
+}
+
+#[test]
+fn fail_trace_error_offsets() {
+    // [[1, "x"]], where a number is expected instead of "x".
+    let buf = [0x91, 0x92, 0x01, 0xa1, b'x'];
+
+    let (res, lines) = record(|| Vec::<Vec<u8>>::deserialize(&mut Deserializer::from_bytes(&buf).with_tracing()));
+    assert!(res.is_err());
+    assert_eq!(4, lines.len());
+    assert_eq!("span kind=\"array\" len=1 offset=1", lines[0]);
+    assert_eq!("span kind=\"array\" len=2 offset=2", lines[1]);
+    assert!(lines[2].starts_with("event message=failed to read array offset=5 error="), "{}", lines[2]);
+    assert!(lines[3].starts_with("event message=failed to read array offset=5 error="), "{}", lines[3]);
+}
@@ -0,0 +1,160 @@
+#![cfg(any(feature = "axum", feature = "actix-web"))]
+
+use std::collections::BTreeMap;
+
+use futures::executor::block_on;
+use serde_derive::{Deserialize, Serialize};
+
+use rmp_serde::web::{MsgPack, MsgPackConfig, MsgPackRejection};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    id: u32,
+    name: String,
+}
+
+fn user() -> User {
+    User { id: 42, name: "John".into() }
+}
+
+#[cfg(feature = "axum")]
+mod axum {
+    use axum_core::body::Body;
+    use axum_core::extract::FromRequest;
+    use axum_core::response::IntoResponse;
+    use http::{Request, StatusCode};
+
+    use super::*;
+
+    fn request(content_type: &str, body: Vec<u8>) -> Request<Body> {
+        Request::post("/").header("content-type", content_type).body(Body::from(body)).unwrap()
+    }
+
+    fn extract<T: serde::de::DeserializeOwned>(req: Request<Body>) -> Result<T, MsgPackRejection> {
+        block_on(MsgPack::<T>::from_request(req, &())).map(MsgPack::into_inner)
+    }
+
+    #[test]
+    fn pass_extract() {
+        let body = rmp_serde::to_vec(&user()).unwrap();
+        assert_eq!(user(), extract::<User>(request("application/msgpack", body.clone())).unwrap());
+        assert_eq!(user(), extract::<User>(request("Application/X-MsgPack; charset=binary", body.clone())).unwrap());
+        assert_eq!(user(), extract::<User>(request("application/vnd.api+msgpack", body)).unwrap());
+    }
+
+    #[test]
+    fn fail_extract_content_type() {
+        let body = rmp_serde::to_vec(&user()).unwrap();
+        let err = extract::<User>(request("application/json", body.clone())).unwrap_err();
+        assert!(matches!(err, MsgPackRejection::UnsupportedContentType));
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, err.into_response().status());
+
+        let req = Request::post("/").body(Body::from(body)).unwrap();
+        assert!(matches!(extract::<User>(req), Err(MsgPackRejection::UnsupportedContentType)));
+    }
+
+    #[test]
+    fn fail_extract_limits() {
+        let body = rmp_serde::to_vec(&user()).unwrap();
+        let mut req = request("application/msgpack", body);
+        req.extensions_mut().insert(MsgPackConfig::new().with_limit(4));
+        let err = extract::<User>(req).unwrap_err();
+        assert!(matches!(err, MsgPackRejection::PayloadTooLarge));
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, err.into_response().status());
+
+        let body = rmp_serde::to_vec(&[[[1]]]).unwrap();
+        let mut req = request("application/msgpack", body);
+        req.extensions_mut().insert(MsgPackConfig::new().with_max_depth(2));
+        let err = extract::<Vec<Vec<Vec<u8>>>>(req).unwrap_err();
+        assert!(matches!(err, MsgPackRejection::Decode(rmp_serde::decode::Error::DepthLimitExceeded)));
+        assert_eq!(StatusCode::BAD_REQUEST, err.into_response().status());
+    }
+
+    #[test]
+    fn fail_extract_streamed_body_limit() {
+        use futures::stream::{self, StreamExt};
+
+        // An endless body without a `Content-Length`, which must not be read past the limit.
+        let chunks = stream::iter(0..).map(|i| {
+            assert!(i < 2, "read past the limit");
+            Ok::<_, std::io::Error>(bytes::Bytes::from_static(&[0xc0; 4]))
+        });
+        let mut req = Request::post("/").header("content-type", "application/msgpack")
+            .body(Body::from_stream(chunks)).unwrap();
+        req.extensions_mut().insert(MsgPackConfig::new().with_limit(4));
+        assert!(matches!(extract::<()>(req), Err(MsgPackRejection::PayloadTooLarge)));
+    }
+
+    #[test]
+    fn fail_extract_trailing_bytes() {
+        let mut body = rmp_serde::to_vec(&user()).unwrap();
+        body.push(0xc0);
+        let err = extract::<User>(request("application/msgpack", body)).unwrap_err();
+        assert!(matches!(err, MsgPackRejection::Decode(..)));
+    }
+
+    #[test]
+    fn pass_into_response() {
+        let res = MsgPack(user()).into_response();
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("application/msgpack", res.headers()["content-type"]);
+
+        let body = block_on(bytes::Bytes::from_request(Request::new(res.into_body()), &())).unwrap();
+        let map: BTreeMap<String, rmpv::Value> = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(Some(&rmpv::Value::from(42)), map.get("id"));
+        assert_eq!(user(), rmp_serde::from_slice::<User>(&body).unwrap());
+    }
+}
+
+#[cfg(feature = "actix-web")]
+mod actix {
+    use actix_web::body::MessageBody;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::{FromRequest, Responder, ResponseError};
+
+    use super::*;
+
+    fn extract<T: serde::de::DeserializeOwned + 'static>(req: TestRequest) -> Result<T, MsgPackRejection> {
+        let (req, mut payload) = req.to_http_parts();
+        block_on(MsgPack::<T>::from_request(&req, &mut payload)).map(MsgPack::into_inner)
+    }
+
+    #[test]
+    fn pass_extract() {
+        let body = rmp_serde::to_vec(&user()).unwrap();
+        let req = TestRequest::post().insert_header(("content-type", "application/msgpack")).set_payload(body);
+        assert_eq!(user(), extract::<User>(req).unwrap());
+    }
+
+    #[test]
+    fn fail_extract() {
+        let body = rmp_serde::to_vec(&user()).unwrap();
+        let req = TestRequest::post().insert_header(("content-type", "text/plain")).set_payload(body.clone());
+        let err = extract::<User>(req).unwrap_err();
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, err.status_code());
+
+        let req = TestRequest::post()
+            .insert_header(("content-type", "application/msgpack"))
+            .app_data(MsgPackConfig::new().with_limit(4))
+            .set_payload(body);
+        let err = extract::<User>(req).unwrap_err();
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, err.status_code());
+
+        let req = TestRequest::post().insert_header(("content-type", "application/msgpack")).set_payload(vec![0xc1]);
+        let err = extract::<User>(req).unwrap_err();
+        assert_eq!(StatusCode::BAD_REQUEST, err.status_code());
+    }
+
+    #[test]
+    fn pass_respond_to() {
+        let req = TestRequest::default().to_http_request();
+        let res = MsgPack(user()).respond_to(&req);
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("application/msgpack", res.headers().get("content-type").unwrap());
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let map: BTreeMap<String, rmpv::Value> = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(Some(&rmpv::Value::from(42)), map.get("id"));
+    }
+}
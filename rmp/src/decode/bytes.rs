@@ -6,6 +6,7 @@ use super::RmpRead;
 
 /// Indicates that an error occurred reading from [Bytes]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 // NOTE: We can't use thiserror because of no_std :(
 pub enum BytesReadError {
@@ -65,6 +66,21 @@ impl<'a> Bytes<'a> {
     pub fn remaining_slice(&self) -> &'a [u8] {
         self.bytes
     }
+    /// Borrows the next `len` bytes and advances past them.
+    #[inline]
+    pub(crate) fn read_slice(&mut self, len: usize) -> Result<&'a [u8], BytesReadError> {
+        if len > self.bytes.len() {
+            return Err(BytesReadError::InsufficientBytes {
+                expected: len,
+                actual: self.bytes.len(),
+                position: self.current_position
+            });
+        }
+        let (slice, newly_remaining) = self.bytes.split_at(len);
+        self.bytes = newly_remaining;
+        self.current_position += len as u64;
+        Ok(slice)
+    }
     /// Return the position of the input buffer.
     ///
     /// This is not required for correctness, it only exists to help mimic
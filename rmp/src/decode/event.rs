@@ -0,0 +1,183 @@
+use super::bytes::{Bytes, BytesReadError};
+use super::{read_marker, RmpRead, ValueReadError};
+use crate::Marker;
+
+/// A single MessagePack item, as produced by [`EventReader`].
+///
+/// Arrays and maps only announce their length, the events of their elements follow. Strings,
+/// binaries and extensions borrow their contents from the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'a> {
+    /// A nil value.
+    Nil,
+    /// A boolean.
+    Bool(bool),
+    /// A positive fixint or an integer encoded with an unsigned marker.
+    UInt(u64),
+    /// A negative fixint or an integer encoded with a signed marker.
+    SInt(i64),
+    /// A 32-bit float.
+    F32(f32),
+    /// A 64-bit float.
+    F64(f64),
+    /// A string. Its contents are not validated to be UTF-8.
+    Str(&'a [u8]),
+    /// A binary.
+    Bin(&'a [u8]),
+    /// The start of an array with the given number of elements.
+    ArrayStart(u32),
+    /// The start of a map with the given number of key-value pairs.
+    MapStart(u32),
+    /// An extension with the given type and data.
+    Ext(i8, &'a [u8]),
+}
+
+/// A pull parser producing MessagePack [`Event`]s from a byte slice.
+///
+/// This performs no allocation and involves no serde, which makes it a building block for
+/// indexers, validators or converters between formats.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{Event, EventReader};
+///
+/// // {"a": [1, -1]}
+/// let buf = [0x81, 0xa1, 0x61, 0x92, 0x01, 0xff];
+/// let events = EventReader::new(&buf).collect::<Result<Vec<_>, _>>().unwrap();
+///
+/// assert_eq!(vec![
+///     Event::MapStart(1),
+///     Event::Str(b"a"),
+///     Event::ArrayStart(2),
+///     Event::UInt(1),
+///     Event::SInt(-1),
+/// ], events);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventReader<'a> {
+    rd: Bytes<'a>,
+}
+
+impl<'a> EventReader<'a> {
+    /// Creates a reader for the given buffer.
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Self {
+        EventReader { rd: Bytes::new(buf) }
+    }
+
+    /// Returns the number of bytes read so far.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.rd.position()
+    }
+
+    /// Returns the part of the buffer that was not read yet.
+    #[inline]
+    pub fn remaining_slice(&self) -> &'a [u8] {
+        self.rd.remaining_slice()
+    }
+
+    /// Reads the next event.
+    ///
+    /// Returns `Ok(None)` once the whole buffer was read.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the buffer ends in the middle of an item, or on the reserved marker, which is
+    /// reported as `ValueReadError::TypeMismatch`. The reader should not be used after an error.
+    pub fn next_event(&mut self) -> Result<Option<Event<'a>>, ValueReadError<BytesReadError>> {
+        if self.rd.remaining_slice().is_empty() {
+            return Ok(None);
+        }
+
+        let rd = &mut self.rd;
+        let event = match read_marker(rd)? {
+            Marker::Null => Event::Nil,
+            Marker::True => Event::Bool(true),
+            Marker::False => Event::Bool(false),
+            Marker::FixPos(val) => Event::UInt(u64::from(val)),
+            Marker::FixNeg(val) => Event::SInt(i64::from(val)),
+            Marker::U8 => Event::UInt(u64::from(rd.read_data_u8()?)),
+            Marker::U16 => Event::UInt(u64::from(rd.read_data_u16()?)),
+            Marker::U32 => Event::UInt(u64::from(rd.read_data_u32()?)),
+            Marker::U64 => Event::UInt(rd.read_data_u64()?),
+            Marker::I8 => Event::SInt(i64::from(rd.read_data_i8()?)),
+            Marker::I16 => Event::SInt(i64::from(rd.read_data_i16()?)),
+            Marker::I32 => Event::SInt(i64::from(rd.read_data_i32()?)),
+            Marker::I64 => Event::SInt(rd.read_data_i64()?),
+            Marker::F32 => Event::F32(rd.read_data_f32()?),
+            Marker::F64 => Event::F64(rd.read_data_f64()?),
+            Marker::FixStr(len) => Event::Str(read_slice(rd, u32::from(len))?),
+            Marker::Str8 => {
+                let len = u32::from(rd.read_data_u8()?);
+                Event::Str(read_slice(rd, len)?)
+            }
+            Marker::Str16 => {
+                let len = u32::from(rd.read_data_u16()?);
+                Event::Str(read_slice(rd, len)?)
+            }
+            Marker::Str32 => {
+                let len = rd.read_data_u32()?;
+                Event::Str(read_slice(rd, len)?)
+            }
+            Marker::Bin8 => {
+                let len = u32::from(rd.read_data_u8()?);
+                Event::Bin(read_slice(rd, len)?)
+            }
+            Marker::Bin16 => {
+                let len = u32::from(rd.read_data_u16()?);
+                Event::Bin(read_slice(rd, len)?)
+            }
+            Marker::Bin32 => {
+                let len = rd.read_data_u32()?;
+                Event::Bin(read_slice(rd, len)?)
+            }
+            Marker::FixArray(len) => Event::ArrayStart(u32::from(len)),
+            Marker::Array16 => Event::ArrayStart(u32::from(rd.read_data_u16()?)),
+            Marker::Array32 => Event::ArrayStart(rd.read_data_u32()?),
+            Marker::FixMap(len) => Event::MapStart(u32::from(len)),
+            Marker::Map16 => Event::MapStart(u32::from(rd.read_data_u16()?)),
+            Marker::Map32 => Event::MapStart(rd.read_data_u32()?),
+            Marker::FixExt1 => read_ext(rd, 1)?,
+            Marker::FixExt2 => read_ext(rd, 2)?,
+            Marker::FixExt4 => read_ext(rd, 4)?,
+            Marker::FixExt8 => read_ext(rd, 8)?,
+            Marker::FixExt16 => read_ext(rd, 16)?,
+            Marker::Ext8 => {
+                let len = u32::from(rd.read_data_u8()?);
+                read_ext(rd, len)?
+            }
+            Marker::Ext16 => {
+                let len = u32::from(rd.read_data_u16()?);
+                read_ext(rd, len)?
+            }
+            Marker::Ext32 => {
+                let len = rd.read_data_u32()?;
+                read_ext(rd, len)?
+            }
+            Marker::Reserved => return Err(ValueReadError::TypeMismatch(Marker::Reserved)),
+        };
+        Ok(Some(event))
+    }
+}
+
+impl<'a> Iterator for EventReader<'a> {
+    type Item = Result<Event<'a>, ValueReadError<BytesReadError>>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[inline]
+fn read_slice<'a>(rd: &mut Bytes<'a>, len: u32) -> Result<&'a [u8], ValueReadError<BytesReadError>> {
+    rd.read_slice(len as usize).map_err(ValueReadError::InvalidDataRead)
+}
+
+#[inline]
+fn read_ext<'a>(rd: &mut Bytes<'a>, len: u32) -> Result<Event<'a>, ValueReadError<BytesReadError>> {
+    let ty = rd.read_data_i8()?;
+    Ok(Event::Ext(ty, read_slice(rd, len)?))
+}
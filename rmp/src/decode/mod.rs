@@ -10,15 +10,19 @@
 //! to avoid data loss (using `BufRead` readers with manual consuming or some other way).
 
 mod dec;
+mod event;
 mod ext;
+mod scan;
 mod sint;
 mod str;
 mod uint;
 
 pub use self::dec::{read_f32, read_f64};
+pub use self::event::{Event, EventReader};
 pub use self::ext::{
     read_ext_meta, read_fixext1, read_fixext16, read_fixext2, read_fixext4, read_fixext8, ExtMeta,
 };
+pub use self::scan::{scan_value, Scan, ScanError, Scanner};
 pub use self::sint::{read_i16, read_i32, read_i64, read_i8, read_nfix};
 #[allow(deprecated)]
 // While we re-export deprecated items, we don't want to trigger warnings while compiling this crate
@@ -190,6 +194,7 @@ pub struct MarkerReadError<E: RmpReadErr = Error>(pub E);
 
 /// An error which can occur when attempting to read a MessagePack value from the reader.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[allow(deprecated)] // Needed for backwards compat
 pub enum ValueReadError<E = Error> {
     /// Failed to read the marker.
@@ -298,6 +303,7 @@ pub fn read_bool<R: RmpRead>(rd: &mut R) -> Result<bool, ValueReadError<R::Error
 
 /// An error which can occur when attempting to read a MessagePack numeric value from the reader.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[allow(deprecated)] // Used for compatibility
 pub enum NumValueReadError<E = Error> {
     /// Failed to read the marker.
@@ -0,0 +1,194 @@
+use core::fmt::{self, Display, Formatter};
+
+use crate::Marker;
+
+/// The outcome of [`scan_value`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scan {
+    /// The buffer starts with a complete value that is this many bytes long.
+    Complete(usize),
+    /// The buffer ends in the middle of a value, and at least this many more bytes are needed to
+    /// complete it.
+    ///
+    /// This is a lower bound derived from the length headers read so far, more bytes may be
+    /// needed once the remaining headers arrive.
+    Incomplete(usize),
+}
+
+/// An error that can occur when scanning a buffer with [`scan_value`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanError {
+    /// The reserved `0xc1` marker was found at the given position.
+    ReservedMarker {
+        position: usize,
+    },
+}
+
+impl Display for ScanError {
+    #[cold]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            ScanError::ReservedMarker { position } => {
+                write!(f, "reserved MessagePack marker at position {}", position)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScanError {}
+
+/// Checks whether the given buffer starts with a complete MessagePack value, without decoding it.
+///
+/// If it does, returns the length of that value. Otherwise returns a lower bound on how many more
+/// bytes are needed, so that framing loops can wait for enough data instead of retrying after
+/// every read.
+///
+/// Only markers and length headers are inspected, the contents of strings, binaries and
+/// extensions are not validated. Nesting is handled without recursion, so deeply nested input
+/// can't overflow the stack.
+///
+/// Use [`Scanner`] to avoid rescanning the start of the buffer as more data arrives.
+///
+/// # Errors
+///
+/// Returns `ScanError::ReservedMarker` if a reserved marker is found before the value is complete.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{scan_value, Scan};
+///
+/// // ["le", "message"], missing the last 3 bytes.
+/// let buf = [0x92, 0xa2, 0x6c, 0x65, 0xa7, 0x6d, 0x65, 0x73, 0x73];
+///
+/// assert_eq!(Scan::Incomplete(3), scan_value(&buf).unwrap());
+/// assert_eq!(Scan::Complete(3), scan_value(&buf[1..]).unwrap());
+/// ```
+#[inline]
+pub fn scan_value(buf: &[u8]) -> Result<Scan, ScanError> {
+    Scanner::new().scan(buf)
+}
+
+/// A resumable version of [`scan_value`].
+///
+/// The scanner remembers how far it got, so feeding it a growing buffer only inspects the newly
+/// arrived headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scanner {
+    /// Position of the first value that was not scanned completely.
+    pos: u64,
+    /// Number of values left to scan, each of them takes at least one byte.
+    pending: u64,
+}
+
+impl Scanner {
+    /// Creates a scanner for a value starting at the beginning of the buffer.
+    #[inline]
+    pub fn new() -> Self {
+        Scanner { pos: 0, pending: 1 }
+    }
+
+    /// Continues scanning the value at the start of `buf`.
+    ///
+    /// `buf` must start with the same bytes that were passed to the previous calls, possibly with
+    /// more bytes appended. Once the value is complete, the scanner keeps returning its length
+    /// until it is [`reset`](Self::reset).
+    ///
+    /// # Errors
+    ///
+    /// See [`scan_value`].
+    pub fn scan(&mut self, buf: &[u8]) -> Result<Scan, ScanError> {
+        let len = buf.len() as u64;
+
+        while self.pending > 0 {
+            let pos = self.pos;
+            let byte = match buf.get(pos as usize) {
+                Some(&byte) => byte,
+                None => return Ok(incomplete((pos - len).saturating_add(self.pending))),
+            };
+            // Values after the current one need at least a byte each.
+            let pending = self.pending - 1;
+
+            // Size of the length header following the marker, how many bytes follow the header in
+            // addition to the length it encodes, and how many values each unit of that length
+            // stands for (zero if it counts bytes instead).
+            let (len_size, extra, items) = match Marker::from_u8(byte) {
+                Marker::FixPos(..) | Marker::FixNeg(..) | Marker::Null | Marker::True | Marker::False => (0, 0, 0),
+                Marker::U8 | Marker::I8 => (0, 1, 0),
+                Marker::U16 | Marker::I16 => (0, 2, 0),
+                Marker::U32 | Marker::I32 | Marker::F32 => (0, 4, 0),
+                Marker::U64 | Marker::I64 | Marker::F64 => (0, 8, 0),
+                Marker::FixStr(n) => (0, u64::from(n), 0),
+                Marker::Str8 | Marker::Bin8 => (1, 0, 0),
+                Marker::Str16 | Marker::Bin16 => (2, 0, 0),
+                Marker::Str32 | Marker::Bin32 => (4, 0, 0),
+                Marker::FixExt1 => (0, 2, 0),
+                Marker::FixExt2 => (0, 3, 0),
+                Marker::FixExt4 => (0, 5, 0),
+                Marker::FixExt8 => (0, 9, 0),
+                Marker::FixExt16 => (0, 17, 0),
+                Marker::Ext8 => (1, 1, 0),
+                Marker::Ext16 => (2, 1, 0),
+                Marker::Ext32 => (4, 1, 0),
+                Marker::FixArray(n) => {
+                    self.pos += 1;
+                    self.pending = pending + u64::from(n);
+                    continue;
+                }
+                Marker::FixMap(n) => {
+                    self.pos += 1;
+                    self.pending = pending + 2 * u64::from(n);
+                    continue;
+                }
+                Marker::Array16 => (2, 0, 1),
+                Marker::Array32 => (4, 0, 1),
+                Marker::Map16 => (2, 0, 2),
+                Marker::Map32 => (4, 0, 2),
+                Marker::Reserved => return Err(ScanError::ReservedMarker { position: pos as usize }),
+            };
+
+            let n = match read_len(buf, pos + 1, len_size) {
+                Some(n) => n,
+                None => return Ok(incomplete((pos + 1 + len_size - len).saturating_add(pending))),
+            };
+            let mut end = pos + 1 + len_size + extra;
+            if items == 0 {
+                end += n;
+                if end > len {
+                    // Stay at this value's marker, so that resuming re-reads its header.
+                    return Ok(incomplete((end - len).saturating_add(pending)));
+                }
+            }
+            self.pos = end;
+            self.pending = pending.saturating_add(items * n);
+        }
+
+        Ok(Scan::Complete(self.pos as usize))
+    }
+
+    /// Resets the scanner to scan a new value from the start of a buffer.
+    #[inline]
+    pub fn reset(&mut self) {
+        *self = Scanner::new();
+    }
+}
+
+impl Default for Scanner {
+    #[inline]
+    fn default() -> Self {
+        Scanner::new()
+    }
+}
+
+/// Reads a big-endian length of `size` bytes at `pos`, or `None` if the buffer is too short.
+#[inline]
+fn read_len(buf: &[u8], pos: u64, size: u64) -> Option<u64> {
+    let bytes = buf.get(pos as usize..(pos + size) as usize)?;
+    Some(bytes.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b)))
+}
+
+#[inline]
+fn incomplete(needed: u64) -> Scan {
+    Scan::Incomplete(usize::try_from(needed).unwrap_or(usize::MAX))
+}
@@ -1,11 +1,23 @@
 #[cfg(feature = "std")]
 use std::error;
 use core::fmt::{self, Display, Formatter};
-use core::str::{from_utf8, Utf8Error};
+use core::str::{self, Utf8Error};
 
 use super::{RmpRead, RmpReadErr, read_marker, ValueReadError};
 use crate::Marker;
 
+/// Validates UTF-8 like `str::from_utf8`, using SIMD instructions if the `simdutf8` feature is
+/// enabled.
+#[inline]
+fn from_utf8(v: &[u8]) -> Result<&str, Utf8Error> {
+    #[cfg(feature = "simdutf8")]
+    if let Ok(s) = simdutf8::basic::from_utf8(v) {
+        return Ok(s);
+    }
+    // The fast path doesn't say where the error is, so invalid input is validated again.
+    str::from_utf8(v)
+}
+
 #[derive(Debug)]
 #[allow(deprecated)] // Only for compatibility
 pub enum DecodeStringError<'a, E = super::Error> {
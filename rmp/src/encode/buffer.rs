@@ -16,6 +16,7 @@ use alloc::vec::Vec;
 ///
 /// This is specific to `#[cfg(not(feature = "std"))]` so it is `#[doc(hidden)]`
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg(not(feature = "std"))]
 #[doc(hidden)]
 pub struct FixedBufCapacityOverflow {
@@ -0,0 +1,182 @@
+use core::fmt::{self, Display, Formatter};
+
+use super::{
+    write_array_len, write_bin, write_bool, write_ext_meta, write_f32, write_f64, write_map_len, write_nil,
+    write_sint, write_str_len, write_uint, RmpWrite, RmpWriteErr, ValueWriteError,
+};
+use crate::decode::Event;
+
+/// An error that can occur when writing events with an [`EventWriter`].
+#[derive(Debug)]
+pub enum EventWriteError<E> {
+    /// Failed to write the event.
+    Write(ValueWriteError<E>),
+    /// A string, binary or extension was longer than MessagePack allows, i.e. `u32::MAX` bytes.
+    TooLong(usize),
+    /// Finished writing in the middle of a value, while this many array or map elements were still
+    /// missing.
+    Incomplete(u64),
+}
+
+impl<E> From<ValueWriteError<E>> for EventWriteError<E> {
+    #[cold]
+    fn from(err: ValueWriteError<E>) -> Self {
+        EventWriteError::Write(err)
+    }
+}
+
+impl<E: RmpWriteErr> Display for EventWriteError<E> {
+    #[cold]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            EventWriteError::Write(ref err) => Display::fmt(err, f),
+            EventWriteError::TooLong(len) => write!(f, "{} bytes are too long for a MessagePack value", len),
+            EventWriteError::Incomplete(missing) => write!(f, "value is missing {} array or map elements", missing),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: RmpWriteErr> std::error::Error for EventWriteError<E> {
+    #[cold]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            EventWriteError::Write(ref err) => Some(err),
+            EventWriteError::TooLong(..) => None,
+            EventWriteError::Incomplete(..) => None,
+        }
+    }
+}
+
+/// Writes MessagePack [`Event`]s, the counterpart of [`EventReader`](crate::decode::EventReader).
+///
+/// Every event is encoded with the most compact marker. The writer keeps count of the array and
+/// map elements announced by `ArrayStart` and `MapStart`, so that [`EventWriter::finish`] can
+/// check that the output ends with a complete value.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{Event, EventReader};
+/// use rmp::encode::EventWriter;
+///
+/// // Uppercase all strings of {"a": ["b"]}
+/// let buf = [0x81, 0xa1, 0x61, 0x91, 0xa1, 0x62];
+///
+/// let mut wr = EventWriter::new(Vec::new());
+/// for event in EventReader::new(&buf) {
+///     match event.unwrap() {
+///         Event::Str(s) => wr.write(Event::Str(&s.to_ascii_uppercase())).unwrap(),
+///         event => wr.write(event).unwrap(),
+///     }
+/// }
+///
+/// assert_eq!(vec![0x81, 0xa1, 0x41, 0x91, 0xa1, 0x42], wr.finish().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct EventWriter<W> {
+    wr: W,
+    /// Number of values still missing to complete the current top-level value.
+    pending: u64,
+}
+
+impl<W: RmpWrite> EventWriter<W> {
+    /// Creates a writer emitting bytes into the given writer.
+    #[inline]
+    pub fn new(wr: W) -> Self {
+        EventWriter { wr, pending: 0 }
+    }
+
+    /// Writes a single event.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing fails, or if a string, binary or extension is too long to be encoded.
+    /// Nothing is written in the latter case.
+    pub fn write(&mut self, event: Event<'_>) -> Result<(), EventWriteError<W::Error>> {
+        let wr = &mut self.wr;
+        let mut items = 0;
+        match event {
+            Event::Nil => write_nil(wr).map_err(ValueWriteError::InvalidMarkerWrite)?,
+            Event::Bool(val) => write_bool(wr, val).map_err(ValueWriteError::InvalidMarkerWrite)?,
+            Event::UInt(val) => {
+                write_uint(wr, val)?;
+            }
+            Event::SInt(val) => {
+                write_sint(wr, val)?;
+            }
+            Event::F32(val) => write_f32(wr, val)?,
+            Event::F64(val) => write_f64(wr, val)?,
+            Event::Str(data) => {
+                write_str_len(wr, checked_len(data)?)?;
+                wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)?;
+            }
+            Event::Bin(data) => {
+                checked_len(data)?;
+                write_bin(wr, data)?;
+            }
+            Event::ArrayStart(len) => {
+                write_array_len(wr, len)?;
+                items = u64::from(len);
+            }
+            Event::MapStart(len) => {
+                write_map_len(wr, len)?;
+                items = 2 * u64::from(len);
+            }
+            Event::Ext(ty, data) => {
+                write_ext_meta(wr, checked_len(data)?, ty)?;
+                wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)?;
+            }
+        }
+
+        // This event is one of the pending values, unless it starts a new top-level value.
+        self.pending = self.pending.saturating_sub(1) + items;
+        Ok(())
+    }
+
+    /// Returns `true` if no value is in progress, i.e. all announced array and map elements were
+    /// written.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// Checks that the output ends with a complete value and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EventWriteError::Incomplete` if array or map elements are missing.
+    pub fn finish(self) -> Result<W, EventWriteError<W::Error>> {
+        if self.pending > 0 {
+            return Err(EventWriteError::Incomplete(self.pending));
+        }
+        Ok(self.wr)
+    }
+}
+
+impl<W> EventWriter<W> {
+    /// Gets a reference to the underlying writer.
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly bypasses the element count checks.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr
+    }
+
+    /// Returns the underlying writer, without checking that the output is complete.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.wr
+    }
+}
+
+#[inline]
+fn checked_len<E>(data: &[u8]) -> Result<u32, EventWriteError<E>> {
+    u32::try_from(data.len()).map_err(|_| EventWriteError::TooLong(data.len()))
+}
@@ -2,6 +2,7 @@
 
 mod bin;
 mod dec;
+mod event;
 mod ext;
 mod map;
 mod sint;
@@ -11,6 +12,7 @@ mod vec;
 
 pub use self::bin::{write_bin, write_bin_len};
 pub use self::dec::{write_f32, write_f64};
+pub use self::event::{EventWriteError, EventWriter};
 pub use self::sint::{write_i16, write_i32, write_i64, write_i8, write_nfix, write_sint};
 pub use self::str::{write_str, write_str_len};
 pub use self::uint::{write_pfix, write_u16, write_u32, write_u64, write_u8, write_uint};
@@ -163,6 +165,17 @@ pub trait RmpWrite {
     /// See also [std::io::Write::write_all]
     fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
 
+    /// Write a header followed by its data, e.g. the marker and length of a string and then its
+    /// bytes.
+    ///
+    /// This is the same as two calls to `write_bytes`, but writers that support it can do it in a
+    /// single call, see [std::io::Write::write_vectored].
+    #[inline]
+    fn write_bytes_vectored(&mut self, header: &[u8], data: &[u8]) -> Result<(), Self::Error> {
+        self.write_bytes(header)?;
+        self.write_bytes(data)
+    }
+
     // Internal helper functions to map I/O error into the `DataWriteError` error.
 
     /// Write a single (signed) byte to this stream.
@@ -198,6 +211,24 @@ impl<T: std::io::Write> RmpWrite for T {
     fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
         self.write_all(buf)
     }
+
+    fn write_bytes_vectored(&mut self, header: &[u8], data: &[u8]) -> Result<(), Self::Error> {
+        use std::io::{ErrorKind, IoSlice};
+
+        // Same as the unstable `Write::write_all_vectored`.
+        let mut bufs = [IoSlice::new(header), IoSlice::new(data)];
+        let mut bufs = &mut bufs[..];
+        IoSlice::advance_slices(&mut bufs, 0);
+        while !bufs.is_empty() {
+            match self.write_vectored(bufs) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
 }
 
 // this conflicts with the impl for &mut std::io::Write on std
@@ -209,10 +240,16 @@ impl<T: RmpWrite> RmpWrite for &mut T {
     fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
         T::write_bytes(self, buf)
     }
+
+    #[inline]
+    fn write_bytes_vectored(&mut self, header: &[u8], data: &[u8]) -> Result<(), Self::Error> {
+        T::write_bytes_vectored(self, header, data)
+    }
 }
 
 /// An error that can occur when attempting to write multi-byte MessagePack value.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[allow(deprecated)] // TODO: Needed for compatibility
 pub enum ValueWriteError<E = Error> {
     /// I/O error while writing marker.
@@ -4,6 +4,7 @@ const FIXMAP_SIZE   : u8 = 0x0f;
 
 /// Format markers.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Marker {
     FixPos(u8),
     FixNeg(i8),
@@ -0,0 +1,86 @@
+use crate::msgpack::decode::*;
+use crate::msgpack::Marker;
+
+fn events(buf: &[u8]) -> Vec<Event<'_>> {
+    EventReader::new(buf).collect::<Result<Vec<_>, _>>().unwrap()
+}
+
+#[test]
+fn from_scalars_read_events() {
+    let buf = [
+        0xc0, 0xc3, 0xc2, 0x7f, 0xe0, 0xcc, 0xff, 0xcd, 0x01, 0x00, 0xd0, 0x80, 0xd1, 0xff, 0x00,
+        0xca, 0x3f, 0x80, 0x00, 0x00, 0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    assert_eq!(vec![
+        Event::Nil,
+        Event::Bool(true),
+        Event::Bool(false),
+        Event::UInt(127),
+        Event::SInt(-32),
+        Event::UInt(255),
+        Event::UInt(256),
+        Event::SInt(-128),
+        Event::SInt(-256),
+        Event::F32(1.0),
+        Event::F64(1.5),
+    ], events(&buf));
+}
+
+#[test]
+fn from_str_and_bin_read_events_borrows() {
+    let buf = [0xa2, 0x68, 0x69, 0xd9, 0x01, 0x21, 0xc4, 0x02, 0x00, 0x01];
+
+    let events = events(&buf);
+    assert_eq!(vec![Event::Str(b"hi"), Event::Str(b"!"), Event::Bin(&[0x00, 0x01])], events);
+    match events[0] {
+        Event::Str(s) => assert_eq!(buf[1..].as_ptr(), s.as_ptr()),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn from_containers_read_events() {
+    let buf = [0xdc, 0x00, 0x01, 0x80, 0xdf, 0x00, 0x00, 0x00, 0x00];
+
+    assert_eq!(vec![Event::ArrayStart(1), Event::MapStart(0), Event::MapStart(0)], events(&buf));
+}
+
+#[test]
+fn from_ext_read_events() {
+    let buf = [0xd4, 0x01, 0x02, 0xc7, 0x03, 0xfe, 0x0a, 0x0b, 0x0c];
+
+    assert_eq!(vec![Event::Ext(1, &[0x02]), Event::Ext(-2, &[0x0a, 0x0b, 0x0c])], events(&buf));
+}
+
+#[test]
+fn from_truncated_str_read_event_fails() {
+    let buf = [0xa3, 0x66, 0x6f];
+    let mut rd = EventReader::new(&buf);
+
+    match rd.next_event() {
+        Err(ValueReadError::InvalidDataRead(bytes::BytesReadError::InsufficientBytes { expected: 3, actual: 2, .. })) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_reserved_read_event_fails() {
+    let mut rd = EventReader::new(&[0xc1]);
+
+    match rd.next_event() {
+        Err(ValueReadError::TypeMismatch(Marker::Reserved)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_empty_read_event_is_none() {
+    let buf = [0xc0];
+    let mut rd = EventReader::new(&buf);
+
+    assert_eq!(Event::Nil, rd.next_event().unwrap().unwrap());
+    assert_eq!(1, rd.position());
+    assert!(rd.next_event().unwrap().is_none());
+    assert!(rd.next().is_none());
+}
@@ -1,10 +1,12 @@
 mod array;
 mod bin;
 mod bool;
+mod event;
 mod ext;
 mod float;
 mod map;
 mod null;
+mod scan;
 mod sint;
 mod string;
 mod uint;
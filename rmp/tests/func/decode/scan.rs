@@ -0,0 +1,103 @@
+use crate::msgpack::decode::*;
+#[cfg(feature = "std")]
+use crate::msgpack::encode::*;
+
+#[test]
+fn from_nil_scan_value() {
+    assert_eq!(Scan::Complete(1), scan_value(&[0xc0]).unwrap());
+    assert_eq!(Scan::Complete(1), scan_value(&[0xc0, 0xc0]).unwrap());
+}
+
+#[test]
+fn from_empty_buf_scan_value() {
+    assert_eq!(Scan::Incomplete(1), scan_value(&[]).unwrap());
+}
+
+#[test]
+fn from_u32_partial_scan_value() {
+    assert_eq!(Scan::Incomplete(2), scan_value(&[0xce, 0x00, 0x00]).unwrap());
+}
+
+#[test]
+fn from_bin32_header_scan_value_reports_payload_len() {
+    // bin32 with a length of 65536 bytes, of which only 2 have arrived.
+    let buf = [0xc6, 0x00, 0x01, 0x00, 0x00, 0x01, 0x02];
+    assert_eq!(Scan::Incomplete(65534), scan_value(&buf).unwrap());
+}
+
+#[test]
+fn from_bin32_partial_header_scan_value() {
+    assert_eq!(Scan::Incomplete(2), scan_value(&[0xc6, 0x00, 0x01]).unwrap());
+}
+
+#[test]
+fn from_array16_scan_value_counts_pending_items() {
+    // array16 of 1000 elements, of which none have arrived.
+    assert_eq!(Scan::Incomplete(1000), scan_value(&[0xdc, 0x03, 0xe8]).unwrap());
+}
+
+#[test]
+fn from_fixmap_scan_value_counts_keys_and_values() {
+    assert_eq!(Scan::Incomplete(4), scan_value(&[0x82]).unwrap());
+    assert_eq!(Scan::Complete(5), scan_value(&[0x82, 0x01, 0x02, 0x03, 0x04, 0xff]).unwrap());
+}
+
+#[test]
+fn from_ext_scan_value() {
+    assert_eq!(Scan::Complete(3), scan_value(&[0xd4, 0x01, 0x02]).unwrap());
+    assert_eq!(Scan::Incomplete(2), scan_value(&[0xc7, 0x03, 0x01, 0xaa]).unwrap());
+}
+
+#[test]
+fn from_reserved_scan_value_fails() {
+    assert_eq!(Err(ScanError::ReservedMarker { position: 2 }), scan_value(&[0x92, 0x01, 0xc1]));
+}
+
+#[test]
+fn from_deeply_nested_array_scan_value() {
+    let mut buf = vec![0x91; 100_000];
+    buf.push(0xc0);
+    assert_eq!(Scan::Complete(buf.len()), scan_value(&buf).unwrap());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn from_every_prefix_scan_value_is_incomplete() {
+    let mut buf = Vec::new();
+    write_map_len(&mut buf, 3).unwrap();
+    write_str(&mut buf, "key").unwrap();
+    write_array_len(&mut buf, 4).unwrap();
+    write_uint(&mut buf, 300).unwrap();
+    write_sint(&mut buf, -70000).unwrap();
+    write_f64(&mut buf, 1.5).unwrap();
+    write_bin(&mut buf, &[0x42; 300]).unwrap();
+    write_u8(&mut buf, 1).unwrap();
+    write_ext_meta(&mut buf, 4, 7).unwrap();
+    buf.extend_from_slice(&[0, 1, 2, 3]);
+    write_str(&mut buf, &"x".repeat(70000)).unwrap();
+    write_nil(&mut buf).unwrap();
+
+    assert_eq!(Scan::Complete(buf.len()), scan_value(&buf).unwrap());
+    for len in 0..buf.len() {
+        match scan_value(&buf[..len]).unwrap() {
+            Scan::Incomplete(needed) => {
+                assert!(needed > 0 && needed <= buf.len() - len, "needed {} at {}", needed, len);
+            }
+            Scan::Complete(..) => panic!("prefix of {} bytes reported as complete", len),
+        }
+    }
+}
+
+#[test]
+fn from_growing_buf_scanner_matches_scan_value() {
+    // {"a": [1, bin8 of 3 bytes], "b": nil} followed by an unrelated byte.
+    let buf = [0x82, 0xa1, 0x61, 0x92, 0x01, 0xc4, 0x03, 0x0a, 0x0b, 0x0c, 0xa1, 0x62, 0xc0, 0xff];
+
+    let mut scanner = Scanner::new();
+    for len in 0..=buf.len() {
+        assert_eq!(scan_value(&buf[..len]).unwrap(), scanner.scan(&buf[..len]).unwrap(), "at {}", len);
+    }
+
+    scanner.reset();
+    assert_eq!(Scan::Complete(1), scanner.scan(&buf[13..]).unwrap());
+}
@@ -27,3 +27,46 @@ fn pass_pack_len_u32() {
 
     assert_eq!([0xc6, 0xff, 0xff, 0xff, 0xff], buf);
 }
+
+#[test]
+#[cfg(feature = "std")]
+fn pass_write_bytes_vectored() {
+    use std::io::{self, IoSlice, Write};
+
+    use crate::msgpack::encode::RmpWrite;
+
+    /// Accepts at most 3 bytes per call, counting the calls.
+    struct Slow(Vec<u8>, usize);
+
+    impl Write for Slow {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write_vectored(&[IoSlice::new(buf)])
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+            self.1 += 1;
+            let mut n = 0;
+            for b in bufs.iter().flat_map(|buf| buf.iter()).take(3) {
+                self.0.push(*b);
+                n += 1;
+            }
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut wr = Slow(Vec::new(), 0);
+    wr.write_bytes_vectored(&[0xc4, 0x05], &[1, 2, 3, 4, 5]).unwrap();
+    assert_eq!(vec![0xc4, 0x05, 1, 2, 3, 4, 5], wr.0);
+    assert_eq!(3, wr.1);
+
+    let mut wr = Slow(Vec::new(), 0);
+    wr.write_bytes_vectored(&[], &[]).unwrap();
+    assert_eq!(0, wr.1);
+
+    let mut buf = [0; 3];
+    (&mut buf[..]).write_bytes_vectored(&[0xc4, 0x02], &[1, 2]).unwrap_err();
+}
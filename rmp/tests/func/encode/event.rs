@@ -0,0 +1,80 @@
+use crate::msgpack::decode::{Event, EventReader};
+use crate::msgpack::encode::*;
+
+#[test]
+fn pass_write_scalar_events() {
+    let mut buf = [0x00; 8];
+    let mut wr = EventWriter::new(&mut buf[..]);
+
+    wr.write(Event::Nil).unwrap();
+    wr.write(Event::Bool(true)).unwrap();
+    wr.write(Event::UInt(300)).unwrap();
+    wr.write(Event::SInt(-1)).unwrap();
+    wr.write(Event::Str(b"a")).unwrap();
+    assert!(wr.is_complete());
+    wr.finish().unwrap();
+
+    assert_eq!([0xc0, 0xc3, 0xcd, 0x01, 0x2c, 0xff, 0xa1, 0x61], buf);
+}
+
+#[test]
+fn pass_write_ext_event() {
+    let mut buf = [0x00; 6];
+    let mut wr = EventWriter::new(&mut buf[..]);
+
+    wr.write(Event::Ext(-1, &[0x00, 0x00, 0x00, 0x01])).unwrap();
+
+    assert_eq!([0xd6, 0xff, 0x00, 0x00, 0x00, 0x01], buf);
+}
+
+#[test]
+fn pass_write_nested_events_tracks_completion() {
+    let mut buf = [0x00; 6];
+    let mut wr = EventWriter::new(&mut buf[..]);
+
+    wr.write(Event::MapStart(1)).unwrap();
+    assert!(!wr.is_complete());
+    wr.write(Event::Str(b"a")).unwrap();
+    wr.write(Event::ArrayStart(2)).unwrap();
+    wr.write(Event::UInt(1)).unwrap();
+    assert!(!wr.is_complete());
+    wr.write(Event::Nil).unwrap();
+    assert!(wr.is_complete());
+
+    wr.finish().unwrap();
+    assert_eq!([0x81, 0xa1, 0x61, 0x92, 0x01, 0xc0], buf);
+}
+
+#[test]
+fn fail_finish_incomplete() {
+    let mut buf = [0x00; 4];
+    let mut wr = EventWriter::new(&mut buf[..]);
+
+    wr.write(Event::MapStart(1)).unwrap();
+    wr.write(Event::Nil).unwrap();
+    wr.write(Event::ArrayStart(0)).unwrap();
+    wr.write(Event::ArrayStart(3)).unwrap();
+
+    match wr.finish() {
+        Err(EventWriteError::Incomplete(3)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_reader_writer_round_trip() {
+    let buf = [
+        0x83, 0xa1, 0x61, 0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0xa1, 0x62, 0x92, 0xc4, 0x01, 0x2a, 0xd4, 0x05, 0x07,
+        0xa1, 0x63, 0x80,
+    ];
+
+    let mut out = [0x00; 24];
+    let mut wr = EventWriter::new(&mut out[..]);
+    for event in EventReader::new(&buf) {
+        wr.write(event.unwrap()).unwrap();
+    }
+    wr.finish().unwrap();
+
+    assert_eq!(buf, out);
+}
@@ -1,6 +1,7 @@
 mod array;
 mod bin;
 mod bool;
+mod event;
 mod ext;
 mod float;
 mod int;